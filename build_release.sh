@@ -7,4 +7,4 @@ if ! command -v wasm-pack &>/dev/null; then
 	echo "Or: https://rustwasm.github.io/wasm-pack/installer/"
 	exit 1
 fi
-wasm-pack build --target web --release --no-typescript
+wasm-pack build --target web --release --no-typescript -- --no-default-features
@@ -1,56 +1,72 @@
+use crate::audio::Audio;
 use crate::camera::Camera;
-use crate::gpu::{GbufferSet, GpuContext};
+use crate::color_grading::ColorLuts;
+use crate::dynamic_resolution::{DynamicResolution, FrameTimeSource};
+use crate::gpu::{stats, GpuContext, GpuTimings};
 use crate::half_cube::HalfCube;
+use crate::hud::Hud;
+use crate::input::{Actions, Bindings, InputMap};
+use crate::platform::{InputState, Platform};
+use crate::render_graph::RenderGraph;
+use crate::render_settings::RenderSettings;
+use crate::scene::{CameraDescriptor, FrameInput};
+use crate::sequencer::{SequenceFrame, Sequencer};
 use crate::stereo_camera::StereoCamera;
+use crate::transition::Transition;
+use crate::ui::UiOverlay;
 use crate::view::ViewState;
 use std::f32::consts::PI;
-use std::{cell::RefCell, rc::Rc};
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-use wasm_bindgen_futures::spawn_local;
+use std::rc::Rc;
 use wgpu::RenderPass;
 
 const JITTER_SIZE: usize = 8;
 
-// -----------------------------------------------------------------------------
-// Debug: enable render features one by one (set to true to enable).
-//
-// Suggested order to isolate issues:
-//  1. All false except leave ENABLE_GBUFFER_PATH = false → clear only (simple path).
-//  2. ENABLE_WAREHOUSE = true → warehouse raymarch.
-//  3. ENABLE_SCENE = true → cubes / scene.
-//  4. ENABLE_GBUFFER_PATH = true → use gbuffer pass (warehouse_gbuffer + scene), then present.
-//  5. ENABLE_TAA = true → TAA resolve (writes to resolve texture).
-//  6. ENABLE_POST = true → brightness + blur + screen composite.
-// -----------------------------------------------------------------------------
-const ENABLE_WAREHOUSE: bool = true;
-const ENABLE_SCENE: bool = true;
-/// false = draw directly to swap (warehouse + scene), no TAA/post. Set true for full pipeline.
-const ENABLE_GBUFFER_PATH: bool = true;
-const ENABLE_TAA: bool = true;
-const ENABLE_POST: bool = true;
-// -----------------------------------------------------------------------------
-
-/// Framebuffer scale for viewport dimensions. 1.0 = full res. Used when WebGPU is in place.
-#[allow(dead_code)]
-const FRAME_BUFFER_SCALE: f32 = 1.0;
-
 pub trait AppInstance {
     fn setup(&mut self, app: &App);
     fn descriptor(&self) -> &crate::scene::SceneDescriptor;
+    fn descriptor_mut(&mut self) -> &mut crate::scene::SceneDescriptor;
     /// Called once per frame before the engine reads the descriptor.
     fn update(&mut self, input: &crate::scene::FrameInput);
     /// Called once per view (once for mono, twice for stereo left/right).
     /// When `pass` is `Some`, WebGPU is active: warehouse is already drawn; the implementor may record more draws (e.g. cubes) into the same pass.
     /// When `is_gbuffer` is true, the pass is the G-buffer pass (2 color + depth); use [HalfCube::draw_instanced_gbuffer].
     fn frame(&mut self, app: &mut App, view: &ViewState, pass: Option<&mut RenderPass<'_>>, is_gbuffer: bool);
+    /// Called once after [AppInstance::setup]; add custom passes or textures to
+    /// the [RenderGraph] here.
+    fn setup_render_graph(&mut self, _graph: &mut RenderGraph) {}
+    /// Draws the instance's controls into the [crate::ui] overlay's "Scene" panel.
+    fn ui(&mut self, _ui: &mut egui::Ui) {}
+    /// Called once when the [GpuContext] is ready (see [Runner::set_gpu]).
+    fn gpu_ready(&mut self, _gpu: &GpuContext) {}
+    /// Names of the instance's scenes, in switching order; empty if it has no scenes to switch.
+    fn scene_names(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+    /// Index of the current scene in [AppInstance::scene_names].
+    fn current_scene(&self) -> usize {
+        0
+    }
+    /// Makes scene `index` current (exit/enter hooks, pass groups in `graph`). Returns `false` if
+    /// there is no such scene or it already is current.
+    fn switch_scene(&mut self, _app: &App, _graph: &mut RenderGraph, _index: usize) -> bool {
+        false
+    }
+    /// Descriptor of the scene switched away from, while a transition still shows it (updated along
+    /// with the current one until [AppInstance::end_transition]); `None` otherwise.
+    fn outgoing_descriptor(&self) -> Option<&crate::scene::SceneDescriptor> {
+        None
+    }
+    /// Like [AppInstance::frame] in the G-buffer pass, for the
+    /// scene in [AppInstance::outgoing_descriptor].
+    fn frame_outgoing(&mut self, _app: &mut App, _view: &ViewState, _pass: &mut RenderPass<'_>) {}
+    /// Called when the transition out of the previous scene ends (or turns into a cut).
+    fn end_transition(&mut self, _app: &App) {}
 }
 
 pub struct App {
-    /// Canvas element; used for 2d fallback clear and WebGPU surface.
-    pub canvas: Rc<web_sys::HtmlCanvasElement>,
-    /// WebGPU context; set after async init (request_adapter/request_device).
-    pub gpu: Option<GpuContext>,
+    /// Canvas element; used for 2d fallback clear and WebGPU surface. `None` when
+    /// rendering headless (offline).
+    pub canvas: Option<Rc<web_sys::HtmlCanvasElement>>,
     pub current_frame: u32,
     pub delta_time: f64,
     pub current_timestamp: f64,
@@ -61,55 +77,39 @@ pub struct App {
     pub aspect_ratio: f32,
     pub cube: HalfCube,
     pub camera: Camera,
+    /// Mono camera of the outgoing scene during a transition (see [App::update_outgoing_view]).
+    pub outgoing_camera: Camera,
+    /// This frame's view of the outgoing scene; `None` without a transition or in stereo.
+    pub outgoing_view: Option<ViewState>,
     pub stereo_camera: StereoCamera,
     pub use_stereo: bool,
     pub jitter_pattern: Vec<f32>,
+    /// Render graph toggles and quality knobs; changed at runtime (JS setters on web).
+    pub render_settings: RenderSettings,
+    /// Bloom multiplier for the screen pass (1 = default); set from the UI or a sequence track.
+    pub bloom_intensity: f32,
+    /// 3D LUTs for the screen pass's grading and Tony McMapface
+    /// tonemapper (see [crate::color_grading]).
+    pub color_luts: ColorLuts,
+    /// Picks the G-buffer render scale each frame (see [App::render_size]).
+    pub resolution: DynamicResolution,
     new_width: u32,
     new_height: u32,
 }
 
 impl App {
-    pub fn init(mut app_instance: Box<dyn AppInstance>) {
-        let document = web_sys::window().unwrap().document().unwrap();
-        let canvas = document
-            .create_element("canvas")
-            .unwrap()
-            .dyn_into::<web_sys::HtmlCanvasElement>()
-            .unwrap();
-
-        #[allow(unused_must_use)]
-        {
-            canvas.style().set_property("position", "fixed");
-            canvas.style().set_property("left", "0");
-            canvas.style().set_property("top", "0");
-            canvas.style().set_property("width", "100%");
-            canvas.style().set_property("height", "100%");
-            document.body().unwrap().append_child(&canvas);
-        }
-
-        log!("Canvas created; WebGPU init starting.");
-
-        let width = canvas.client_width() as u32;
-        let height = canvas.client_height() as u32;
-        let aspect_ratio: f32 = if width != 0 && height != 0 {
-            canvas.set_width(width);
-            canvas.set_height(height);
-            width as f32 / height as f32
-        } else {
-            1.0
-        };
-
-        let screen = web_sys::window().unwrap().screen().unwrap();
-        let canvas_rc = Rc::new(canvas);
-        let canvas_rc_for_resize = canvas_rc.clone();
+    /// Creates app state for a `width` x `height` framebuffer. `canvas` is `None` when rendering
+    /// headless (offline); `max_size` is the largest expected framebuffer (screen size on web).
+    pub fn new(
+        canvas: Option<Rc<web_sys::HtmlCanvasElement>>,
+        width: u32,
+        height: u32,
+        aspect_ratio: f32,
+        max_size: (u32, u32),
+    ) -> Self {
         let fovy = 2.0 * ((PI / 4.0).tan() / (1.0 + aspect_ratio * aspect_ratio).sqrt()).atan();
-        let gpu_rc = Rc::new(RefCell::new(None::<GpuContext>));
-        let gpu_rc_for_loop = gpu_rc.clone();
-        let gbuffer_rc = Rc::new(RefCell::new(None::<GbufferSet>));
-        let gbuffer_rc_for_loop = gbuffer_rc.clone();
-        let mut app = App {
-            canvas: canvas_rc.clone(),
-            gpu: None,
+        let mut app = Self {
+            canvas,
             current_frame: 0,
             current_timestamp: 0.0,
             delta_time: 0.0,
@@ -118,449 +118,523 @@ impl App {
             height: 0,
             new_width: width,
             new_height: height,
-            max_width: screen.width().ok().unwrap() as u32,
-            max_height: screen.height().ok().unwrap() as u32,
+            max_width: max_size.0,
+            max_height: max_size.1,
             cube: HalfCube::new(),
             camera: Camera::new(PI / 2.0, aspect_ratio, 0.1, f32::INFINITY),
+            outgoing_camera: Camera::new(PI / 2.0, aspect_ratio, 0.1, f32::INFINITY),
+            outgoing_view: None,
             stereo_camera: StereoCamera::new(fovy, aspect_ratio, 0.1, f32::INFINITY),
             use_stereo: false,
             jitter_pattern: crate::utils::halton_sequence_2d(JITTER_SIZE, 2, 3),
+            render_settings: RenderSettings::default(),
+            bloom_intensity: 1.0,
+            color_luts: ColorLuts::default(),
+            resolution: DynamicResolution::new(),
         };
         app.stereo_camera.set_eye_distance(0.08);
         app.stereo_camera.set_convergence_distance(2.0);
+        app
+    }
 
-        app_instance.as_mut().setup(&app);
-
-        let app_rc0 = Rc::new(RefCell::new(app));
-        let pending_resize = Rc::new(RefCell::new(None::<(u32, u32)>));
-        let pending_resize_for_resize = pending_resize.clone();
-        let pending_stereo_toggle = Rc::new(RefCell::new(false));
-        let mouse_delta = Rc::new(RefCell::new((0.0f32, 0.0f32)));
-        let keys_held = Rc::new(RefCell::new(0u32));
-
-        let closure = Closure::wrap(Box::new(move || {
-            let width = canvas_rc_for_resize.client_width() as u32;
-            let height = canvas_rc_for_resize.client_height() as u32;
-            if width != 0 && height != 0 && canvas_rc_for_resize.width() != width && canvas_rc_for_resize.height() != height {
-                canvas_rc_for_resize.set_width(width);
-                canvas_rc_for_resize.set_height(height);
-                *pending_resize_for_resize.borrow_mut() = Some((width, height));
-            }
-        }) as Box<dyn FnMut()>);
-        web_sys::window()
-            .unwrap()
-            .set_onresize(Option::Some(closure.as_ref().unchecked_ref()));
-        closure.forget();
+    /// Requests a framebuffer resize; applied at the next [App::begin_frame].
+    pub const fn resize(&mut self, width: u32, height: u32) {
+        self.new_width = width;
+        self.new_height = height;
+    }
 
-        // Pointer lock on canvas click.
-        {
-            let canvas_for_click = canvas_rc.clone();
-            let closure = Closure::wrap(Box::new(move || {
-                canvas_for_click.request_pointer_lock();
-            }) as Box<dyn FnMut()>);
-            #[allow(unused_must_use)]
-            {
-                canvas_rc.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref());
+    /// Advances the frame clock to `timestamp` (milliseconds) and applies a pending resize.
+    pub fn begin_frame(&mut self, timestamp: f64) {
+        self.delta_time = timestamp - self.current_timestamp;
+        self.current_timestamp = timestamp;
+
+        if self.new_width > 0 && self.new_height > 0 {
+            if self.max_height < self.new_height {
+                self.max_height = self.new_height;
             }
-            closure.forget();
+            if self.max_width < self.new_width {
+                self.max_width = self.new_width;
+            }
+            self.width = self.new_width;
+            self.height = self.new_height;
+            self.aspect_ratio = self.width as f32 / self.height as f32;
+            self.new_width = 0;
+            self.new_height = 0;
+            log!("Resize: {}x{}", self.width, self.height);
         }
+    }
 
-        // Mouse move: accumulate deltas only while pointer is locked.
-        {
-            let mouse_delta_for_move = mouse_delta.clone();
-            let document_for_mouse = document.clone();
-            let closure = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
-                if document_for_mouse.pointer_lock_element().is_some() {
-                    let mut d = mouse_delta_for_move.borrow_mut();
-                    d.0 += e.movement_x() as f32;
-                    d.1 += e.movement_y() as f32;
-                }
-            }) as Box<dyn FnMut(web_sys::MouseEvent)>);
-            #[allow(unused_must_use)]
-            {
-                document.add_event_listener_with_callback("mousemove", closure.as_ref().unchecked_ref());
-            }
-            closure.forget();
+    /// Size the G-buffer path renders at: the framebuffer scaled by the current render scale.
+    /// Stereo and the forward path always render at full size.
+    pub fn render_size(&self) -> (u32, u32) {
+        if self.use_stereo || !self.render_settings.gbuffer {
+            return (self.width, self.height);
         }
+        let scale = self.resolution.scale();
+        let scaled = |size: u32| ((size as f32 * scale).round() as u32).clamp(1, size.max(1));
+        (scaled(self.width), scaled(self.height))
+    }
 
-        // Keyboard: track held keys as a bitfield.
-        {
-            use crate::scene::FrameInput;
-            fn key_mask(code: &str) -> u32 {
-                match code {
-                    "KeyW" => FrameInput::KEY_W,
-                    "KeyA" => FrameInput::KEY_A,
-                    "KeyS" => FrameInput::KEY_S,
-                    "KeyD" => FrameInput::KEY_D,
-                    "Space" => FrameInput::KEY_SPACE,
-                    "ShiftLeft" | "ShiftRight" => FrameInput::KEY_SHIFT,
-                    _ => 0,
-                }
-            }
+    /// Updates the mono (jittered) and stereo cameras from the scene camera and returns
+    /// this frame's views.
+    pub fn update_views(&mut self, descriptor: &crate::scene::SceneDescriptor) -> Vec<ViewState> {
+        let aspect_ratio = self.aspect_ratio;
+        let (render_width, render_height) = self.render_size();
+        let jitter = self.jitter();
+        let cam = &descriptor.camera;
+        aim_mono_camera(&mut self.camera, cam, aspect_ratio, jitter);
+
+        if self.use_stereo {
+            self.stereo_camera.look_at(cam.position, cam.target, cam.up);
+            self.stereo_camera.set_aspect(aspect_ratio);
+            self.stereo_camera.update();
+        }
 
-            let keys_for_down = keys_held.clone();
-            let closure = Closure::wrap(Box::new(move |e: web_sys::KeyboardEvent| {
-                let mask = key_mask(&e.code());
-                if mask != 0 {
-                    *keys_for_down.borrow_mut() |= mask;
-                }
-            }) as Box<dyn FnMut(web_sys::KeyboardEvent)>);
-            #[allow(unused_must_use)]
-            {
-                document.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref());
-            }
-            closure.forget();
+        if self.use_stereo {
+            self.stereo_camera
+                .to_view_states((self.width as i32, self.height as i32))
+        } else {
+            let vp = (0, 0, render_width as i32, render_height as i32);
+            vec![self.camera.to_view_state(vp)]
+        }
+    }
 
-            let keys_for_up = keys_held.clone();
-            let closure = Closure::wrap(Box::new(move |e: web_sys::KeyboardEvent| {
-                let mask = key_mask(&e.code());
-                if mask != 0 {
-                    *keys_for_up.borrow_mut() &= !mask;
-                }
-            }) as Box<dyn FnMut(web_sys::KeyboardEvent)>);
-            #[allow(unused_must_use)]
-            {
-                document.add_event_listener_with_callback("keyup", closure.as_ref().unchecked_ref());
-            }
-            closure.forget();
+    /// Updates [App::outgoing_camera] from the outgoing scene's descriptor (same jitter as the mono
+    /// view) and sets [App::outgoing_view]; clears it without a descriptor or in stereo.
+    pub fn update_outgoing_view(&mut self, descriptor: Option<&crate::scene::SceneDescriptor>) {
+        self.outgoing_view = None;
+        let Some(descriptor) = descriptor.filter(|_| !self.use_stereo) else {
+            return;
+        };
+        let (render_width, render_height) = self.render_size();
+        let jitter = self.jitter();
+        aim_mono_camera(
+            &mut self.outgoing_camera,
+            &descriptor.camera,
+            self.aspect_ratio,
+            jitter,
+        );
+        let vp = (0, 0, render_width as i32, render_height as i32);
+        self.outgoing_view = Some(self.outgoing_camera.to_view_state(vp));
+    }
+
+    /// This frame's subpixel jitter in NDC, for the G-buffer render size.
+    fn jitter(&self) -> (f32, f32) {
+        let (render_width, render_height) = self.render_size();
+        let frame = self.current_frame as usize;
+        let jitter_x = self.jitter_pattern[(frame % JITTER_SIZE) * 2] / render_width as f32;
+        let jitter_y = self.jitter_pattern[(frame % JITTER_SIZE) * 2 + 1] / render_height as f32;
+        (jitter_x, jitter_y)
+    }
+}
+
+/// Points a mono `camera` like the scene camera `cam`, jittered by `jitter`.
+fn aim_mono_camera(
+    camera: &mut Camera,
+    cam: &CameraDescriptor,
+    aspect_ratio: f32,
+    jitter: (f32, f32),
+) {
+    camera.set_fov(cam.fov);
+    camera.set_aspect(aspect_ratio);
+    camera.set_jitter(jitter.0, jitter.1);
+    camera.look_at(cam.position, cam.target, cam.up);
+    camera.update();
+}
+
+/// Platform-independent frame loop: owns the [App], the [AppInstance] and (once initialized) the
+/// [GpuContext]. Platforms call [Runner::frame] once per display frame; headless callers use
+/// [Runner::update] and [Runner::render] directly with their own targets.
+pub struct Runner {
+    pub app: App,
+    instance: Box<dyn AppInstance>,
+    gpu: Option<GpuContext>,
+    /// Drawn while [RenderSettings::hud] is on; fed every frame regardless.
+    hud: Hud,
+    /// Run while [RenderSettings::ui] is on.
+    ui: UiOverlay,
+    /// Turns each frame's [InputState] into [FrameInput::actions].
+    input_map: InputMap,
+    /// Timeline driving scenes, camera and parameters while set (see [crate::sequencer]).
+    sequencer: Option<Sequencer>,
+    /// Music; its position is the frame clock while it plays (see [crate::audio]).
+    audio: Audio,
+    /// HDR presentation last asked of the platform (see [Platform::set_extended_range]).
+    hdr_requested: bool,
+    pub graph: RenderGraph,
+}
+
+impl Runner {
+    /// Creates the [App] for a `size` framebuffer (see [App::new]) and runs [AppInstance::setup].
+    pub fn new(
+        mut instance: Box<dyn AppInstance>,
+        canvas: Option<Rc<web_sys::HtmlCanvasElement>>,
+        size: (u32, u32),
+        max_size: (u32, u32),
+    ) -> Self {
+        let (width, height) = size;
+        let aspect_ratio = if width != 0 && height != 0 {
+            width as f32 / height as f32
+        } else {
+            1.0
+        };
+        let app = App::new(canvas, width, height, aspect_ratio, max_size);
+        instance.setup(&app);
+        let mut graph = RenderGraph::new();
+        instance.setup_render_graph(&mut graph);
+        Self {
+            app,
+            instance,
+            gpu: None,
+            hud: Hud::new(),
+            ui: UiOverlay::new(),
+            input_map: InputMap::default(),
+            sequencer: None,
+            audio: Audio::default(),
+            hdr_requested: false,
+            graph,
         }
+    }
+
+    /// Installs the GPU context once async init completes and creates the GPU-side meshes.
+    pub fn set_gpu(&mut self, gpu: GpuContext) {
+        self.app.cube = HalfCube::init_from_gpu(&gpu.device, &gpu.queue, gpu.surface_format);
+        self.instance.gpu_ready(&gpu);
+        log!(
+            "[GPU] Frame timing: {}",
+            if gpu.timer.is_some() {
+                "timestamp queries"
+            } else {
+                "display delta"
+            }
+        );
+        self.gpu = Some(gpu);
+    }
+
+    pub fn toggle_stereo(&mut self) {
+        self.app.use_stereo = !self.app.use_stereo;
+        log!("Stereo: {}", self.app.use_stereo);
+    }
+
+    /// Whether frames present in extended range (HDR output on and supported).
+    #[cfg(target_arch = "wasm32")]
+    pub fn hdr_active(&self) -> bool {
+        self.gpu.as_ref().is_some_and(GpuContext::hdr_active)
+    }
+
+    /// Replaces the input bindings; actions held under the old ones report a release next frame.
+    pub fn set_bindings(&mut self, bindings: Bindings) {
+        self.input_map.bindings = bindings;
+    }
+
+    /// Plays `sequencer` from the next frame on (replacing any other); `None` stops playback and
+    /// leaves scenes and camera where the sequence left them.
+    pub fn set_sequencer(&mut self, sequencer: Option<Sequencer>) {
+        self.sequencer = sequencer;
+    }
+
+    /// The playing sequence, to seek, pause or loop it.
+    #[cfg(target_arch = "wasm32")]
+    pub const fn sequencer_mut(&mut self) -> Option<&mut Sequencer> {
+        self.sequencer.as_mut()
+    }
+
+    /// The music player, to load, play, pause or seek a track.
+    pub const fn audio_mut(&mut self) -> &mut Audio {
+        &mut self.audio
+    }
+
+    /// Names of the instance's scenes (see [AppInstance::scene_names]).
+    pub fn scene_names(&self) -> Vec<&'static str> {
+        self.instance.scene_names()
+    }
 
-        let f = Rc::new(RefCell::new(None));
-        let g = f.clone();
-        let app_instance_rc = Rc::new(RefCell::new(app_instance));
-        let app_rc_for_loop = app_rc0.clone();
-        let pending_resize_for_loop = pending_resize.clone();
-        let pending_stereo_toggle_for_loop = pending_stereo_toggle.clone();
-        let mouse_delta_for_loop = mouse_delta.clone();
-        let keys_held_for_loop = keys_held.clone();
-        let pending_init = Rc::new(RefCell::new(None::<(HalfCube, GpuContext)>));
-        let pending_init_for_loop = pending_init.clone();
-
-        // Async WebGPU init. Never borrows app_rc; stores (cube, gpu) in pending_init for the rAF loop to apply.
+    /// Index of the current scene in [Runner::scene_names].
+    pub fn current_scene(&self) -> usize {
+        self.instance.current_scene()
+    }
+
+    /// Switches to scene `index`, blending with `transition` from the next frame rendered (the
+    /// outgoing scene keeps running until it ends). Returns `false` if there is no such scene or it
+    /// already is current.
+    pub fn switch_scene(&mut self, index: usize, transition: Transition) -> bool {
+        if !self
+            .instance
+            .switch_scene(&self.app, &mut self.graph, index)
         {
-            let pending_init_async = pending_init.clone();
-            let canvas_for_gpu = canvas_rc.as_ref().clone();
-            spawn_local(async move {
-                if let Some(gpu) = crate::gpu::init_gpu(canvas_for_gpu).await {
-                    let cube = crate::half_cube::HalfCube::init_from_gpu(
-                        &gpu.device,
-                        &gpu.queue,
-                        gpu.surface_format,
-                    );
-                    *pending_init_async.borrow_mut() = Some((cube, gpu));
-                    log!("WebGPU initialized.");
-                }
-            });
+            return false;
+        }
+        // The outgoing scene's view carries on from where the mono camera was.
+        self.app.outgoing_camera = self.app.camera.clone();
+        self.graph.start_transition(transition);
+        if !transition.is_blended() {
+            self.instance.end_transition(&self.app);
         }
+        log!(
+            "Scene: {}",
+            self.scene_names().get(index).copied().unwrap_or("?")
+        );
+        true
+    }
 
-        let closure = Closure::wrap(Box::new(move |timestamp| {
-            #[allow(unused_must_use)]
-            {
-                web_sys::window().unwrap().request_animation_frame(
-                    (f.borrow().as_ref().unwrap() as &Closure<dyn FnMut(f64)>)
-                        .as_ref()
-                        .unchecked_ref(),
-                );
-            }
+    /// Steps through the scenes (wrapping) on the `next_scene` / `previous_scene` actions.
+    fn switch_scene_on_actions(&mut self, actions: &Actions) {
+        let count = self.instance.scene_names().len();
+        if count < 2 {
+            return;
+        }
+        let current = self.current_scene();
+        if actions.pressed("next_scene") {
+            self.switch_scene((current + 1) % count, Transition::default());
+        } else if actions.pressed("previous_scene") {
+            self.switch_scene((current + count - 1) % count, Transition::default());
+        }
+    }
 
-            let Ok(mut app) = app_rc_for_loop.try_borrow_mut() else {
-                return;
+    /// Handles the `toggle_stereo`, `toggle_ui` and `toggle_hud` actions, and `pause_sequence` /
+    /// `restart_sequence` for the sequence and the music together.
+    fn toggle_on_actions(&mut self, actions: &Actions) {
+        if actions.pressed("toggle_stereo") {
+            self.toggle_stereo();
+        }
+        let settings = &mut self.app.render_settings;
+        if actions.pressed("toggle_ui") {
+            settings.ui = !settings.ui;
+        }
+        if actions.pressed("toggle_hud") {
+            settings.hud = !settings.hud;
+        }
+        if actions.pressed("pause_sequence") {
+            let playing = match self.sequencer.as_mut() {
+                Some(sequencer) => {
+                    sequencer.set_playing(!sequencer.is_playing());
+                    sequencer.is_playing()
+                }
+                None => !self.audio.frame().playing,
             };
-            if let Some((cube, gpu)) = pending_init_for_loop.borrow_mut().take() {
-                app.cube = cube;
-                *gpu_rc_for_loop.borrow_mut() = Some(gpu);
-            }
-            if let Some((w, h)) = pending_resize_for_loop.borrow_mut().take() {
-                app.new_width = w;
-                app.new_height = h;
+            if playing {
+                self.audio.play();
+            } else {
+                self.audio.pause();
             }
-            if pending_stereo_toggle_for_loop.replace(false) {
-                app.use_stereo = !app.use_stereo;
-                log!("Stereo: {}", app.use_stereo);
+        }
+        if actions.pressed("restart_sequence") {
+            if let Some(sequencer) = self.sequencer.as_mut() {
+                sequencer.seek(0.0);
             }
-            app.delta_time = timestamp - app.current_timestamp;
-            app.current_timestamp = timestamp;
+            self.audio.seek(0.0);
+        }
+    }
 
-            let resized = if app.new_width > 0 && app.new_height > 0 {
-                if app.max_height < app.new_height {
-                    app.max_height = app.new_height;
-                }
-                if app.max_width < app.new_width {
-                    app.max_width = app.new_width;
-                }
-                app.width = app.new_width;
-                app.height = app.new_height;
-                app.aspect_ratio = app.width as f32 / app.height as f32;
-                app.new_width = 0;
-                app.new_height = 0;
-                log!("Resize: {}x{}", app.width, app.height);
-                true
+    /// Advances the clock (to `timestamp`, or by the music while it plays), runs the UI (when
+    /// shown) and feeds `input` to the instance, and returns this frame's views.
+    pub fn update(&mut self, timestamp: f64, mut input: InputState) -> Vec<ViewState> {
+        let timestamp = self.audio.update(timestamp);
+        self.app.begin_frame(timestamp);
+        self.hud.record_frame_time(self.app.delta_time);
+        if self.app.render_settings.ui {
+            let events = std::mem::take(&mut input.ui_events);
+            self.ui.run(
+                &mut self.app,
+                self.instance.as_mut(),
+                events,
+                input.ui_modifiers,
+            );
+            if self.ui.wants_keyboard() {
+                input.keys_down.clear();
+            }
+        } else {
+            crate::ui::skip_frame();
+        }
+        input.touch.draw();
+        let actions = self
+            .input_map
+            .update(&input, (self.app.delta_time / 1000.0) as f32);
+        self.switch_scene_on_actions(&actions);
+        let audio = *self.audio.frame();
+        // A sequence follows the music while a track is loaded, so both can be scrubbed together.
+        if let (Some(sequencer), Some(position_s)) = (self.sequencer.as_mut(), audio.position_s) {
+            sequencer.follow(position_s);
+        }
+        // After following the music, so this frame's track position doesn't undo a restart.
+        self.toggle_on_actions(&actions);
+        let mut sequence = self
+            .sequencer
+            .as_mut()
+            .map(|s| s.update(self.app.current_timestamp));
+        if let Some((scene, transition)) =
+            sequence.as_mut().and_then(|frame| frame.switch_to.take())
+        {
+            if let Some(index) = self.scene_names().iter().position(|name| *name == scene) {
+                self.switch_scene(index, transition);
             } else {
-                false
-            };
-            let _ = resized;
-
-            let mut app_instance = app_instance_rc.borrow_mut();
-            let (mdx, mdy) = mouse_delta_for_loop.replace((0.0, 0.0));
-            let frame_input = crate::scene::FrameInput {
-                timestamp: app.current_timestamp,
-                delta_time: app.delta_time,
-                mouse_dx: mdx,
-                mouse_dy: mdy,
-                keys_held: *keys_held_for_loop.borrow(),
-            };
-            app_instance.update(&frame_input);
-
-            let aspect_ratio = app.aspect_ratio;
-            let fb_w = app.width as f32;
-            let fb_h = app.height as f32;
-            let frame = app.current_frame as usize;
-            let jitter_x = app.jitter_pattern[(frame % JITTER_SIZE) * 2] / fb_w;
-            let jitter_y = app.jitter_pattern[(frame % JITTER_SIZE) * 2 + 1] / fb_h;
-
-            let cam = &app_instance.descriptor().camera;
-            app.camera.set_fov(cam.fov);
-            app.camera.set_aspect(aspect_ratio);
-            app.camera.set_jitter(jitter_x, jitter_y);
-            app.camera.look_at(cam.position, cam.target, cam.up);
-            app.camera.update();
-
-            if app.use_stereo {
-                app.stereo_camera.look_at(cam.position, cam.target, cam.up);
-                app.stereo_camera.set_aspect(aspect_ratio);
-                app.stereo_camera.update();
+                log_error!("Sequence: no scene named '{scene}'");
             }
+        }
+        let frame_input = FrameInput {
+            timestamp: self.app.current_timestamp,
+            delta_time: self.app.delta_time,
+            actions,
+            mouse_dx: input.mouse_dx,
+            mouse_dy: input.mouse_dy,
+            gamepad: input.gamepad,
+            parameters: sequence
+                .as_mut()
+                .map(|frame| std::mem::take(&mut frame.parameters))
+                .unwrap_or_default(),
+            audio,
+        };
+        self.instance.update(&frame_input);
+        if let Some(frame) = &sequence {
+            self.apply_sequence(frame);
+        }
+        self.app
+            .update_outgoing_view(self.instance.outgoing_descriptor());
+        self.app.update_views(self.instance.descriptor())
+    }
 
-            let fb_width = (app.width as f32 / FRAME_BUFFER_SCALE).ceil().max(1.0) as i32;
-            let fb_height = (app.height as f32 / FRAME_BUFFER_SCALE).ceil().max(1.0) as i32;
-            let views: Vec<ViewState> = if app.use_stereo {
-                app.stereo_camera
-                    .to_view_states((fb_width, fb_height))
-            } else {
-                let vp = (0, 0, fb_width, fb_height);
-                vec![app.camera.to_view_state(vp)]
-            };
+    /// Overrides what the scene just set with the sequence's camera and bloom tracks.
+    fn apply_sequence(&mut self, frame: &SequenceFrame) {
+        let camera = &mut self.instance.descriptor_mut().camera;
+        if let Some(position) = frame.camera_position {
+            camera.position = position;
+        }
+        if let Some(target) = frame.camera_target {
+            camera.target = target;
+        }
+        if let Some(fov) = frame.camera_fov {
+            camera.fov = fov;
+        }
+        if let Some(intensity) = frame.bloom_intensity {
+            self.app.bloom_intensity = intensity;
+        }
+    }
 
-            let time_s = (app.current_timestamp / 1000.0) as f32;
-            let width = app.width;
-            let height = app.height;
+    /// Records `views` into `encoder`, ending in `target_view`. No-op until the GPU is set.
+    pub fn render(
+        &mut self,
+        views: &[ViewState],
+        encoder: &mut wgpu::CommandEncoder,
+        target_view: &wgpu::TextureView,
+    ) {
+        if let Some(gpu) = self.gpu.as_mut() {
+            gpu.configure_surface(self.app.width, self.app.height);
+            let settings = &self.app.render_settings;
+            gpu.set_raymarch_steps(settings.raymarch_steps, settings.shadow_steps);
+            gpu.set_bloom_intensity(self.app.bloom_intensity);
+            gpu.set_audio_bands(self.audio.frame().bands);
+            let exposure = &self.app.camera.exposure;
+            let delta_s = (self.app.delta_time / 1000.0) as f32;
+            gpu.set_exposure(
+                exposure.ev100(),
+                exposure.compensation,
+                settings.eye_adaptation(),
+                delta_s,
+            );
+            gpu.set_color_grading(settings.tonemapper, settings.grading, &self.app.color_luts);
+            gpu.set_output_peak(settings.hdr_peak());
+            if settings.hud {
+                self.hud.draw(&self.app, gpu.timer.is_some());
+            }
+            self.graph.execute(
+                gpu,
+                &mut self.app,
+                self.instance.as_mut(),
+                views,
+                encoder,
+                target_view,
+            );
+            self.hud.record_draws(stats::take());
+        }
+        crate::debug_draw::end_frame();
+        crate::text::end_frame();
+        crate::ui::end_frame();
+    }
 
-            if let Some(gpu) = gpu_rc_for_loop.borrow_mut().as_mut() {
-                gpu.configure_surface(width, height);
-                let Ok(frame_tex) = gpu.surface.get_current_texture() else {
-                    app.current_frame += 1;
-                    return;
-                };
+    /// One display frame: picks up resizes and input from `platform`, renders to its surface (or
+    /// the 2d fallback before the GPU is ready), and presents.
+    pub fn frame(&mut self, platform: &mut dyn Platform, timestamp: f64) {
+        let (width, height) = platform.framebuffer_size();
+        if width != 0 && height != 0 && (width != self.app.width || height != self.app.height) {
+            self.app.resize(width, height);
+        }
+        let update_start = platform.now_ms();
+        let views = self.update(timestamp, platform.take_input());
+        let render_start = platform.now_ms();
+
+        if let Some(gpu) = self.gpu.as_mut() {
+            let reconfigured = gpu.configure_surface(self.app.width, self.app.height);
+            // Extended range needs the screen pass (the only one that tonemaps); configuring the
+            // surface resets it.
+            let settings = &self.app.render_settings;
+            let hdr = settings.hdr && settings.post && gpu.hdr_capable();
+            if hdr != self.hdr_requested || (hdr && reconfigured) {
+                let active = platform.set_extended_range(hdr);
+                if hdr && !active && !self.hdr_requested {
+                    log!("[GPU] HDR output unsupported by the browser; presenting SDR.");
+                }
+                self.hdr_requested = hdr;
+                gpu.set_hdr_active(active);
+            }
+            if let Some(Ok(frame_tex)) =
+                gpu.surface.as_ref().map(wgpu::Surface::get_current_texture)
+            {
                 let swap_view = frame_tex
                     .texture
                     .create_view(&wgpu::TextureViewDescriptor::default());
                 let mut encoder = gpu
                     .device
                     .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
-
-                if app.current_frame == 0 {
-                    log!(
-                        "[Frame 0] Drawing with GPU (views: {}, warehouse: {}, scene: {}, gbuffer: {}, taa: {}, post: {}).",
-                        views.len(),
-                        ENABLE_WAREHOUSE,
-                        ENABLE_SCENE,
-                        ENABLE_GBUFFER_PATH,
-                        ENABLE_TAA,
-                        ENABLE_POST
-                    );
+                if let Some(timer) = gpu.timer.as_ref() {
+                    timer.begin(&mut encoder);
                 }
-                let use_gbuffer_path = views.len() == 1 && ENABLE_GBUFFER_PATH;
-                if use_gbuffer_path {
-                    let history_index = gpu.taa_history_index();
-                    gpu.ensure_gbuffer(width, height, &gbuffer_rc_for_loop);
-                    let gbuffer_guard = gbuffer_rc_for_loop.borrow();
-                    let gbuffer = gbuffer_guard.as_ref().unwrap();
-                    let color_view = gbuffer.color_view();
-                    let velocity_view = gbuffer.velocity_view();
-                    let depth_view = gbuffer.depth_view();
-                    {
-                        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                            label: Some("gbuffer"),
-                            color_attachments: &[
-                                Some(wgpu::RenderPassColorAttachment {
-                                    view: &color_view,
-                                    resolve_target: None,
-                                    ops: wgpu::Operations {
-                                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                                        store: wgpu::StoreOp::Store,
-                                    },
-                                    depth_slice: None,
-                                }),
-                                Some(wgpu::RenderPassColorAttachment {
-                                    view: &velocity_view,
-                                    resolve_target: None,
-                                    ops: wgpu::Operations {
-                                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                                        store: wgpu::StoreOp::Store,
-                                    },
-                                    depth_slice: None,
-                                }),
-                            ],
-                            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                                view: &depth_view,
-                                depth_ops: Some(wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear(0.0),
-                                    store: wgpu::StoreOp::Store,
-                                }),
-                                stencil_ops: None,
-                            }),
-                            timestamp_writes: None,
-                            multiview_mask: None,
-                            occlusion_query_set: None,
-                        });
-                        let view_state = &views[0];
-                        let (vx, vy, vw, vh) = view_state.viewport;
-                        pass.set_viewport(vx as f32, vy as f32, vw as f32, vh as f32, 0.0, 1.0);
-                        if ENABLE_WAREHOUSE {
-                            gpu.draw_warehouse_gbuffer(
-                                &mut pass,
-                                view_state,
-                                time_s,
-                                width,
-                                height,
-                            );
-                        }
-                        if ENABLE_SCENE {
-                            app_instance.frame(&mut *app, view_state, Some(&mut pass), true);
-                        }
-                    }
-                    if ENABLE_TAA {
-                        gpu.run_taa_pass(&mut encoder, gbuffer, history_index);
-                    }
-                    let resolve_view = gbuffer.resolve_view();
-                    if ENABLE_POST && ENABLE_TAA {
-                        gpu.run_bloom_passes(&mut encoder, &resolve_view, gbuffer);
-                        let bloom_mip0 = gbuffer.bloom_mip_view(0);
-                        let cam_dir = views[0].direction();
-                        gpu.run_screen_pass(&mut encoder, &resolve_view, &bloom_mip0, &swap_view, [cam_dir.x, cam_dir.y, cam_dir.z]);
-                    } else {
-                        let source = if ENABLE_TAA { &resolve_view } else { &color_view };
-                        gpu.run_present_pass(&mut encoder, source, &swap_view);
-                    }
-                } else {
-                    let depth_view = gpu.main_depth_view();
-                    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: Some("main"),
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &swap_view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color {
-                                    r: 0.02,
-                                    g: 0.02,
-                                    b: 0.08,
-                                    a: 1.0,
-                                }),
-                                store: wgpu::StoreOp::Store,
-                            },
-                            depth_slice: None,
-                        })],
-                        depth_stencil_attachment: depth_view.map(|v| wgpu::RenderPassDepthStencilAttachment {
-                            view: v,
-                            depth_ops: Some(wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(0.0),
-                                store: wgpu::StoreOp::Store,
-                            }),
-                            stencil_ops: None,
-                        }),
-                        timestamp_writes: None,
-                        multiview_mask: None,
-                        occlusion_query_set: None,
-                    });
-                    for view_state in &views {
-                        let (vx, vy, vw, vh) = view_state.viewport;
-                        pass.set_viewport(
-                            vx as f32,
-                            vy as f32,
-                            vw as f32,
-                            vh as f32,
-                            0.0,
-                            1.0,
-                        );
-                        // Always run fullscreen pass: raymarch when ENABLE_WAREHOUSE, else solid gray so cubes show on top.
-                        gpu.draw_warehouse(
-                            &mut pass,
-                            view_state,
-                            time_s,
-                            width,
-                            height,
-                            !ENABLE_WAREHOUSE,
-                        );
-                        if ENABLE_SCENE {
-                            app_instance.frame(&mut *app, view_state, Some(&mut pass), false);
-                        }
+                self.render(&views, &mut encoder, &swap_view);
+                if let Some(gpu) = self.gpu.as_mut() {
+                    if let Some(timer) = gpu.timer.as_mut() {
+                        timer.end(&mut encoder);
                     }
+                    gpu.queue.submit(Some(encoder.finish()));
                 }
-                gpu.queue.submit(Some(encoder.finish()));
                 frame_tex.present();
-            } else {
-                for view in &views {
-                    app_instance.frame(&mut *app, view, None, false);
+                if let Some(timer) = self.gpu.as_mut().and_then(|gpu| gpu.timer.as_mut()) {
+                    timer.after_submit();
                 }
             }
-
-            app.current_frame += 1;
-        }) as Box<dyn FnMut(f64)>);
-
-        *g.borrow_mut() = Some(closure);
-
-        let vr_supported = js_sys::eval("navigator.xr !== undefined")
-            .unwrap()
-            .as_bool()
-            .unwrap();
-        log!("WebXR support: {}", vr_supported);
-
-        if vr_supported {
-            Self::add_vr_button(pending_stereo_toggle.clone());
+            self.hud.record_cpu(
+                render_start - update_start,
+                platform.now_ms() - render_start,
+            );
+            let timings = self.read_gpu_timings();
+            self.update_resolution(timings.as_ref());
         } else {
-            log!("WebXR not available (navigator.xr is undefined).");
+            for view in &views {
+                self.instance.frame(&mut self.app, view, None, false);
+            }
         }
 
-        log!("Starting render loop...");
-        #[allow(unused_must_use)]
-        {
-            web_sys::window()
-                .unwrap()
-                .request_animation_frame(g.borrow().as_ref().unwrap().as_ref().unchecked_ref());
-        }
+        self.app.current_frame += 1;
     }
 
-    fn add_vr_button(pending_stereo_toggle: Rc<RefCell<bool>>) {
-        let document = web_sys::window().unwrap().document().unwrap();
-        let button = document
-            .create_element("div")
-            .unwrap()
-            .dyn_into::<web_sys::HtmlDivElement>()
-            .unwrap();
-
-        #[allow(unused_must_use)]
-        {
-            button.set_inner_text("🤓");
-            button.style().set_property("position", "fixed");
-            button.style().set_property("right", "0");
-            button.style().set_property("bottom", "0");
-            button.style().set_property("font-size", "10em");
-            button.style().set_property("cursor", "pointer");
-            button.style().set_property("user-select", "none");
-            button.style().set_property("transform", "rotate(45deg)");
-            button
-                .style()
-                .set_property("text-shadow", "#f00 -0.05em 0.05em 0.1em, #0ff 0.05em -0.05em 0.1em");
-            document.body().unwrap().append_child(&button);
-        }
+    /// Latest GPU timings, if a readback finished; also passed to the HUD.
+    fn read_gpu_timings(&mut self) -> Option<GpuTimings> {
+        let gpu = self.gpu.as_ref()?;
+        let timings = gpu.timer.as_ref()?.read(&gpu.device)?;
+        self.hud.record_gpu(&timings);
+        Some(timings)
+    }
 
-        let closure = Closure::wrap(Box::new(move || {
-            *pending_stereo_toggle.borrow_mut() = true;
-        }) as Box<dyn FnMut()>);
-        #[allow(unused_must_use)]
-        {
-            button.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref());
-        }
-        closure.forget();
+    /// Updates [App::resolution] from the settings and the latest frame time: GPU time when timed
+    /// (on frames a readback lands), display delta otherwise.
+    fn update_resolution(&mut self, timings: Option<&GpuTimings>) {
+        let timed = self.gpu.as_ref().is_some_and(|gpu| gpu.timer.is_some());
+        let sample = match timings {
+            Some(timings) => Some((timings.frame_ms, FrameTimeSource::Gpu)),
+            None if timed => None,
+            None => Some((self.app.delta_time, FrameTimeSource::Display)),
+        };
+        let elapsed_ms = self.app.delta_time;
+        self.app
+            .resolution
+            .update(&self.app.render_settings, sample, elapsed_ms);
     }
 }
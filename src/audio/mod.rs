@@ -0,0 +1,157 @@
+//! Music: plays a track, makes its position the demo clock, and measures its spectrum each frame.
+//!
+//! While a track plays, [Audio::update] derives [crate::app::App::current_timestamp] from the
+//! track position rather than the display clock, so visuals keep pace with the music (and a
+//! [crate::sequencer::Sequencer] follows the track, see [crate::app::Runner]). The clock stays
+//! continuous: when playback starts, pauses or seeks, it carries on from where it was.
+//!
+//! Each frame's spectrum is split into [BANDS] log-spaced bands ([BAND_EDGES_HZ]), each a 0..1
+//! level (an FFT magnitude mapped from -100..-30 dB, smoothed over frames: Web Audio's
+//! `AnalyserNode` defaults). Scenes get them in [crate::scene::FrameInput::audio], shaders in the
+//! screen pass's uniforms.
+//!
+//! Backends ([AudioBackend]):
+//! - web: Web Audio (`AudioContext`, decoded buffer into an `AnalyserNode`); any format the browser
+//!   decodes. Browsers only start audio after the user has interacted with the page.
+//! - native: no output device; plays a WAV file silently on the frame clock and runs the same
+//!   analysis on its samples, so offline renders react to the music deterministically.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod offline;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use offline::OfflineAudio;
+#[cfg(target_arch = "wasm32")]
+pub use web::WebAudio;
+
+/// Spectrum bands per frame.
+pub const BANDS: usize = 8;
+/// Band boundaries in Hz: band `i` spans `BAND_EDGES_HZ[i]..BAND_EDGES_HZ[i + 1]` (bass first).
+pub const BAND_EDGES_HZ: [f32; BANDS + 1] = [
+    20.0, 60.0, 150.0, 400.0, 1000.0, 2400.0, 5000.0, 10000.0, 20000.0,
+];
+/// Samples per analysis window (`AnalyserNode.fftSize` default).
+pub(crate) const FFT_SIZE: usize = 2048;
+/// Levels map this dB range to 0..1 (`AnalyserNode` `minDecibels` / `maxDecibels` defaults).
+pub(crate) const MIN_DECIBELS: f32 = -100.0;
+pub(crate) const MAX_DECIBELS: f32 = -30.0;
+/// Weight of the previous frame's magnitudes (`AnalyserNode.smoothingTimeConstant` default).
+pub(crate) const SMOOTHING: f32 = 0.8;
+
+/// The track's state on one frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AudioFrame {
+    /// Track position in seconds; `None` until a track is loaded.
+    pub position_s: Option<f64>,
+    /// Whether the track is audibly advancing (loaded, started, not paused or ended).
+    pub playing: bool,
+    /// 0..1 level per band ([BAND_EDGES_HZ]); zero in silence.
+    pub bands: [f32; BANDS],
+}
+
+/// Track playback and analysis for one platform; see the module docs.
+pub trait AudioBackend {
+    /// Replaces the track with `source` (a URL on the web, a WAV file path natively), paused at 0.
+    /// Loading may finish later (web); errors found up front are returned.
+    fn load(&mut self, source: &str) -> Result<(), String>;
+    /// Starts or resumes playback (once loaded, if still loading).
+    fn play(&mut self);
+    fn pause(&mut self);
+    /// Moves to `time_s` (clamped to the track), playing or not.
+    fn seek(&mut self, time_s: f64);
+    /// Advances to the display frame at `frame_timestamp` (milliseconds) and analyses it.
+    fn update(&mut self, frame_timestamp: f64) -> AudioFrame;
+}
+
+/// The [AudioBackend] for this platform, plus the demo clock derived from it.
+pub struct Audio {
+    backend: Box<dyn AudioBackend>,
+    frame: AudioFrame,
+    /// Demo clock (ms) on the last frame, and the frame timestamp it was computed at.
+    clock: Option<(f64, f64)>,
+    /// Demo clock minus track position (ms) while the clock follows the track.
+    origin_ms: Option<f64>,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        #[cfg(target_arch = "wasm32")]
+        let backend = Box::new(WebAudio::default());
+        #[cfg(not(target_arch = "wasm32"))]
+        let backend = Box::new(OfflineAudio::default());
+        Self::with_backend(backend)
+    }
+}
+
+impl Audio {
+    pub fn with_backend(backend: Box<dyn AudioBackend>) -> Self {
+        Self {
+            backend,
+            frame: AudioFrame::default(),
+            clock: None,
+            origin_ms: None,
+        }
+    }
+
+    /// See [AudioBackend::load].
+    pub fn load(&mut self, source: &str) -> Result<(), String> {
+        self.origin_ms = None;
+        self.backend.load(source)
+    }
+
+    pub fn play(&mut self) {
+        self.backend.play();
+    }
+
+    pub fn pause(&mut self) {
+        self.backend.pause();
+    }
+
+    /// See [AudioBackend::seek]; the demo clock keeps running from where it is.
+    pub fn seek(&mut self, time_s: f64) {
+        self.origin_ms = None;
+        self.backend.seek(time_s);
+    }
+
+    /// The track's state as of the last [Audio::update].
+    pub const fn frame(&self) -> &AudioFrame {
+        &self.frame
+    }
+
+    /// Advances the track to the display frame at `frame_timestamp` (ms) and returns the demo clock
+    /// for it: the display clock, or the track position (offset to stay continuous) while playing.
+    pub fn update(&mut self, frame_timestamp: f64) -> f64 {
+        self.frame = self.backend.update(frame_timestamp);
+        let Some((clock, last_timestamp)) = self.clock else {
+            self.clock = Some((frame_timestamp, frame_timestamp));
+            return frame_timestamp;
+        };
+        let display = clock + (frame_timestamp - last_timestamp);
+        let now = if let Some(position_s) = self.frame.position_s.filter(|_| self.frame.playing) {
+            let position_ms = position_s * 1000.0;
+            let origin = *self.origin_ms.get_or_insert(display - position_ms);
+            (origin + position_ms).max(clock)
+        } else {
+            self.origin_ms = None;
+            display
+        };
+        self.clock = Some((now, frame_timestamp));
+        now
+    }
+}
+
+/// Averages per-bin levels (0..1, bin `k` at `k * sample_rate / FFT_SIZE` Hz) into [BANDS] bands.
+pub(crate) fn band_levels(levels: &[f32], sample_rate: f32) -> [f32; BANDS] {
+    let hz_per_bin = sample_rate / FFT_SIZE as f32;
+    let mut bands = [0.0; BANDS];
+    for (band, edges) in bands.iter_mut().zip(BAND_EDGES_HZ.windows(2)) {
+        let first = (edges[0] / hz_per_bin).ceil() as usize;
+        let end = ((edges[1] / hz_per_bin).ceil() as usize).min(levels.len());
+        if first < end {
+            *band = levels[first..end].iter().sum::<f32>() / (end - first) as f32;
+        }
+    }
+    bands
+}
@@ -0,0 +1,228 @@
+//! Native [AudioBackend]: plays a WAV file without output, advancing with the frame timestamps, and
+//! analyses it the way an `AnalyserNode` does (Blackman window, FFT, smoothing, dB mapping).
+
+use std::f32::consts::TAU;
+
+use super::{
+    band_levels, AudioBackend, AudioFrame, BANDS, FFT_SIZE, MAX_DECIBELS, MIN_DECIBELS, SMOOTHING,
+};
+
+/// Decoded samples, channels mixed down to mono.
+struct Track {
+    samples: Vec<f32>,
+    sample_rate: f32,
+}
+
+impl Track {
+    fn duration_s(&self) -> f64 {
+        self.samples.len() as f64 / f64::from(self.sample_rate)
+    }
+}
+
+/// Silent WAV playback on the frame clock; deterministic for a given sequence of timestamps.
+pub struct OfflineAudio {
+    track: Option<Track>,
+    position_s: f64,
+    playing: bool,
+    last_timestamp: Option<f64>,
+    /// Smoothed magnitude per FFT bin, carried across frames.
+    magnitudes: Vec<f32>,
+}
+
+impl Default for OfflineAudio {
+    fn default() -> Self {
+        Self {
+            track: None,
+            position_s: 0.0,
+            playing: false,
+            last_timestamp: None,
+            magnitudes: vec![0.0; FFT_SIZE / 2],
+        }
+    }
+}
+
+impl OfflineAudio {
+    /// Band levels for the window of samples ending at the current position (silence while paused,
+    /// so levels fall off like the analyser's do when a source stops).
+    fn analyse(&mut self) -> [f32; BANDS] {
+        let Some(track) = &self.track else {
+            return [0.0; BANDS];
+        };
+        let samples = if self.playing {
+            track.samples.as_slice()
+        } else {
+            &[]
+        };
+        let end = (self.position_s * f64::from(track.sample_rate)) as usize;
+        let start = end as isize - FFT_SIZE as isize;
+        let mut re: Vec<f32> = (0..FFT_SIZE)
+            .map(|n| {
+                let sample = usize::try_from(start + n as isize)
+                    .ok()
+                    .and_then(|i| samples.get(i));
+                sample.copied().unwrap_or(0.0) * blackman(n)
+            })
+            .collect();
+        let mut im = vec![0.0; FFT_SIZE];
+        fft(&mut re, &mut im);
+
+        let levels: Vec<f32> = self
+            .magnitudes
+            .iter_mut()
+            .enumerate()
+            .map(|(k, smoothed)| {
+                let magnitude = re[k].hypot(im[k]) / FFT_SIZE as f32;
+                *smoothed = SMOOTHING * *smoothed + (1.0 - SMOOTHING) * magnitude;
+                let db = 20.0 * smoothed.log10();
+                ((db - MIN_DECIBELS) / (MAX_DECIBELS - MIN_DECIBELS)).clamp(0.0, 1.0)
+            })
+            .collect();
+        band_levels(&levels, track.sample_rate)
+    }
+}
+
+impl AudioBackend for OfflineAudio {
+    fn load(&mut self, source: &str) -> Result<(), String> {
+        let bytes = std::fs::read(source).map_err(|e| format!("reading {source}: {e}"))?;
+        let track = read_wav(&bytes).map_err(|e| format!("{source}: {e}"))?;
+        log!(
+            "Audio: {source} ({:.1} s, {} Hz).",
+            track.duration_s(),
+            track.sample_rate
+        );
+        self.track = Some(track);
+        self.position_s = 0.0;
+        self.playing = false;
+        self.magnitudes.fill(0.0);
+        Ok(())
+    }
+
+    fn play(&mut self) {
+        if let Some(track) = &self.track {
+            if self.position_s >= track.duration_s() {
+                self.position_s = 0.0;
+            }
+            self.playing = true;
+        }
+    }
+
+    fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    fn seek(&mut self, time_s: f64) {
+        let duration = self.track.as_ref().map_or(0.0, Track::duration_s);
+        self.position_s = time_s.clamp(0.0, duration);
+    }
+
+    fn update(&mut self, frame_timestamp: f64) -> AudioFrame {
+        if let (true, Some(track), Some(last)) = (self.playing, &self.track, self.last_timestamp) {
+            self.position_s += (frame_timestamp - last).max(0.0) / 1000.0;
+            if self.position_s >= track.duration_s() {
+                self.position_s = track.duration_s();
+                self.playing = false;
+            }
+        }
+        self.last_timestamp = Some(frame_timestamp);
+        AudioFrame {
+            position_s: self.track.as_ref().map(|_| self.position_s),
+            playing: self.playing,
+            bands: self.analyse(),
+        }
+    }
+}
+
+/// Blackman window (alpha 0.16), as the analyser applies before its FFT.
+fn blackman(n: usize) -> f32 {
+    let x = TAU * n as f32 / FFT_SIZE as f32;
+    0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos()
+}
+
+/// In-place radix-2 FFT; the length must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -TAU / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let (tr, ti) = (re[b] * cos - im[b] * sin, re[b] * sin + im[b] * cos);
+                (re[b], im[b]) = (re[a] - tr, im[a] - ti);
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Decodes a RIFF WAV file: integer PCM (8, 16, 24 or 32 bit) or 32-bit float, any channel count.
+fn read_wav(bytes: &[u8]) -> Result<Track, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a WAV file".into());
+    }
+    let u16_at = |b: &[u8], i: usize| u16::from_le_bytes([b[i], b[i + 1]]);
+    let u32_at = |b: &[u8], i: usize| u32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]);
+
+    let (mut format, mut data) = (None, None);
+    let mut rest = &bytes[12..];
+    while rest.len() >= 8 {
+        let size = u32_at(rest, 4) as usize;
+        // Tolerate a truncated last chunk (files cut off while recording).
+        let body = rest.get(8..8 + size).unwrap_or_else(|| &rest[8..]);
+        match &rest[0..4] {
+            b"fmt " if body.len() >= 16 => {
+                let mut tag = u16_at(body, 0);
+                // WAVE_FORMAT_EXTENSIBLE: the real tag leads the subformat GUID.
+                if tag == 0xFFFE && body.len() >= 26 {
+                    tag = u16_at(body, 24);
+                }
+                format = Some((tag, u16_at(body, 2), u32_at(body, 4), u16_at(body, 14)));
+            }
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        rest = rest.get(8 + size + (size & 1)..).unwrap_or(&[]);
+    }
+    let (tag, channels, sample_rate, bits) = format.ok_or("no fmt chunk")?;
+    let data = data.ok_or("no data chunk")?;
+    if channels == 0 || sample_rate == 0 {
+        return Err("bad fmt chunk".into());
+    }
+
+    let decode: fn(&[u8]) -> f32 = match (tag, bits) {
+        (1, 8) => |b| (f32::from(b[0]) - 128.0) / 128.0,
+        (1, 16) => |b| f32::from(i16::from_le_bytes([b[0], b[1]])) / 32768.0,
+        (1, 24) => |b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0,
+        (1, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+        (3, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        _ => return Err(format!("unsupported sample format (tag {tag}, {bits} bit)")),
+    };
+    let sample_bytes = usize::from(bits / 8);
+    let frame_bytes = sample_bytes * usize::from(channels);
+    let samples = data
+        .chunks_exact(frame_bytes)
+        .map(|frame| {
+            frame.chunks_exact(sample_bytes).map(decode).sum::<f32>() / f32::from(channels)
+        })
+        .collect();
+    Ok(Track {
+        samples,
+        sample_rate: sample_rate as f32,
+    })
+}
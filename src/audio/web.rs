@@ -0,0 +1,237 @@
+//! Browser [AudioBackend]: Web Audio. The track is fetched and decoded into an `AudioBuffer`,
+//! played through an `AnalyserNode` to the speakers, and timed by the `AudioContext` clock.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{
+    AnalyserNode, AudioBuffer, AudioBufferSourceNode, AudioContext, AudioContextState,
+    AudioScheduledSourceNode,
+};
+
+use super::{
+    band_levels, AudioBackend, AudioFrame, FFT_SIZE, MAX_DECIBELS, MIN_DECIBELS, SMOOTHING,
+};
+
+/// The context clock advances in audio blocks, coarser than frames; positions are predicted from
+/// the frame timestamps and pulled towards it by this fraction of the difference per frame...
+const DRIFT_CORRECTION: f64 = 0.1;
+/// ...unless they are this far apart (seconds), e.g. after the tab was in the background.
+const MAX_DRIFT_S: f64 = 0.1;
+
+/// Context and analyser, created on the first load.
+struct Nodes {
+    context: AudioContext,
+    analyser: AnalyserNode,
+}
+
+pub struct WebAudio {
+    nodes: Option<Nodes>,
+    /// Set by the loading task, with the load it belongs to; picked up by the next update.
+    decoded: Rc<RefCell<Option<(u32, AudioBuffer)>>>,
+    /// Counts loads, so a slow fetch can't replace a later track.
+    load_count: u32,
+    buffer: Option<AudioBuffer>,
+    /// The playing source, and the context time at which it was (or would have been) at position 0.
+    source: Option<(AudioBufferSourceNode, f64)>,
+    /// Position while no source plays.
+    paused_at: f64,
+    /// Play requested; starts the track once it is decoded.
+    wants_play: bool,
+    /// Smoothed position and the frame timestamp it is for.
+    estimate: Option<(f64, f64)>,
+    /// Analyser output, one byte per frequency bin.
+    bins: Vec<u8>,
+}
+
+impl Default for WebAudio {
+    fn default() -> Self {
+        Self {
+            nodes: None,
+            decoded: Rc::new(RefCell::new(None)),
+            load_count: 0,
+            buffer: None,
+            source: None,
+            paused_at: 0.0,
+            wants_play: false,
+            estimate: None,
+            bins: vec![0; FFT_SIZE / 2],
+        }
+    }
+}
+
+impl WebAudio {
+    fn duration(&self) -> f64 {
+        self.buffer.as_ref().map_or(0.0, AudioBuffer::duration)
+    }
+
+    /// Creates the context and analyser if there are none yet.
+    fn nodes(&mut self) -> Result<&Nodes, JsValue> {
+        if self.nodes.is_none() {
+            let context = AudioContext::new()?;
+            let analyser = context.create_analyser()?;
+            analyser.set_fft_size(FFT_SIZE as u32);
+            analyser.set_smoothing_time_constant(f64::from(SMOOTHING));
+            analyser.set_min_decibels(f64::from(MIN_DECIBELS));
+            analyser.set_max_decibels(f64::from(MAX_DECIBELS));
+            analyser.connect_with_audio_node(&context.destination())?;
+            self.nodes = Some(Nodes { context, analyser });
+        }
+        Ok(self.nodes.as_ref().expect("created above"))
+    }
+
+    /// Starts a source at [WebAudio::paused_at] if playback is wanted and the track is decoded.
+    fn start_source(&mut self) {
+        if self.source.is_some() || !self.wants_play {
+            return;
+        }
+        let (Some(nodes), Some(buffer)) = (&self.nodes, &self.buffer) else {
+            return;
+        };
+        if self.paused_at >= buffer.duration() {
+            self.paused_at = 0.0;
+        }
+        let started = nodes.context.create_buffer_source().and_then(|source| {
+            source.set_buffer(Some(buffer));
+            source.connect_with_audio_node(&nodes.analyser)?;
+            source.start_with_when_and_grain_offset(0.0, self.paused_at)?;
+            Ok(source)
+        });
+        match started {
+            Ok(source) => {
+                self.source = Some((source, nodes.context.current_time() - self.paused_at));
+                self.estimate = None;
+            }
+            Err(e) => {
+                log_error!("Audio: starting playback failed: {e:?}");
+            }
+        }
+    }
+
+    /// Stops the playing source, keeping its position in [WebAudio::paused_at].
+    fn stop_source(&mut self) {
+        let Some((source, start)) = self.source.take() else {
+            return;
+        };
+        if let Some(nodes) = &self.nodes {
+            self.paused_at = (nodes.context.current_time() - start).clamp(0.0, self.duration());
+        }
+        AudioScheduledSourceNode::stop(&source).ok();
+        source.disconnect().ok();
+        self.estimate = None;
+    }
+}
+
+impl AudioBackend for WebAudio {
+    fn load(&mut self, source: &str) -> Result<(), String> {
+        let context = self
+            .nodes()
+            .map_err(|e| format!("Web Audio unavailable: {e:?}"))?
+            .context
+            .clone();
+        self.stop_source();
+        self.buffer = None;
+        self.paused_at = 0.0;
+        self.wants_play = false;
+        self.load_count += 1;
+
+        let (decoded, load, url) = (self.decoded.clone(), self.load_count, source.to_string());
+        spawn_local(async move {
+            match fetch_and_decode(&context, &url).await {
+                Ok(buffer) => {
+                    log!("Audio: {url} ({:.1} s).", buffer.duration());
+                    *decoded.borrow_mut() = Some((load, buffer));
+                }
+                Err(e) => {
+                    log_error!("Audio: {url}: {e:?}");
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn play(&mut self) {
+        self.wants_play = true;
+        if let Some(nodes) = &self.nodes {
+            // Suspended until the page has had a user gesture; resumes by itself once allowed.
+            nodes.context.resume().ok();
+        }
+        self.start_source();
+    }
+
+    fn pause(&mut self) {
+        self.wants_play = false;
+        self.stop_source();
+    }
+
+    fn seek(&mut self, time_s: f64) {
+        self.stop_source();
+        self.paused_at = time_s.clamp(0.0, self.duration());
+        self.start_source();
+    }
+
+    fn update(&mut self, frame_timestamp: f64) -> AudioFrame {
+        let decoded = self.decoded.borrow_mut().take();
+        if let Some((_, buffer)) = decoded.filter(|(load, _)| *load == self.load_count) {
+            self.buffer = Some(buffer);
+            self.start_source();
+        }
+        let Some(nodes) = &self.nodes else {
+            return AudioFrame::default();
+        };
+
+        let running = nodes.context.state() == AudioContextState::Running;
+        let duration = self.duration();
+        let mut position = self.paused_at;
+        if let Some((_, start)) = &self.source {
+            let clock = nodes.context.current_time() - start;
+            position = match self.estimate {
+                Some((estimate, timestamp)) if running => {
+                    let predicted = estimate + (frame_timestamp - timestamp) / 1000.0;
+                    if (clock - predicted).abs() > MAX_DRIFT_S {
+                        clock
+                    } else {
+                        predicted + (clock - predicted) * DRIFT_CORRECTION
+                    }
+                }
+                _ => clock,
+            }
+            .clamp(0.0, duration);
+            self.estimate = Some((position, frame_timestamp));
+            if clock >= duration {
+                self.wants_play = false;
+                self.stop_source();
+                position = duration;
+            }
+        }
+
+        let nodes = self.nodes.as_ref().expect("checked above");
+        nodes.analyser.get_byte_frequency_data(&mut self.bins);
+        let levels: Vec<f32> = self
+            .bins
+            .iter()
+            .map(|&level| f32::from(level) / 255.0)
+            .collect();
+        AudioFrame {
+            position_s: self.buffer.as_ref().map(|_| position),
+            playing: running && self.source.is_some(),
+            bands: band_levels(&levels, nodes.context.sample_rate()),
+        }
+    }
+}
+
+async fn fetch_and_decode(context: &AudioContext, url: &str) -> Result<AudioBuffer, JsValue> {
+    let window = web_sys::window().ok_or("no window")?;
+    let response: web_sys::Response = JsFuture::from(window.fetch_with_str(url))
+        .await?
+        .dyn_into()?;
+    if !response.ok() {
+        return Err(format!("HTTP {}", response.status()).into());
+    }
+    let data: js_sys::ArrayBuffer = JsFuture::from(response.array_buffer()?).await?.dyn_into()?;
+    JsFuture::from(context.decode_audio_data(&data)?)
+        .await?
+        .dyn_into()
+}
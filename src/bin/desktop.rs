@@ -0,0 +1,19 @@
+//! Runs the demo in a native window (winit + wgpu), for debugging with native GPU tools.
+//!
+//! Usage: `cargo run --bin desktop`. Click to capture the mouse, Escape to release it.
+
+// Dependencies belong to the library; the binary only forwards to it.
+#![allow(unused_crate_dependencies)]
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    if let Err(e) = wasm2::run_native() {
+        eprintln!("desktop: {e}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    eprintln!("desktop is a native tool; build it for the host target.");
+}
@@ -0,0 +1,54 @@
+//! Renders a fixed-timestep PNG sequence of the demo (native only).
+//!
+//! Usage: `cargo run --release --bin render_frames -- [--frames N] [--fps F] [--width W]
+//! [--height H] [--supersample S] [--out DIR] [--input recording.json] [--sequence sequence.json]
+//! [--audio music.wav] [--tonemapper aces|agx|reinhard|tony_mc_mapface|none] [--lut grade.cube]
+//! [--tony-mc-mapface-lut tony_mc_mapface.dds]`
+
+// Dependencies belong to the library; the binary only forwards arguments.
+#![allow(unused_crate_dependencies)]
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    use wasm2::offline::{render_sequence, OfflineConfig};
+
+    fn parse<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+        value.and_then(|v| v.parse().ok()).unwrap_or_else(|| {
+            eprintln!("{flag} expects a value");
+            std::process::exit(2);
+        })
+    }
+
+    let mut config = OfflineConfig::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--frames" => config.frames = parse(&flag, args.next()),
+            "--fps" => config.fps = parse(&flag, args.next()),
+            "--width" => config.width = parse(&flag, args.next()),
+            "--height" => config.height = parse(&flag, args.next()),
+            "--supersample" => config.supersample = parse(&flag, args.next()),
+            "--out" => config.output_dir = parse(&flag, args.next()),
+            "--input" => config.input = Some(parse(&flag, args.next())),
+            "--sequence" => config.sequence = Some(parse(&flag, args.next())),
+            "--audio" => config.audio = Some(parse(&flag, args.next())),
+            "--tonemapper" => config.tonemapper = parse(&flag, args.next()),
+            "--lut" => config.grading_lut = Some(parse(&flag, args.next())),
+            "--tony-mc-mapface-lut" => config.tony_mc_mapface_lut = Some(parse(&flag, args.next())),
+            _ => {
+                eprintln!("unknown argument: {flag}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if let Err(e) = render_sequence(&config) {
+        eprintln!("render_frames: {e}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    eprintln!("render_frames is a native tool; build it for the host target.");
+}
@@ -4,7 +4,52 @@ use crate::projection::perspective_infinite_reversed_z;
 use crate::stereo_camera::Eye;
 use crate::view::ViewState;
 
-/// Camera with jitter support for TAA. Uses inverted infinite (reversed Z) projection; `far` is ignored.
+/// Scene scale before tonemapping for a camera at `ev100` (see [CameraExposure::ev100]),
+/// brightened by `compensation` stops. Saturation based: the luminance that just saturates the
+/// sensor maps to 1.
+pub fn exposure_from_ev100(ev100: f32, compensation: f32) -> f32 {
+    compensation.exp2() / (1.2 * ev100.exp2())
+}
+
+/// Manual exposure settings, as on a physical camera. The defaults (f/1.2, 1/60 s, ISO 6400) give
+/// about the 0.6 scale the screen pass used before exposure control.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraExposure {
+    /// f-number; each full stop (×√2) halves the light.
+    pub aperture: f32,
+    /// Shutter time in seconds.
+    pub shutter_s: f32,
+    pub iso: f32,
+    /// Stops added on top (also applied to auto-exposure).
+    pub compensation: f32,
+}
+
+impl Default for CameraExposure {
+    fn default() -> Self {
+        Self {
+            aperture: 1.2,
+            shutter_s: 1.0 / 60.0,
+            iso: 6400.0,
+            compensation: 0.0,
+        }
+    }
+}
+
+impl CameraExposure {
+    /// Exposure value at ISO 100 for these settings: higher means less light reaches the image.
+    pub fn ev100(&self) -> f32 {
+        (self.aperture * self.aperture / self.shutter_s * 100.0 / self.iso).log2()
+    }
+
+    /// Scene scale before tonemapping (see [exposure_from_ev100]).
+    pub fn exposure(&self) -> f32 {
+        exposure_from_ev100(self.ev100(), self.compensation)
+    }
+}
+
+/// Camera with jitter support for TAA. Uses inverted infinite (reversed Z) projection;
+/// `far` is ignored.
+#[derive(Clone)]
 pub struct Camera {
     jitter_x: f32,
     jitter_y: f32,
@@ -22,6 +67,8 @@ pub struct Camera {
     previous_view_projection: Mat4,
     view_projection_no_jitter: Mat4,
     previous_view_projection_no_jitter: Mat4,
+    /// Manual exposure; the screen pass uses it unless auto-exposure is on.
+    pub exposure: CameraExposure,
 }
 
 impl Camera {
@@ -42,6 +89,7 @@ impl Camera {
             previous_view_projection: Mat4::IDENTITY,
             view_projection_no_jitter: Mat4::IDENTITY,
             previous_view_projection_no_jitter: Mat4::IDENTITY,
+            exposure: CameraExposure::default(),
         };
         c.set_fovy_from_fov();
         c.update_projection();
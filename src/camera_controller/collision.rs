@@ -0,0 +1,358 @@
+use crate::chunk::Chunk;
+use glam::Vec3;
+
+/// Most surfaces one move slides along before it stops where it is.
+const MAX_SLIDES: usize = 4;
+/// Sphere tracing steps per slide.
+const MAX_STEPS: usize = 64;
+/// Gap the sphere keeps from surfaces, so the next frame doesn't start inside them.
+const SKIN: f32 = 0.01;
+/// Gaps below this count as touching.
+const CONTACT: f32 = 1e-3;
+/// Offset for the central differences that estimate surface normals.
+const NORMAL_STEP: f32 = 0.01;
+/// Surfaces whose normal points at least this far up are floors (about 45°).
+const FLOOR_NORMAL_Y: f32 = 0.7;
+
+/// Geometry the camera can't pass through, as a signed distance: positive outside, negative inside.
+/// It may underestimate the distance (sphere tracing stays correct), never overestimate it.
+pub trait Solid {
+    fn distance(&self, point: Vec3) -> f32;
+}
+
+/// The raymarched warehouse: a CPU port of `warehouse_sdf` (`src/wgsl/warehouse.wgsl`).
+///
+/// It has the room, pillars, ceiling bars and pipes. The lights, the rebar inside the pillars and
+/// the centimetre-sized holes in the concrete are left out.
+pub struct WarehouseSolid;
+
+impl Solid for WarehouseSolid {
+    fn distance(&self, p: Vec3) -> f32 {
+        let pillar_pos = rep(p + Vec3::new(-15.0, -2.5, 5.0), Vec3::new(15.0, 0.0, 20.0));
+        let main_walls = -sd_box(p + Vec3::new(0.0, -2.5, 0.0), Vec3::new(20.0, 5.0, 40.0));
+        let pillars = sd_box(pillar_pos, Vec3::new(0.5, 5.0, 0.5));
+        let bars1 = sd_box(
+            rep(p + Vec3::new(0.0, -7.0, 0.0), Vec3::new(15.0, 1000.0, 0.0)),
+            Vec3::new(0.5, 0.5, 40.0),
+        );
+        let bars2 = sd_box(
+            rep(
+                p + Vec3::new(0.0, -7.0, 5.0),
+                Vec3::new(1000.0, 1000.0, 20.0),
+            ),
+            Vec3::new(20.0, 0.5, 0.5),
+        );
+        let bars_and_pillars = smooth_union(bars1.min(bars2), pillars, 0.25);
+        let room = smooth_union(main_walls, bars_and_pillars, 1.0 / 16.0);
+
+        let pipes = sd_vertical_capsule(
+            Vec3::new(p.x, p.z, p.y) - Vec3::new(18.0, -50.0, 6.0),
+            200.0,
+            0.10,
+        )
+        .min(sd_vertical_capsule(
+            Vec3::new(p.x, p.z, p.y) - Vec3::new(18.0, -50.0, 5.6),
+            200.0,
+            0.10,
+        ));
+        room.min(pipes)
+    }
+}
+
+fn sd_box(p: Vec3, b: Vec3) -> f32 {
+    let q = p.abs() - b;
+    q.max(Vec3::ZERO).length() + q.max_element().min(0.0)
+}
+
+fn sd_vertical_capsule(mut p: Vec3, h: f32, r: f32) -> f32 {
+    p.y -= p.y.clamp(0.0, h);
+    p.length() - r
+}
+
+/// The shader's `opRep`, truncating remainder and all. A zero period leaves that axis alone: the
+/// GPU's remainder by zero is NaN, which its `min` / `max` then ignore, so those boxes are
+/// unbounded along it (they span the room either way).
+fn rep(p: Vec3, c: Vec3) -> Vec3 {
+    let axis = |p: f32, c: f32| {
+        if c == 0.0 {
+            p
+        } else {
+            (p + 0.5 * c) % c - 0.5 * c
+        }
+    };
+    Vec3::new(axis(p.x, c.x), axis(p.y, c.y), axis(p.z, c.z))
+}
+
+fn smooth_union(d1: f32, d2: f32, k: f32) -> f32 {
+    let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+    d2 + (d1 - d2) * h - k * h * (1.0 - h)
+}
+
+/// A [Chunk]'s solid voxels as cubes of `voxel_size`, voxel (0, 0, 0) spanning `origin` to
+/// `origin + voxel_size` (the chunk mesh's layout, scaled and moved).
+pub struct ChunkSolid {
+    chunk: Chunk,
+    origin: Vec3,
+    voxel_size: f32,
+}
+
+impl ChunkSolid {
+    /// Voxels searched around the point each way; farther away, the distance is reported as this
+    /// many voxels (a lower bound, which is all sphere tracing needs).
+    const SEARCH: i32 = 2;
+
+    pub const fn new(chunk: Chunk, origin: Vec3, voxel_size: f32) -> Self {
+        Self {
+            chunk,
+            origin,
+            voxel_size,
+        }
+    }
+}
+
+impl Solid for ChunkSolid {
+    fn distance(&self, point: Vec3) -> f32 {
+        let local = (point - self.origin) / self.voxel_size;
+        let cell = local.floor().as_ivec3();
+        let size = [self.chunk.nx, self.chunk.ny, self.chunk.nz].map(|n| n as i32);
+        let range = |axis: usize| {
+            (cell[axis] - Self::SEARCH).max(0)..=(cell[axis] + Self::SEARCH).min(size[axis] - 1)
+        };
+        let mut nearest = Self::SEARCH as f32;
+        for z in range(2) {
+            for y in range(1) {
+                for x in range(0) {
+                    if self.chunk.get(x as usize, y as usize, z as usize) != 0 {
+                        let center = Vec3::new(x as f32, y as f32, z as f32) + 0.5;
+                        nearest = nearest.min(sd_box(local - center, Vec3::splat(0.5)));
+                    }
+                }
+            }
+        }
+        nearest * self.voxel_size
+    }
+}
+
+/// Where a sphere ended up after [CameraCollision::slide].
+#[derive(Clone, Copy, Debug)]
+pub struct Slide {
+    pub position: Vec3,
+    /// The velocity less its components into the surfaces touched on the way.
+    pub velocity: Vec3,
+    /// Whether one of those surfaces was a floor (see [FLOOR_NORMAL_Y]).
+    pub floor: bool,
+}
+
+/// Keeps a sphere of [CameraCollision::radius] out of a set of [Solid]s, sliding it along the
+/// surfaces it runs into.
+pub struct CameraCollision {
+    pub solids: Vec<Box<dyn Solid>>,
+    pub radius: f32,
+    /// Off, the sphere moves freely.
+    pub enabled: bool,
+}
+
+impl CameraCollision {
+    pub fn new(solids: Vec<Box<dyn Solid>>) -> Self {
+        Self {
+            solids,
+            radius: 0.3,
+            enabled: true,
+        }
+    }
+
+    /// Distance to the nearest solid's surface (negative inside one).
+    pub fn distance(&self, point: Vec3) -> f32 {
+        self.solids
+            .iter()
+            .map(|solid| solid.distance(point))
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Direction out of the nearest surface, from the distance's gradient.
+    pub fn normal(&self, point: Vec3) -> Vec3 {
+        let gradient = |axis: Vec3| {
+            self.distance(point + axis * NORMAL_STEP) - self.distance(point - axis * NORMAL_STEP)
+        };
+        Vec3::new(gradient(Vec3::X), gradient(Vec3::Y), gradient(Vec3::Z)).normalize_or(Vec3::Y)
+    }
+
+    /// Moves the sphere at `from` by `velocity` for `dt` seconds: it stops at the first surface
+    /// in the way, carries on along it with what's left of the motion, and so on. A sphere that
+    /// starts inside a solid is pushed out first.
+    pub fn slide(&self, from: Vec3, velocity: Vec3, dt: f32) -> Slide {
+        if !self.enabled {
+            return Slide {
+                position: from + velocity * dt,
+                velocity,
+                floor: false,
+            };
+        }
+        let mut slide = Slide {
+            position: self.push_out(from),
+            velocity,
+            floor: false,
+        };
+        let mut motion = velocity * dt;
+        for _ in 0..MAX_SLIDES {
+            let Some(direction) = motion.try_normalize() else {
+                break;
+            };
+            let length = motion.length();
+            let (travel, normal) = self.cast(slide.position, direction, length);
+            slide.position += direction * travel;
+            let Some(normal) = normal else { break };
+            slide.floor |= normal.y >= FLOOR_NORMAL_Y;
+            motion = clip(direction * (length - travel), normal);
+            slide.velocity = clip(slide.velocity, normal);
+        }
+        slide.position = self.push_out(slide.position);
+        slide
+    }
+
+    /// Sphere traces from `from` along `direction` for up to `max`: how far the sphere gets, and
+    /// the normal of the surface that stopped it. Surfaces it is leaving don't stop it.
+    fn cast(&self, from: Vec3, direction: Vec3, max: f32) -> (f32, Option<Vec3>) {
+        let mut travel = 0.0;
+        for _ in 0..MAX_STEPS {
+            let point = from + direction * travel;
+            let mut step = self.distance(point) - self.radius - SKIN;
+            if step < CONTACT {
+                let normal = self.normal(point);
+                if direction.dot(normal) < 0.0 {
+                    return (travel, Some(normal));
+                }
+                // Moving along or away from the surface: a skin's width at a time stays clear of
+                // the radius, where the gap alone would crawl (walking keeps touching the floor).
+                step = SKIN;
+            }
+            travel += step;
+            if travel >= max {
+                return (max, None);
+            }
+        }
+        (travel, None)
+    }
+
+    /// Moves a sphere overlapping a solid out along its normal, to [SKIN] off the surface.
+    fn push_out(&self, mut position: Vec3) -> Vec3 {
+        for _ in 0..MAX_SLIDES {
+            let depth = self.radius + SKIN - self.distance(position);
+            if depth <= CONTACT {
+                break;
+            }
+            position += self.normal(position) * depth;
+        }
+        position
+    }
+}
+
+/// `vector` without its component into a surface facing `normal`.
+fn clip(vector: Vec3, normal: Vec3) -> Vec3 {
+    vector - normal * vector.dot(normal).min(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Everything on the far side of a plane through `normal * offset` is solid.
+    struct HalfSpace {
+        normal: Vec3,
+        offset: f32,
+    }
+
+    impl Solid for HalfSpace {
+        fn distance(&self, point: Vec3) -> f32 {
+            point.dot(self.normal) - self.offset
+        }
+    }
+
+    /// A floor at y = 0 and a wall at x = 1.
+    fn room() -> CameraCollision {
+        CameraCollision::new(vec![
+            Box::new(HalfSpace {
+                normal: Vec3::Y,
+                offset: 0.0,
+            }),
+            Box::new(HalfSpace {
+                normal: Vec3::NEG_X,
+                offset: -1.0,
+            }),
+        ])
+    }
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, 2.0 * CONTACT),
+            "{actual} != {expected}"
+        );
+    }
+
+    /// An 8³ chunk with voxel (1, 1, 1) solid, in half-unit voxels from (10, 0, 0).
+    fn one_voxel() -> ChunkSolid {
+        let mut chunk = Chunk::new(8, 8, 8);
+        chunk.set(1, 1, 1, 1);
+        ChunkSolid::new(chunk, Vec3::new(10.0, 0.0, 0.0), 0.5)
+    }
+
+    #[test]
+    fn chunk_distance_is_negative_inside_a_voxel() {
+        let distance = one_voxel().distance(Vec3::new(10.75, 0.75, 0.75));
+        assert!((distance + 0.25).abs() < 1e-6, "{distance}");
+    }
+
+    #[test]
+    fn chunk_distance_outside_is_to_the_nearest_face() {
+        let distance = one_voxel().distance(Vec3::new(11.5, 0.75, 0.75));
+        assert!((distance - 0.5).abs() < 1e-6, "{distance}");
+    }
+
+    #[test]
+    fn chunk_distance_past_the_search_window_is_its_lower_bound() {
+        let solid = one_voxel();
+        let bound = ChunkSolid::SEARCH as f32 * 0.5;
+        // 4.5 voxels from the solid one, inside the chunk, and far outside it.
+        for point in [Vec3::new(13.25, 0.75, 0.75), Vec3::new(100.0, 0.75, 0.75)] {
+            let distance = solid.distance(point);
+            assert!((distance - bound).abs() < 1e-6, "{distance}");
+        }
+    }
+
+    #[test]
+    fn stops_at_a_wall() {
+        let collision = room();
+        let slide = collision.slide(Vec3::new(0.0, 1.0, 0.0), Vec3::new(5.0, 0.0, 0.0), 0.5);
+        let stop = 1.0 - collision.radius - SKIN;
+        assert_near(slide.position, Vec3::new(stop, 1.0, 0.0));
+        assert_near(slide.velocity, Vec3::ZERO);
+        assert!(!slide.floor);
+    }
+
+    #[test]
+    fn slides_along_a_wall() {
+        let collision = room();
+        let slide = collision.slide(Vec3::new(0.5, 1.0, 0.0), Vec3::new(1.0, 0.0, 1.0), 0.5);
+        let stop = 1.0 - collision.radius - SKIN;
+        assert_near(slide.position, Vec3::new(stop, 1.0, 0.5));
+        assert_near(slide.velocity, Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn pushes_out_of_a_solid_it_starts_in() {
+        let collision = room();
+        let slide = collision.slide(Vec3::new(1.2, 1.0, 0.0), Vec3::ZERO, 0.1);
+        let stop = 1.0 - collision.radius - SKIN;
+        assert_near(slide.position, Vec3::new(stop, 1.0, 0.0));
+    }
+
+    #[test]
+    fn reports_landing_on_a_floor() {
+        let collision = room();
+        let slide = collision.slide(Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -5.0, 0.0), 0.1);
+        let rest = collision.radius + SKIN;
+        assert_near(slide.position, Vec3::new(0.0, rest, 0.0));
+        assert_near(slide.velocity, Vec3::ZERO);
+        assert!(slide.floor);
+    }
+}
@@ -0,0 +1,158 @@
+use super::{delta_s, direction, yaw_pitch, zoom_fov, CameraCollision, CameraController};
+use crate::scene::{CameraDescriptor, FrameInput};
+use glam::Vec3;
+
+/// Free flight: yaw and pitch from the look axes, velocity easing towards the `move_*` axes.
+///
+/// With [FlyController::collision] it slides along what it runs into, and can walk instead: on
+/// the floor under gravity, jumping with the `jump` action (`toggle_walk` switches).
+pub struct FlyController {
+    /// Units per second at full `move_*` deflection.
+    pub speed: f32,
+    /// Speed multiplier while the `boost` action is held.
+    pub boost: f32,
+    /// How quickly the velocity reaches the input's (per second; higher is snappier, 0 = never).
+    pub acceleration: f32,
+    /// How quickly the camera coasts to a stop without input (per second).
+    pub damping: f32,
+    /// Pitch limit either side of the horizon (radians).
+    pub max_pitch: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// What the camera can't pass through; `None` flies through everything.
+    pub collision: Option<CameraCollision>,
+    /// Walk rather than fly (while colliding).
+    pub walk: bool,
+    /// Walking: camera height above the feet (the collision sphere's bottom).
+    pub eye_height: f32,
+    /// Walking: downward acceleration (units per second squared) and a jump's upward speed.
+    pub gravity: f32,
+    pub jump_speed: f32,
+    velocity: Vec3,
+    /// Walking, whether the last move ended on a floor (so a jump may start).
+    grounded: bool,
+}
+
+impl Default for FlyController {
+    fn default() -> Self {
+        Self {
+            speed: 5.0,
+            boost: 2.5,
+            acceleration: 12.0,
+            damping: 8.0,
+            max_pitch: 1.5,
+            yaw: 0.0,
+            pitch: 0.0,
+            collision: None,
+            walk: false,
+            eye_height: 1.7,
+            gravity: 20.0,
+            jump_speed: 7.0,
+            velocity: Vec3::ZERO,
+            grounded: false,
+        }
+    }
+}
+
+impl FlyController {
+    /// Whether the camera walks: [FlyController::walk] with collision on.
+    pub fn walking(&self) -> bool {
+        self.walk
+            && self
+                .collision
+                .as_ref()
+                .is_some_and(|collision| collision.enabled)
+    }
+}
+
+impl CameraController for FlyController {
+    fn update(&mut self, camera: &mut CameraDescriptor, input: &FrameInput) {
+        // Axes are already per frame (see crate::input): look in radians, move in
+        // seconds of travel.
+        let actions = &input.actions;
+        if actions.pressed("toggle_walk") && self.collision.is_some() {
+            self.walk = !self.walk;
+            log!("Camera: {}", if self.walk { "walk" } else { "fly" });
+        }
+        self.yaw -= actions.axis("look_x");
+        self.pitch = (self.pitch + actions.axis("look_y")).clamp(-self.max_pitch, self.max_pitch);
+        zoom_fov(camera, input);
+
+        let dt = delta_s(input);
+        if dt > 0.0 {
+            let forward = Vec3::new(self.yaw.sin(), 0.0, self.yaw.cos());
+            let right = Vec3::new(-self.yaw.cos(), 0.0, self.yaw.sin());
+            let speed = if actions.held("boost") {
+                self.speed * self.boost
+            } else {
+                self.speed
+            };
+            let walking = self.walking();
+            let mut movement =
+                forward * actions.axis("move_forward") + right * actions.axis("move_right");
+            if !walking {
+                movement += Vec3::Y * actions.axis("move_up");
+            }
+            let wanted = movement * speed / dt;
+            let rate = if wanted == Vec3::ZERO {
+                self.damping
+            } else {
+                self.acceleration
+            };
+            let eased = self.velocity.lerp(wanted, 1.0 - (-rate * dt).exp());
+            self.velocity = if walking {
+                // Steering eases like flying; rising and falling are up to gravity and jumps.
+                let vertical = if self.grounded && actions.pressed("jump") {
+                    self.jump_speed
+                } else {
+                    self.velocity.y - self.gravity * dt
+                };
+                Vec3::new(eased.x, vertical, eased.z)
+            } else {
+                eased
+            };
+
+            match &self.collision {
+                Some(collision) => {
+                    // Walking, the sphere is the body, resting on the floor with the eyes above it.
+                    let eyes = if walking {
+                        Vec3::Y * (self.eye_height - collision.radius)
+                    } else {
+                        Vec3::ZERO
+                    };
+                    let slide = collision.slide(camera.position - eyes, self.velocity, dt);
+                    camera.position = slide.position + eyes;
+                    self.velocity = slide.velocity;
+                    self.grounded = slide.floor;
+                }
+                None => camera.position += self.velocity * dt,
+            }
+        }
+        self.place(camera);
+    }
+
+    fn sync(&mut self, camera: &CameraDescriptor) {
+        (self.yaw, self.pitch) = yaw_pitch(camera.target - camera.position);
+        self.pitch = self.pitch.clamp(-self.max_pitch, self.max_pitch);
+        self.velocity = Vec3::ZERO;
+        self.grounded = false;
+    }
+
+    /// Aims `camera` along the controller's yaw and pitch from where it is.
+    fn place(&self, camera: &mut CameraDescriptor) {
+        camera.target = camera.position + direction(self.yaw, self.pitch);
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.speed, 0.5..=20.0).text("Speed"));
+        ui.add(egui::Slider::new(&mut self.acceleration, 1.0..=30.0).text("Acceleration"));
+        ui.add(egui::Slider::new(&mut self.damping, 1.0..=30.0).text("Damping"));
+        if let Some(collision) = &mut self.collision {
+            ui.checkbox(&mut collision.enabled, "Collide");
+            ui.add_enabled(
+                collision.enabled,
+                egui::Checkbox::new(&mut self.walk, "Walk"),
+            );
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Camera controllers: turn each frame's input into camera moves on a scene's [CameraDescriptor].
+//!
+//! - [FlyController]: free flight; looks with `look_x` / `look_y`, moves with `move_*` (speeding up
+//!   and slowing down smoothly), `zoom` narrows the FOV. Given a [CameraCollision] it slides along
+//!   [Solid]s (the warehouse, voxel chunks) instead of passing through, and can walk and jump.
+//! - [OrbitController]: circles a target; drags swing around it, `move_forward` and `zoom` dolly,
+//!   `move_right` / `move_up` pan the target.
+//! - [PathController]: rides a Catmull-Rom spline through a list of points at a constant speed.
+//!
+//! A scene may drive one directly, or keep a [CameraRig] with one of each and switch at runtime
+//! (the `camera_mode` action, or the UI). Controllers pick up wherever the camera is when they
+//! take over ([CameraController::sync]), so switching, a sequence's camera tracks
+//! ([crate::sequencer]) or the UI moving the camera never make it jump back.
+
+mod collision;
+mod fly;
+mod orbit;
+mod path;
+
+pub use collision::{CameraCollision, ChunkSolid, Slide, Solid, WarehouseSolid};
+pub use fly::FlyController;
+pub use orbit::OrbitController;
+pub use path::PathController;
+
+use crate::scene::{CameraDescriptor, FrameInput};
+use glam::Vec3;
+use std::f32::consts::PI;
+
+/// Horizontal FOV range for the `zoom` axis (radians; the UI slider's range).
+pub const MIN_FOV: f32 = 30.0 * PI / 180.0;
+pub const MAX_FOV: f32 = 150.0 * PI / 180.0;
+
+pub trait CameraController {
+    /// Moves `camera` for this frame's `input`.
+    fn update(&mut self, camera: &mut CameraDescriptor, input: &FrameInput);
+
+    /// Takes over from wherever `camera` is (left there by another controller, a sequence or the
+    /// UI), so the next update continues from it.
+    fn sync(&mut self, camera: &CameraDescriptor);
+
+    /// Puts `camera` where the controller's own state says, without input (e.g. after a reset).
+    fn place(&self, camera: &mut CameraDescriptor);
+
+    /// Adds the controller's settings to the [crate::ui] overlay.
+    fn ui(&mut self, _ui: &mut egui::Ui) {}
+}
+
+/// View direction for a yaw (around +Y, 0 = +Z) and pitch (up from the horizon).
+pub fn direction(yaw: f32, pitch: f32) -> Vec3 {
+    Vec3::new(
+        pitch.cos() * yaw.sin(),
+        pitch.sin(),
+        pitch.cos() * yaw.cos(),
+    )
+}
+
+/// Yaw and pitch of `direction` (the inverse of [direction]); (0, 0) for a zero vector.
+pub fn yaw_pitch(direction: Vec3) -> (f32, f32) {
+    let direction = direction.normalize_or_zero();
+    (
+        direction.x.atan2(direction.z),
+        direction.y.clamp(-1.0, 1.0).asin(),
+    )
+}
+
+/// Zooming in (positive `zoom`) narrows the FOV proportionally, so pinches feel the same
+/// at any FOV.
+fn zoom_fov(camera: &mut CameraDescriptor, input: &FrameInput) {
+    camera.fov = (camera.fov * (-input.actions.axis("zoom")).exp()).clamp(MIN_FOV, MAX_FOV);
+}
+
+/// Frame time in seconds.
+fn delta_s(input: &FrameInput) -> f32 {
+    (input.delta_time / 1000.0) as f32
+}
+
+/// Which of a [CameraRig]'s controllers drives the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraMode {
+    Fly,
+    Orbit,
+    Path,
+}
+
+impl CameraMode {
+    pub const ALL: [Self; 3] = [Self::Fly, Self::Orbit, Self::Path];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Fly => "Fly",
+            Self::Orbit => "Orbit",
+            Self::Path => "Path",
+        }
+    }
+}
+
+/// One controller of each kind, and the one in use. The `camera_mode` action steps through them
+/// (skipping the path while it has no points).
+pub struct CameraRig {
+    pub fly: FlyController,
+    pub orbit: OrbitController,
+    pub path: PathController,
+    mode: CameraMode,
+    /// Camera position and target as the active controller left them; anything else means
+    /// something else moved the camera since, and the controller syncs to it.
+    placed: Option<(Vec3, Vec3)>,
+}
+
+impl CameraRig {
+    pub const fn new(
+        mode: CameraMode,
+        fly: FlyController,
+        orbit: OrbitController,
+        path: PathController,
+    ) -> Self {
+        Self {
+            fly,
+            orbit,
+            path,
+            mode,
+            placed: None,
+        }
+    }
+
+    pub const fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Hands the camera to `mode`'s controller, which continues from where the camera is.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.placed = None;
+            log!("Camera: {}", mode.name());
+        }
+    }
+
+    /// Switches to `mode` and moves `camera` to that controller's own placement (a scene's opening
+    /// shot, say) instead of continuing from the camera.
+    pub fn reset(&mut self, mode: CameraMode, camera: &mut CameraDescriptor) {
+        self.mode = mode;
+        self.controller().place(camera);
+        self.placed = Some((camera.position, camera.target));
+    }
+
+    pub fn controller(&mut self) -> &mut dyn CameraController {
+        match self.mode {
+            CameraMode::Fly => &mut self.fly,
+            CameraMode::Orbit => &mut self.orbit,
+            CameraMode::Path => &mut self.path,
+        }
+    }
+
+    /// Switches on the `camera_mode` action, then moves `camera` with the active controller.
+    pub fn update(&mut self, camera: &mut CameraDescriptor, input: &FrameInput) {
+        if input.actions.pressed("camera_mode") {
+            let next = CameraMode::ALL
+                .iter()
+                .cycle()
+                .skip_while(|mode| **mode != self.mode)
+                .skip(1)
+                .find(|mode| **mode != CameraMode::Path || !self.path.is_empty());
+            if let Some(&mode) = next {
+                self.set_mode(mode);
+            }
+        }
+        if self.placed != Some((camera.position, camera.target)) {
+            self.controller().sync(camera);
+        }
+        self.controller().update(camera, input);
+        self.placed = Some((camera.position, camera.target));
+    }
+
+    /// Mode picker and the active controller's settings.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let mut mode = self.mode;
+        ui.horizontal(|ui| {
+            ui.label("Camera");
+            for option in CameraMode::ALL {
+                if option != CameraMode::Path || !self.path.is_empty() {
+                    ui.radio_value(&mut mode, option, option.name());
+                }
+            }
+        });
+        self.set_mode(mode);
+        self.controller().ui(ui);
+    }
+}
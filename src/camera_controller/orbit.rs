@@ -0,0 +1,81 @@
+use super::{delta_s, direction, yaw_pitch, CameraController};
+use crate::scene::{CameraDescriptor, FrameInput};
+use glam::Vec3;
+
+/// Circles [OrbitController::target] at [OrbitController::distance]. Dragging the view left swings
+/// the camera right around the target, like grabbing the scene.
+pub struct OrbitController {
+    pub target: Vec3,
+    /// Camera direction from the target (see [super::direction]).
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Units per second at full `move_forward` deflection.
+    pub dolly_speed: f32,
+    /// Target movement per second at full `move_right` / `move_up` deflection, in distances.
+    pub pan_speed: f32,
+    /// Yaw the camera drifts by per second on its own (radians).
+    pub auto_rotate: f32,
+}
+
+impl Default for OrbitController {
+    fn default() -> Self {
+        Self {
+            target: Vec3::ZERO,
+            yaw: 0.0,
+            pitch: 0.3,
+            distance: 10.0,
+            min_pitch: -1.3,
+            max_pitch: 1.3,
+            min_distance: 2.0,
+            max_distance: 50.0,
+            dolly_speed: 5.0,
+            pan_speed: 0.5,
+            auto_rotate: 0.0,
+        }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, camera: &mut CameraDescriptor, input: &FrameInput) {
+        let actions = &input.actions;
+        self.yaw -= actions.axis("look_x") + self.auto_rotate * delta_s(input);
+        self.pitch = (self.pitch - actions.axis("look_y")).clamp(self.min_pitch, self.max_pitch);
+        let dolly = actions.axis("move_forward") * self.dolly_speed;
+        self.distance = ((self.distance - dolly) * (-actions.axis("zoom")).exp())
+            .clamp(self.min_distance, self.max_distance);
+
+        // Pan in the view plane: right along the screen, up along world up.
+        let right = Vec3::new(-self.yaw.cos(), 0.0, self.yaw.sin());
+        let pan = right * actions.axis("move_right") + Vec3::Y * actions.axis("move_up");
+        self.target += pan * self.pan_speed * self.distance;
+        self.place(camera);
+    }
+
+    /// Keeps the camera's position and view direction: the new target is where the camera looks,
+    /// at the current distance unless its own target is far enough away.
+    fn sync(&mut self, camera: &CameraDescriptor) {
+        let view = camera.target - camera.position;
+        let length = view.length();
+        if length >= self.min_distance {
+            self.distance = length.min(self.max_distance);
+        }
+        self.target = camera.position + view.normalize_or(Vec3::Z) * self.distance;
+        let (yaw, pitch) = yaw_pitch(-view);
+        (self.yaw, self.pitch) = (yaw, pitch.clamp(self.min_pitch, self.max_pitch));
+    }
+
+    fn place(&self, camera: &mut CameraDescriptor) {
+        camera.target = self.target;
+        camera.position = self.target + direction(self.yaw, self.pitch) * self.distance;
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.auto_rotate, -1.0..=1.0).text("Auto rotate"));
+        ui.add(egui::Slider::new(&mut self.pan_speed, 0.0..=2.0).text("Pan speed"));
+    }
+}
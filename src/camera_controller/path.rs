@@ -0,0 +1,176 @@
+use super::{delta_s, zoom_fov, CameraController};
+use crate::scene::{CameraDescriptor, FrameInput};
+use glam::Vec3;
+
+/// Arc length samples per spline segment, to travel at a constant speed.
+const SAMPLES_PER_SEGMENT: usize = 16;
+/// How far ahead along the path (units) the camera looks without [PathController::look_at].
+const LOOK_AHEAD: f32 = 1.0;
+
+/// Rides a uniform Catmull-Rom spline through a list of points (passing through each) at
+/// [PathController::speed]. Closed paths loop; open ones stop at the end.
+pub struct PathController {
+    points: Vec<Vec3>,
+    closed: bool,
+    /// Path length up to each arc length sample ([SAMPLES_PER_SEGMENT] per segment,
+    /// from the start).
+    lengths: Vec<f32>,
+    /// Units per second along the path.
+    pub speed: f32,
+    /// Point kept in view; `None` looks along the path.
+    pub look_at: Option<Vec3>,
+    /// How far along the path the camera is.
+    distance: f32,
+}
+
+impl Default for PathController {
+    fn default() -> Self {
+        Self::new(Vec::new(), false)
+    }
+}
+
+impl PathController {
+    /// A path through `points`; `closed` joins the last point back to the first.
+    pub fn new(points: Vec<Vec3>, closed: bool) -> Self {
+        let mut path = Self {
+            points,
+            closed,
+            lengths: Vec::new(),
+            speed: 2.0,
+            look_at: None,
+            distance: 0.0,
+        };
+        let samples = path.segments() * SAMPLES_PER_SEGMENT;
+        if samples > 0 {
+            let mut length = 0.0;
+            let mut previous = path.point(0.0);
+            path.lengths = (0..=samples)
+                .map(|j| {
+                    let point = path.point(j as f32 / SAMPLES_PER_SEGMENT as f32);
+                    length += point.distance(previous);
+                    previous = point;
+                    length
+                })
+                .collect();
+        }
+        path
+    }
+
+    /// Whether there is no path to ride (fewer than two points).
+    pub fn is_empty(&self) -> bool {
+        self.points.len() < 2
+    }
+
+    pub fn length(&self) -> f32 {
+        self.lengths.last().copied().unwrap_or(0.0)
+    }
+
+    /// Point `distance` units along the path (wrapping around a closed path).
+    pub fn position_at(&self, distance: f32) -> Vec3 {
+        self.point(self.parameter(self.wrap(distance)))
+    }
+
+    fn segments(&self) -> usize {
+        match self.points.len() {
+            0 | 1 => 0,
+            n if self.closed => n,
+            n => n - 1,
+        }
+    }
+
+    fn wrap(&self, distance: f32) -> f32 {
+        let length = self.length();
+        if self.closed && length > 0.0 {
+            distance.rem_euclid(length)
+        } else {
+            distance.clamp(0.0, length)
+        }
+    }
+
+    /// Spline point at parameter `s`: segment `floor(s)` runs from point `floor(s)` to the next.
+    fn point(&self, s: f32) -> Vec3 {
+        let n = self.points.len() as isize;
+        let segment = (s.max(0.0) as usize).min(self.segments().saturating_sub(1));
+        let t = s - segment as f32;
+        let at = |i: isize| {
+            self.points[if self.closed {
+                i.rem_euclid(n)
+            } else {
+                i.clamp(0, n - 1)
+            } as usize]
+        };
+        let i = segment as isize;
+        catmull_rom(at(i - 1), at(i), at(i + 1), at(i + 2), t)
+    }
+
+    /// Spline parameter `distance` units along the path (not wrapped).
+    fn parameter(&self, distance: f32) -> f32 {
+        let i = self
+            .lengths
+            .partition_point(|&length| length <= distance)
+            .clamp(1, self.lengths.len() - 1);
+        let (before, after) = (self.lengths[i - 1], self.lengths[i]);
+        let f = if after > before {
+            ((distance - before) / (after - before)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        ((i - 1) as f32 + f) / SAMPLES_PER_SEGMENT as f32
+    }
+}
+
+/// Uniform Catmull-Rom segment from `p1` (t = 0) to `p2` (t = 1).
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let (t2, t3) = (t * t, t * t * t);
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+impl CameraController for PathController {
+    fn update(&mut self, camera: &mut CameraDescriptor, input: &FrameInput) {
+        if self.is_empty() {
+            return;
+        }
+        self.distance = self.wrap(self.distance + self.speed * delta_s(input));
+        zoom_fov(camera, input);
+        self.place(camera);
+    }
+
+    /// Continues from the point of the path nearest the camera.
+    fn sync(&mut self, camera: &CameraDescriptor) {
+        let gap = |j: usize| {
+            self.point(j as f32 / SAMPLES_PER_SEGMENT as f32)
+                .distance_squared(camera.position)
+        };
+        if let Some(nearest) = (0..self.lengths.len()).min_by(|&a, &b| gap(a).total_cmp(&gap(b))) {
+            self.distance = self.lengths[nearest];
+        }
+    }
+
+    fn place(&self, camera: &mut CameraDescriptor) {
+        if self.is_empty() {
+            return;
+        }
+        let position = self.position_at(self.distance);
+        let target = self.look_at.unwrap_or_else(|| {
+            if self.closed || self.distance + LOOK_AHEAD <= self.length() {
+                self.position_at(self.distance + LOOK_AHEAD)
+            } else {
+                // Past the end of an open path: keep looking the way it was heading.
+                2.0 * position - self.position_at(self.distance - LOOK_AHEAD)
+            }
+        });
+        camera.position = position;
+        camera.target = if target == position {
+            position + Vec3::Z
+        } else {
+            target
+        };
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.speed, -10.0..=10.0).text("Speed"));
+    }
+}
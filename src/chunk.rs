@@ -446,5 +446,6 @@ impl ChunkMeshGpu {
         pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
         pass.draw_indexed(0..self.index_count, 0, 0..1);
+        crate::gpu::stats::record_draw(1);
     }
 }
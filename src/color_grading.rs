@@ -0,0 +1,549 @@
+//! Color pipeline options for the screen pass: the tonemap operator, white balance / contrast /
+//! saturation, and 3D LUTs.
+//!
+//! The screen pass grades in linear scene light (after exposure), tonemaps, encodes sRGB and then
+//! applies the grading LUT, so `.cube` files made for sRGB footage apply as authored. [Tonemapper]
+//! and [ColorGrading] live in [crate::render_settings::RenderSettings]; LUTs are data, held by
+//! [crate::app::App] in [ColorLuts].
+
+use glam::{Mat3, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Largest `.cube` LUT accepted (per side).
+const MAX_LUT_SIZE: u32 = 128;
+/// DXGI_FORMAT_R9G9B9E5_SHAREDEXP, the format of Tony McMapface's LUT.
+const DXGI_FORMAT_RGB9E5: u32 = 67;
+
+/// Tonemap operator of the screen pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tonemapper {
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve (the original look).
+    #[default]
+    AcesFitted,
+    /// Troy Sobotka's AgX (Benjamin Wrensch's polynomial fit): desaturates highlights towards
+    /// white instead of skewing their hue.
+    Agx,
+    /// Reinhard on luminance, reaching white at a finite brightness.
+    ReinhardExtended,
+    /// Tomasz Stachowiak's LUT-based operator. Needs its LUT ([LutKind::TonyMcMapface]); uses
+    /// [Tonemapper::Agx] until one is loaded.
+    TonyMcMapface,
+    /// Clamps to [0, 1].
+    None,
+}
+
+impl Tonemapper {
+    pub const ALL: [Self; 5] = [
+        Self::AcesFitted,
+        Self::Agx,
+        Self::ReinhardExtended,
+        Self::TonyMcMapface,
+        Self::None,
+    ];
+
+    /// Parses `"aces"`, `"agx"`, `"reinhard"`, `"tony_mc_mapface"` or `"none"` (case-insensitive).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.name().eq_ignore_ascii_case(name))
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::AcesFitted => "aces",
+            Self::Agx => "agx",
+            Self::ReinhardExtended => "reinhard",
+            Self::TonyMcMapface => "tony_mc_mapface",
+            Self::None => "none",
+        }
+    }
+
+    /// Value of `ScreenUniforms.tonemapper` in `screen_parity.wgsl`.
+    pub const fn shader_mode(self) -> u32 {
+        match self {
+            Self::AcesFitted => 0,
+            Self::Agx => 1,
+            Self::ReinhardExtended => 2,
+            Self::TonyMcMapface => 3,
+            Self::None => 4,
+        }
+    }
+}
+
+impl std::str::FromStr for Tonemapper {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        Self::from_name(name).ok_or_else(|| format!("unknown tonemapper {name:?}"))
+    }
+}
+
+/// Grading applied around the tonemapper. The defaults change nothing.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorGrading {
+    /// White balance, -1..1: negative cools the image (bluer), positive warms it.
+    pub temperature: f32,
+    /// -1..1: negative towards magenta, positive towards green.
+    pub tint: f32,
+    /// Scales log luminance around middle grey (1 = unchanged).
+    pub contrast: f32,
+    /// 0 = greyscale, 1 = unchanged.
+    pub saturation: f32,
+    /// Blend of the grading LUT ([LutKind::Grading]) over the ungraded image, 0..1.
+    pub lut_strength: f32,
+}
+
+impl Default for ColorGrading {
+    fn default() -> Self {
+        Self {
+            temperature: 0.0,
+            tint: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            lut_strength: 1.0,
+        }
+    }
+}
+
+impl ColorGrading {
+    /// Clamps every knob into its range (non-finite values reset to the default).
+    #[must_use]
+    pub fn sanitized(self) -> Self {
+        let default = Self::default();
+        let clamp = |v: f32, default: f32, min: f32, max: f32| {
+            if v.is_finite() {
+                v.clamp(min, max)
+            } else {
+                default
+            }
+        };
+        Self {
+            temperature: clamp(self.temperature, default.temperature, -1.0, 1.0),
+            tint: clamp(self.tint, default.tint, -1.0, 1.0),
+            contrast: clamp(self.contrast, default.contrast, 0.25, 4.0),
+            saturation: clamp(self.saturation, default.saturation, 0.0, 2.0),
+            lut_strength: clamp(self.lut_strength, default.lut_strength, 0.0, 1.0),
+        }
+    }
+
+    /// Linear Rec. 709 matrix for [ColorGrading::temperature] and [ColorGrading::tint]: a von Kries
+    /// adaptation in LMS from a white point on (or off, with tint) the daylight locus to D65.
+    pub fn white_balance(&self) -> Mat3 {
+        if self.temperature == 0.0 && self.tint == 0.0 {
+            return Mat3::IDENTITY;
+        }
+        // Row-major, hence the transposes.
+        let rgb_to_lms = Mat3::from_cols_array(&[
+            0.390_405,
+            0.549_941,
+            0.008_926_32,
+            0.070_841_6,
+            0.963_172,
+            0.001_357_75,
+            0.023_108_2,
+            0.128_021,
+            0.936_245,
+        ])
+        .transpose();
+        let lms_to_rgb = Mat3::from_cols_array(&[
+            2.858_47,
+            -1.628_79,
+            -0.024_891,
+            -0.210_182,
+            1.158_2,
+            0.000_324_281,
+            -0.041_812,
+            -0.118_169,
+            1.068_67,
+        ])
+        .transpose();
+
+        let t1 = self.temperature * 10.0 / 6.0;
+        let t2 = self.tint * 10.0 / 6.0;
+        // CIE xy of the white point: x along the daylight locus, y off it with tint.
+        let x = 0.31271 - t1 * if t1 < 0.0 { 0.1 } else { 0.05 };
+        let y = 2.87 * x - 3.0 * x * x - 0.275_095_07 + t2 * 0.05;
+        let white = xy_to_lms(x, y);
+        let d65 = Vec3::new(0.949_237, 1.035_42, 1.087_28);
+        lms_to_rgb * Mat3::from_diagonal(d65 / white) * rgb_to_lms
+    }
+}
+
+/// LMS cone response of CIE xy chromaticity `(x, y)` at luminance 1.
+fn xy_to_lms(x: f32, y: f32) -> Vec3 {
+    let xyz = Vec3::new(x / y, 1.0, (1.0 - x - y) / y);
+    Mat3::from_cols_array(&[
+        0.7328, 0.4296, -0.1624, -0.7036, 1.6975, 0.0061, 0.0030, 0.0136, 0.9834,
+    ])
+    .transpose()
+        * xyz
+}
+
+/// What a [Lut3d] is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LutKind {
+    /// Creative grade, looked up with the sRGB-encoded image (see [ColorGrading::lut_strength]).
+    Grading,
+    /// [Tonemapper::TonyMcMapface]'s LUT, looked up with `x / (x + 1)` of the linear image.
+    TonyMcMapface,
+}
+
+impl LutKind {
+    /// Parses `"grading"` or `"tony_mc_mapface"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "grading" => Some(Self::Grading),
+            "tony_mc_mapface" => Some(Self::TonyMcMapface),
+            _ => None,
+        }
+    }
+}
+
+/// A 3D color lookup table: `size`³ RGB texels, red varying fastest, then green, then blue.
+#[derive(Clone, Debug)]
+pub struct Lut3d {
+    pub title: Option<String>,
+    pub size: u32,
+    /// Input values mapping to the first and last texel per channel.
+    pub domain_min: Vec3,
+    pub domain_max: Vec3,
+    pub texels: Vec<Vec3>,
+}
+
+impl Lut3d {
+    /// Parses an Adobe / Resolve `.cube` file (`LUT_3D_SIZE`, `DOMAIN_MIN` / `DOMAIN_MAX` or
+    /// `LUT_3D_INPUT_RANGE`, one `r g b` line per texel). 1D LUTs are rejected.
+    pub fn parse_cube(text: &str) -> Result<Self, String> {
+        let mut lut = Self {
+            title: None,
+            size: 0,
+            domain_min: Vec3::ZERO,
+            domain_max: Vec3::ONE,
+            texels: Vec::new(),
+        };
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let mut words = line.split_whitespace();
+            let Some(keyword) = words.next() else {
+                continue;
+            };
+            if keyword.starts_with('#') {
+                continue;
+            }
+            let error = |what: &str| format!(".cube line {}: {what}", number + 1);
+            let floats = |count: usize| -> Result<Vec<f32>, String> {
+                let values: Vec<f32> = line
+                    .split_whitespace()
+                    .skip(usize::from(keyword.parse::<f32>().is_err()))
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| error("expected numbers"))?;
+                if values.len() == count {
+                    Ok(values)
+                } else {
+                    Err(error(&format!("expected {count} numbers")))
+                }
+            };
+            match keyword {
+                "TITLE" => {
+                    lut.title = Some(line["TITLE".len()..].trim().trim_matches('"').to_string());
+                }
+                "LUT_3D_SIZE" => {
+                    lut.size = words
+                        .next()
+                        .and_then(|w| w.parse().ok())
+                        .ok_or_else(|| error("bad LUT_3D_SIZE"))?;
+                    if !(2..=MAX_LUT_SIZE).contains(&lut.size) {
+                        return Err(error(&format!("LUT_3D_SIZE must be 2..={MAX_LUT_SIZE}")));
+                    }
+                    lut.texels
+                        .reserve((lut.size * lut.size * lut.size) as usize);
+                }
+                "LUT_1D_SIZE" => return Err(error("1D LUTs are not supported")),
+                "DOMAIN_MIN" => lut.domain_min = Vec3::from_slice(&floats(3)?),
+                "DOMAIN_MAX" => lut.domain_max = Vec3::from_slice(&floats(3)?),
+                "LUT_3D_INPUT_RANGE" => {
+                    let range = floats(2)?;
+                    (lut.domain_min, lut.domain_max) =
+                        (Vec3::splat(range[0]), Vec3::splat(range[1]));
+                }
+                _ if keyword.parse::<f32>().is_ok() => {
+                    lut.texels.push(Vec3::from_slice(&floats(3)?));
+                }
+                // Other keywords (e.g. a 1D shaper's LUT_1D_INPUT_RANGE) don't affect a 3D table.
+                _ => {}
+            }
+        }
+        lut.validate()?;
+        Ok(lut)
+    }
+
+    /// Reads a 3D `R9G9B9E5_SHAREDEXP` DDS texture with a DX10 header, as Tony McMapface's
+    /// `tony_mc_mapface.dds` is distributed (domain 0..1).
+    pub fn from_dds(bytes: &[u8]) -> Result<Self, String> {
+        let word = |offset: usize| {
+            bytes
+                .get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        if bytes.get(..4) != Some(b"DDS ".as_slice())
+            || bytes.get(84..88) != Some(b"DX10".as_slice())
+        {
+            return Err("not a DDS file with a DX10 header".into());
+        }
+        let (height, width, depth) = (word(12), word(16), word(24));
+        if word(128) != Some(DXGI_FORMAT_RGB9E5) {
+            return Err("DDS LUT must be R9G9B9E5_SHAREDEXP".into());
+        }
+        let size = width
+            .filter(|&w| Some(w) == height && Some(w) == depth)
+            .ok_or("DDS LUT must be a cube")?;
+        if !(2..=MAX_LUT_SIZE).contains(&size) {
+            return Err(format!("DDS LUT size must be 2..={MAX_LUT_SIZE}"));
+        }
+        let count = (size * size * size) as usize;
+        let data = bytes
+            .get(148..148 + count * 4)
+            .ok_or("DDS LUT is truncated")?;
+        let texels = data
+            .chunks_exact(4)
+            .map(|b| rgb9e5_to_rgb(u32::from_le_bytes([b[0], b[1], b[2], b[3]])))
+            .collect();
+        Ok(Self {
+            title: None,
+            size,
+            domain_min: Vec3::ZERO,
+            domain_max: Vec3::ONE,
+            texels,
+        })
+    }
+
+    /// A DDS file ([Lut3d::from_dds]) or `.cube` text ([Lut3d::parse_cube]), by content.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.starts_with(b"DDS ") {
+            return Self::from_dds(bytes);
+        }
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| "LUT is neither DDS nor .cube text".to_string())?;
+        Self::parse_cube(text)
+    }
+
+    /// Texels packed as `Rgb9e5Ufloat` (negative values clamp to 0), for upload.
+    pub fn to_rgb9e5(&self) -> Vec<u32> {
+        self.texels.iter().map(|&rgb| rgb_to_rgb9e5(rgb)).collect()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.size == 0 {
+            return Err(".cube file has no LUT_3D_SIZE".into());
+        }
+        let expected = (self.size * self.size * self.size) as usize;
+        if self.texels.len() != expected {
+            return Err(format!(
+                ".cube file has {} texels, expected {expected}",
+                self.texels.len()
+            ));
+        }
+        if !self.domain_max.cmpgt(self.domain_min).all() {
+            return Err(".cube DOMAIN_MAX must exceed DOMAIN_MIN".into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Lut3d {
+    /// Reads a `.cube` or DDS file (see [Lut3d::from_bytes]).
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("reading {}: {e}", path.display()))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+/// The loaded LUTs, with a revision that changes whenever one does (so the GPU copies re-upload).
+#[derive(Default)]
+pub struct ColorLuts {
+    grading: Option<Lut3d>,
+    tony_mc_mapface: Option<Lut3d>,
+    revision: u32,
+}
+
+impl ColorLuts {
+    pub const fn get(&self, kind: LutKind) -> Option<&Lut3d> {
+        match kind {
+            LutKind::Grading => self.grading.as_ref(),
+            LutKind::TonyMcMapface => self.tony_mc_mapface.as_ref(),
+        }
+    }
+
+    /// Replaces (or with `None`, removes) the `kind` LUT.
+    pub fn set(&mut self, kind: LutKind, lut: Option<Lut3d>) {
+        match kind {
+            LutKind::Grading => self.grading = lut,
+            LutKind::TonyMcMapface => self.tony_mc_mapface = lut,
+        }
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    pub const fn revision(&self) -> u32 {
+        self.revision
+    }
+}
+
+/// Largest value `Rgb9e5Ufloat` holds: (511 / 512) * 2^16.
+const RGB9E5_MAX: f32 = 65408.0;
+
+fn rgb_to_rgb9e5(rgb: Vec3) -> u32 {
+    let rgb = rgb.clamp(Vec3::ZERO, Vec3::splat(RGB9E5_MAX));
+    let max = rgb.max_element();
+    if max <= 0.0 || max.is_nan() {
+        return 0;
+    }
+    // Shared exponent (biased by 15) so the largest channel's mantissa fits in 9 bits.
+    let mut exponent = (max.log2().floor() as i32).max(-16) + 16;
+    let mut scale = ((exponent - 15 - 9) as f32).exp2();
+    if (max / scale).round() >= 512.0 {
+        exponent += 1;
+        scale *= 2.0;
+    }
+    let mantissa = |v: f32| ((v / scale).round() as u32).min(511);
+    mantissa(rgb.x) | mantissa(rgb.y) << 9 | mantissa(rgb.z) << 18 | (exponent as u32) << 27
+}
+
+fn rgb9e5_to_rgb(packed: u32) -> Vec3 {
+    let scale = ((packed >> 27) as f32 - 15.0 - 9.0).exp2();
+    let mantissa = |shift: u32| ((packed >> shift) & 0x1ff) as f32 * scale;
+    Vec3::new(mantissa(0), mantissa(9), mantissa(18))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Write as _;
+
+    /// `.cube` text for an identity LUT of `size`, after `header`.
+    fn identity_cube(header: &str, size: u32) -> String {
+        let channel = |index: u32| (index % size) as f32 / (size - 1) as f32;
+        let mut text = format!("{header}\nLUT_3D_SIZE {size}\n");
+        for i in 0..size * size * size {
+            let _ = writeln!(
+                text,
+                "{} {} {}",
+                channel(i),
+                channel(i / size),
+                channel(i / (size * size))
+            );
+        }
+        text
+    }
+
+    #[test]
+    fn parses_identity_cube() {
+        let lut = Lut3d::parse_cube(&identity_cube("# comment\nTITLE \"Identity\"", 3)).unwrap();
+        assert_eq!(lut.title.as_deref(), Some("Identity"));
+        assert_eq!(lut.size, 3);
+        assert_eq!(lut.texels.len(), 27);
+        // Red varies fastest, then green, then blue.
+        assert_eq!(lut.texels[1], Vec3::new(0.5, 0.0, 0.0));
+        assert_eq!(lut.texels[3], Vec3::new(0.0, 0.5, 0.0));
+        assert_eq!(lut.texels[9], Vec3::new(0.0, 0.0, 0.5));
+        assert_eq!((lut.domain_min, lut.domain_max), (Vec3::ZERO, Vec3::ONE));
+    }
+
+    #[test]
+    fn rejects_wrong_texel_count() {
+        let mut text = identity_cube("", 2);
+        text += "1 1 1\n";
+        assert_eq!(
+            Lut3d::parse_cube(&text).unwrap_err(),
+            ".cube file has 9 texels, expected 8"
+        );
+        let short = identity_cube("", 2)
+            .lines()
+            .take(5)
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(
+            Lut3d::parse_cube(&short).unwrap_err(),
+            ".cube file has 3 texels, expected 8"
+        );
+    }
+
+    #[test]
+    fn reports_line_errors() {
+        assert_eq!(
+            Lut3d::parse_cube("0 0 0").unwrap_err(),
+            ".cube file has no LUT_3D_SIZE"
+        );
+        assert_eq!(
+            Lut3d::parse_cube("LUT_3D_SIZE x").unwrap_err(),
+            ".cube line 1: bad LUT_3D_SIZE"
+        );
+        assert_eq!(
+            Lut3d::parse_cube("\nLUT_3D_SIZE 1").unwrap_err(),
+            format!(".cube line 2: LUT_3D_SIZE must be 2..={MAX_LUT_SIZE}")
+        );
+        assert_eq!(
+            Lut3d::parse_cube("LUT_1D_SIZE 16").unwrap_err(),
+            ".cube line 1: 1D LUTs are not supported"
+        );
+        assert_eq!(
+            Lut3d::parse_cube("LUT_3D_SIZE 2\n0 0").unwrap_err(),
+            ".cube line 2: expected 3 numbers"
+        );
+        assert_eq!(
+            Lut3d::parse_cube("LUT_3D_SIZE 2\n0 zero 0").unwrap_err(),
+            ".cube line 2: expected numbers"
+        );
+    }
+
+    #[test]
+    fn reads_domain() {
+        let lut =
+            Lut3d::parse_cube(&identity_cube("DOMAIN_MIN -1 0 0\nDOMAIN_MAX 2 4 8", 2)).unwrap();
+        assert_eq!(lut.domain_min, Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(lut.domain_max, Vec3::new(2.0, 4.0, 8.0));
+
+        let lut = Lut3d::parse_cube(&identity_cube("LUT_3D_INPUT_RANGE 0 16", 2)).unwrap();
+        assert_eq!(
+            (lut.domain_min, lut.domain_max),
+            (Vec3::ZERO, Vec3::splat(16.0))
+        );
+
+        let inverted = identity_cube("DOMAIN_MIN 0 0 0\nDOMAIN_MAX 1 0 1", 2);
+        assert_eq!(
+            Lut3d::parse_cube(&inverted).unwrap_err(),
+            ".cube DOMAIN_MAX must exceed DOMAIN_MIN"
+        );
+        assert_eq!(
+            Lut3d::parse_cube("DOMAIN_MIN 0 0").unwrap_err(),
+            ".cube line 1: expected 3 numbers"
+        );
+    }
+
+    #[test]
+    fn rgb9e5_round_trips() {
+        for rgb in [
+            Vec3::ZERO,
+            Vec3::ONE,
+            Vec3::new(0.25, 0.5, 0.75),
+            Vec3::new(1000.0, 1.0, 0.001),
+            Vec3::splat(RGB9E5_MAX),
+        ] {
+            let back = rgb9e5_to_rgb(rgb_to_rgb9e5(rgb));
+            // 9-bit mantissas against the shared exponent of the largest channel.
+            let tolerance = rgb.max_element() / 512.0;
+            assert!(
+                (back - rgb).abs().max_element() <= tolerance,
+                "{rgb} came back as {back}"
+            );
+        }
+        // Out-of-range channels clamp; the others keep the largest one's exponent (2^7 here).
+        assert_eq!(
+            rgb9e5_to_rgb(rgb_to_rgb9e5(Vec3::new(-1.0, 256.0, 1e9))),
+            Vec3::new(0.0, 256.0, RGB9E5_MAX)
+        );
+        assert_eq!(rgb_to_rgb9e5(Vec3::NAN), 0);
+    }
+}
@@ -0,0 +1,264 @@
+//! Immediate-mode 3D debug drawing for any system or scene.
+//!
+//! Calls like `DebugDraw::new().sphere(center, 1.0, color)` queue wireframe primitives for the
+//! current frame; they are drawn once and cleared when the frame ends, so call them every frame
+//! (from [crate::scene::Scene::update], not per view). Primitives are
+//! [crate::polyline::Polylines] a fixed number of pixels wide.
+//!
+//! On the G-buffer path the built-in `debug` pass draws them over [crate::render_graph::RESOLVE]
+//! after TAA, depth-tested against the G-buffer depth; on the forward path (stereo) they are drawn
+//! per view at the end of the forward pass, against its depth buffer. [DebugDraw::on_top] skips the
+//! depth test.
+//!
+//! Without the `debug-draw` cargo feature (off in release builds) every call is a no-op and nothing
+//! is drawn.
+
+use glam::{Mat4, Vec3, Vec4};
+
+#[cfg(feature = "debug-draw")]
+use std::cell::RefCell;
+
+#[cfg(feature = "debug-draw")]
+use crate::polyline::{DepthTest, LineCap, LineJoin, LineStyle, Polylines};
+use crate::view::ViewState;
+
+/// Segments per circle (spheres).
+const CIRCLE_SEGMENTS: usize = 32;
+/// How far [DebugDraw::frustum] extends from the near plane; the projection itself is infinite.
+pub const FRUSTUM_DEPTH: f32 = 10.0;
+
+/// Queues debug primitives. Cheap to copy; configure with [DebugDraw::on_top] / [DebugDraw::width].
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(not(feature = "debug-draw"), allow(dead_code))]
+pub struct DebugDraw {
+    on_top: bool,
+    width: f32,
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "debug-draw")]
+thread_local! {
+    /// Depth-tested and always-on-top primitives for the current frame.
+    static LAYERS: RefCell<[Polylines; 2]> = RefCell::new({
+        let mut on_top = Polylines::new();
+        on_top.set_on_top(true);
+        [Polylines::new(), on_top]
+    });
+}
+
+impl DebugDraw {
+    /// Depth-tested, 2 px wide.
+    pub const fn new() -> Self {
+        Self {
+            on_top: false,
+            width: 2.0,
+        }
+    }
+
+    /// Draws over all geometry.
+    #[must_use]
+    pub const fn on_top(self) -> Self {
+        Self {
+            on_top: true,
+            ..self
+        }
+    }
+
+    /// Line width in pixels.
+    #[must_use]
+    pub const fn width(self, width: f32) -> Self {
+        Self { width, ..self }
+    }
+
+    pub fn line(self, a: Vec3, b: Vec3, color: Vec4) {
+        self.strip(&[a, b], color, false);
+    }
+
+    /// Axis-aligned box.
+    pub fn aabb(self, min: Vec3, max: Vec3, color: Vec4) {
+        let corner = |x: f32, y: f32, z: f32| Vec3::new(x, y, z);
+        for y in [min.y, max.y] {
+            let face = [
+                corner(min.x, y, min.z),
+                corner(max.x, y, min.z),
+                corner(max.x, y, max.z),
+                corner(min.x, y, max.z),
+            ];
+            self.strip(&face, color, true);
+        }
+        for (x, z) in [
+            (min.x, min.z),
+            (max.x, min.z),
+            (max.x, max.z),
+            (min.x, max.z),
+        ] {
+            self.line(corner(x, min.y, z), corner(x, max.y, z), color);
+        }
+    }
+
+    /// Three great circles (one per axis plane).
+    pub fn sphere(self, center: Vec3, radius: f32, color: Vec4) {
+        for (u, v) in [(Vec3::X, Vec3::Y), (Vec3::Y, Vec3::Z), (Vec3::Z, Vec3::X)] {
+            self.circle(center, u * radius, v * radius, color);
+        }
+    }
+
+    /// The view's frustum, from its near plane to [FRUSTUM_DEPTH] beyond it.
+    pub fn frustum(self, view: &ViewState, color: Vec4) {
+        // Reversed-Z infinite projection: ndc depth is near / distance.
+        let near = view.projection.col(3).z;
+        let inverse = view.view_projection_no_jitter.inverse();
+        let rect = |depth: f32| {
+            [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .map(|(x, y)| inverse.project_point3(Vec3::new(x, y, depth)))
+        };
+        let near_rect = rect(1.0);
+        let far_rect = rect(near / (near + FRUSTUM_DEPTH));
+        self.strip(&near_rect, color, true);
+        self.strip(&far_rect, color, true);
+        for (a, b) in near_rect.into_iter().zip(far_rect) {
+            self.line(a, b, color);
+        }
+    }
+
+    /// Unit axes of `transform` (X red, Y green, Z blue), as arrows from its origin.
+    pub fn axes(self, transform: Mat4) {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        let colors = [
+            Vec4::new(1.0, 0.2, 0.2, 1.0),
+            Vec4::new(0.2, 1.0, 0.2, 1.0),
+            Vec4::new(0.3, 0.4, 1.0, 1.0),
+        ];
+        for (axis, color) in [Vec3::X, Vec3::Y, Vec3::Z].into_iter().zip(colors) {
+            self.arrow(origin, transform.transform_point3(axis), color);
+        }
+    }
+
+    /// Line from `from` to `to` with a wireframe cone head at `to`.
+    pub fn arrow(self, from: Vec3, to: Vec3, color: Vec4) {
+        let shaft = to - from;
+        let length = shaft.length();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let direction = shaft / length;
+        let head = (length * 0.2).min(0.5);
+        let base = to - direction * head;
+        let (u, v) = direction.any_orthonormal_pair();
+        self.line(from, to, color);
+        for side in [u, v, -u, -v] {
+            self.line(to, base + side * head * 0.4, color);
+        }
+    }
+
+    /// Square grid on the XZ plane through `center`: `cells` x `cells` cells of `spacing`.
+    pub fn grid(self, center: Vec3, cells: u32, spacing: f32, color: Vec4) {
+        let half = cells as f32 * spacing * 0.5;
+        for i in 0..=cells {
+            let offset = i as f32 * spacing - half;
+            self.line(
+                center + Vec3::new(offset, 0.0, -half),
+                center + Vec3::new(offset, 0.0, half),
+                color,
+            );
+            self.line(
+                center + Vec3::new(-half, 0.0, offset),
+                center + Vec3::new(half, 0.0, offset),
+                color,
+            );
+        }
+    }
+
+    /// Ellipse `center + u * cos(a) + v * sin(a)`.
+    fn circle(self, center: Vec3, u: Vec3, v: Vec3, color: Vec4) {
+        let points: Vec<Vec3> = (0..CIRCLE_SEGMENTS)
+            .map(|i| {
+                let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                center + u * angle.cos() + v * angle.sin()
+            })
+            .collect();
+        self.strip(&points, color, true);
+    }
+
+    #[cfg(feature = "debug-draw")]
+    fn strip(self, points: &[Vec3], color: Vec4, closed: bool) {
+        let style = LineStyle {
+            width: self.width,
+            join: LineJoin::Miter,
+            cap: LineCap::Round,
+            closed,
+            ..Default::default()
+        };
+        LAYERS.with_borrow_mut(|layers| {
+            layers[usize::from(self.on_top)].add_strip(points, color, &style);
+        });
+    }
+
+    #[cfg(not(feature = "debug-draw"))]
+    #[allow(clippy::unused_self)]
+    fn strip(self, _points: &[Vec3], _color: Vec4, _closed: bool) {}
+}
+
+/// Creates the GPU state for drawing into `color_format` with `depth` and uploads this frame's
+/// primitives. Call before the pass that draws them.
+#[cfg(feature = "debug-draw")]
+pub(crate) fn prepare(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    color_format: wgpu::TextureFormat,
+    depth: DepthTest<'_>,
+) {
+    LAYERS.with_borrow_mut(|layers| {
+        for layer in layers {
+            layer.prepare(device, queue, color_format, depth);
+        }
+    });
+}
+
+/// Draws this frame's primitives for `view` (same format and depth test as [prepare]).
+/// `view_projection` is the view's matrix, with or without jitter to match what is already
+/// in the pass.
+#[cfg(feature = "debug-draw")]
+pub(crate) fn draw(
+    pass: &mut wgpu::RenderPass<'_>,
+    view: &ViewState,
+    view_projection: Mat4,
+    color_format: wgpu::TextureFormat,
+    depth: DepthTest<'_>,
+) {
+    let (x, y, w, h) = view.viewport;
+    let viewport = [x as f32, y as f32, w as f32, h as f32];
+    LAYERS.with_borrow(|layers| {
+        for layer in layers {
+            layer.draw_with(
+                pass,
+                view.eye,
+                view_projection,
+                viewport,
+                color_format,
+                depth,
+            );
+        }
+    });
+}
+
+/// Whether anything was queued this frame.
+#[cfg(feature = "debug-draw")]
+pub(crate) fn is_empty() -> bool {
+    LAYERS.with_borrow(|layers| layers.iter().all(Polylines::is_empty))
+}
+
+/// Drops this frame's primitives.
+pub(crate) fn end_frame() {
+    #[cfg(feature = "debug-draw")]
+    LAYERS.with_borrow_mut(|layers| {
+        for layer in layers {
+            layer.clear();
+        }
+    });
+}
@@ -1,6 +1,9 @@
 use crate::app::{App, AppInstance};
+use crate::gpu::GpuContext;
+use crate::render_graph::RenderGraph;
 use crate::scene::{FrameInput, Scene, SceneDescriptor};
 use crate::scene1::Scene1;
+use crate::scene2::Scene2;
 use wasm_bindgen::JsCast;
 use web_sys::CanvasRenderingContext2d;
 use wgpu::RenderPass;
@@ -8,6 +11,8 @@ use wgpu::RenderPass;
 pub struct Demo {
     scenes: Vec<Box<dyn Scene>>,
     current_scene: usize,
+    /// Scene switched away from while its transition runs; still updated and drawn (G-buffer only).
+    outgoing_scene: Option<usize>,
 }
 
 impl Demo {
@@ -15,6 +20,7 @@ impl Demo {
         Demo {
             scenes: Vec::new(),
             current_scene: 0,
+            outgoing_scene: None,
         }
     }
 }
@@ -24,16 +30,91 @@ impl AppInstance for Demo {
         log!("Initializing scenes...");
         let scene1 = Box::new(Scene1::new(app));
         self.scenes.push(scene1);
+        self.scenes.push(Box::new(Scene2::new()));
+        if let Some(scene) = self.scenes.get_mut(self.current_scene) {
+            scene.enter(app);
+        }
+    }
+
+    fn setup_render_graph(&mut self, graph: &mut RenderGraph) {
+        for (i, scene) in self.scenes.iter_mut().enumerate() {
+            graph.set_group(Some(scene.name()));
+            scene.render_passes(graph);
+            graph.set_group_active(scene.name(), i == self.current_scene);
+        }
+        graph.set_group(None);
+    }
+
+    fn gpu_ready(&mut self, gpu: &GpuContext) {
+        for scene in &mut self.scenes {
+            scene.gpu_ready(gpu);
+        }
+    }
+
+    fn scene_names(&self) -> Vec<&'static str> {
+        self.scenes.iter().map(|scene| scene.name()).collect()
+    }
+
+    fn current_scene(&self) -> usize {
+        self.current_scene
+    }
+
+    fn switch_scene(&mut self, app: &App, graph: &mut RenderGraph, index: usize) -> bool {
+        if index >= self.scenes.len() || index == self.current_scene {
+            return false;
+        }
+        if let Some(scene) = self.scenes.get_mut(self.current_scene) {
+            scene.exit(app);
+            graph.set_group_active(scene.name(), false);
+        }
+        self.outgoing_scene = Some(self.current_scene);
+        self.current_scene = index;
+        let scene = &mut self.scenes[index];
+        graph.set_group_active(scene.name(), true);
+        scene.enter(app);
+        true
+    }
+
+    fn outgoing_descriptor(&self) -> Option<&SceneDescriptor> {
+        Some(self.scenes[self.outgoing_scene?].descriptor())
+    }
+
+    fn frame_outgoing(
+        &mut self,
+        app: &mut App,
+        view: &crate::view::ViewState,
+        pass: &mut RenderPass<'_>,
+    ) {
+        if let Some(index) = self.outgoing_scene {
+            self.scenes[index].on_frame(app, view, Some(pass), true);
+        }
+    }
+
+    fn end_transition(&mut self, _app: &App) {
+        self.outgoing_scene = None;
     }
 
     fn descriptor(&self) -> &SceneDescriptor {
         self.scenes[self.current_scene].descriptor()
     }
 
+    fn descriptor_mut(&mut self) -> &mut SceneDescriptor {
+        self.scenes[self.current_scene].descriptor_mut()
+    }
+
     fn update(&mut self, input: &FrameInput) {
         if self.current_scene < self.scenes.len() {
             self.scenes[self.current_scene].update(input);
         }
+        if let Some(index) = self.outgoing_scene {
+            self.scenes[index].update(input);
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        if self.current_scene < self.scenes.len() {
+            self.scenes[self.current_scene].ui(ui);
+        }
     }
 
     fn frame(
@@ -47,8 +128,8 @@ impl AppInstance for Demo {
             if pass.is_none() {
                 if let Some(ctx) = app
                     .canvas
-                    .get_context("2d")
-                    .ok()
+                    .as_ref()
+                    .and_then(|c| c.get_context("2d").ok())
                     .flatten()
                     .and_then(|c| c.dyn_into::<CanvasRenderingContext2d>().ok())
                 {
@@ -0,0 +1,201 @@
+//! Dynamic resolution: picks the G-buffer render scale from measured frame times.
+//!
+//! Frame times come from GPU timestamp queries when the device supports them
+//! ([crate::gpu::GpuContext::timer]), otherwise from the requestAnimationFrame delta. The
+//! display delta never drops below the refresh interval, so with it the controller can only see
+//! that a frame fit, not by how much; it then probes upward in small steps instead of jumping to
+//! the estimated scale.
+
+use crate::render_settings::RenderSettings;
+
+/// Minimum time between scale changes; each change reallocates the G-buffer.
+const ADJUST_INTERVAL_MS: f64 = 500.0;
+/// Scales are quantized to this step so small jitter in frame time doesn't cause reallocations.
+const SCALE_STEP: f32 = 0.05;
+/// Largest change per adjustment.
+const MAX_SCALE_CHANGE: f32 = 0.15;
+/// Smoothing factor for the frame time average.
+const SMOOTHING: f64 = 0.1;
+
+/// Where the frame time passed to [DynamicResolution::update] was measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameTimeSource {
+    /// GPU time between the first and last pass.
+    Gpu,
+    /// Time between display callbacks (vsync-limited).
+    Display,
+}
+
+pub struct DynamicResolution {
+    scale: f32,
+    /// Smoothed frame time in milliseconds; 0 until the first sample.
+    frame_ms: f64,
+    since_change_ms: f64,
+}
+
+impl Default for DynamicResolution {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DynamicResolution {
+    pub const fn new() -> Self {
+        Self {
+            scale: 1.0,
+            frame_ms: 0.0,
+            since_change_ms: 0.0,
+        }
+    }
+
+    /// Current render scale (fraction of the framebuffer size per axis).
+    pub const fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Applies `settings` (a fixed scale, or the bounds for the dynamic one) and feeds this frame's
+    /// time sample, if it has one (GPU readbacks don't land every frame). Call once per frame;
+    /// `elapsed_ms` is the time since the previous call.
+    pub fn update(
+        &mut self,
+        settings: &RenderSettings,
+        sample: Option<(f64, FrameTimeSource)>,
+        elapsed_ms: f64,
+    ) {
+        let max_scale = settings.render_scale;
+        if !settings.dynamic_resolution {
+            self.scale = max_scale;
+            return;
+        }
+        self.scale = self.scale.clamp(settings.min_render_scale, max_scale);
+        self.since_change_ms += elapsed_ms.max(0.0);
+        let Some((frame_ms, source)) = sample.filter(|&(ms, _)| ms.is_finite() && ms > 0.0) else {
+            return;
+        };
+        self.frame_ms = if self.frame_ms > 0.0 {
+            (frame_ms - self.frame_ms).mul_add(SMOOTHING, self.frame_ms)
+        } else {
+            frame_ms
+        };
+        if self.since_change_ms < ADJUST_INTERVAL_MS {
+            return;
+        }
+
+        let budget_ms = 1000.0 / f64::from(settings.target_fps);
+        // Cost is roughly proportional to pixel count, i.e. scale squared.
+        let ideal = self.scale * ((budget_ms / self.frame_ms).sqrt() as f32);
+        let over_budget = self.frame_ms > budget_ms * 1.05;
+        let target = match source {
+            _ if over_budget => ideal,
+            FrameTimeSource::Gpu if self.frame_ms < budget_ms * 0.8 => ideal,
+            FrameTimeSource::Gpu => self.scale,
+            FrameTimeSource::Display => self.scale + SCALE_STEP,
+        };
+        let change = (target - self.scale).clamp(-MAX_SCALE_CHANGE, MAX_SCALE_CHANGE);
+        let scale = ((self.scale + change) / SCALE_STEP).round() * SCALE_STEP;
+        let scale = scale.clamp(settings.min_render_scale, max_scale);
+        if (scale - self.scale).abs() > f32::EPSILON {
+            self.scale = scale;
+            self.since_change_ms = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FrameTimeSource::{Display, Gpu};
+
+    /// 60 FPS target (16.7 ms budget), scale between 0.5 and 1.
+    fn settings() -> RenderSettings {
+        RenderSettings {
+            dynamic_resolution: true,
+            ..RenderSettings::default()
+        }
+    }
+
+    fn assert_scale(resolution: &DynamicResolution, expected: f32) {
+        let scale = resolution.scale();
+        assert!(
+            (scale - expected).abs() < 1e-4,
+            "scale {scale}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn waits_for_the_adjust_interval() {
+        let mut resolution = DynamicResolution::new();
+        resolution.update(&settings(), Some((33.0, Gpu)), 100.0);
+        resolution.update(&settings(), Some((33.0, Gpu)), 300.0);
+        assert_scale(&resolution, 1.0);
+        // Frames without a sample still count towards the interval.
+        resolution.update(&settings(), None, 100.0);
+        assert_scale(&resolution, 1.0);
+        resolution.update(&settings(), Some((33.0, Gpu)), 0.0);
+        assert_scale(&resolution, 0.85);
+        // A change restarts the interval.
+        resolution.update(&settings(), Some((33.0, Gpu)), 100.0);
+        assert_scale(&resolution, 0.85);
+    }
+
+    #[test]
+    fn drops_when_over_budget_by_at_most_one_step() {
+        let mut resolution = DynamicResolution::new();
+        for _ in 0..4 {
+            resolution.update(&settings(), Some((100.0, Gpu)), ADJUST_INTERVAL_MS);
+        }
+        assert_scale(&resolution, 0.5);
+        // Never below the minimum scale.
+        resolution.update(&settings(), Some((100.0, Gpu)), ADJUST_INTERVAL_MS);
+        assert_scale(&resolution, 0.5);
+    }
+
+    #[test]
+    fn gpu_time_holds_near_budget_and_grows_well_under_it() {
+        let mut resolution = DynamicResolution::new();
+        resolution.update(&settings(), Some((33.0, Gpu)), ADJUST_INTERVAL_MS);
+        assert_scale(&resolution, 0.85);
+
+        let mut steady = DynamicResolution::new();
+        steady.update(&settings(), Some((33.0, Gpu)), ADJUST_INTERVAL_MS);
+        // Within 80-105% of the budget the scale holds. (Setting the average skips the smoothing.)
+        steady.frame_ms = 15.0;
+        steady.update(&settings(), Some((15.0, Gpu)), ADJUST_INTERVAL_MS);
+        assert_scale(&steady, 0.85);
+
+        resolution.frame_ms = 5.0;
+        resolution.update(&settings(), Some((5.0, Gpu)), ADJUST_INTERVAL_MS);
+        assert_scale(&resolution, 1.0);
+    }
+
+    #[test]
+    fn display_time_probes_upward_in_small_steps() {
+        let mut resolution = DynamicResolution::new();
+        resolution.update(&settings(), Some((33.0, Display)), ADJUST_INTERVAL_MS);
+        assert_scale(&resolution, 0.85);
+        // A vsync-limited delta at the budget only shows that the frame fit.
+        resolution.frame_ms = 16.7;
+        resolution.update(&settings(), Some((16.7, Display)), ADJUST_INTERVAL_MS);
+        assert_scale(&resolution, 0.9);
+    }
+
+    #[test]
+    fn follows_the_settings_every_frame() {
+        let mut resolution = DynamicResolution::new();
+        let mut settings = settings();
+        settings.render_scale = 0.7;
+        resolution.update(&settings, None, 0.0);
+        assert_scale(&resolution, 0.7);
+
+        settings.dynamic_resolution = false;
+        settings.render_scale = 0.6;
+        resolution.update(&settings, Some((100.0, Gpu)), ADJUST_INTERVAL_MS);
+        assert_scale(&resolution, 0.6);
+
+        settings.dynamic_resolution = true;
+        settings.min_render_scale = 0.65;
+        settings.render_scale = 0.8;
+        resolution.update(&settings, Some((f64::NAN, Gpu)), ADJUST_INTERVAL_MS);
+        assert_scale(&resolution, 0.65);
+    }
+}
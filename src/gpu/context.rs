@@ -1,12 +1,17 @@
-//! WebGPU device, queue, surface, and pipelines. Async init for wasm (request_adapter / request_device).
-
-use std::cell::RefCell;
-use std::rc::Rc;
+//! WebGPU device, queue, surface, and pipelines. Async init for wasm (request_adapter /
+//! request_device); headless init (no surface) for the native offline renderer.
 
+use crate::audio::BANDS;
+use crate::color_grading::{ColorGrading, ColorLuts, LutKind, Tonemapper};
+use crate::transition::TransitionKind;
 use crate::view::ViewState;
 use wgpu::util::DeviceExt;
 
+use super::exposure::{AutoExposure, EyeAdaptation};
+use super::lut::LutTextures;
+use super::stats;
 use super::targets::{GbufferSet, BLOOM_MIP_LEVELS};
+use super::timer::GpuTimer;
 use super::warehouse::{WarehouseUniforms, FULLSCREEN_TRIANGLE};
 
 const WAREHOUSE_WGSL: &str = include_str!("../wgsl/warehouse.wgsl");
@@ -23,15 +28,49 @@ const SCREEN_PARITY_WGSL: &str = include_str!("../wgsl/screen_parity.wgsl");
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct ScreenUniforms {
     pub camera_dir: [f32; 3],
-    pub _pad: f32,
+    /// [crate::transition::TransitionKind::shader_mode]; 0 = no transition.
+    pub transition_mode: u32,
+    /// 0 = all outgoing scene, 1 = all incoming.
+    pub transition_progress: f32,
+    /// Scales the bloom added to the scene (see [GpuContext::set_bloom_intensity]).
+    pub bloom_intensity: f32,
+    /// Scene scale before tonemapping (see [GpuContext::set_exposure]).
+    pub exposure: f32,
+    /// [Tonemapper::shader_mode].
+    pub tonemapper: u32,
+    /// Music spectrum levels, bass first (see [crate::audio::BAND_EDGES_HZ]).
+    pub audio_bands: [f32; BANDS],
+    /// [ColorGrading::white_balance], as WGSL `mat3x3` columns (padded to 16 bytes).
+    pub white_balance: [[f32; 4]; 3],
+    /// Input range of the grading LUT.
+    pub lut_domain_min: [f32; 3],
+    /// 0 without a grading LUT.
+    pub lut_strength: f32,
+    pub lut_domain_max: [f32; 3],
+    pub contrast: f32,
+    pub saturation: f32,
+    /// Brightest output value: 1 for SDR, the display peak over SDR white in HDR output.
+    pub output_peak: f32,
+    pub _pad: [f32; 2],
+}
+
+/// The outgoing scene for [GpuContext::run_screen_pass] during a scene transition.
+pub struct ScreenTransition {
+    /// The outgoing scene's resolve, this frame.
+    pub resolve_view: wgpu::TextureView,
+    /// The outgoing scene's bloom (first mip), this frame.
+    pub bloom_view: wgpu::TextureView,
+    pub kind: TransitionKind,
+    pub progress: f32,
 }
 
-/// Device, queue, surface, and adapter (for resize config). Created once via [init_gpu].
+/// Device, queue, surface, and adapter (for resize config). Created once via [init_gpu]
+/// (or `init_gpu_headless` on native, where `surface` is `None`).
 pub struct GpuContext {
     pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
-    pub surface: wgpu::Surface<'static>,
+    pub surface: Option<wgpu::Surface<'static>>,
     config_size: (u32, u32),
     pub surface_format: wgpu::TextureFormat,
     warehouse_pipeline: wgpu::RenderPipeline,
@@ -44,34 +83,61 @@ pub struct GpuContext {
     taa_bind_group_layout: wgpu::BindGroupLayout,
     present_pipeline: wgpu::RenderPipeline,
     present_bind_group_layout: wgpu::BindGroupLayout,
-    /// Shared layout for all single-texture fullscreen passes (brightness, downsample, upsample, lens).
+    /// Shared layout for all single-texture fullscreen passes (brightness,
+    /// downsample, upsample, lens).
     bloom_tex_sampler_layout: wgpu::BindGroupLayout,
     brightness_pipeline: wgpu::RenderPipeline,
     downsample_pipeline: wgpu::RenderPipeline,
     upsample_pipeline: wgpu::RenderPipeline,
     lens_pipeline: wgpu::RenderPipeline,
+    /// Plain bilinear copy between `Rgba16Float` targets (TAA history across render size changes).
+    resample_pipeline: wgpu::RenderPipeline,
     screen_pipeline: wgpu::RenderPipeline,
     screen_bind_group_layout: wgpu::BindGroupLayout,
     screen_uniform_buffer: wgpu::Buffer,
-    taa_history_index: usize,
     main_depth: Option<(wgpu::Texture, wgpu::TextureView)>,
     main_depth_size: (u32, u32),
+    /// (primary march, soft shadow) iterations for the warehouse shaders.
+    raymarch_steps: (u32, u32),
+    bloom_intensity: f32,
+    audio_bands: [f32; BANDS],
+    exposure: AutoExposure,
+    tonemapper: Tonemapper,
+    grading: ColorGrading,
+    luts: LutTextures,
+    /// The surface presents in extended range (see [GpuContext::set_hdr_active]).
+    hdr_active: bool,
+    /// See [GpuContext::set_output_peak].
+    output_peak: f32,
+    /// Frame and per-pass GPU timing; `None` without timestamp query support.
+    pub timer: Option<GpuTimer>,
 }
 
 impl GpuContext {
-    pub fn configure_surface(&mut self, width: u32, height: u32) {
+    /// Starts the GPU timing span `name` (see [GpuTimer::mark]); no-op without a timer.
+    pub fn mark_pass(&self, encoder: &mut wgpu::CommandEncoder, name: &'static str) {
+        if let Some(timer) = &self.timer {
+            timer.mark(encoder, name);
+        }
+    }
+
+    /// Resizes the surface and main depth buffer to `width` x `height`. Returns whether the surface
+    /// was (re)configured, which resets its presentation to standard range.
+    pub fn configure_surface(&mut self, width: u32, height: u32) -> bool {
         if width == 0 || height == 0 {
-            return;
+            return false;
         }
         if self.config_size == (width, height) {
-            return;
+            return false;
         }
         self.config_size = (width, height);
-        let config = self
-            .surface
-            .get_default_config(&self.adapter, width, height)
-            .expect("surface not supported by adapter");
-        self.surface.configure(&self.device, &config);
+        if let Some(surface) = &self.surface {
+            let mut config = surface
+                .get_default_config(&self.adapter, width, height)
+                .expect("surface not supported by adapter");
+            config.format = self.surface_format;
+            surface.configure(&self.device, &config);
+        }
 
         if self.main_depth_size != (width, height) {
             self.main_depth_size = (width, height);
@@ -88,6 +154,30 @@ impl GpuContext {
             let view = depth.create_view(&Default::default());
             self.main_depth = Some((depth, view));
         }
+        self.surface.is_some()
+    }
+
+    /// Whether the surface can present HDR: it has a float format (picked by [init_gpu] for HDR
+    /// displays). Extended range still needs the platform's support.
+    pub fn hdr_capable(&self) -> bool {
+        self.surface.is_some() && self.surface_format == wgpu::TextureFormat::Rgba16Float
+    }
+
+    /// Records whether the surface presents in extended range, as set by
+    /// [crate::platform::Platform::set_extended_range]. While it does, the screen pass tonemaps up
+    /// to [GpuContext::set_output_peak] instead of 1.
+    pub const fn set_hdr_active(&mut self, active: bool) {
+        self.hdr_active = active;
+    }
+
+    pub const fn hdr_active(&self) -> bool {
+        self.hdr_active
+    }
+
+    /// Display peak over SDR white for HDR output (see
+    /// [crate::render_settings::RenderSettings::hdr_peak]).
+    pub const fn set_output_peak(&mut self, peak: f32) {
+        self.output_peak = peak;
     }
 
     pub fn main_depth_view(&self) -> Option<&wgpu::TextureView> {
@@ -97,7 +187,11 @@ impl GpuContext {
     pub fn render_clear(&mut self, width: u32, height: u32) {
         self.configure_surface(width, height);
 
-        let Ok(frame) = self.surface.get_current_texture() else {
+        let Some(Ok(frame)) = self
+            .surface
+            .as_ref()
+            .map(wgpu::Surface::get_current_texture)
+        else {
             return;
         };
         let view = frame
@@ -136,6 +230,66 @@ impl GpuContext {
         frame.present();
     }
 
+    /// Iteration limits for the warehouse raymarch (see [crate::render_settings::RenderSettings]).
+    pub const fn set_raymarch_steps(&mut self, march: u32, shadow: u32) {
+        self.raymarch_steps = (march, shadow);
+    }
+
+    /// Bloom multiplier for the screen pass (1 = default look, 0 = no bloom).
+    pub const fn set_bloom_intensity(&mut self, intensity: f32) {
+        self.bloom_intensity = intensity;
+    }
+
+    /// This frame's music spectrum ([crate::audio::AudioFrame::bands]) for the screen pass.
+    pub const fn set_audio_bands(&mut self, bands: [f32; BANDS]) {
+        self.audio_bands = bands;
+    }
+
+    /// Screen pass exposure: `ev100` (see [crate::camera::CameraExposure::ev100]) brightened by
+    /// `compensation` stops, or with `adaptation` the auto-exposure pass's result (compensated the
+    /// same way), eased towards this frame's over `delta_s` seconds.
+    pub fn set_exposure(
+        &mut self,
+        ev100: f32,
+        compensation: f32,
+        adaptation: Option<EyeAdaptation>,
+        delta_s: f32,
+    ) {
+        self.exposure
+            .set(&self.queue, ev100, compensation, adaptation, delta_s);
+    }
+
+    /// Tonemap operator, grading and LUTs for the screen pass; uploads `luts` when they changed.
+    pub fn set_color_grading(
+        &mut self,
+        tonemapper: Tonemapper,
+        grading: ColorGrading,
+        luts: &ColorLuts,
+    ) {
+        self.tonemapper = tonemapper;
+        self.grading = grading;
+        self.luts.update(&self.device, &self.queue, luts);
+    }
+
+    /// Records the auto-exposure pass over `resolve_view` (`width` x `height`); no-op unless
+    /// [GpuContext::set_exposure] turned adaptation on.
+    pub fn run_exposure_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        resolve_view: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+    ) {
+        self.exposure.run(
+            &self.device,
+            &self.queue,
+            encoder,
+            resolve_view,
+            width,
+            height,
+        );
+    }
+
     pub fn draw_warehouse(
         &self,
         pass: &mut wgpu::RenderPass,
@@ -145,13 +299,16 @@ impl GpuContext {
         fb_height: u32,
         background_only: bool,
     ) {
-        let uniforms = WarehouseUniforms::from_view(view, time_s, fb_width, fb_height, background_only);
+        let mut uniforms =
+            WarehouseUniforms::from_view(view, time_s, fb_width, fb_height, background_only);
+        (uniforms.march_steps, uniforms.shadow_steps) = self.raymarch_steps;
         self.queue
             .write_buffer(&self.warehouse_uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
         pass.set_pipeline(&self.warehouse_pipeline);
         pass.set_bind_group(0, &self.warehouse_bind_group, &[]);
         pass.set_vertex_buffer(0, self.fullscreen_vertex_buffer.slice(..));
         pass.draw(0..3, 0..1);
+        stats::record_draw(1);
     }
 
     pub fn draw_warehouse_gbuffer(
@@ -162,63 +319,42 @@ impl GpuContext {
         fb_width: u32,
         fb_height: u32,
     ) {
-        let uniforms = WarehouseUniforms::from_view(view, time_s, fb_width, fb_height, false);
+        let mut uniforms = WarehouseUniforms::from_view(view, time_s, fb_width, fb_height, false);
+        (uniforms.march_steps, uniforms.shadow_steps) = self.raymarch_steps;
         self.queue
             .write_buffer(&self.warehouse_uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
         pass.set_pipeline(&self.warehouse_gbuffer_pipeline);
         pass.set_bind_group(0, &self.warehouse_bind_group, &[]);
         pass.set_vertex_buffer(0, self.fullscreen_vertex_buffer.slice(..));
         pass.draw(0..3, 0..1);
+        stats::record_draw(1);
     }
 
-    pub fn ensure_gbuffer(&mut self, width: u32, height: u32, gbuffer_rc: &Rc<RefCell<Option<GbufferSet>>>) {
-        if width == 0 || height == 0 {
-            panic!("ensure_gbuffer called with zero size");
-        }
-        let need_new = gbuffer_rc
-            .borrow()
-            .as_ref()
-            .map(|g| g.width != width || g.height != height)
-            .unwrap_or(true);
-        if need_new {
-            *gbuffer_rc.borrow_mut() = Some(GbufferSet::new(&self.device, width, height));
-        }
-    }
-
-    pub fn taa_history_index(&mut self) -> usize {
-        let i = self.taa_history_index;
-        self.taa_history_index = 1 - self.taa_history_index;
-        i
-    }
-
+    /// Records TAA: color + velocity + previous history -> resolve and next history (MRT).
     pub fn run_taa_pass(
         &self,
         encoder: &mut wgpu::CommandEncoder,
-        gbuffer: &GbufferSet,
-        history_read_index: usize,
+        color_view: &wgpu::TextureView,
+        velocity_view: &wgpu::TextureView,
+        history_read_view: &wgpu::TextureView,
+        resolve_view: &wgpu::TextureView,
+        history_write_view: &wgpu::TextureView,
     ) {
-        let history_write_index = 1 - history_read_index;
-        let color_view = gbuffer.color_view();
-        let velocity_view = gbuffer.velocity_view();
-        let history_read_view = gbuffer.history_view(history_read_index);
-        let resolve_view = gbuffer.resolve_view();
-        let history_write_view = gbuffer.history_view(history_write_index);
-
         let taa_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("taa_bind_group"),
             layout: &self.taa_bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&color_view),
+                    resource: wgpu::BindingResource::TextureView(color_view),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&velocity_view),
+                    resource: wgpu::BindingResource::TextureView(velocity_view),
                 },
                 wgpu::BindGroupEntry {
                     binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&history_read_view),
+                    resource: wgpu::BindingResource::TextureView(history_read_view),
                 },
                 wgpu::BindGroupEntry {
                     binding: 3,
@@ -232,7 +368,7 @@ impl GpuContext {
                 label: Some("taa"),
                 color_attachments: &[
                     Some(wgpu::RenderPassColorAttachment {
-                        view: &resolve_view,
+                        view: resolve_view,
                         resolve_target: None,
                         ops: wgpu::Operations {
                             load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -241,7 +377,7 @@ impl GpuContext {
                         depth_slice: None,
                     }),
                     Some(wgpu::RenderPassColorAttachment {
-                        view: &history_write_view,
+                        view: history_write_view,
                         resolve_target: None,
                         ops: wgpu::Operations {
                             load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -259,6 +395,7 @@ impl GpuContext {
             pass.set_bind_group(0, &taa_bind_group, &[]);
             pass.set_vertex_buffer(0, self.fullscreen_vertex_buffer.slice(..));
             pass.draw(0..3, 0..1);
+            stats::record_draw(1);
         }
     }
 
@@ -299,16 +436,20 @@ impl GpuContext {
         pass.set_bind_group(0, &bg, &[]);
         pass.set_vertex_buffer(0, self.fullscreen_vertex_buffer.slice(..));
         pass.draw(0..3, 0..1);
+        stats::record_draw(1);
     }
 
-    /// Runs the full bloom mip chain: brightness+downsample, downsample loop, lens, upsample loop.
+    /// Runs the bloom mip chain with `levels` downsample levels (2..=[BLOOM_MIP_LEVELS]):
+    /// brightness+downsample, downsample loop, lens, upsample loop back to mip[0].
     pub fn run_bloom_passes(
         &self,
         encoder: &mut wgpu::CommandEncoder,
         resolve_view: &wgpu::TextureView,
         gbuffer: &GbufferSet,
+        levels: usize,
     ) {
-        let n_downsample = BLOOM_MIP_LEVELS - 1; // 4
+        let levels = levels.clamp(2, BLOOM_MIP_LEVELS);
+        let n_downsample = levels - 1;
 
         // 1. Brightness extraction + initial downsample: resolve -> mip[0]
         let mip0_view = gbuffer.bloom_mip_view(0);
@@ -329,16 +470,23 @@ impl GpuContext {
             );
         }
 
-        // 3. Lens flare pass: mip[4] -> mip[5]
-        let lens_src = gbuffer.bloom_mip_view(BLOOM_MIP_LEVELS - 1);
+        // 3. Lens flare pass: smallest mip -> lens texture (sized like mip[BLOOM_MIP_LEVELS - 1])
+        self.mark_pass(encoder, "lens");
+        let lens_src = gbuffer.bloom_mip_view(levels - 1);
         let lens_dst = gbuffer.bloom_mip_view(BLOOM_MIP_LEVELS);
         let (w, h) = gbuffer.bloom_mip_size(BLOOM_MIP_LEVELS);
         self.run_fullscreen_tex_pass(
-            encoder, "bloom_lens", &self.lens_pipeline,
-            &lens_src, &lens_dst, w, h,
+            encoder,
+            "bloom_lens",
+            &self.lens_pipeline,
+            &lens_src,
+            &lens_dst,
+            w,
+            h,
         );
 
-        // 4. Upsample chain: mip[i+1] -> mip[i], first iteration reads from mip[5] (lens output)
+        // 4. Upsample chain: mip[i+1] -> mip[i], first iteration reads from the lens output
+        self.mark_pass(encoder, "bloom");
         for j in 0..n_downsample {
             let i = n_downsample - 1 - j;
             let src_idx = if j == 0 { BLOOM_MIP_LEVELS } else { i + 1 };
@@ -352,7 +500,30 @@ impl GpuContext {
         }
     }
 
-    /// Records screen pass: resolve + bloom mip[0] -> swap chain. ACES tonemap, sRGB, starburst, vignette.
+    /// Bilinear copy of `source_view` into all of `target_view` (`Rgba16Float`, `width` x
+    /// `height`). Used to carry TAA history over when the render size changes.
+    pub fn run_resample_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source_view: &wgpu::TextureView,
+        target_view: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+    ) {
+        self.run_fullscreen_tex_pass(
+            encoder,
+            "resample",
+            &self.resample_pipeline,
+            source_view,
+            target_view,
+            width,
+            height,
+        );
+    }
+
+    /// Records screen pass: resolve + bloom mip[0] -> swap chain. Starburst, vignette, exposure,
+    /// grading, tonemap, sRGB, grading LUT.
+    /// With a `transition`, blends from its outgoing scene to this one.
     pub fn run_screen_pass(
         &self,
         encoder: &mut wgpu::CommandEncoder,
@@ -360,18 +531,94 @@ impl GpuContext {
         bloom_view: &wgpu::TextureView,
         swap_chain_view: &wgpu::TextureView,
         camera_dir: [f32; 3],
+        transition: Option<&ScreenTransition>,
     ) {
-        let uniforms = ScreenUniforms { camera_dir, _pad: 0.0 };
-        self.queue.write_buffer(&self.screen_uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+        let (lut_domain_min, lut_domain_max) = self.luts.domain(LutKind::Grading);
+        let uniforms = ScreenUniforms {
+            camera_dir,
+            transition_mode: transition.map_or(0, |t| t.kind.shader_mode()),
+            transition_progress: transition.map_or(1.0, |t| t.progress),
+            bloom_intensity: self.bloom_intensity,
+            exposure: self.exposure.manual(),
+            tonemapper: self.tonemapper_mode(),
+            audio_bands: self.audio_bands,
+            white_balance: self
+                .grading
+                .white_balance()
+                .to_cols_array_2d()
+                .map(|[x, y, z]| [x, y, z, 0.0]),
+            lut_domain_min: lut_domain_min.to_array(),
+            // Grading LUTs are authored for SDR.
+            lut_strength: if self.luts.is_loaded(LutKind::Grading) && !self.hdr_active {
+                self.grading.lut_strength
+            } else {
+                0.0
+            },
+            lut_domain_max: lut_domain_max.to_array(),
+            contrast: self.grading.contrast,
+            saturation: self.grading.saturation,
+            output_peak: if self.hdr_active {
+                self.output_peak.max(1.0)
+            } else {
+                1.0
+            },
+            _pad: [0.0; 2],
+        };
+        // Without a transition the outgoing bindings are unused; bind this frame's views.
+        let (outgoing_resolve, outgoing_bloom) = transition
+            .map_or((resolve_view, bloom_view), |t| {
+                (&t.resolve_view, &t.bloom_view)
+            });
+        self.queue.write_buffer(
+            &self.screen_uniform_buffer,
+            0,
+            bytemuck::bytes_of(&uniforms),
+        );
+        if self.exposure.is_auto() {
+            // Queue writes land before the encoder's commands, so this overrides the manual value.
+            let offset = std::mem::offset_of!(ScreenUniforms, exposure) as u64;
+            self.exposure
+                .copy_exposure(encoder, &self.screen_uniform_buffer, offset);
+        }
 
         let bg = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("screen_bg"),
             layout: &self.screen_bind_group_layout,
             entries: &[
-                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(resolve_view) },
-                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(bloom_view) },
-                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&self.linear_sampler) },
-                wgpu::BindGroupEntry { binding: 3, resource: self.screen_uniform_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(resolve_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(bloom_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.linear_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.screen_uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(outgoing_resolve),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(outgoing_bloom),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(self.luts.view(LutKind::Grading)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: wgpu::BindingResource::TextureView(
+                        self.luts.view(LutKind::TonyMcMapface),
+                    ),
+                },
             ],
         });
         let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -394,6 +641,21 @@ impl GpuContext {
         pass.set_bind_group(0, &bg, &[]);
         pass.set_vertex_buffer(0, self.fullscreen_vertex_buffer.slice(..));
         pass.draw(0..3, 0..1);
+        stats::record_draw(1);
+    }
+
+    /// [Tonemapper::shader_mode] of the operator in use: Tony McMapface falls back to AgX without
+    /// its LUT. HDR output uses extended Reinhard (the one operator with a variable white) up
+    /// to the display peak.
+    fn tonemapper_mode(&self) -> u32 {
+        match self.tonemapper {
+            Tonemapper::None => Tonemapper::None.shader_mode(),
+            _ if self.hdr_active => Tonemapper::ReinhardExtended.shader_mode(),
+            Tonemapper::TonyMcMapface if !self.luts.is_loaded(LutKind::TonyMcMapface) => {
+                Tonemapper::Agx.shader_mode()
+            }
+            tonemapper => tonemapper.shader_mode(),
+        }
     }
 
     pub fn run_present_pass(
@@ -442,27 +704,115 @@ impl GpuContext {
         pass.set_bind_group(0, &present_bind_group, &[]);
         pass.set_vertex_buffer(0, self.fullscreen_vertex_buffer.slice(..));
         pass.draw(0..3, 0..1);
+        stats::record_draw(1);
     }
 }
 
-pub async fn init_gpu(canvas: web_sys::HtmlCanvasElement) -> Option<GpuContext> {
-    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-        #[cfg(target_arch = "wasm32")]
-        backends: wgpu::Backends::BROWSER_WEBGPU,
-        #[cfg(not(target_arch = "wasm32"))]
-        backends: wgpu::Backends::all(),
-        ..Default::default()
-    });
+impl GpuContext {
+    /// Builds every pipeline for `device`. `surface_format` is the format of the final (swap chain
+    /// or offscreen) target.
+    fn new(
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface: Option<wgpu::Surface<'static>>,
+        surface_format: wgpu::TextureFormat,
+    ) -> Self {
+        let (
+            warehouse_pipeline,
+            warehouse_gbuffer_pipeline,
+            warehouse_bind_group,
+            warehouse_uniform_buffer,
+            fullscreen_vertex_buffer,
+        ) = create_warehouse_pipelines(&device, surface_format);
 
-    #[cfg(target_arch = "wasm32")]
-    let surface = instance
-        .create_surface(wgpu::SurfaceTarget::Canvas(canvas))
-        .ok()?;
+        log!("[GPU] Creating TAA and present pipelines...");
+        let (
+            taa_pipeline,
+            taa_bind_group_layout,
+            present_pipeline,
+            present_bind_group_layout,
+            linear_sampler,
+        ) = create_taa_and_present_pipelines(&device, surface_format);
 
-    #[cfg(not(target_arch = "wasm32"))]
-    return None;
+        log!("[GPU] Creating post pipelines (brightness, downsample, upsample, lens, screen)...");
+        let (
+            bloom_tex_sampler_layout,
+            brightness_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            lens_pipeline,
+            resample_pipeline,
+            screen_pipeline,
+            screen_bind_group_layout,
+            screen_uniform_buffer,
+        ) = create_post_pipelines(&device, surface_format);
+        log!("[GPU] All pipelines created.");
+        let exposure = AutoExposure::new(&device);
+        let luts = LutTextures::new(&device);
+        let timer = GpuTimer::new(&device, &queue);
+
+        Self {
+            adapter,
+            device,
+            queue,
+            surface,
+            config_size: (0, 0),
+            surface_format,
+            warehouse_pipeline,
+            warehouse_gbuffer_pipeline,
+            warehouse_bind_group,
+            warehouse_uniform_buffer,
+            fullscreen_vertex_buffer,
+            linear_sampler,
+            taa_pipeline,
+            taa_bind_group_layout,
+            present_pipeline,
+            present_bind_group_layout,
+            bloom_tex_sampler_layout,
+            brightness_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            lens_pipeline,
+            resample_pipeline,
+            screen_pipeline,
+            screen_bind_group_layout,
+            screen_uniform_buffer,
+            main_depth: None,
+            main_depth_size: (0, 0),
+            raymarch_steps: (256, 32),
+            bloom_intensity: 1.0,
+            audio_bands: [0.0; BANDS],
+            exposure,
+            tonemapper: Tonemapper::default(),
+            grading: ColorGrading::default(),
+            luts,
+            hdr_active: false,
+            output_peak: 1.0,
+            timer,
+        }
+    }
+}
 
+/// wgpu instance for the current target: browser WebGPU on wasm32, any native backend otherwise.
+pub fn create_instance() -> wgpu::Instance {
     #[cfg(target_arch = "wasm32")]
+    let backends = wgpu::Backends::BROWSER_WEBGPU;
+    #[cfg(not(target_arch = "wasm32"))]
+    let backends = wgpu::Backends::all();
+    wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    })
+}
+
+/// Adapter/device init for a platform surface (canvas or window, see [crate::platform::Platform]).
+/// With `hdr_display`, picks an `Rgba16Float` surface when offered (see [GpuContext::hdr_capable]).
+pub async fn init_gpu(
+    instance: wgpu::Instance,
+    surface: wgpu::Surface<'static>,
+    hdr_display: bool,
+) -> Option<GpuContext> {
     let adapter = instance
         .request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::default(),
@@ -472,82 +822,61 @@ pub async fn init_gpu(canvas: web_sys::HtmlCanvasElement) -> Option<GpuContext>
         .await
         .ok()?;
 
-    #[cfg(target_arch = "wasm32")]
-    let (device, queue) = adapter
-        .request_device(&wgpu::DeviceDescriptor {
-            label: None,
-            required_features: wgpu::Features::empty(),
-            required_limits: wgpu::Limits::default(),
-            memory_hints: wgpu::MemoryHints::Performance,
-            ..Default::default()
-        })
-        .await
-        .ok()?;
+    let (device, queue) = request_device(&adapter).await?;
 
-    #[cfg(target_arch = "wasm32")]
-    let surface_format = surface
-        .get_default_config(&adapter, 1, 1)
-        .map(|c| c.format)
+    // The screen pass encodes sRGB itself, so prefer a non-sRGB swap chain format.
+    let caps = surface.get_capabilities(&adapter);
+    let hdr_format = wgpu::TextureFormat::Rgba16Float;
+    let surface_format = (hdr_display && caps.formats.contains(&hdr_format))
+        .then_some(hdr_format)
+        .or_else(|| caps.formats.iter().copied().find(|f| !f.is_srgb()))
+        .or_else(|| caps.formats.first().copied())
         .unwrap_or(wgpu::TextureFormat::Bgra8Unorm);
-    #[cfg(target_arch = "wasm32")]
-    let (warehouse_pipeline, warehouse_gbuffer_pipeline, warehouse_bind_group, warehouse_uniform_buffer, fullscreen_vertex_buffer) =
-        create_warehouse_pipelines(&device, surface_format);
-
-    #[cfg(target_arch = "wasm32")]
-    log!("[GPU] Creating TAA and present pipelines...");
-    #[cfg(target_arch = "wasm32")]
-    let (taa_pipeline, taa_bind_group_layout, present_pipeline, present_bind_group_layout, linear_sampler) =
-        create_taa_and_present_pipelines(&device, surface_format);
-
-    #[cfg(target_arch = "wasm32")]
-    log!("[GPU] Creating post pipelines (brightness, downsample, upsample, lens, screen)...");
-    #[cfg(target_arch = "wasm32")]
-    let (
-        bloom_tex_sampler_layout,
-        brightness_pipeline,
-        downsample_pipeline,
-        upsample_pipeline,
-        lens_pipeline,
-        screen_pipeline,
-        screen_bind_group_layout,
-        screen_uniform_buffer,
-    ) = create_post_pipelines(&device, surface_format);
-    #[cfg(target_arch = "wasm32")]
-    log!("[GPU] All pipelines created.");
 
-    #[cfg(target_arch = "wasm32")]
-    Some(GpuContext {
+    Some(GpuContext::new(
         adapter,
         device,
         queue,
-        surface,
-        config_size: (0, 0),
+        Some(surface),
         surface_format,
-        warehouse_pipeline,
-        warehouse_gbuffer_pipeline,
-        warehouse_bind_group,
-        warehouse_uniform_buffer,
-        fullscreen_vertex_buffer,
-        linear_sampler,
-        taa_pipeline,
-        taa_bind_group_layout,
-        present_pipeline,
-        present_bind_group_layout,
-        bloom_tex_sampler_layout,
-        brightness_pipeline,
-        downsample_pipeline,
-        upsample_pipeline,
-        lens_pipeline,
-        screen_pipeline,
-        screen_bind_group_layout,
-        screen_uniform_buffer,
-        taa_history_index: 0,
-        main_depth: None,
-        main_depth_size: (0, 0),
-    })
+    ))
+}
+
+/// Native init without a surface: final passes render into an offscreen `color_format` texture
+/// owned by the caller (see [crate::offline]).
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn init_gpu_headless(color_format: wgpu::TextureFormat) -> Option<GpuContext> {
+    let instance = create_instance();
+
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        })
+        .await
+        .ok()?;
+
+    let (device, queue) = request_device(&adapter).await?;
+
+    Some(GpuContext::new(adapter, device, queue, None, color_format))
+}
+
+async fn request_device(adapter: &wgpu::Adapter) -> Option<(wgpu::Device, wgpu::Queue)> {
+    // Optional: frame and per-pass timing for dynamic resolution and the HUD (see GpuTimer).
+    let optional_features = adapter.features() & wgpu::Features::TIMESTAMP_QUERY;
+    adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: None,
+            required_features: optional_features,
+            required_limits: wgpu::Limits::default(),
+            memory_hints: wgpu::MemoryHints::Performance,
+            ..Default::default()
+        })
+        .await
+        .ok()
 }
 
-#[cfg(target_arch = "wasm32")]
 fn create_warehouse_pipelines(
     device: &wgpu::Device,
     color_format: wgpu::TextureFormat,
@@ -703,7 +1032,6 @@ fn create_warehouse_pipelines(
     )
 }
 
-#[cfg(target_arch = "wasm32")]
 fn create_taa_and_present_pipelines(
     device: &wgpu::Device,
     surface_format: wgpu::TextureFormat,
@@ -862,7 +1190,6 @@ fn create_taa_and_present_pipelines(
     (taa_pipeline, taa_bind_group_layout, present_pipeline, present_bind_group_layout, linear_sampler)
 }
 
-#[cfg(target_arch = "wasm32")]
 fn create_post_pipelines(
     device: &wgpu::Device,
     surface_format: wgpu::TextureFormat,
@@ -873,6 +1200,7 @@ fn create_post_pipelines(
     wgpu::RenderPipeline,
     wgpu::RenderPipeline,
     wgpu::RenderPipeline,
+    wgpu::RenderPipeline,
     wgpu::BindGroupLayout,
     wgpu::Buffer,
 ) {
@@ -946,8 +1274,10 @@ fn create_post_pipelines(
     let downsample_pipeline = make_bloom_pipeline("downsample", BLUR_WGSL);
     let upsample_pipeline = make_bloom_pipeline("upsample", BLUR_UPSAMPLE_WGSL);
     let lens_pipeline = make_bloom_pipeline("lens", LENS_WGSL);
+    let resample_pipeline = make_bloom_pipeline("resample", PRESENT_WGSL);
 
-    // Screen pipeline: 2 textures + sampler + uniform buffer
+    // Screen pipeline: 2 textures + sampler + uniform buffer, plus the outgoing scene's 2 textures
+    // and 2 LUTs
     let screen_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: Some("screen_parity"),
         source: wgpu::ShaderSource::Wgsl(SCREEN_PARITY_WGSL.into()),
@@ -991,6 +1321,46 @@ fn create_post_pipelines(
                 },
                 count: None,
             },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 5,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 6,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 7,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D3,
+                    multisampled: false,
+                },
+                count: None,
+            },
         ],
     });
     let screen_pl = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -1037,6 +1407,7 @@ fn create_post_pipelines(
         downsample_pipeline,
         upsample_pipeline,
         lens_pipeline,
+        resample_pipeline,
         screen_pipeline,
         screen_layout,
         screen_uniform_buffer,
@@ -0,0 +1,255 @@
+//! Auto-exposure: a luminance histogram of the resolve, averaged and eased over time into the
+//! exposure the screen pass applies before tonemapping. The result stays on the GPU; the screen
+//! pass's uniforms pick it up with a buffer copy.
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::camera::exposure_from_ev100;
+
+use super::stats;
+
+const EXPOSURE_WGSL: &str = include_str!("../wgsl/exposure.wgsl");
+
+/// Histogram workgroup size per axis (see `build_histogram` in `exposure.wgsl`).
+const WORKGROUP_SIZE: u32 = 16;
+const BINS: u64 = 256;
+/// Matches `EV_OFFSET` in `exposure.wgsl`: EV100 of an average luminance of 1.
+const EV_OFFSET: f32 = 3.0;
+
+/// How the eye adapts: EV100s it settles between, and how fast it gets there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EyeAdaptation {
+    /// Per second; each second covers `1 - exp(-speed)` of the way to the target.
+    pub speed: f32,
+    pub min_ev: f32,
+    pub max_ev: f32,
+}
+
+/// Must match WGSL ExposureUniforms.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ExposureUniforms {
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation: f32,
+    compensation: f32,
+    min_ev: f32,
+    max_ev: f32,
+}
+
+/// Must match WGSL ExposureState.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ExposureState {
+    ev100: f32,
+    exposure: f32,
+}
+
+/// Pipelines and buffers of the auto-exposure pass, plus this frame's exposure settings
+/// (see [super::GpuContext::set_exposure]).
+pub struct AutoExposure {
+    histogram_pipeline: wgpu::ComputePipeline,
+    average_pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+    histogram_buffer: wgpu::Buffer,
+    /// [ExposureState], adapted in place frame to frame.
+    state_buffer: wgpu::Buffer,
+    ev100: f32,
+    compensation: f32,
+    adaptation: Option<EyeAdaptation>,
+    delta_s: f32,
+}
+
+impl AutoExposure {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let compute = wgpu::ShaderStages::COMPUTE;
+        let storage = wgpu::BufferBindingType::Storage { read_only: false };
+        let buffer_entry = |binding: u32, ty: wgpu::BufferBindingType| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: compute,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("exposure_bind_layout"),
+            entries: &[
+                buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                buffer_entry(1, storage),
+                buffer_entry(2, storage),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: compute,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("exposure_pl"),
+            bind_group_layouts: &[&bind_group_layout],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("exposure"),
+            source: wgpu::ShaderSource::Wgsl(EXPOSURE_WGSL.into()),
+        });
+        let make_pipeline = |entry_point: &'static str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let buffer = |label: &'static str, size: u64, usage: wgpu::BufferUsages| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        Self {
+            histogram_pipeline: make_pipeline("build_histogram"),
+            average_pipeline: make_pipeline("average"),
+            bind_group_layout,
+            uniform_buffer: buffer(
+                "exposure_uniforms",
+                std::mem::size_of::<ExposureUniforms>() as u64,
+                wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            ),
+            histogram_buffer: buffer("exposure_histogram", BINS * 4, wgpu::BufferUsages::STORAGE),
+            state_buffer: buffer(
+                "exposure_state",
+                std::mem::size_of::<ExposureState>() as u64,
+                wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+            ),
+            ev100: 0.0,
+            compensation: 0.0,
+            adaptation: None,
+            delta_s: 0.0,
+        }
+    }
+
+    /// See [super::GpuContext::set_exposure]. Switching adaptation on starts it from `ev100`, so
+    /// the image doesn't jump.
+    pub fn set(
+        &mut self,
+        queue: &wgpu::Queue,
+        ev100: f32,
+        compensation: f32,
+        adaptation: Option<EyeAdaptation>,
+        delta_s: f32,
+    ) {
+        if adaptation.is_some() && self.adaptation.is_none() {
+            let state = ExposureState {
+                ev100,
+                exposure: exposure_from_ev100(ev100, compensation),
+            };
+            queue.write_buffer(&self.state_buffer, 0, bytemuck::bytes_of(&state));
+        }
+        self.ev100 = ev100;
+        self.compensation = compensation;
+        self.adaptation = adaptation;
+        self.delta_s = delta_s;
+    }
+
+    /// Whether the screen pass takes its exposure from [AutoExposure::run].
+    pub const fn is_auto(&self) -> bool {
+        self.adaptation.is_some()
+    }
+
+    /// Exposure from the manual settings.
+    pub fn manual(&self) -> f32 {
+        exposure_from_ev100(self.ev100, self.compensation)
+    }
+
+    /// Histograms `resolve_view` (`width` x `height`) and adapts the exposure towards its average.
+    /// No-op without adaptation.
+    pub fn run(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        resolve_view: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+    ) {
+        let Some(adaptation) = self.adaptation else {
+            return;
+        };
+        // A few stops of headroom either side of the EV range, so the average isn't clipped to it.
+        let min_log_luminance = adaptation.min_ev - EV_OFFSET - 2.0;
+        let max_log_luminance = adaptation.max_ev - EV_OFFSET + 2.0;
+        let uniforms = ExposureUniforms {
+            min_log_luminance,
+            log_luminance_range: (max_log_luminance - min_log_luminance).max(1.0),
+            adaptation: 1.0 - (-self.delta_s.max(0.0) * adaptation.speed).exp(),
+            compensation: self.compensation,
+            min_ev: adaptation.min_ev,
+            max_ev: adaptation.max_ev,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("exposure_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.histogram_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.state_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(resolve_view),
+                },
+            ],
+        });
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("exposure"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.set_pipeline(&self.histogram_pipeline);
+        pass.dispatch_workgroups(
+            width.div_ceil(WORKGROUP_SIZE),
+            height.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+        stats::record_dispatch();
+        pass.set_pipeline(&self.average_pipeline);
+        pass.dispatch_workgroups(1, 1, 1);
+        stats::record_dispatch();
+    }
+
+    /// Copies the adapted exposure into `buffer` at `offset` (the screen pass's uniforms).
+    pub fn copy_exposure(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        buffer: &wgpu::Buffer,
+        offset: u64,
+    ) {
+        encoder.copy_buffer_to_buffer(&self.state_buffer, 4, buffer, offset, 4);
+    }
+}
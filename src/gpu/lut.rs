@@ -0,0 +1,108 @@
+//! GPU copies of the screen pass's 3D LUTs ([crate::color_grading::ColorLuts]), as `Rgb9e5Ufloat`
+//! volume textures.
+
+use glam::Vec3;
+
+use crate::color_grading::{ColorLuts, Lut3d, LutKind};
+
+struct GpuLut {
+    _texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    domain_min: Vec3,
+    domain_max: Vec3,
+}
+
+/// The uploaded LUTs, plus a 1³ placeholder bound in place of a missing one.
+pub struct LutTextures {
+    grading: Option<GpuLut>,
+    tony_mc_mapface: Option<GpuLut>,
+    placeholder: wgpu::TextureView,
+    /// [ColorLuts::revision] last uploaded.
+    revision: Option<u32>,
+}
+
+impl LutTextures {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            grading: None,
+            tony_mc_mapface: None,
+            placeholder: create_texture(device, "lut_placeholder", 1)
+                .create_view(&Default::default()),
+            revision: None,
+        }
+    }
+
+    /// Uploads `luts` if they changed since the last call.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, luts: &ColorLuts) {
+        if self.revision == Some(luts.revision()) {
+            return;
+        }
+        self.revision = Some(luts.revision());
+        let upload = |kind: LutKind, label: &'static str| {
+            luts.get(kind).map(|lut| upload(device, queue, label, lut))
+        };
+        self.grading = upload(LutKind::Grading, "grading_lut");
+        self.tony_mc_mapface = upload(LutKind::TonyMcMapface, "tony_mc_mapface_lut");
+    }
+
+    pub const fn is_loaded(&self, kind: LutKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    /// The `kind` LUT's view, or the placeholder.
+    pub fn view(&self, kind: LutKind) -> &wgpu::TextureView {
+        self.get(kind).map_or(&self.placeholder, |lut| &lut.view)
+    }
+
+    /// Input range of the `kind` LUT (0..1 without one).
+    pub fn domain(&self, kind: LutKind) -> (Vec3, Vec3) {
+        self.get(kind).map_or((Vec3::ZERO, Vec3::ONE), |lut| {
+            (lut.domain_min, lut.domain_max)
+        })
+    }
+
+    const fn get(&self, kind: LutKind) -> Option<&GpuLut> {
+        match kind {
+            LutKind::Grading => self.grading.as_ref(),
+            LutKind::TonyMcMapface => self.tony_mc_mapface.as_ref(),
+        }
+    }
+}
+
+fn create_texture(device: &wgpu::Device, label: &'static str, size: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: size,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D3,
+        format: wgpu::TextureFormat::Rgb9e5Ufloat,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn upload(device: &wgpu::Device, queue: &wgpu::Queue, label: &'static str, lut: &Lut3d) -> GpuLut {
+    let texture = create_texture(device, label, lut.size);
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&lut.to_rgb9e5()),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(lut.size * 4),
+            rows_per_image: Some(lut.size),
+        },
+        texture.size(),
+    );
+    let view = texture.create_view(&Default::default());
+    GpuLut {
+        _texture: texture,
+        view,
+        domain_min: lut.domain_min,
+        domain_max: lut.domain_max,
+    }
+}
@@ -1,10 +1,19 @@
 //! WebGPU backend (wgpu). Device, queue, surface, pipelines, and targets.
 
 mod context;
+mod exposure;
+mod lut;
+pub mod stats;
 mod targets;
+mod timer;
 mod warehouse;
 
-pub use context::GpuContext;
-pub use context::init_gpu;
-pub use targets::GbufferSet;
+#[cfg(not(target_arch = "wasm32"))]
+pub use context::init_gpu_headless;
+pub use context::{create_instance, init_gpu};
+pub use context::{GpuContext, ScreenTransition};
+pub use exposure::EyeAdaptation;
+pub use stats::DrawStats;
+pub use targets::{GbufferSet, BLOOM_MIP_COUNT, BLOOM_MIP_LEVELS};
+pub use timer::GpuTimings;
 pub use warehouse::{WarehouseUniforms, FULLSCREEN_TRIANGLE};
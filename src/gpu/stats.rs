@@ -0,0 +1,54 @@
+//! Per-frame draw statistics for the HUD: every draw and dispatch site reports here, and the frame
+//! loop collects the totals with [take] once the frame is recorded.
+
+use std::cell::Cell;
+
+/// Commands recorded in one frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DrawStats {
+    /// Direct and indirect draw calls.
+    pub draw_calls: u32,
+    /// Instances of the direct draws (indirect counts live on the GPU).
+    pub instances: u64,
+    /// Draw calls whose counts come from a GPU buffer.
+    pub indirect_draws: u32,
+    pub dispatches: u32,
+}
+
+thread_local! {
+    static STATS: Cell<DrawStats> = Cell::new(DrawStats::default());
+}
+
+fn update(f: impl FnOnce(&mut DrawStats)) {
+    STATS.with(|stats| {
+        let mut value = stats.get();
+        f(&mut value);
+        stats.set(value);
+    });
+}
+
+/// A direct draw of `instances` instances.
+pub fn record_draw(instances: u32) {
+    update(|s| {
+        s.draw_calls += 1;
+        s.instances += u64::from(instances);
+    });
+}
+
+/// An indirect draw.
+pub fn record_indirect_draw() {
+    update(|s| {
+        s.draw_calls += 1;
+        s.indirect_draws += 1;
+    });
+}
+
+/// A compute dispatch.
+pub fn record_dispatch() {
+    update(|s| s.dispatches += 1);
+}
+
+/// Totals since the last call.
+pub fn take() -> DrawStats {
+    STATS.take()
+}
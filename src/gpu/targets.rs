@@ -1,4 +1,5 @@
-//! Render targets: G-buffer (color, depth reversed-Z, velocity), TAA history, resolve, bloom mip chain.
+//! Render targets: G-buffer (color, depth reversed-Z, velocity), TAA history, resolve,
+//! bloom mip chain.
 
 pub const BLOOM_MIP_LEVELS: usize = 5;
 pub const BLOOM_MIP_COUNT: usize = BLOOM_MIP_LEVELS + 1;
@@ -0,0 +1,230 @@
+//! GPU frame timer: timestamp queries around the frame and between its passes,
+//! read back asynchronously.
+//!
+//! Only available when the adapter supports [wgpu::Features::TIMESTAMP_QUERY]. Between
+//! [GpuTimer::begin] and [GpuTimer::end], [GpuTimer::mark] starts a named span that runs until the
+//! next mark (or the end); spans with the same name are summed. Results arrive a frame or two late;
+//! callers use whatever [GpuTimer::read] has, and skip frames without a result.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+/// Readback buffers in flight; a frame without a free one is simply not timed.
+const READBACK_COUNT: usize = 3;
+/// Timestamps per frame: begin, end, and up to 30 marks. Later marks are ignored (their time goes
+/// to the span before).
+const MAX_TIMESTAMPS: u32 = 32;
+const TIMESTAMP_SIZE: u64 = std::mem::size_of::<u64>() as u64;
+
+const IDLE: u8 = 0;
+const MAPPING: u8 = 1;
+const MAPPED: u8 = 2;
+
+/// One frame's result from [GpuTimer::read].
+#[derive(Clone, Debug, Default)]
+pub struct GpuTimings {
+    /// [GpuTimer::begin] to [GpuTimer::end], in milliseconds.
+    pub frame_ms: f64,
+    /// Milliseconds per [GpuTimer::mark] name, in the order first marked.
+    pub passes: Vec<(&'static str, f64)>,
+}
+
+struct Readback {
+    buffer: wgpu::Buffer,
+    state: Arc<AtomicU8>,
+    /// Span started by each timestamp copied into `buffer` (`None` before the first mark and
+    /// at the end).
+    spans: Vec<Option<&'static str>>,
+    /// [GpuTimer::end] call that wrote `buffer`, to tell the newest of several mapped readbacks.
+    serial: u64,
+}
+
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readbacks: Vec<Readback>,
+    /// Names of the spans started by this frame's timestamps so far; empty outside begin / end.
+    spans: RefCell<Vec<Option<&'static str>>>,
+    /// Readback written by [GpuTimer::end], mapped after submit.
+    pending: Option<usize>,
+    /// Frames ended so far (see [Readback::serial]).
+    serial: u64,
+    /// Nanoseconds per timestamp tick.
+    period_ns: f64,
+}
+
+impl GpuTimer {
+    /// `None` if the device was created without [wgpu::Features::TIMESTAMP_QUERY].
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("frame_timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: MAX_TIMESTAMPS,
+        });
+        let size = u64::from(MAX_TIMESTAMPS) * TIMESTAMP_SIZE;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame_timestamps_resolve"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readbacks = (0..READBACK_COUNT)
+            .map(|_| Readback {
+                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("frame_timestamps_readback"),
+                    size,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                state: Arc::new(AtomicU8::new(IDLE)),
+                spans: Vec::new(),
+                serial: 0,
+            })
+            .collect();
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readbacks,
+            spans: RefCell::new(Vec::new()),
+            pending: None,
+            serial: 0,
+            period_ns: f64::from(queue.get_timestamp_period()),
+        })
+    }
+
+    /// Writes the start timestamp. Call before recording the frame's first pass.
+    pub fn begin(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.spans.borrow_mut().clear();
+        self.write_timestamp(encoder, None);
+    }
+
+    /// Starts a span called `name` that lasts until the next mark. No-op outside begin / end.
+    pub fn mark(&self, encoder: &mut wgpu::CommandEncoder, name: &'static str) {
+        let count = self.spans.borrow().len() as u32;
+        // One timestamp stays reserved for the end.
+        if count > 0 && count < MAX_TIMESTAMPS - 1 {
+            self.write_timestamp(encoder, Some(name));
+        }
+    }
+
+    /// Writes the end timestamp and queues the copy to a free readback buffer.
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.spans.get_mut().is_empty() {
+            return;
+        }
+        self.write_timestamp(encoder, None);
+        let spans = std::mem::take(self.spans.get_mut());
+        let count = spans.len() as u32;
+        let Some(index) = self
+            .readbacks
+            .iter()
+            .position(|r| r.state.load(Ordering::Acquire) == IDLE)
+        else {
+            return;
+        };
+        let size = u64::from(count) * TIMESTAMP_SIZE;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readbacks[index].buffer,
+            0,
+            size,
+        );
+        self.serial += 1;
+        self.readbacks[index].spans = spans;
+        self.readbacks[index].serial = self.serial;
+        self.pending = Some(index);
+    }
+
+    /// Starts mapping the buffer written by [GpuTimer::end]. Call after submitting the encoder.
+    pub fn after_submit(&mut self) {
+        let Some(index) = self.pending.take() else {
+            return;
+        };
+        let readback = &self.readbacks[index];
+        let state = readback.state.clone();
+        state.store(MAPPING, Ordering::Release);
+        let size = readback.spans.len() as u64 * TIMESTAMP_SIZE;
+        readback
+            .buffer
+            .slice(..size)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                state.store(
+                    if result.is_ok() { MAPPED } else { IDLE },
+                    Ordering::Release,
+                );
+            });
+    }
+
+    /// Timings of the newest frame among the readbacks that finished mapping, if any. All mapped
+    /// readbacks are released.
+    pub fn read(&self, device: &wgpu::Device) -> Option<GpuTimings> {
+        // Native backends only run map callbacks when polled; on the web this is a no-op.
+        let _ = device.poll(wgpu::PollType::Poll);
+        let mut latest: Option<(u64, GpuTimings)> = None;
+        for readback in &self.readbacks {
+            if readback.state.load(Ordering::Acquire) != MAPPED {
+                continue;
+            }
+            {
+                let size = readback.spans.len() as u64 * TIMESTAMP_SIZE;
+                let data = readback.buffer.slice(..size).get_mapped_range();
+                let ticks: &[u64] = bytemuck::cast_slice(&data);
+                if latest
+                    .as_ref()
+                    .is_none_or(|(serial, _)| readback.serial > *serial)
+                {
+                    let timings = self.timings(ticks, &readback.spans);
+                    latest = timings.map(|timings| (readback.serial, timings)).or(latest);
+                }
+            }
+            readback.buffer.unmap();
+            readback.state.store(IDLE, Ordering::Release);
+        }
+        latest.map(|(_, timings)| timings)
+    }
+
+    /// Sums the spans between consecutive `ticks`; `None` if the timestamps aren't increasing
+    /// (some backends return zeros for a while).
+    fn timings(&self, ticks: &[u64], spans: &[Option<&'static str>]) -> Option<GpuTimings> {
+        let (&first, &last) = (ticks.first()?, ticks.last()?);
+        if last <= first {
+            return None;
+        }
+        let to_ms = |ticks: u64| ticks as f64 * self.period_ns / 1_000_000.0;
+        let mut timings = GpuTimings {
+            frame_ms: to_ms(last - first),
+            passes: Vec::new(),
+        };
+        for (pair, span) in ticks.windows(2).zip(spans) {
+            let Some(name) = *span else {
+                continue;
+            };
+            let ms = to_ms(pair[1].saturating_sub(pair[0]));
+            match timings.passes.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, total)) => *total += ms,
+                None => timings.passes.push((name, ms)),
+            }
+        }
+        Some(timings)
+    }
+
+    /// Empty compute pass whose only job is to write the next timestamp, starting span `name`.
+    fn write_timestamp(&self, encoder: &mut wgpu::CommandEncoder, name: Option<&'static str>) {
+        let mut spans = self.spans.borrow_mut();
+        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("frame_timestamp"),
+            timestamp_writes: Some(wgpu::ComputePassTimestampWrites {
+                query_set: &self.query_set,
+                beginning_of_pass_write_index: Some(spans.len() as u32),
+                end_of_pass_write_index: None,
+            }),
+        });
+        spans.push(name);
+    }
+}
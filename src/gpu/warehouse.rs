@@ -24,14 +24,19 @@ pub struct WarehouseUniforms {
     _pad_after_resolution: f32,                        // offset 412  (pad to 416 for vec2 align 8)
     pub viewport_origin: [f32; 2],                     // offset 416
     pub viewport_size: [f32; 2],                       // offset 424
-    _pad1: [f32; 2],                                   // offset 432
+    /// 1.0 = solid background, no raymarch.
+    pub background_only: f32,                          // offset 432
+    /// Soft shadow march iterations.
+    pub shadow_steps: u32,                             // offset 436
     /// Diagonal FOV (radians) matching reference UV convention; zoom = 1/tan(fov/2).
     pub fov: f32,                                      // offset 440
-    _struct_end_pad: f32,                              // offset 444  (round struct to 448 = 16*28)
+    /// Primary march iterations (clamped to `maxIterations` in WGSL). Struct ends at 448 = 16*28.
+    pub march_steps: u32,                              // offset 444
 }
 
 impl WarehouseUniforms {
-    /// When `background_only` is true, shader outputs solid gray (no raymarch). Use when warehouse is disabled so cubes draw on top.
+    /// When `background_only` is true, shader outputs solid gray (no raymarch). Use when warehouse
+    /// is disabled so cubes draw on top.
     pub fn from_view(
         view: &ViewState,
         time_s: f32,
@@ -59,9 +64,10 @@ impl WarehouseUniforms {
             _pad_after_resolution: 0.0,
             viewport_origin: [vx as f32, vy as f32],
             viewport_size: [vw as f32, vh as f32],
-            _pad1: [if background_only { 1.0 } else { 0.0 }, 0.0],
+            background_only: if background_only { 1.0 } else { 0.0 },
+            shadow_steps: 32,
             fov,
-            _struct_end_pad: 0.0,
+            march_steps: 256,
         }
     }
 }
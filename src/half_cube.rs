@@ -6,6 +6,8 @@
 use wgpu::RenderPass;
 use wgpu::util::DeviceExt;
 
+use crate::gpu::stats;
+
 const CUBE_WGSL: &str = include_str!("wgsl/cube.wgsl");
 const CUBE_GBUFFER_WGSL: &str = include_str!("wgsl/cube_gbuffer.wgsl");
 
@@ -344,6 +346,7 @@ impl HalfCubeGpu {
         pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
         pass.draw_indexed(0..self.index_count, 0, 0..count);
+        stats::record_draw(count);
     }
 
     fn draw_gbuffer(&self, pass: &mut RenderPass<'_>, view: &crate::view::ViewState, count: u32) {
@@ -378,5 +381,6 @@ impl HalfCubeGpu {
         pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
         pass.draw_indexed(0..self.index_count, 0, 0..count);
+        stats::record_draw(count);
     }
 }
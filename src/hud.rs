@@ -0,0 +1,229 @@
+//! Performance HUD: frame time graph, CPU update / render time, draw statistics and per-pass GPU
+//! timings, drawn with [crate::text] in the top-left corner of every view.
+//!
+//! Shown while [crate::render_settings::RenderSettings::hud] is on (F3, or
+//! `setRenderFeature("hud", …)` on the web). GPU timings need [wgpu::Features::TIMESTAMP_QUERY];
+//! without it the HUD says so and shows everything else. Numbers are smoothed so they can be read;
+//! the graph shows raw frame times.
+
+use glam::{Vec2, Vec4};
+
+use crate::app::App;
+use crate::gpu::{DrawStats, GpuTimings};
+use crate::text;
+
+/// Frames in the frame time graph.
+const HISTORY: usize = 120;
+/// Line height in pixels.
+const TEXT_SIZE: f32 = 14.0;
+/// Frame time graph size in pixels; its top is twice the frame budget.
+const GRAPH_SIZE: Vec2 = Vec2::new(240.0, 48.0);
+/// Panel position and padding in pixels.
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+/// Weight of the newest sample in the smoothed numbers.
+const SMOOTHING: f64 = 0.1;
+
+const BACKGROUND: Vec4 = Vec4::new(0.0, 0.0, 0.0, 0.6);
+const TEXT_COLOR: Vec4 = Vec4::new(0.9, 0.9, 0.9, 1.0);
+const DIM_COLOR: Vec4 = Vec4::new(0.6, 0.6, 0.6, 1.0);
+const GOOD_COLOR: Vec4 = Vec4::new(0.3, 0.9, 0.4, 1.0);
+const SLOW_COLOR: Vec4 = Vec4::new(1.0, 0.8, 0.2, 1.0);
+const BAD_COLOR: Vec4 = Vec4::new(1.0, 0.3, 0.25, 1.0);
+
+/// Collects per-frame measurements from [crate::app::Runner] and draws them.
+pub struct Hud {
+    /// Frame times in milliseconds, oldest first from `next`.
+    frame_ms: [f32; HISTORY],
+    next: usize,
+    /// Entries of `frame_ms` written so far (up to [HISTORY]).
+    recorded: usize,
+    cpu_update_ms: f64,
+    cpu_render_ms: f64,
+    draws: DrawStats,
+    /// Smoothed GPU timings; `None` until the first readback.
+    gpu: Option<GpuTimings>,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hud {
+    pub const fn new() -> Self {
+        Self {
+            frame_ms: [0.0; HISTORY],
+            next: 0,
+            recorded: 0,
+            cpu_update_ms: 0.0,
+            cpu_render_ms: 0.0,
+            draws: DrawStats {
+                draw_calls: 0,
+                instances: 0,
+                indirect_draws: 0,
+                dispatches: 0,
+            },
+            gpu: None,
+        }
+    }
+
+    /// Time since the previous frame.
+    pub fn record_frame_time(&mut self, ms: f64) {
+        self.frame_ms[self.next] = ms as f32;
+        self.next = (self.next + 1) % HISTORY;
+        self.recorded = (self.recorded + 1).min(HISTORY);
+    }
+
+    /// CPU time spent updating the scene and recording + submitting the frame.
+    pub fn record_cpu(&mut self, update_ms: f64, render_ms: f64) {
+        self.cpu_update_ms = smooth(self.cpu_update_ms, update_ms);
+        self.cpu_render_ms = smooth(self.cpu_render_ms, render_ms);
+    }
+
+    /// Commands recorded by the last frame.
+    pub const fn record_draws(&mut self, draws: DrawStats) {
+        self.draws = draws;
+    }
+
+    /// A frame's GPU timings (a frame or two late). Passes that no longer run drop out.
+    pub fn record_gpu(&mut self, timings: &GpuTimings) {
+        let previous = self.gpu.take();
+        let passes = timings
+            .passes
+            .iter()
+            .map(|&(name, ms)| {
+                let old = previous
+                    .as_ref()
+                    .and_then(|p| p.passes.iter().find(|(n, _)| *n == name));
+                (name, old.map_or(ms, |&(_, old)| smooth(old, ms)))
+            })
+            .collect();
+        let frame_ms = previous.map_or(timings.frame_ms, |p| smooth(p.frame_ms, timings.frame_ms));
+        self.gpu = Some(GpuTimings { frame_ms, passes });
+    }
+
+    /// Queues the HUD for this frame. `gpu_timing`: whether the device has timestamp queries.
+    pub fn draw(&self, app: &App, gpu_timing: bool) {
+        let budget_ms = 1000.0 / app.render_settings.target_fps.max(1.0);
+        let average_ms = self.frame_ms.iter().sum::<f32>() / self.recorded.max(1) as f32;
+        let fps = if average_ms > 0.0 {
+            1000.0 / average_ms
+        } else {
+            0.0
+        };
+        let (render_width, render_height) = app.render_size();
+
+        let mut lines = vec![
+            (
+                format!("{average_ms:6.2} ms  {fps:5.1} fps"),
+                frame_color(average_ms, budget_ms),
+            ),
+            (
+                format!(
+                    "{}x{} @ {render_width}x{render_height}",
+                    app.width, app.height
+                ),
+                DIM_COLOR,
+            ),
+            (
+                format!(
+                    "CPU  update {:5.2} ms  render {:5.2} ms",
+                    self.cpu_update_ms, self.cpu_render_ms
+                ),
+                TEXT_COLOR,
+            ),
+            (
+                format!(
+                    "draws {}  instances {}  indirect {}  dispatches {}",
+                    self.draws.draw_calls,
+                    self.draws.instances,
+                    self.draws.indirect_draws,
+                    self.draws.dispatches
+                ),
+                TEXT_COLOR,
+            ),
+        ];
+        match &self.gpu {
+            Some(gpu) => {
+                lines.push((
+                    format!("GPU  {:6.2} ms", gpu.frame_ms),
+                    frame_color(gpu.frame_ms as f32, budget_ms),
+                ));
+                for (name, ms) in &gpu.passes {
+                    lines.push((format!("  {name:<12}{ms:6.2} ms"), DIM_COLOR));
+                }
+            }
+            None if gpu_timing => lines.push(("GPU  waiting for timings".to_string(), DIM_COLOR)),
+            None => lines.push((
+                "GPU  timings unavailable (no TIMESTAMP_QUERY)".to_string(),
+                DIM_COLOR,
+            )),
+        }
+
+        let line_height = text::measure_text(TEXT_SIZE, "0").y;
+        let text_width = lines
+            .iter()
+            .map(|(line, _)| text::measure_text(TEXT_SIZE, line).x)
+            .fold(GRAPH_SIZE.x, f32::max);
+        let origin = Vec2::splat(MARGIN + PADDING);
+        let panel = Vec2::new(
+            text_width,
+            GRAPH_SIZE.y + PADDING + line_height * lines.len() as f32,
+        );
+        text::draw_rect(Vec2::splat(MARGIN), panel + 2.0 * PADDING, BACKGROUND);
+
+        self.draw_graph(origin, budget_ms);
+        let mut position = origin + Vec2::new(0.0, GRAPH_SIZE.y + PADDING);
+        for (line, color) in &lines {
+            text::draw_text(position, TEXT_SIZE, *color, line);
+            position.y += line_height;
+        }
+    }
+
+    /// One bar per frame, newest on the right, over a line at the frame budget.
+    fn draw_graph(&self, origin: Vec2, budget_ms: f32) {
+        let max_ms = budget_ms * 2.0;
+        let bar_width = GRAPH_SIZE.x / HISTORY as f32;
+        text::draw_rect(origin, GRAPH_SIZE, Vec4::new(1.0, 1.0, 1.0, 0.05));
+        for i in 0..HISTORY {
+            let ms = self.frame_ms[(self.next + i) % HISTORY];
+            let height = (ms / max_ms).min(1.0) * GRAPH_SIZE.y;
+            let bar = Vec2::new(
+                origin.x + i as f32 * bar_width,
+                origin.y + GRAPH_SIZE.y - height,
+            );
+            text::draw_rect(
+                bar,
+                Vec2::new(bar_width, height),
+                frame_color(ms, budget_ms),
+            );
+        }
+        let budget_y = origin.y + GRAPH_SIZE.y * 0.5;
+        text::draw_rect(
+            Vec2::new(origin.x, budget_y),
+            Vec2::new(GRAPH_SIZE.x, 1.0),
+            DIM_COLOR,
+        );
+    }
+}
+
+fn smooth(average: f64, sample: f64) -> f64 {
+    if average == 0.0 {
+        sample
+    } else {
+        average + (sample - average) * SMOOTHING
+    }
+}
+
+/// Green within `budget_ms` (give or take display jitter), yellow up to 1.5x, red beyond.
+fn frame_color(ms: f32, budget_ms: f32) -> Vec4 {
+    if ms <= budget_ms * 1.05 {
+        GOOD_COLOR
+    } else if ms <= budget_ms * 1.5 {
+        SLOW_COLOR
+    } else {
+        BAD_COLOR
+    }
+}
@@ -0,0 +1,483 @@
+//! Input actions and axes: named controls bound to keys, mouse, wheel and gamepad.
+//!
+//! Bindings live in a serializable [Bindings] table, so scenes ask for `move_forward` rather than
+//! raw keys and users can rebind without code changes.
+//!
+//! Each frame [InputMap::update] turns the platform's [InputState] into [Actions]
+//! ([crate::scene::FrameInput::actions]):
+//! - An *action* is held while any of its bindings is, and reports pressed / released edges on the
+//!   frames it starts and stops (a press and release between two frames is not seen).
+//! - An *axis* is the sum of its bindings' values *for this frame*: continuous sources (keys,
+//!   sticks, triggers, the touch joystick) are multiplied by the frame time in seconds, so their
+//!   `scale` is per second; mouse motion (pixels, including touch drags), wheel (notches) and pinch
+//!   are per event. E.g. `look_x` bound to the mouse at 0.002 and the right stick at 2.5 turns by
+//!   0.002 rad per pixel or 2.5 rad/s at full deflection.
+//!
+//! On the web the table is exposed to JavaScript (`getInputBindings`, `setInputBindings`, see
+//! [crate::platform::web]) and persisted to localStorage under [STORAGE_KEY]; the desktop build
+//! reads `input_bindings.json` from the working directory.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::platform::gamepad::GamepadState;
+use crate::platform::InputState;
+
+/// localStorage key for the JSON-serialized bindings.
+pub const STORAGE_KEY: &str = "wasm2.input_bindings";
+
+/// Mouse buttons, as bits in [InputState::mouse_buttons].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+impl MouseButton {
+    pub const fn mask(self) -> u8 {
+        match self {
+            Self::Left => 1 << 0,
+            Self::Middle => 1 << 1,
+            Self::Right => 1 << 2,
+        }
+    }
+}
+
+/// Gamepad buttons in the standard layout (see [GamepadState]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+    Home,
+}
+
+impl GamepadButton {
+    /// The button's `GamepadState::BUTTON_*` bit.
+    pub const fn mask(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Analog gamepad inputs: sticks (-1..1, y up) and triggers (0..1).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    pub const fn value(self, pad: &GamepadState) -> f32 {
+        match self {
+            Self::LeftX => pad.left_stick.x,
+            Self::LeftY => pad.left_stick.y,
+            Self::RightX => pad.right_stick.x,
+            Self::RightY => pad.right_stick.y,
+            Self::LeftTrigger => pad.left_trigger,
+            Self::RightTrigger => pad.right_trigger,
+        }
+    }
+}
+
+/// What holds an action down. Keys are `KeyboardEvent.code` names (`"KeyW"`, `"Space"`,
+/// `"ShiftLeft"`; winit uses the same names).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum ButtonBinding {
+    Key {
+        code: String,
+    },
+    /// Only while the pointer is captured (clicks on the page or the UI don't count).
+    Mouse {
+        button: MouseButton,
+    },
+    Gamepad {
+        button: GamepadButton,
+    },
+    /// Held while the axis is past `threshold` in its direction (negative: below it).
+    GamepadAxis {
+        axis: GamepadAxis,
+        threshold: f32,
+    },
+    /// The frame's wheel movement, up (away from the user) or down.
+    WheelUp,
+    WheelDown,
+}
+
+/// What drives an axis; see the module docs for units.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum AxisBinding {
+    /// `+scale` per second while `positive` is held, `-scale` while `negative` is.
+    Keys {
+        negative: String,
+        positive: String,
+        scale: f32,
+    },
+    GamepadButtons {
+        negative: GamepadButton,
+        positive: GamepadButton,
+        scale: f32,
+    },
+    /// Deflection times `scale` per second.
+    Gamepad {
+        axis: GamepadAxis,
+        scale: f32,
+    },
+    /// Captured mouse motion in pixels (x right, y down) times `scale`.
+    MouseX {
+        scale: f32,
+    },
+    MouseY {
+        scale: f32,
+    },
+    /// Wheel notches (positive up) times `scale`.
+    Wheel {
+        scale: f32,
+    },
+    /// Touch joystick deflection (x right, y up) times `scale` per
+    /// second; see [crate::platform::touch].
+    TouchX {
+        scale: f32,
+    },
+    TouchY {
+        scale: f32,
+    },
+    /// Touch pinch (log of the finger distance ratio, positive when spreading) times `scale`.
+    Pinch {
+        scale: f32,
+    },
+}
+
+/// Named actions and axes and what they are bound to. Serialized as
+/// `{ "actions": { name: [binding, ...] }, "axes": { name: [binding, ...] } }`; a missing section
+/// keeps its defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bindings {
+    pub actions: BTreeMap<String, Vec<ButtonBinding>>,
+    pub axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl Default for Bindings {
+    /// The demo's controls: WASD / left stick / touch joystick to move, Space and Shift / triggers
+    /// to rise and sink, mouse / right stick / touch drag to look, wheel / pinch to zoom, E /
+    /// left stick click to move faster (not Ctrl: Ctrl+W closes the browser tab), L / Y to
+    /// toggle labels, `]` and `[` / the bumpers to switch to the next or previous scene, C /
+    /// Select to switch camera mode, G / X to toggle walking, Space / A to jump, V to toggle
+    /// stereo, F1 the UI, F3 the HUD, and F5 / F6 to pause and restart the sequence and music.
+    fn default() -> Self {
+        let key = |code: &str| ButtonBinding::Key { code: code.into() };
+        let keys = |negative: &str, positive: &str| AxisBinding::Keys {
+            negative: negative.into(),
+            positive: positive.into(),
+            scale: 1.0,
+        };
+        let stick = |axis, scale| AxisBinding::Gamepad { axis, scale };
+        let mut bindings = Self {
+            actions: BTreeMap::new(),
+            axes: BTreeMap::new(),
+        };
+        bindings
+            .bind_axis("move_forward", keys("KeyS", "KeyW"))
+            .bind_axis("move_forward", stick(GamepadAxis::LeftY, 1.0))
+            .bind_axis("move_right", keys("KeyA", "KeyD"))
+            .bind_axis("move_right", stick(GamepadAxis::LeftX, 1.0))
+            .bind_axis("move_forward", AxisBinding::TouchY { scale: 1.0 })
+            .bind_axis("move_right", AxisBinding::TouchX { scale: 1.0 })
+            .bind_axis("move_up", keys("ShiftLeft", "Space"))
+            .bind_axis("move_up", stick(GamepadAxis::RightTrigger, 1.0))
+            .bind_axis("move_up", stick(GamepadAxis::LeftTrigger, -1.0))
+            .bind_axis("look_x", AxisBinding::MouseX { scale: 0.002 })
+            .bind_axis("look_x", stick(GamepadAxis::RightX, 2.5))
+            .bind_axis("look_y", AxisBinding::MouseY { scale: -0.002 })
+            .bind_axis("look_y", stick(GamepadAxis::RightY, 2.5))
+            .bind_axis("zoom", AxisBinding::Wheel { scale: 0.1 })
+            .bind_axis("zoom", AxisBinding::Pinch { scale: 1.0 })
+            .bind_action("boost", key("KeyE"))
+            .bind_action(
+                "boost",
+                ButtonBinding::Gamepad {
+                    button: GamepadButton::LeftStick,
+                },
+            )
+            .bind_action("toggle_labels", key("KeyL"))
+            .bind_action(
+                "toggle_labels",
+                ButtonBinding::Gamepad {
+                    button: GamepadButton::North,
+                },
+            )
+            .bind_action("next_scene", key("BracketRight"))
+            .bind_action(
+                "next_scene",
+                ButtonBinding::Gamepad {
+                    button: GamepadButton::RightBumper,
+                },
+            )
+            .bind_action("previous_scene", key("BracketLeft"))
+            .bind_action(
+                "previous_scene",
+                ButtonBinding::Gamepad {
+                    button: GamepadButton::LeftBumper,
+                },
+            )
+            .bind_action("camera_mode", key("KeyC"))
+            .bind_action(
+                "camera_mode",
+                ButtonBinding::Gamepad {
+                    button: GamepadButton::Select,
+                },
+            )
+            .bind_action("toggle_walk", key("KeyG"))
+            .bind_action(
+                "toggle_walk",
+                ButtonBinding::Gamepad {
+                    button: GamepadButton::West,
+                },
+            )
+            .bind_action("jump", key("Space"))
+            .bind_action(
+                "jump",
+                ButtonBinding::Gamepad {
+                    button: GamepadButton::South,
+                },
+            )
+            .bind_action("toggle_stereo", key("KeyV"))
+            .bind_action("toggle_ui", key("F1"))
+            .bind_action("toggle_hud", key("F3"))
+            .bind_action("pause_sequence", key("F5"))
+            .bind_action("restart_sequence", key("F6"));
+        bindings
+    }
+}
+
+impl Bindings {
+    /// Adds `binding` to action `name` (creating it).
+    pub fn bind_action(&mut self, name: &str, binding: ButtonBinding) -> &mut Self {
+        self.actions
+            .entry(name.to_string())
+            .or_default()
+            .push(binding);
+        self
+    }
+
+    /// Adds `binding` to axis `name` (creating it).
+    pub fn bind_axis(&mut self, name: &str, binding: AxisBinding) -> &mut Self {
+        self.axes.entry(name.to_string()).or_default().push(binding);
+        self
+    }
+
+    /// Bindings saved in localStorage, if any parse.
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> Option<Self> {
+        let storage = web_sys::window()?.local_storage().ok()??;
+        let text = storage.get_item(STORAGE_KEY).ok()??;
+        let value = js_sys::JSON::parse(&text).ok()?;
+        serde_wasm_bindgen::from_value::<Self>(value).ok()
+    }
+
+    /// Writes the bindings to localStorage (ignored if storage is unavailable).
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) {
+        let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) else {
+            return;
+        };
+        let text = serde_wasm_bindgen::to_value(self)
+            .ok()
+            .and_then(|v| js_sys::JSON::stringify(&v).ok())
+            .and_then(|s| s.as_string());
+        if let Some(text) = text {
+            if storage.set_item(STORAGE_KEY, &text).is_err() {
+                log_error!("Could not save input bindings.");
+            }
+        }
+    }
+}
+
+/// One frame of actions and axes, from [InputMap::update].
+#[derive(Clone, Debug, Default)]
+pub struct Actions {
+    held: HashSet<String>,
+    pressed: HashSet<String>,
+    released: HashSet<String>,
+    axes: HashMap<String, f32>,
+}
+
+impl Actions {
+    /// Whether action `name` is held this frame.
+    pub fn held(&self, name: &str) -> bool {
+        self.held.contains(name)
+    }
+
+    /// Whether action `name` went down this frame.
+    pub fn pressed(&self, name: &str) -> bool {
+        self.pressed.contains(name)
+    }
+
+    /// Whether action `name` was let go this frame.
+    pub fn released(&self, name: &str) -> bool {
+        self.released.contains(name)
+    }
+
+    /// Axis `name` for this frame; 0 when unbound.
+    pub fn axis(&self, name: &str) -> f32 {
+        self.axes.get(name).copied().unwrap_or(0.0)
+    }
+}
+
+/// The [Bindings] plus last frame's held actions (for the edges). Owned by [crate::app::Runner].
+#[derive(Debug, Default)]
+pub struct InputMap {
+    pub bindings: Bindings,
+    held: HashSet<String>,
+}
+
+impl InputMap {
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            held: HashSet::new(),
+        }
+    }
+
+    /// Evaluates every action and axis against `input`; `dt_s` is the frame time in seconds.
+    pub fn update(&mut self, input: &InputState, dt_s: f32) -> Actions {
+        let key = |code: &str| input.keys_down.contains(code);
+        let pad = &input.gamepad;
+        let button_held = |binding: &ButtonBinding| match binding {
+            ButtonBinding::Key { code } => key(code),
+            ButtonBinding::Mouse { button } => input.mouse_buttons & button.mask() != 0,
+            ButtonBinding::Gamepad { button } => pad.button(button.mask()),
+            ButtonBinding::GamepadAxis { axis, threshold } => {
+                let value = axis.value(pad);
+                if *threshold < 0.0 {
+                    value <= *threshold
+                } else {
+                    value >= *threshold
+                }
+            }
+            ButtonBinding::WheelUp => input.wheel > 0.0,
+            ButtonBinding::WheelDown => input.wheel < 0.0,
+        };
+        let held: HashSet<String> = self
+            .bindings
+            .actions
+            .iter()
+            .filter(|(_, bindings)| bindings.iter().any(button_held))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let touch_stick = input.touch.stick();
+        let direction = |negative: bool, positive: bool| {
+            f32::from(u8::from(positive)) - f32::from(u8::from(negative))
+        };
+        let axis_value = |binding: &AxisBinding| match binding {
+            AxisBinding::Keys {
+                negative,
+                positive,
+                scale,
+            } => direction(key(negative), key(positive)) * scale * dt_s,
+            AxisBinding::GamepadButtons {
+                negative,
+                positive,
+                scale,
+            } => direction(pad.button(negative.mask()), pad.button(positive.mask())) * scale * dt_s,
+            AxisBinding::Gamepad { axis, scale } => axis.value(pad) * scale * dt_s,
+            AxisBinding::MouseX { scale } => input.mouse_dx * scale,
+            AxisBinding::MouseY { scale } => input.mouse_dy * scale,
+            AxisBinding::Wheel { scale } => input.wheel * scale,
+            AxisBinding::TouchX { scale } => touch_stick.x * scale * dt_s,
+            AxisBinding::TouchY { scale } => touch_stick.y * scale * dt_s,
+            AxisBinding::Pinch { scale } => input.touch.pinch * scale,
+        };
+        let axes = self
+            .bindings
+            .axes
+            .iter()
+            .map(|(name, bindings)| (name.clone(), bindings.iter().map(axis_value).sum()))
+            .collect();
+
+        let actions = Actions {
+            pressed: held.difference(&self.held).cloned().collect(),
+            released: self.held.difference(&held).cloned().collect(),
+            held,
+            axes,
+        };
+        self.held.clone_from(&actions.held);
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(codes: &[&str]) -> InputState {
+        InputState {
+            keys_down: codes.iter().map(|code| (*code).to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn actions_report_edges_once() {
+        let mut map = InputMap::default();
+        let actions = map.update(&keys(&["KeyE"]), 0.016);
+        assert!(actions.held("boost") && actions.pressed("boost") && !actions.released("boost"));
+
+        let actions = map.update(&keys(&["KeyE"]), 0.016);
+        assert!(actions.held("boost") && !actions.pressed("boost"));
+
+        let actions = map.update(&keys(&[]), 0.016);
+        assert!(!actions.held("boost") && !actions.pressed("boost") && actions.released("boost"));
+
+        let actions = map.update(&keys(&[]), 0.016);
+        assert!(!actions.released("boost"));
+    }
+
+    #[test]
+    fn any_binding_holds_an_action() {
+        let mut map = InputMap::default();
+        map.update(&keys(&["KeyE"]), 0.016);
+        let mut input = keys(&[]);
+        input.gamepad.buttons = GamepadButton::LeftStick.mask();
+        // Switching from the key to the stick click keeps the action held, without edges.
+        let actions = map.update(&input, 0.016);
+        assert!(actions.held("boost") && !actions.pressed("boost") && !actions.released("boost"));
+    }
+
+    #[test]
+    fn rebinding_releases_held_actions() {
+        let mut map = InputMap::default();
+        map.update(&keys(&["KeyE"]), 0.016);
+        map.bindings = Bindings {
+            actions: BTreeMap::new(),
+            axes: BTreeMap::new(),
+        };
+        let actions = map.update(&keys(&["KeyE"]), 0.016);
+        assert!(actions.released("boost") && !actions.held("boost"));
+    }
+}
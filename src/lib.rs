@@ -1,33 +1,57 @@
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
 #[macro_use]
 mod utils;
 
 mod app;
+pub mod audio;
 mod camera;
+pub mod camera_controller;
 mod chunk;
+pub mod color_grading;
+pub mod debug_draw;
 mod demo;
-mod gpu;
-mod projection;
+mod dynamic_resolution;
 mod ecs;
 mod fast_rand;
+mod gpu;
 mod half_cube;
-mod line_2d_strip;
-mod particles;
+mod hud;
+pub mod input;
+mod projection;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod offline;
+pub mod particles;
+pub mod platform;
+pub mod polyline;
+pub mod render_graph;
+pub mod render_settings;
 mod scene;
 mod scene1;
+mod scene2;
+pub mod sequencer;
 mod stereo_camera;
+pub mod text;
+pub mod transition;
+mod ui;
 mod view;
 #[cfg(target_arch = "wasm32")]
 mod xr;
 
-use crate::app::App;
 use crate::demo::Demo;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen(start)]
 pub fn main() -> Result<(), JsValue> {
     console_error_panic_hook::set_once();
-    let demo = Demo::new();
-    App::init(Box::new(demo));
+    platform::web::run(Box::new(Demo::new()));
     Ok(())
 }
+
+/// Opens a desktop window running the demo (native only). Blocks until the window closes.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_native() -> Result<(), String> {
+    platform::native::run(Box::new(Demo::new()))
+}
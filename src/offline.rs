@@ -0,0 +1,335 @@
+//! Offline frame sequence renderer (native only).
+//!
+//! Drives an [AppInstance] with a fixed timestep instead of `requestAnimationFrame`, renders each
+//! frame headless into an offscreen texture and writes numbered PNGs (`frame_00000.png`, ...).
+//! Input comes from an optional JSON recording so runs are deterministic and can be diffed
+//! frame-by-frame. With `supersample > 1` frames render at `supersample`x the output size and are
+//! box-filtered (in linear space) on readback. An optional timeline script ([crate::sequencer])
+//! and WAV track ([crate::audio], analysed but not heard) play from frame 0.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::Runner;
+use crate::color_grading::{Lut3d, LutKind, Tonemapper};
+use crate::demo::Demo;
+use crate::platform::InputState;
+use crate::sequencer::{Sequence, Sequencer};
+
+/// Offscreen target format. Non-sRGB like the browser's default canvas format: the screen pass
+/// already encodes sRGB, so bytes go to the PNG as-is.
+const OUTPUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Settings for one sequence render.
+#[derive(Clone, Debug)]
+pub struct OfflineConfig {
+    /// Number of frames to render.
+    pub frames: u32,
+    /// Fixed timestep rate; frame `i` is rendered at `i * 1000 / fps` milliseconds.
+    pub fps: f64,
+    /// Output image size.
+    pub width: u32,
+    pub height: u32,
+    /// Render at `supersample`x the output size and downsample. 1 = off.
+    pub supersample: u32,
+    /// Directory receiving the numbered PNGs (created if missing).
+    pub output_dir: PathBuf,
+    /// Optional input recording (see [InputPlayback]).
+    pub input: Option<PathBuf>,
+    /// Optional timeline script, played from frame 0 (see [crate::sequencer]).
+    pub sequence: Option<PathBuf>,
+    /// Optional WAV music, played from frame 0; drives the clock and the audio bands.
+    pub audio: Option<PathBuf>,
+    /// Tonemap operator of the screen pass.
+    pub tonemapper: Tonemapper,
+    /// Optional `.cube` grading LUT (see [crate::color_grading]).
+    pub grading_lut: Option<PathBuf>,
+    /// Optional `tony_mc_mapface.dds`, for [Tonemapper::TonyMcMapface].
+    pub tony_mc_mapface_lut: Option<PathBuf>,
+}
+
+impl Default for OfflineConfig {
+    fn default() -> Self {
+        Self {
+            frames: 600,
+            fps: 60.0,
+            width: 1920,
+            height: 1080,
+            supersample: 1,
+            output_dir: PathBuf::from("frames"),
+            input: None,
+            sequence: None,
+            audio: None,
+            tonemapper: Tonemapper::default(),
+            grading_lut: None,
+            tony_mc_mapface_lut: None,
+        }
+    }
+}
+
+/// Per-frame input as stored in a recording. Timing is not stored: it comes from
+/// the fixed timestep.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordedInput {
+    pub mouse_dx: f32,
+    pub mouse_dy: f32,
+    /// `KeyboardEvent.code` names of the keys held.
+    pub keys: Vec<String>,
+    /// Older recordings' key bitfield, bits in [LEGACY_KEYS] order; added to `keys`.
+    pub keys_held: u32,
+}
+
+/// Key codes for [RecordedInput::keys_held] bits 0..6.
+const LEGACY_KEYS: [&str; 6] = ["KeyW", "KeyA", "KeyS", "KeyD", "Space", "ShiftLeft"];
+
+/// Deterministic [crate::scene::FrameInput] source: a JSON array of [RecordedInput], one entry per
+/// frame. Frames past the end of the recording get no input.
+#[derive(Debug, Default)]
+pub struct InputPlayback {
+    frames: Vec<RecordedInput>,
+}
+
+impl InputPlayback {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("reading {}: {e}", path.display()))?;
+        let frames =
+            serde_json::from_str(&text).map_err(|e| format!("parsing {}: {e}", path.display()))?;
+        Ok(Self { frames })
+    }
+
+    /// Input for `frame`; the runner stamps it with the fixed-timestep clock.
+    pub fn frame_input(&self, frame: u32) -> InputState {
+        let Some(recorded) = self.frames.get(frame as usize) else {
+            return InputState::default();
+        };
+        let legacy = LEGACY_KEYS
+            .iter()
+            .enumerate()
+            .filter(|&(bit, _)| recorded.keys_held & 1 << bit != 0)
+            .map(|(_, code)| (*code).to_string());
+        InputState {
+            mouse_dx: recorded.mouse_dx,
+            mouse_dy: recorded.mouse_dy,
+            keys_down: recorded.keys.iter().cloned().chain(legacy).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Renders `config.frames` frames of the [Demo] to `config.output_dir`. Blocks until done.
+pub fn render_sequence(config: &OfflineConfig) -> Result<(), String> {
+    if config.frames == 0
+        || config.width == 0
+        || config.height == 0
+        || !(config.fps.is_finite() && config.fps > 0.0)
+    {
+        return Err("frames, width, height and fps must be positive".into());
+    }
+    let supersample = config.supersample.max(1);
+    let (render_width, render_height) = config
+        .width
+        .checked_mul(supersample)
+        .zip(config.height.checked_mul(supersample))
+        .ok_or("width and height times supersample overflow")?;
+
+    let playback = match &config.input {
+        Some(path) => InputPlayback::load(path)?,
+        None => InputPlayback::default(),
+    };
+    let sequence = config.sequence.as_deref().map(Sequence::load).transpose()?;
+    let grading_lut = config.grading_lut.as_deref().map(Lut3d::load).transpose()?;
+    let tony_mc_mapface_lut = config
+        .tony_mc_mapface_lut
+        .as_deref()
+        .map(Lut3d::load)
+        .transpose()?;
+    std::fs::create_dir_all(&config.output_dir)
+        .map_err(|e| format!("creating {}: {e}", config.output_dir.display()))?;
+
+    let gpu = pollster::block_on(crate::gpu::init_gpu_headless(OUTPUT_FORMAT))
+        .ok_or("no suitable GPU adapter/device")?;
+    let device = gpu.device.clone();
+    let queue = gpu.queue.clone();
+    let max_size = device.limits().max_texture_dimension_2d;
+    if render_width.max(render_height) > max_size {
+        return Err(format!(
+            "{render_width}x{render_height} (size times supersample) exceeds the GPU's \
+             {max_size} texture limit"
+        ));
+    }
+
+    let size = (render_width, render_height);
+    let mut runner = Runner::new(Box::new(Demo::new()), None, size, size);
+    runner.set_gpu(gpu);
+    runner.set_sequencer(sequence.map(Sequencer::new));
+    runner.app.render_settings.tonemapper = config.tonemapper;
+    runner.app.color_luts.set(LutKind::Grading, grading_lut);
+    runner
+        .app
+        .color_luts
+        .set(LutKind::TonyMcMapface, tony_mc_mapface_lut);
+    if let Some(path) = &config.audio {
+        let audio = runner.audio_mut();
+        audio.load(&path.to_string_lossy())?;
+        audio.play();
+    }
+
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("offline_target"),
+        size: wgpu::Extent3d {
+            width: render_width,
+            height: render_height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: OUTPUT_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+    let padded_row = (render_width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("offline_readback"),
+        size: u64::from(padded_row) * u64::from(render_height),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let frame_ms = 1000.0 / config.fps;
+    let mut pixels = vec![0u8; (render_width * render_height * 4) as usize];
+    for frame in 0..config.frames {
+        let views = runner.update(f64::from(frame) * frame_ms, playback.frame_input(frame));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        runner.render(&views, &mut encoder, &target_view);
+        encoder.copy_texture_to_buffer(
+            target.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(render_height),
+                },
+            },
+            target.size(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        read_back(
+            &device,
+            &readback,
+            padded_row,
+            render_width * 4,
+            &mut pixels,
+        )?;
+        let image = if supersample > 1 {
+            downsample(&pixels, render_width, render_height, supersample)
+        } else {
+            pixels.clone()
+        };
+        let path = config.output_dir.join(format!("frame_{frame:05}.png"));
+        write_png(&path, config.width, config.height, &image)?;
+        if frame % 60 == 0 || frame + 1 == config.frames {
+            log!(
+                "[Offline] {}/{} -> {}",
+                frame + 1,
+                config.frames,
+                path.display()
+            );
+        }
+
+        runner.app.current_frame += 1;
+    }
+    Ok(())
+}
+
+/// Maps `buffer`, copies `row_bytes` of every `padded_row`-aligned row into `out`, and unmaps.
+fn read_back(
+    device: &wgpu::Device,
+    buffer: &wgpu::Buffer,
+    padded_row: u32,
+    row_bytes: u32,
+    out: &mut [u8],
+) -> Result<(), String> {
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| {});
+    device
+        .poll(wgpu::PollType::wait_indefinitely())
+        .map_err(|e| format!("device poll: {e:?}"))?;
+    {
+        let data = slice.get_mapped_range();
+        for (dst, src) in out
+            .chunks_exact_mut(row_bytes as usize)
+            .zip(data.chunks_exact(padded_row as usize))
+        {
+            dst.copy_from_slice(&src[..row_bytes as usize]);
+        }
+    }
+    buffer.unmap();
+    Ok(())
+}
+
+/// Box-filters sRGB RGBA8 `pixels` by `factor` in each dimension, averaging in linear space.
+fn downsample(pixels: &[u8], width: u32, height: u32, factor: u32) -> Vec<u8> {
+    fn to_linear(c: u8) -> f32 {
+        let c = f32::from(c) / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+    fn to_srgb(c: f32) -> u8 {
+        let c = if c <= 0.003_130_8 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+    }
+    let lut: Vec<f32> = (0..=255u8).map(to_linear).collect();
+    let (out_w, out_h) = (width / factor, height / factor);
+    let inv_count = 1.0 / (factor * factor) as f32;
+    let mut out = Vec::with_capacity((out_w * out_h * 4) as usize);
+    for y in 0..out_h {
+        for x in 0..out_w {
+            let mut sum = [0.0f32; 4];
+            for sy in 0..factor {
+                let row = ((y * factor + sy) * width) as usize;
+                for sx in 0..factor {
+                    let i = (row + (x * factor + sx) as usize) * 4;
+                    sum[0] += lut[usize::from(pixels[i])];
+                    sum[1] += lut[usize::from(pixels[i + 1])];
+                    sum[2] += lut[usize::from(pixels[i + 2])];
+                    sum[3] += f32::from(pixels[i + 3]) / 255.0;
+                }
+            }
+            out.push(to_srgb(sum[0] * inv_count));
+            out.push(to_srgb(sum[1] * inv_count));
+            out.push(to_srgb(sum[2] * inv_count));
+            out.push((sum[3] * inv_count * 255.0 + 0.5) as u8);
+        }
+    }
+    out
+}
+
+fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("creating {}: {e}", path.display()))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("writing {}: {e}", path.display()))?;
+    writer
+        .write_image_data(rgba)
+        .map_err(|e| format!("writing {}: {e}", path.display()))
+}
@@ -0,0 +1,281 @@
+//! Particle texture atlas: sprites and flipbook sheets packed into one texture at startup.
+//!
+//! Images are straight-alpha RGBA8 (sRGB color). [AtlasBuilder::build] shelf-packs them into the
+//! smallest square power-of-two texture that fits, padding each image with copies of its edge
+//! pixels so linear filtering doesn't pick up neighbours.
+
+use std::collections::HashMap;
+
+use glam::Vec2;
+
+const MIN_ATLAS_SIZE: u32 = 64;
+/// Largest atlas; WebGPU guarantees 8192 but this keeps uploads reasonable.
+const MAX_ATLAS_SIZE: u32 = 4096;
+/// Edge pixels repeated around each image.
+const PADDING: u32 = 2;
+
+/// Straight-alpha RGBA8 image.
+#[derive(Clone, Debug)]
+pub struct AtlasImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl AtlasImage {
+    /// `pixels` is `width * height` RGBA8 texels, row-major, top row first.
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            (width * height * 4) as usize,
+            "AtlasImage: pixel count mismatch"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Decodes a PNG (any color type and bit depth; converted to RGBA8).
+    pub fn from_png(bytes: &[u8]) -> Result<Self, String> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(
+            png::Transformations::normalize_to_color8() | png::Transformations::ALPHA,
+        );
+        let mut reader = decoder
+            .read_info()
+            .map_err(|e| format!("decoding png: {e}"))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|e| format!("decoding png: {e}"))?;
+        buffer.truncate(info.buffer_size());
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            other => return Err(format!("decoding png: unexpected color type {other:?}")),
+        };
+        Ok(Self::from_rgba(info.width, info.height, pixels))
+    }
+
+    /// Image from `f(u, v)` -> linear RGBA in [0, 1], with `u`, `v` at texel centers in [0, 1].
+    pub fn from_fn(width: u32, height: u32, f: impl Fn(f32, f32) -> [f32; 4]) -> Self {
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let u = (x as f32 + 0.5) / width as f32;
+                let v = (y as f32 + 0.5) / height as f32;
+                let [r, g, b, a] = f(u, v);
+                pixels.extend([
+                    linear_to_srgb(r),
+                    linear_to_srgb(g),
+                    linear_to_srgb(b),
+                    unorm8(a),
+                ]);
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// Where a sprite (or flipbook sheet) ended up in a [TextureAtlas].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasRegion {
+    /// Top-left corner in atlas UV.
+    pub(super) uv_min: Vec2,
+    /// Size in atlas UV.
+    pub(super) uv_size: Vec2,
+    /// Flipbook grid (1 x 1 for a plain sprite). Frames run left to right, then top to bottom.
+    pub(super) columns: u32,
+    pub(super) rows: u32,
+    pub(super) frames: u32,
+}
+
+impl AtlasRegion {
+    /// Number of flipbook frames (1 for a plain sprite).
+    pub const fn frames(&self) -> u32 {
+        self.frames
+    }
+}
+
+struct Entry {
+    name: String,
+    image: AtlasImage,
+    columns: u32,
+    rows: u32,
+    frames: u32,
+}
+
+/// Collects named images, then packs them into a [TextureAtlas].
+#[derive(Default)]
+pub struct AtlasBuilder {
+    entries: Vec<Entry>,
+}
+
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a single sprite.
+    pub fn add(&mut self, name: &str, image: AtlasImage) -> &mut Self {
+        self.add_flipbook(name, image, 1, 1, 1)
+    }
+
+    /// Adds a flipbook sheet: a `columns` x `rows` grid of equally sized frames, of which the first
+    /// `frames` are used.
+    pub fn add_flipbook(
+        &mut self,
+        name: &str,
+        image: AtlasImage,
+        columns: u32,
+        rows: u32,
+        frames: u32,
+    ) -> &mut Self {
+        let columns = columns.max(1);
+        let rows = rows.max(1);
+        self.entries.push(Entry {
+            name: name.to_owned(),
+            image,
+            columns,
+            rows,
+            frames: frames.clamp(1, columns * rows),
+        });
+        self
+    }
+
+    /// Packs the images, tallest first, into shelves.
+    pub fn build(self) -> Result<TextureAtlas, String> {
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.entries[i].image.height));
+
+        let mut size = MIN_ATLAS_SIZE;
+        let placements = loop {
+            if let Some(placements) = self.pack(&order, size) {
+                break placements;
+            }
+            size *= 2;
+            if size > MAX_ATLAS_SIZE {
+                return Err(format!(
+                    "atlas images don't fit in {MAX_ATLAS_SIZE}x{MAX_ATLAS_SIZE}"
+                ));
+            }
+        };
+
+        let mut pixels = vec![0; (size * size * 4) as usize];
+        let mut regions = HashMap::new();
+        for (&i, &(x, y)) in order.iter().zip(&placements) {
+            let entry = &self.entries[i];
+            blit_padded(&mut pixels, size, &entry.image, x, y);
+            let texel = 1.0 / size as f32;
+            regions.insert(
+                entry.name.clone(),
+                AtlasRegion {
+                    uv_min: Vec2::new(x as f32, y as f32) * texel,
+                    uv_size: Vec2::new(entry.image.width as f32, entry.image.height as f32) * texel,
+                    columns: entry.columns,
+                    rows: entry.rows,
+                    frames: entry.frames,
+                },
+            );
+        }
+        log!(
+            "[Particles] Packed {} atlas images into {size}x{size}.",
+            regions.len()
+        );
+        Ok(TextureAtlas {
+            size,
+            pixels,
+            regions,
+        })
+    }
+
+    /// Top-left corner of each image (in `order`), or `None` if they don't fit in `size` x `size`.
+    fn pack(&self, order: &[usize], size: u32) -> Option<Vec<(u32, u32)>> {
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        let mut placements = Vec::with_capacity(order.len());
+        for &i in order {
+            let image = &self.entries[i].image;
+            let (w, h) = (image.width + 2 * PADDING, image.height + 2 * PADDING);
+            if x + w > size {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            if x + w > size || y + h > size {
+                return None;
+            }
+            placements.push((x + PADDING, y + PADDING));
+            x += w;
+            shelf_height = shelf_height.max(h);
+        }
+        Some(placements)
+    }
+}
+
+/// Packed RGBA8 atlas and the regions of its images. Hand it to [super::ParticleSystem::set_atlas].
+pub struct TextureAtlas {
+    size: u32,
+    pixels: Vec<u8>,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    /// Region of the image added as `name`.
+    pub fn region(&self, name: &str) -> Option<AtlasRegion> {
+        self.regions.get(name).copied()
+    }
+
+    /// Width and height in texels.
+    pub const fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+/// Copies `image` to (`x`, `y`) in the `size`-wide atlas, extending its edges into the padding.
+fn blit_padded(atlas: &mut [u8], size: u32, image: &AtlasImage, x: u32, y: u32) {
+    let pad = i64::from(PADDING);
+    for dy in -pad..i64::from(image.height) + pad {
+        let src_y = dy.clamp(0, i64::from(image.height) - 1) as u32;
+        for dx in -pad..i64::from(image.width) + pad {
+            let src_x = dx.clamp(0, i64::from(image.width) - 1) as u32;
+            let src = ((src_y * image.width + src_x) * 4) as usize;
+            let dst =
+                (((i64::from(y) + dy) as u32 * size + (i64::from(x) + dx) as u32) * 4) as usize;
+            atlas[dst..dst + 4].copy_from_slice(&image.pixels[src..src + 4]);
+        }
+    }
+}
+
+fn unorm8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+fn linear_to_srgb(v: f32) -> u8 {
+    let v = v.clamp(0.0, 1.0);
+    unorm8(if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    })
+}
@@ -0,0 +1,141 @@
+//! Particle collisions: screen-space against the G-buffer depth, with analytic colliders as the
+//! fallback for particles outside the view (or when there is no G-buffer, e.g. stereo).
+
+use glam::{Mat4, Vec3};
+
+use crate::view::ViewState;
+
+/// Analytic colliders per system (size of each collider array in the uniforms).
+pub const MAX_COLLIDERS: usize = 8;
+
+/// What happens to a particle that hits a surface.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollisionMode {
+    #[default]
+    Off,
+    /// Reflect off the surface (relative to the surface's own motion).
+    Bounce,
+    /// Recycle the particle.
+    Kill,
+}
+
+/// Solid shape used where the depth buffer can't help.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collider {
+    /// Half-space boundary: points with `dot(normal, p) >= offset` are free space.
+    Plane { normal: Vec3, offset: f32 },
+    /// Solid ball; particles bounce off its outside.
+    Sphere { center: Vec3, radius: f32 },
+}
+
+/// Collision settings for a [super::ParticleSystem].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collision {
+    pub mode: CollisionMode,
+    /// Fraction of the normal velocity kept on a bounce.
+    pub restitution: f32,
+    /// Fraction of the tangential velocity lost on a bounce.
+    pub friction: f32,
+    /// How far behind a surface a particle still counts as penetrating it (world units). Deeper
+    /// particles are assumed to be behind the object, not inside it.
+    pub thickness: f32,
+    /// Used for particles outside the view. At most [MAX_COLLIDERS] planes and [MAX_COLLIDERS]
+    /// spheres; extras are ignored.
+    pub colliders: Vec<Collider>,
+}
+
+impl Default for Collision {
+    fn default() -> Self {
+        Self {
+            mode: CollisionMode::Off,
+            restitution: 0.5,
+            friction: 0.1,
+            thickness: 0.5,
+            colliders: Vec::new(),
+        }
+    }
+}
+
+/// Depth and velocity targets of the view that just rendered, for screen-space collisions.
+pub struct CollisionTargets<'a> {
+    pub view: &'a ViewState,
+    pub depth: &'a wgpu::TextureView,
+    pub velocity: &'a wgpu::TextureView,
+}
+
+/// `CollisionParams` in `particles_sim.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct CollisionUniforms {
+    view_projection: [f32; 16],
+    inverse_view_projection: [f32; 16],
+    previous_view_projection: [f32; 16],
+    previous_inverse_view_projection: [f32; 16],
+    camera_position: [f32; 3],
+    near: f32,
+    mode: u32,
+    screen: u32,
+    restitution: f32,
+    friction: f32,
+    thickness: f32,
+    plane_count: u32,
+    sphere_count: u32,
+    _pad: f32,
+    planes: [[f32; 4]; MAX_COLLIDERS],
+    spheres: [[f32; 4]; MAX_COLLIDERS],
+}
+
+impl CollisionUniforms {
+    /// `view` is the view that rendered the depth target, if there is one bound.
+    pub(super) fn new(collision: &Collision, view: Option<&ViewState>) -> Self {
+        let mut planes = [[0.0; 4]; MAX_COLLIDERS];
+        let mut spheres = [[0.0; 4]; MAX_COLLIDERS];
+        let (mut plane_count, mut sphere_count) = (0, 0);
+        for collider in &collision.colliders {
+            match *collider {
+                Collider::Plane { normal, offset } if plane_count < MAX_COLLIDERS => {
+                    planes[plane_count] = normal.normalize_or(Vec3::Y).extend(offset).to_array();
+                    plane_count += 1;
+                }
+                Collider::Sphere { center, radius } if sphere_count < MAX_COLLIDERS => {
+                    spheres[sphere_count] = center.extend(radius).to_array();
+                    sphere_count += 1;
+                }
+                _ => {}
+            }
+        }
+        // Jitter-free matrices: they match the velocity target, and sub-pixel offsets
+        // don't matter here.
+        let (view_projection, previous_view_projection, camera_position, near) =
+            view.map_or((Mat4::IDENTITY, Mat4::IDENTITY, Vec3::ZERO, 0.0), |view| {
+                (
+                    view.view_projection_no_jitter,
+                    view.previous_view_projection_no_jitter,
+                    view.inverse_view.col(3).truncate(),
+                    view.projection.col(3).z,
+                )
+            });
+        Self {
+            view_projection: view_projection.to_cols_array(),
+            inverse_view_projection: view_projection.inverse().to_cols_array(),
+            previous_view_projection: previous_view_projection.to_cols_array(),
+            previous_inverse_view_projection: previous_view_projection.inverse().to_cols_array(),
+            camera_position: camera_position.to_array(),
+            near,
+            mode: match collision.mode {
+                CollisionMode::Off => 0,
+                CollisionMode::Bounce => 1,
+                CollisionMode::Kill => 2,
+            },
+            screen: u32::from(view.is_some()),
+            restitution: collision.restitution,
+            friction: collision.friction.clamp(0.0, 1.0),
+            thickness: collision.thickness.max(0.0),
+            plane_count: plane_count as u32,
+            sphere_count: sphere_count as u32,
+            _pad: 0.0,
+            planes,
+            spheres,
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Emitter and force descriptors for [super::ParticleSystem], plus their GPU layouts.
+
+use glam::{Vec3, Vec4};
+
+use super::atlas::AtlasRegion;
+
+/// Keys per [Curve].
+pub const CURVE_KEYS: usize = 4;
+
+/// Piecewise-linear function of normalized particle age (0 = spawn, 1 = death), evaluated on the
+/// GPU. Ages before the first key or after the last hold the end values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Curve<T> {
+    times: [f32; CURVE_KEYS],
+    values: [T; CURVE_KEYS],
+}
+
+impl<T: Copy> Curve<T> {
+    pub const fn constant(value: T) -> Self {
+        Self {
+            times: [0.0; CURVE_KEYS],
+            values: [value; CURVE_KEYS],
+        }
+    }
+
+    /// Curve through `(age, value)` keys, sorted by age. Only the first [CURVE_KEYS] are used;
+    /// an empty slice is not allowed.
+    pub fn new(keys: &[(f32, T)]) -> Self {
+        let last = keys[keys.len().min(CURVE_KEYS) - 1];
+        let key = |i: usize| {
+            keys.get(i)
+                .filter(|_| i < CURVE_KEYS)
+                .copied()
+                .unwrap_or(last)
+        };
+        Self {
+            times: std::array::from_fn(|i| key(i).0),
+            values: std::array::from_fn(|i| key(i).1),
+        }
+    }
+}
+
+/// How each particle's quad is oriented.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Billboard {
+    /// Faces the camera.
+    View,
+    /// Long axis along the screen-projected velocity; `stretch` seconds of travel are added
+    /// to the length.
+    Velocity { stretch: f32 },
+}
+
+/// How a flipbook advances through its frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlipbookMode {
+    /// At [Sprite::fps], wrapping around.
+    #[default]
+    Loop,
+    /// At [Sprite::fps], holding the last frame.
+    Clamp,
+    /// All frames spread over the particle's lifetime (`fps` is ignored).
+    OverLife,
+}
+
+/// Texture for an emitter's particles: a region of the system's [super::TextureAtlas], optionally a
+/// flipbook. Texels multiply the emitter color; alpha is straight (premultiplied in the shader).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    pub region: AtlasRegion,
+    /// Flipbook frames per second (0 = hold the start frame).
+    pub fps: f32,
+    pub mode: FlipbookMode,
+    /// Cross-fade between consecutive frames instead of stepping.
+    pub blend_frames: bool,
+    /// Start each particle on a random frame (variation for looping flipbooks, or a random
+    /// still variant per particle with `fps` 0).
+    pub random_frame: bool,
+}
+
+impl Sprite {
+    /// Still sprite, or the first frame of a flipbook region until `fps` / `mode` are set.
+    pub const fn new(region: AtlasRegion) -> Self {
+        Self {
+            region,
+            fps: 0.0,
+            mode: FlipbookMode::Loop,
+            blend_frames: true,
+            random_frame: false,
+        }
+    }
+}
+
+/// Where new particles appear, relative to [EmitterDesc::position].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmitterShape {
+    Point,
+    /// Uniformly inside a sphere.
+    Sphere {
+        radius: f32,
+    },
+    /// Uniformly inside an axis-aligned box.
+    Box {
+        half_extents: Vec3,
+    },
+    /// Uniformly on a disc in the XZ plane.
+    Disc {
+        radius: f32,
+    },
+}
+
+/// One particle emitter. Spawned particles start with a random velocity inside a cone around
+/// `direction` and live for a random time in `lifetime`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EmitterDesc {
+    pub position: Vec3,
+    pub shape: EmitterShape,
+    /// Particles per second (0 = only [super::ParticleSystem::burst]).
+    pub rate: f32,
+    /// Axis of the initial velocity cone.
+    pub direction: Vec3,
+    /// Half-angle of the cone in radians (0 = straight along `direction`, PI = any direction).
+    pub cone_angle: f32,
+    /// Initial speed range (world units per second).
+    pub speed: (f32, f32),
+    /// Lifetime range in seconds.
+    pub lifetime: (f32, f32),
+    /// Linear HDR color; alpha is kept for blending.
+    pub color: Vec4,
+    /// Quad half-size in world units.
+    pub size: f32,
+    /// Multiplier on `size` over the particle's life.
+    pub size_over_life: Curve<f32>,
+    /// Multiplier on `color` over the particle's life (alpha fades it out).
+    pub color_over_life: Curve<Vec4>,
+    pub billboard: Billboard,
+    /// Atlas texture; `None` draws a soft round spot.
+    pub sprite: Option<Sprite>,
+}
+
+impl Default for EmitterDesc {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            shape: EmitterShape::Point,
+            rate: 100.0,
+            direction: Vec3::Y,
+            cone_angle: 0.3,
+            speed: (1.0, 2.0),
+            lifetime: (1.0, 2.0),
+            color: Vec4::ONE,
+            size: 0.02,
+            size_over_life: Curve::constant(1.0),
+            color_over_life: Curve::new(&[
+                (0.0, Vec4::ONE),
+                (0.8, Vec4::ONE),
+                (1.0, Vec4::new(1.0, 1.0, 1.0, 0.0)),
+            ]),
+            billboard: Billboard::View,
+            sprite: None,
+        }
+    }
+}
+
+/// Forces applied to every live particle each step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Forces {
+    /// Acceleration (world units per second squared).
+    pub gravity: Vec3,
+    /// Linear drag coefficient per second (velocity *= 1 - drag * dt).
+    pub drag: f32,
+    /// Curl noise acceleration strength (0 = off).
+    pub curl_strength: f32,
+    /// Curl noise spatial frequency (features per world unit).
+    pub curl_scale: f32,
+}
+
+impl Default for Forces {
+    fn default() -> Self {
+        Self {
+            gravity: Vec3::new(0.0, -9.81, 0.0),
+            drag: 0.1,
+            curl_strength: 0.0,
+            curl_scale: 1.0,
+        }
+    }
+}
+
+/// GPU layout of an emitter (`Emitter` in `particles_sim.wgsl`), with this frame's spawn range.
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct EmitterGpu {
+    position: [f32; 3],
+    shape: u32,
+    shape_params: [f32; 4],
+    direction: [f32; 3],
+    cone_cos: f32,
+    speed_min: f32,
+    speed_max: f32,
+    lifetime_min: f32,
+    lifetime_max: f32,
+    color: [f32; 4],
+    size: f32,
+    spawn_start: u32,
+    spawn_count: u32,
+    billboard: u32,
+    stretch: f32,
+    _pad: [f32; 3],
+    size_times: [f32; 4],
+    size_values: [f32; 4],
+    color_times: [f32; 4],
+    color_values: [[f32; 4]; 4],
+    sprite_rect: [f32; 4],
+    /// Columns, rows, frames, flags ([SPRITE_MODE_MASK] mode, [SPRITE_BLEND], [SPRITE_RANDOM]).
+    sprite_grid: [u32; 4],
+    sprite_fps: f32,
+    _pad_end: [f32; 3],
+}
+
+/// `sprite_grid.w` bits, mirrored in `particles.wgsl`. Mode 0 = untextured, then
+/// [FlipbookMode] + 1.
+const SPRITE_MODE_MASK: u32 = 0x3;
+const SPRITE_BLEND: u32 = 0x4;
+const SPRITE_RANDOM: u32 = 0x8;
+
+impl EmitterGpu {
+    pub(super) fn new(desc: &EmitterDesc, spawn_start: u32, spawn_count: u32) -> Self {
+        let (shape, shape_params) = match desc.shape {
+            EmitterShape::Point => (0, [0.0; 4]),
+            EmitterShape::Sphere { radius } => (1, [radius, 0.0, 0.0, 0.0]),
+            EmitterShape::Box { half_extents } => {
+                (2, [half_extents.x, half_extents.y, half_extents.z, 0.0])
+            }
+            EmitterShape::Disc { radius } => (3, [radius, 0.0, 0.0, 0.0]),
+        };
+        let (billboard, stretch) = match desc.billboard {
+            Billboard::View => (0, 0.0),
+            Billboard::Velocity { stretch } => (1, stretch),
+        };
+        let (sprite_rect, sprite_grid, sprite_fps) =
+            desc.sprite.map_or(([0.0; 4], [1, 1, 1, 0], 0.0), |sprite| {
+                let region = sprite.region;
+                let mode = match sprite.mode {
+                    FlipbookMode::Loop => 1,
+                    FlipbookMode::Clamp => 2,
+                    FlipbookMode::OverLife => 3,
+                };
+                let flags = (mode & SPRITE_MODE_MASK)
+                    | if sprite.blend_frames { SPRITE_BLEND } else { 0 }
+                    | if sprite.random_frame {
+                        SPRITE_RANDOM
+                    } else {
+                        0
+                    };
+                (
+                    [
+                        region.uv_min.x,
+                        region.uv_min.y,
+                        region.uv_size.x,
+                        region.uv_size.y,
+                    ],
+                    [region.columns, region.rows, region.frames, flags],
+                    sprite.fps.max(0.0),
+                )
+            });
+        Self {
+            position: desc.position.to_array(),
+            shape,
+            shape_params,
+            direction: desc.direction.normalize_or(Vec3::Y).to_array(),
+            cone_cos: desc.cone_angle.clamp(0.0, std::f32::consts::PI).cos(),
+            speed_min: desc.speed.0,
+            speed_max: desc.speed.1,
+            lifetime_min: desc.lifetime.0.max(1e-3),
+            lifetime_max: desc.lifetime.1.max(desc.lifetime.0).max(1e-3),
+            color: desc.color.to_array(),
+            size: desc.size,
+            spawn_start,
+            spawn_count,
+            billboard,
+            stretch,
+            _pad: [0.0; 3],
+            size_times: desc.size_over_life.times,
+            size_values: desc.size_over_life.values,
+            color_times: desc.color_over_life.times,
+            color_values: desc.color_over_life.values.map(|v| v.to_array()),
+            sprite_rect,
+            sprite_grid,
+            sprite_fps,
+            _pad_end: [0.0; 3],
+        }
+    }
+}
@@ -0,0 +1,971 @@
+//! GPU particle system: compute-shader emission and simulation, drawn with
+//! indirect instanced quads.
+//!
+//! Particle state (position, velocity, age, color) lives in storage buffers and never touches the
+//! CPU. Each step `emit` pops dead slots from an atomic free list, `simulate` integrates forces and
+//! recycles expired particles, and the survivors' indices form the alive list the draw reads; its
+//! count becomes the instance count of the indirect draw. The CPU only uploads emitter descriptors
+//! and spawn counts.
+//!
+//! The step is recorded by [ParticleSimPass], a side-effect-only [GraphPass], so it runs after the
+//! frame's draws. The result (from the previous step) is drawn as camera-facing or
+//! velocity-stretched billboards with additive or alpha blending ([ParticleBlend]), depth-tested
+//! but not depth-writing:
+//! - G-buffer path: [ParticleDrawPass] draws into [COLOR] before TAA and fades particles where they
+//!   meet opaque geometry (soft particles) using the reversed-Z [DEPTH].
+//! - Forward path (stereo): scenes call [ParticleSystem::draw] from
+//!   [crate::scene::Scene::on_frame].
+//!
+//! With [Collision] enabled, the step also reads the G-buffer [DEPTH] / [VELOCITY] (optional inputs
+//! of [ParticleSimPass]) so particles bounce off or die on whatever was rendered, falling back to
+//! analytic [Collider]s off screen and on the forward path.
+//!
+//! Emitters with a [Sprite] sample a [TextureAtlas] (built once with [AtlasBuilder], see [sprites]
+//! for procedural smoke, spark and magic images) and can animate through flipbook frames.
+
+mod atlas;
+mod collision;
+mod emitter;
+pub mod sprites;
+
+pub use atlas::{AtlasBuilder, AtlasImage, AtlasRegion, TextureAtlas};
+pub use collision::{Collider, Collision, CollisionMode, CollisionTargets, MAX_COLLIDERS};
+pub use emitter::{
+    Billboard, Curve, EmitterDesc, EmitterShape, FlipbookMode, Forces, Sprite, CURVE_KEYS,
+};
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use collision::CollisionUniforms;
+use emitter::EmitterGpu;
+use wgpu::util::DeviceExt;
+use wgpu::RenderPass;
+
+use crate::gpu::stats;
+use crate::render_graph::{GraphPass, PassContext, Slot, COLOR, DEPTH, VELOCITY};
+use crate::stereo_camera::Eye;
+use crate::view::ViewState;
+
+const PARTICLES_WGSL: &str = include_str!("../wgsl/particles.wgsl");
+const PARTICLES_SIM_WGSL: &str = include_str!("../wgsl/particles_sim.wgsl");
+
+/// Emitters per system (size of the emitter storage buffer).
+pub const MAX_EMITTERS: usize = 16;
+const WORKGROUP_SIZE: u32 = 64;
+/// Bytes per particle (`Particle` in the shaders).
+const PARTICLE_STRIDE: u64 = 64;
+/// Longest simulation step; longer frames (e.g. a backgrounded tab) are clamped.
+const MAX_STEP_S: f32 = 0.1;
+/// Render uniform slots, one per [Eye], so both stereo views can be drawn in one pass.
+const UNIFORM_SLOTS: u64 = 3;
+/// Dynamic uniform offsets must be 256-byte aligned.
+const UNIFORM_STRIDE: u64 = 256;
+
+/// How particles combine with the scene. Output is premultiplied either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParticleBlend {
+    /// Light-like: order independent, never darkens (sparks, fire, magic).
+    #[default]
+    Additive,
+    /// Premultiplied "over" (smoke, dust). Particles are not sorted, so overlaps may be misordered.
+    Alpha,
+}
+
+impl ParticleBlend {
+    const fn index(self) -> usize {
+        match self {
+            Self::Additive => 0,
+            Self::Alpha => 1,
+        }
+    }
+
+    const fn blend_state(self) -> wgpu::BlendState {
+        match self {
+            Self::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            },
+            Self::Alpha => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+        }
+    }
+}
+
+/// Handle returned by [ParticleSystem::add_emitter].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EmitterId(usize);
+
+struct EmitterState {
+    desc: EmitterDesc,
+    /// Fractional particles carried over between steps.
+    accumulator: f32,
+    /// Extra particles requested by [ParticleSystem::burst] for the next step.
+    burst: u32,
+}
+
+/// A pool of `capacity` GPU particles fed by up to [MAX_EMITTERS] emitters.
+pub struct ParticleSystem {
+    capacity: u32,
+    emitters: Vec<EmitterState>,
+    pub forces: Forces,
+    pub collision: Collision,
+    /// Multiplier on every particle's size.
+    pub size_scale: f32,
+    pub blend: ParticleBlend,
+    /// Soft particle fade distance in world units (0 = hard intersections). G-buffer path only.
+    pub softness: f32,
+    gpu: Option<ParticleSystemGpu>,
+    /// Atlas set by [ParticleSystem::set_atlas], uploaded on the next step.
+    pending_atlas: Option<Rc<TextureAtlas>>,
+    last_time_s: Option<f32>,
+    step: u32,
+}
+
+/// Storage buffers, compute pipelines and draw pipelines; created on the first step.
+struct ParticleSystemGpu {
+    emit_pipeline: wgpu::ComputePipeline,
+    simulate_pipeline: wgpu::ComputePipeline,
+    sim_bind_group: wgpu::BindGroup,
+    sim_uniform_buffer: wgpu::Buffer,
+    collision_layout: wgpu::BindGroupLayout,
+    collision_uniform_buffer: wgpu::Buffer,
+    /// Bound for depth and velocity when there are no [CollisionTargets].
+    empty_target_view: wgpu::TextureView,
+    emitter_buffer: wgpu::Buffer,
+    alive_buffer: wgpu::Buffer,
+    indirect_buffer: wgpu::Buffer,
+    /// Forward pass (target format) pipelines, indexed by [ParticleBlend::index].
+    forward_pipelines: [wgpu::RenderPipeline; 2],
+    /// Soft particle pipelines for the HDR [COLOR] target, indexed by [ParticleBlend::index].
+    soft_pipelines: [wgpu::RenderPipeline; 2],
+    render_bind_group: wgpu::BindGroup,
+    render_layout: wgpu::BindGroupLayout,
+    depth_layout: wgpu::BindGroupLayout,
+    particle_buffer: wgpu::Buffer,
+    atlas_sampler: wgpu::Sampler,
+    /// [UNIFORM_SLOTS] x [RenderUniforms], one per eye.
+    render_uniform_buffer: wgpu::Buffer,
+    queue: wgpu::Queue,
+}
+
+/// `SimParams` in `particles_sim.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimUniforms {
+    gravity: [f32; 3],
+    dt: f32,
+    drag: f32,
+    curl_strength: f32,
+    curl_scale: f32,
+    time: f32,
+    capacity: u32,
+    spawn_total: u32,
+    emitter_count: u32,
+    seed: u32,
+}
+
+/// `ParticleUniforms` in `particles.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct RenderUniforms {
+    view_projection: [f32; 16],
+    camera_position: [f32; 3],
+    near: f32,
+    camera_right: [f32; 3],
+    softness: f32,
+    camera_up: [f32; 3],
+    size_scale: f32,
+}
+
+impl ParticleSystem {
+    /// Empty system with room for `capacity` live particles (GPU buffers are created on
+    /// the first step).
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            emitters: Vec::new(),
+            forces: Forces::default(),
+            collision: Collision::default(),
+            size_scale: 1.0,
+            blend: ParticleBlend::default(),
+            softness: 0.2,
+            gpu: None,
+            pending_atlas: None,
+            last_time_s: None,
+            step: 0,
+        }
+    }
+
+    /// Adds an emitter; `None` once [MAX_EMITTERS] are in use.
+    pub fn add_emitter(&mut self, desc: EmitterDesc) -> Option<EmitterId> {
+        if self.emitters.len() >= MAX_EMITTERS {
+            return None;
+        }
+        self.emitters.push(EmitterState {
+            desc,
+            accumulator: 0.0,
+            burst: 0,
+        });
+        Some(EmitterId(self.emitters.len() - 1))
+    }
+
+    /// Emitter settings, e.g. to move an emitter or change its rate.
+    pub fn emitter_mut(&mut self, id: EmitterId) -> &mut EmitterDesc {
+        &mut self.emitters[id.0].desc
+    }
+
+    /// Texture for emitters with a [Sprite]; their regions must come from this atlas. Shared so
+    /// several systems can use one atlas; each uploads its own copy on its next step.
+    pub fn set_atlas(&mut self, atlas: Rc<TextureAtlas>) {
+        self.pending_atlas = Some(atlas);
+    }
+
+    /// Spawns `count` extra particles from `id` on the next step.
+    pub fn burst(&mut self, id: EmitterId, count: u32) {
+        let emitter = &mut self.emitters[id.0];
+        emitter.burst = emitter.burst.saturating_add(count);
+    }
+
+    /// Records one simulation step at `time_s` into `encoder`. Creates the GPU
+    /// resources on first use; `target_format` is the forward pass color format.
+    /// `targets` enables screen-space collisions.
+    pub fn simulate(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target_format: wgpu::TextureFormat,
+        time_s: f32,
+        targets: Option<&CollisionTargets<'_>>,
+    ) {
+        let gpu = self.gpu.get_or_insert_with(|| {
+            ParticleSystemGpu::new(device, queue, target_format, self.capacity)
+        });
+        if let Some(atlas) = self.pending_atlas.take() {
+            gpu.set_atlas(device, &atlas);
+        }
+        let dt = self
+            .last_time_s
+            .map_or(0.0, |last| (time_s - last).clamp(0.0, MAX_STEP_S));
+        self.last_time_s = Some(time_s);
+        self.step = self.step.wrapping_add(1);
+
+        let mut spawn_total = 0u32;
+        let mut emitters = [EmitterGpu::default(); MAX_EMITTERS];
+        for (gpu_emitter, state) in emitters.iter_mut().zip(&mut self.emitters) {
+            state.accumulator += state.desc.rate.max(0.0) * dt;
+            let from_rate = state.accumulator.floor();
+            state.accumulator -= from_rate;
+            let count = (from_rate as u32)
+                .saturating_add(std::mem::take(&mut state.burst))
+                .min(self.capacity - spawn_total);
+            *gpu_emitter = EmitterGpu::new(&state.desc, spawn_total, count);
+            spawn_total += count;
+        }
+
+        let uniforms = SimUniforms {
+            gravity: self.forces.gravity.to_array(),
+            dt,
+            drag: self.forces.drag,
+            curl_strength: self.forces.curl_strength,
+            curl_scale: self.forces.curl_scale,
+            time: time_s,
+            capacity: self.capacity,
+            spawn_total,
+            emitter_count: self.emitters.len() as u32,
+            seed: self.step,
+        };
+        gpu.queue
+            .write_buffer(&gpu.sim_uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+        gpu.queue
+            .write_buffer(&gpu.emitter_buffer, 0, bytemuck::cast_slice(&emitters));
+        let collision = CollisionUniforms::new(&self.collision, targets.map(|t| t.view));
+        gpu.queue.write_buffer(
+            &gpu.collision_uniform_buffer,
+            0,
+            bytemuck::bytes_of(&collision),
+        );
+        let (depth_view, velocity_view) = targets
+            .map_or((&gpu.empty_target_view, &gpu.empty_target_view), |t| {
+                (t.depth, t.velocity)
+            });
+        let collision_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles_collision_bind_group"),
+            layout: &gpu.collision_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: gpu.collision_uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(velocity_view),
+                },
+            ],
+        });
+        // Alive list count; rebuilt by `simulate`.
+        encoder.clear_buffer(&gpu.alive_buffer, 0, Some(4));
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("particles_sim"),
+                timestamp_writes: None,
+            });
+            pass.set_bind_group(0, &gpu.sim_bind_group, &[]);
+            pass.set_bind_group(1, &collision_bind_group, &[]);
+            if spawn_total > 0 {
+                pass.set_pipeline(&gpu.emit_pipeline);
+                pass.dispatch_workgroups(spawn_total.div_ceil(WORKGROUP_SIZE), 1, 1);
+                stats::record_dispatch();
+            }
+            pass.set_pipeline(&gpu.simulate_pipeline);
+            pass.dispatch_workgroups(self.capacity.div_ceil(WORKGROUP_SIZE), 1, 1);
+            stats::record_dispatch();
+        }
+        // Instance count of the indirect draw = alive count.
+        encoder.copy_buffer_to_buffer(&gpu.alive_buffer, 0, &gpu.indirect_buffer, 4, 4);
+    }
+
+    /// Draws the live particles for `view` into a forward pass (target format, depth test only).
+    /// No-op before the first [ParticleSystem::simulate].
+    pub fn draw(&self, pass: &mut RenderPass<'_>, view: &ViewState) {
+        let Some(gpu) = self.gpu.as_ref() else {
+            return;
+        };
+        let offset = self.write_render_uniforms(gpu, view, 0.0);
+        pass.set_pipeline(&gpu.forward_pipelines[self.blend.index()]);
+        pass.set_bind_group(0, &gpu.render_bind_group, &[offset]);
+        pass.draw_indirect(&gpu.indirect_buffer, 0);
+        stats::record_indirect_draw();
+    }
+
+    /// Draws the live particles for `view` into an HDR color pass without a depth attachment; they
+    /// are depth-tested in the shader against `depth_view` and faded by [ParticleSystem::softness].
+    pub fn draw_soft(
+        &self,
+        device: &wgpu::Device,
+        pass: &mut RenderPass<'_>,
+        view: &ViewState,
+        depth_view: &wgpu::TextureView,
+    ) {
+        let Some(gpu) = self.gpu.as_ref() else {
+            return;
+        };
+        let depth_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles_depth_bind_group"),
+            layout: &gpu.depth_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(depth_view),
+            }],
+        });
+        let offset = self.write_render_uniforms(gpu, view, self.softness.max(0.0));
+        pass.set_pipeline(&gpu.soft_pipelines[self.blend.index()]);
+        pass.set_bind_group(0, &gpu.render_bind_group, &[offset]);
+        pass.set_bind_group(1, &depth_bind_group, &[]);
+        pass.draw_indirect(&gpu.indirect_buffer, 0);
+        stats::record_indirect_draw();
+    }
+
+    /// Writes `view`'s uniforms into its eye's slot; returns the slot's dynamic offset.
+    fn write_render_uniforms(
+        &self,
+        gpu: &ParticleSystemGpu,
+        view: &ViewState,
+        softness: f32,
+    ) -> u32 {
+        let slot = match view.eye {
+            Eye::Mono => 0,
+            Eye::Left => 1,
+            Eye::Right => 2,
+        };
+        let uniforms = RenderUniforms {
+            view_projection: view.view_projection.to_cols_array(),
+            camera_position: view.inverse_view.col(3).truncate().to_array(),
+            near: view.projection.col(3).z,
+            camera_right: view.inverse_view.col(0).truncate().to_array(),
+            softness,
+            camera_up: view.inverse_view.col(1).truncate().to_array(),
+            size_scale: self.size_scale,
+        };
+        let offset = slot * UNIFORM_STRIDE;
+        gpu.queue.write_buffer(
+            &gpu.render_uniform_buffer,
+            offset,
+            bytemuck::bytes_of(&uniforms),
+        );
+        offset as u32
+    }
+}
+
+/// Steps a shared [ParticleSystem] once per frame. Has no outputs, so the graph never culls it and
+/// runs it after the draws; register it from [crate::scene::Scene::render_passes].
+///
+/// Reads [DEPTH] and [VELOCITY] for collisions when the (mono) G-buffer path produces them.
+pub struct ParticleSimPass {
+    system: Rc<RefCell<ParticleSystem>>,
+}
+
+impl ParticleSimPass {
+    pub const fn new(system: Rc<RefCell<ParticleSystem>>) -> Self {
+        Self { system }
+    }
+}
+
+impl GraphPass for ParticleSimPass {
+    fn inputs(&self) -> Vec<Slot> {
+        Vec::new()
+    }
+
+    fn optional_inputs(&self) -> Vec<Slot> {
+        vec![DEPTH, VELOCITY]
+    }
+
+    fn outputs(&self) -> Vec<Slot> {
+        Vec::new()
+    }
+
+    fn record(&mut self, ctx: &mut PassContext<'_>) {
+        let format = ctx.target_format();
+        let mut system = self.system.borrow_mut();
+        let (depth, velocity) = (ctx.view(DEPTH), ctx.view(VELOCITY));
+        let targets = match (ctx.views, &depth, &velocity) {
+            ([view], Some(depth), Some(velocity))
+                if system.collision.mode != CollisionMode::Off =>
+            {
+                Some(CollisionTargets {
+                    view,
+                    depth,
+                    velocity,
+                })
+            }
+            _ => None,
+        };
+        system.simulate(
+            ctx.device,
+            ctx.queue,
+            ctx.encoder,
+            format,
+            ctx.time_s,
+            targets.as_ref(),
+        );
+    }
+}
+
+/// Draws a shared [ParticleSystem] as soft particles into [COLOR] before TAA, depth-tested against
+/// [DEPTH] in the shader. G-buffer path only; the forward path uses [ParticleSystem::draw].
+pub struct ParticleDrawPass {
+    system: Rc<RefCell<ParticleSystem>>,
+}
+
+impl ParticleDrawPass {
+    pub const fn new(system: Rc<RefCell<ParticleSystem>>) -> Self {
+        Self { system }
+    }
+}
+
+impl GraphPass for ParticleDrawPass {
+    fn inputs(&self) -> Vec<Slot> {
+        vec![COLOR, DEPTH]
+    }
+
+    fn outputs(&self) -> Vec<Slot> {
+        vec![COLOR]
+    }
+
+    fn record(&mut self, ctx: &mut PassContext<'_>) {
+        let (Some(color_view), Some(depth_view)) = (ctx.view(COLOR), ctx.view(DEPTH)) else {
+            return;
+        };
+        let Some(view_state) = ctx.views.first() else {
+            return;
+        };
+        let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("particles"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            // No depth attachment: `fs_soft` samples [DEPTH] and tests against it itself.
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+            occlusion_query_set: None,
+        });
+        let (vx, vy, vw, vh) = view_state.viewport;
+        pass.set_viewport(vx as f32, vy as f32, vw as f32, vh as f32, 0.0, 1.0);
+        self.system
+            .borrow()
+            .draw_soft(ctx.device, &mut pass, view_state, &depth_view);
+    }
+}
+
+impl ParticleSystemGpu {
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color_format: wgpu::TextureFormat,
+        capacity: u32,
+    ) -> Self {
+        log!("[Particles] Creating GPU particle system ({capacity} particles)...");
+        let storage = wgpu::BufferUsages::STORAGE;
+        let particle_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles"),
+            size: u64::from(capacity) * PARTICLE_STRIDE,
+            usage: storage,
+            mapped_at_creation: false,
+        });
+        // Free list starts full: count, then every slot (reversed so slot 0 is popped first).
+        let free_list: Vec<u32> = std::iter::once(capacity)
+            .chain((0..capacity).rev())
+            .collect();
+        let free_list_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("particles_free_list"),
+            contents: bytemuck::cast_slice(&free_list),
+            usage: storage,
+        });
+        let alive_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles_alive_list"),
+            size: 4 + u64::from(capacity) * 4,
+            usage: storage | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let emitter_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles_emitters"),
+            size: (MAX_EMITTERS * std::mem::size_of::<EmitterGpu>()) as u64,
+            usage: storage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sim_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles_sim_uniforms"),
+            size: std::mem::size_of::<SimUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let indirect_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("particles_indirect"),
+            contents: wgpu::util::DrawIndirectArgs {
+                vertex_count: 6,
+                instance_count: 0,
+                first_vertex: 0,
+                first_instance: 0,
+            }
+            .as_bytes(),
+            usage: wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let buffer_entry =
+            |binding: u32, visibility: wgpu::ShaderStages, ty: wgpu::BufferBindingType| {
+                wgpu::BindGroupLayoutEntry {
+                    binding,
+                    visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }
+            };
+
+        // Simulation
+        let compute = wgpu::ShaderStages::COMPUTE;
+        let read_write = wgpu::BufferBindingType::Storage { read_only: false };
+        let sim_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles_sim_layout"),
+            entries: &[
+                buffer_entry(0, compute, wgpu::BufferBindingType::Uniform),
+                buffer_entry(1, compute, read_write),
+                buffer_entry(
+                    2,
+                    compute,
+                    wgpu::BufferBindingType::Storage { read_only: true },
+                ),
+                buffer_entry(3, compute, read_write),
+                buffer_entry(4, compute, read_write),
+            ],
+        });
+        let sim_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles_sim_bind_group"),
+            layout: &sim_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sim_uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: particle_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: emitter_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: free_list_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: alive_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let target_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: compute,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let collision_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles_collision_layout"),
+            entries: &[
+                buffer_entry(0, compute, wgpu::BufferBindingType::Uniform),
+                // Depth32Float and Rg16Float, loaded as unfilterable float.
+                target_entry(1),
+                target_entry(2),
+            ],
+        });
+        let collision_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles_collision_uniforms"),
+            size: std::mem::size_of::<CollisionUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let empty_target_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("particles_empty_target"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&Default::default());
+        let sim_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particles_sim_pl"),
+            bind_group_layouts: &[&sim_layout, &collision_layout],
+            immediate_size: 0,
+        });
+        let sim_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("particles_sim"),
+            source: wgpu::ShaderSource::Wgsl(PARTICLES_SIM_WGSL.into()),
+        });
+        let make_compute_pipeline = |entry_point: &'static str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&sim_pipeline_layout),
+                module: &sim_shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let emit_pipeline = make_compute_pipeline("emit");
+        let simulate_pipeline = make_compute_pipeline("simulate");
+
+        // Drawing
+        let vertex = wgpu::ShaderStages::VERTEX;
+        let read_only = wgpu::BufferBindingType::Storage { read_only: true };
+        let render_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles_bind_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: vertex | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<RenderUniforms>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                buffer_entry(1, vertex, read_only),
+                buffer_entry(2, vertex, read_only),
+                buffer_entry(3, vertex, read_only),
+                // Vertex stage reads the size for the half-texel inset.
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: vertex | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let depth_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles_depth_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let render_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles_uniform"),
+            size: UNIFORM_SLOTS * UNIFORM_STRIDE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let atlas_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("particles_atlas_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        // Until an atlas is set: 1x1 white (only untextured emitters can be drawn).
+        let atlas_view = create_atlas_texture(device, queue, 1, &[255; 4]);
+        let render_bind_group = create_render_bind_group(
+            device,
+            &render_layout,
+            [
+                &render_uniform_buffer,
+                &particle_buffer,
+                &alive_buffer,
+                &emitter_buffer,
+            ],
+            &atlas_view,
+            &atlas_sampler,
+        );
+        let forward_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particles_layout"),
+            bind_group_layouts: &[&render_layout],
+            immediate_size: 0,
+        });
+        let soft_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particles_soft_layout"),
+            bind_group_layouts: &[&render_layout, &depth_layout],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("particles"),
+            source: wgpu::ShaderSource::Wgsl(PARTICLES_WGSL.into()),
+        });
+        let make_render_pipeline =
+            |label: &'static str,
+             layout: &wgpu::PipelineLayout,
+             fs_entry: &'static str,
+             format: wgpu::TextureFormat,
+             blend: ParticleBlend,
+             depth_stencil: Option<wgpu::DepthStencilState>| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: Some("vs"),
+                        buffers: &[],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: Some(fs_entry),
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(blend.blend_state()),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        cull_mode: None,
+                        ..Default::default()
+                    },
+                    depth_stencil,
+                    multisample: wgpu::MultisampleState::default(),
+                    cache: None,
+                    multiview_mask: None,
+                })
+            };
+        let blends = [ParticleBlend::Additive, ParticleBlend::Alpha];
+        // Tested against opaque geometry, but translucent: no depth writes.
+        let depth_test = wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Greater,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        };
+        let forward_pipelines = blends.map(|blend| {
+            make_render_pipeline(
+                "particles",
+                &forward_layout,
+                "fs",
+                color_format,
+                blend,
+                Some(depth_test.clone()),
+            )
+        });
+        let soft_pipelines = blends.map(|blend| {
+            make_render_pipeline(
+                "particles_soft",
+                &soft_layout,
+                "fs_soft",
+                wgpu::TextureFormat::Rgba16Float,
+                blend,
+                None,
+            )
+        });
+
+        Self {
+            emit_pipeline,
+            simulate_pipeline,
+            sim_bind_group,
+            sim_uniform_buffer,
+            collision_layout,
+            collision_uniform_buffer,
+            empty_target_view,
+            emitter_buffer,
+            alive_buffer,
+            indirect_buffer,
+            forward_pipelines,
+            soft_pipelines,
+            render_bind_group,
+            render_layout,
+            depth_layout,
+            particle_buffer,
+            atlas_sampler,
+            render_uniform_buffer,
+            queue: queue.clone(),
+        }
+    }
+
+    /// Uploads `atlas` and rebinds it.
+    fn set_atlas(&mut self, device: &wgpu::Device, atlas: &TextureAtlas) {
+        let atlas_view = create_atlas_texture(device, &self.queue, atlas.size(), atlas.pixels());
+        self.render_bind_group = create_render_bind_group(
+            device,
+            &self.render_layout,
+            [
+                &self.render_uniform_buffer,
+                &self.particle_buffer,
+                &self.alive_buffer,
+                &self.emitter_buffer,
+            ],
+            &atlas_view,
+            &self.atlas_sampler,
+        );
+    }
+}
+
+/// Square sRGB atlas texture of `size` x `size` RGBA8 `pixels`.
+fn create_atlas_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    size: u32,
+    pixels: &[u8],
+) -> wgpu::TextureView {
+    let extent = wgpu::Extent3d {
+        width: size,
+        height: size,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("particles_atlas"),
+        size: extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        texture.as_image_copy(),
+        pixels,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(size * 4),
+            rows_per_image: None,
+        },
+        extent,
+    );
+    texture.create_view(&Default::default())
+}
+
+/// Group 0 of the draw pipelines: uniforms, particles, alive list, emitters, atlas, sampler.
+fn create_render_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffers: [&wgpu::Buffer; 4],
+    atlas_view: &wgpu::TextureView,
+    atlas_sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    let [uniforms, particles, alive, emitters] = buffers;
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("particles_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: uniforms,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<RenderUniforms>() as u64),
+                }),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: particles.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: alive.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: emitters.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(atlas_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Sampler(atlas_sampler),
+            },
+        ],
+    })
+}
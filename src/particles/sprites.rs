@@ -0,0 +1,98 @@
+//! Procedural particle sprites, so scenes get smoke, sparks and magic without shipping image files.
+//! All are white (tinted by the emitter color) with the shape in alpha.
+
+use std::f32::consts::TAU;
+
+use super::atlas::AtlasImage;
+
+/// Round spot with a soft edge.
+pub fn soft_disc(size: u32) -> AtlasImage {
+    AtlasImage::from_fn(size, size, |u, v| {
+        let r = (u * 2.0 - 1.0).hypot(v * 2.0 - 1.0);
+        let a = (1.0 - r).clamp(0.0, 1.0);
+        [1.0, 1.0, 1.0, a * a]
+    })
+}
+
+/// Streak along v with a bright core; meant for velocity-stretched billboards.
+pub fn spark(width: u32, height: u32) -> AtlasImage {
+    AtlasImage::from_fn(width, height, |u, v| {
+        let x = u * 2.0 - 1.0;
+        let y = v * 2.0 - 1.0;
+        let across = (-x * x * 12.0).exp();
+        let along = (1.0 - y.abs()).clamp(0.0, 1.0);
+        let core = (-x * x * 60.0).exp() * along;
+        let a = (across * along.sqrt()).clamp(0.0, 1.0);
+        let heat = 0.6 + 0.4 * core;
+        [1.0, heat, heat, a]
+    })
+}
+
+/// Smoke puff flipbook: a noisy blob that billows out and thins over `columns * rows` frames.
+pub fn smoke(frame_size: u32, columns: u32, rows: u32) -> AtlasImage {
+    let frames = columns * rows;
+    AtlasImage::from_fn(frame_size * columns, frame_size * rows, |u, v| {
+        let (frame, x, y) = frame_coords(u, v, columns, rows);
+        let t = frame as f32 / (frames - 1).max(1) as f32;
+        let r = x.hypot(y);
+        let detail = fbm(x * 2.5 + t * 1.5, y * 2.5 - t * 2.0);
+        let radius = 0.55 + 0.3 * t;
+        let mask = (1.0 - r / radius + (detail - 0.5) * 0.6).clamp(0.0, 1.0);
+        let density = mask * (0.5 + 0.5 * detail) * (1.0 - 0.5 * t);
+        [1.0, 1.0, 1.0, density.clamp(0.0, 1.0)]
+    })
+}
+
+/// Twinkling four-pointed star flipbook with a pulsing ring; loops seamlessly.
+pub fn magic(frame_size: u32, columns: u32, rows: u32) -> AtlasImage {
+    let frames = columns * rows;
+    AtlasImage::from_fn(frame_size * columns, frame_size * rows, |u, v| {
+        let (frame, x, y) = frame_coords(u, v, columns, rows);
+        let phase = frame as f32 / frames as f32 * TAU;
+        let r = x.hypot(y);
+        let angle = y.atan2(x) + phase * 0.25;
+        let ray_length = 0.6 + 0.35 * phase.sin();
+        let rays = (angle * 2.0).cos().abs().powi(24) * (1.0 - r / ray_length).clamp(0.0, 1.0);
+        let core = (-r * r * 40.0).exp();
+        let ring_radius = 0.3 + 0.15 * (phase + 1.0).sin();
+        let ring = (-(r - ring_radius).powi(2) * 400.0).exp() * 0.5;
+        let a = (rays + core + ring).clamp(0.0, 1.0);
+        [1.0, 1.0, 1.0, a]
+    })
+}
+
+/// Frame index and position within the frame (in [-1, 1]) for sheet coordinates `u`, `v`.
+fn frame_coords(u: f32, v: f32, columns: u32, rows: u32) -> (u32, f32, f32) {
+    let (cu, cv) = (u * columns as f32, v * rows as f32);
+    let (column, row) = ((cu as u32).min(columns - 1), (cv as u32).min(rows - 1));
+    let x = (cu - column as f32) * 2.0 - 1.0;
+    let y = (cv - row as f32) * 2.0 - 1.0;
+    (row * columns + column, x, y)
+}
+
+fn hash(x: i32, y: i32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343) ^ (y as u32).wrapping_mul(0xd816_3841);
+    h = (h ^ (h >> 13)).wrapping_mul(0x5bd1_e995);
+    (h ^ (h >> 15)) as f32 / u32::MAX as f32
+}
+
+/// Smooth value noise in [0, 1].
+fn value_noise(x: f32, y: f32) -> f32 {
+    let (xi, yi) = (x.floor() as i32, y.floor() as i32);
+    let (fx, fy) = (x - x.floor(), y - y.floor());
+    let (sx, sy) = (fx * fx * (3.0 - 2.0 * fx), fy * fy * (3.0 - 2.0 * fy));
+    let top = hash(xi, yi) + (hash(xi + 1, yi) - hash(xi, yi)) * sx;
+    let bottom = hash(xi, yi + 1) + (hash(xi + 1, yi + 1) - hash(xi, yi + 1)) * sx;
+    top + (bottom - top) * sy
+}
+
+/// Four octaves of [value_noise], in [0, 1].
+fn fbm(x: f32, y: f32) -> f32 {
+    let (mut sum, mut amplitude, mut frequency) = (0.0, 0.5, 1.0);
+    for _ in 0..4 {
+        sum += value_noise(x * frequency, y * frequency) * amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / 0.9375
+}
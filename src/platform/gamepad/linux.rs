@@ -0,0 +1,139 @@
+//! Linux joystick API (`/dev/input/js*`): non-blocking reads of `js_event`s, mapped from
+//! the kernel's Xbox controller layout (`xpad`, also used by most XInput-style pads) to
+//! the standard one.
+
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read};
+use std::os::unix::fs::OpenOptionsExt;
+
+use super::GamepadState;
+
+/// Devices tried, in order.
+const DEVICES: [&str; 4] = [
+    "/dev/input/js0",
+    "/dev/input/js1",
+    "/dev/input/js2",
+    "/dev/input/js3",
+];
+/// How often to look for a gamepad while none is open.
+const RESCAN_MS: f64 = 2000.0;
+
+/// `struct js_event`: u32 time, i16 value, u8 type, u8 number.
+const EVENT_SIZE: usize = 8;
+const EVENT_BUTTON: u8 = 0x01;
+const EVENT_AXIS: u8 = 0x02;
+/// Set on the synthetic events describing the initial state after opening.
+const EVENT_INIT: u8 = 0x80;
+
+const AXIS_COUNT: usize = 8;
+const BUTTON_COUNT: usize = 11;
+
+/// The first joystick device that opens; reopened (every [RESCAN_MS]) after it goes away.
+pub struct NativeGamepad {
+    device: Option<File>,
+    /// Raw `xpad` axes (-1..1): left x / y, left trigger, right x / y, right trigger, d-pad x / y.
+    axes: [f32; AXIS_COUNT],
+    /// Raw `xpad` buttons: A, B, X, Y, LB, RB, back, start, guide, left / right stick.
+    buttons: [bool; BUTTON_COUNT],
+    next_scan_ms: f64,
+}
+
+impl Default for NativeGamepad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NativeGamepad {
+    pub const fn new() -> Self {
+        Self {
+            device: None,
+            axes: [0.0; AXIS_COUNT],
+            buttons: [false; BUTTON_COUNT],
+            next_scan_ms: 0.0,
+        }
+    }
+
+    /// Reads the events queued since the last call and returns the resulting state.
+    pub fn poll(&mut self, now_ms: f64) -> GamepadState {
+        if self.device.is_none() && now_ms >= self.next_scan_ms {
+            self.next_scan_ms = now_ms + RESCAN_MS;
+            self.open();
+        }
+        let Some(device) = self.device.as_mut() else {
+            return GamepadState::default();
+        };
+        let mut buffer = [0u8; EVENT_SIZE * 64];
+        loop {
+            match device.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => {
+                    for event in buffer[..read].chunks_exact(EVENT_SIZE) {
+                        let value = f32::from(i16::from_ne_bytes([event[4], event[5]])) / 32767.0;
+                        let (kind, number) = (event[6] & !EVENT_INIT, usize::from(event[7]));
+                        match kind {
+                            EVENT_AXIS if number < AXIS_COUNT => self.axes[number] = value,
+                            EVENT_BUTTON if number < BUTTON_COUNT => {
+                                self.buttons[number] = value != 0.0;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    log!("[Gamepad] Disconnected ({e}).");
+                    self.device = None;
+                    return GamepadState::default();
+                }
+            }
+        }
+        self.standard_state()
+    }
+
+    fn open(&mut self) {
+        for path in DEVICES {
+            let file = OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(path);
+            if let Ok(file) = file {
+                log!("[Gamepad] Using {path}.");
+                self.device = Some(file);
+                // Triggers rest at -1; the initial events overwrite this if the pad reports them.
+                self.axes = [0.0, 0.0, -1.0, 0.0, 0.0, -1.0, 0.0, 0.0];
+                self.buttons = [false; BUTTON_COUNT];
+                return;
+            }
+        }
+    }
+
+    /// The `xpad` state rearranged into the standard layout.
+    fn standard_state(&self) -> GamepadState {
+        let [left_x, left_y, left_trigger, right_x, right_y, right_trigger, dpad_x, dpad_y] =
+            self.axes;
+        let button = |i: usize| if self.buttons[i] { 1.0 } else { 0.0 };
+        let dpad = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+        let buttons = [
+            button(0),
+            button(1),
+            button(2),
+            button(3),
+            button(4),
+            button(5),
+            (left_trigger + 1.0) * 0.5,
+            (right_trigger + 1.0) * 0.5,
+            button(6),
+            button(7),
+            button(9),
+            button(10),
+            dpad(dpad_y < -0.5),
+            dpad(dpad_y > 0.5),
+            dpad(dpad_x < -0.5),
+            dpad(dpad_x > 0.5),
+            button(8),
+        ];
+        GamepadState::from_standard(&[left_x, left_y, right_x, right_y], &buttons)
+    }
+}
@@ -0,0 +1,134 @@
+//! Gamepad state for [crate::scene::FrameInput]: sticks, triggers and buttons in the W3C "standard"
+//! gamepad layout (Xbox-style), with deadzones applied.
+//!
+//! Platforms poll once per frame in [super::Platform::take_input]: the web through
+//! `navigator.getGamepads()`, native builds through [NativeGamepad] (the Linux joystick API; macOS
+//! and Windows have no backend and report no gamepad). Only the first connected gamepad is read.
+
+#[cfg(all(not(target_arch = "wasm32"), target_os = "linux"))]
+mod linux;
+#[cfg(all(not(target_arch = "wasm32"), target_os = "linux"))]
+pub use linux::NativeGamepad;
+
+use glam::Vec2;
+
+/// Stick deflection (0..1) ignored around the center; the rest is rescaled to start at 0.
+pub const STICK_DEADZONE: f32 = 0.15;
+/// Trigger travel (0..1) ignored at rest.
+pub const TRIGGER_DEADZONE: f32 = 0.05;
+/// Analog value at which a standard-layout button counts as pressed.
+const BUTTON_THRESHOLD: f32 = 0.5;
+
+/// One gamepad's state for a frame. All zero when none is connected.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GamepadState {
+    pub connected: bool,
+    /// x right, y up; inside the unit circle, zero within [STICK_DEADZONE].
+    pub left_stick: Vec2,
+    pub right_stick: Vec2,
+    /// 0 (released) ..= 1 (fully pressed), zero within [TRIGGER_DEADZONE].
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    /// `BUTTON_*` bits held.
+    pub buttons: u32,
+}
+
+impl GamepadState {
+    // Bits follow the standard layout's button indices.
+    /// A (Xbox) / Cross (PlayStation).
+    pub const BUTTON_SOUTH: u32 = 1 << 0;
+    /// B / Circle.
+    pub const BUTTON_EAST: u32 = 1 << 1;
+    /// X / Square.
+    pub const BUTTON_WEST: u32 = 1 << 2;
+    /// Y / Triangle.
+    pub const BUTTON_NORTH: u32 = 1 << 3;
+    pub const BUTTON_LEFT_BUMPER: u32 = 1 << 4;
+    pub const BUTTON_RIGHT_BUMPER: u32 = 1 << 5;
+    /// Set while the trigger is past half travel.
+    pub const BUTTON_LEFT_TRIGGER: u32 = 1 << 6;
+    pub const BUTTON_RIGHT_TRIGGER: u32 = 1 << 7;
+    pub const BUTTON_SELECT: u32 = 1 << 8;
+    pub const BUTTON_START: u32 = 1 << 9;
+    /// Stick clicks.
+    pub const BUTTON_LEFT_STICK: u32 = 1 << 10;
+    pub const BUTTON_RIGHT_STICK: u32 = 1 << 11;
+    pub const BUTTON_DPAD_UP: u32 = 1 << 12;
+    pub const BUTTON_DPAD_DOWN: u32 = 1 << 13;
+    pub const BUTTON_DPAD_LEFT: u32 = 1 << 14;
+    pub const BUTTON_DPAD_RIGHT: u32 = 1 << 15;
+    pub const BUTTON_HOME: u32 = 1 << 16;
+
+    /// State from standard-layout `axes` (left x, left y, right x, right y; -1..1, y down) and
+    /// button values (0..1, the triggers at 6 and 7). Missing entries read as 0.
+    pub fn from_standard(axes: &[f32], buttons: &[f32]) -> Self {
+        let axis = |i: usize| {
+            axes.get(i)
+                .copied()
+                .filter(|v| v.is_finite())
+                .unwrap_or(0.0)
+        };
+        let button = |i: usize| {
+            buttons
+                .get(i)
+                .copied()
+                .filter(|v| v.is_finite())
+                .unwrap_or(0.0)
+        };
+        let bits = (0..buttons.len().min(32))
+            .filter(|&i| button(i) >= BUTTON_THRESHOLD)
+            .fold(0, |bits, i| bits | 1 << i);
+        Self {
+            connected: true,
+            left_stick: stick_deadzone(Vec2::new(axis(0), -axis(1))),
+            right_stick: stick_deadzone(Vec2::new(axis(2), -axis(3))),
+            left_trigger: trigger_deadzone(button(6)),
+            right_trigger: trigger_deadzone(button(7)),
+            buttons: bits,
+        }
+    }
+
+    pub const fn button(&self, mask: u32) -> bool {
+        self.buttons & mask != 0
+    }
+}
+
+/// Radial deadzone: zero inside [STICK_DEADZONE], then rescaled so the output still spans 0..1
+/// (no jump at the edge) and clamped to the unit circle.
+pub fn stick_deadzone(raw: Vec2) -> Vec2 {
+    let length = raw.length();
+    if length <= STICK_DEADZONE {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)).min(1.0);
+    raw * (scaled / length)
+}
+
+/// Zero inside [TRIGGER_DEADZONE], then rescaled to 0..1.
+pub fn trigger_deadzone(raw: f32) -> f32 {
+    ((raw - TRIGGER_DEADZONE) / (1.0 - TRIGGER_DEADZONE)).clamp(0.0, 1.0)
+}
+
+/// Desktop systems without a gamepad backend (macOS, Windows): never connected.
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "linux")))]
+pub struct NativeGamepad;
+
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "linux")))]
+impl Default for NativeGamepad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "linux")))]
+impl NativeGamepad {
+    /// Says once (at startup) that gamepads are ignored here.
+    pub fn new() -> Self {
+        log!("[Gamepad] No gamepad support on this OS (only Linux /dev/input/js*); ignoring pads.");
+        Self
+    }
+
+    pub fn poll(&mut self, _now_ms: f64) -> GamepadState {
+        GamepadState::default()
+    }
+}
@@ -0,0 +1,190 @@
+//! Platform layer: surface creation, input events, frame callbacks, and timing.
+//!
+//! Everything above this layer ([crate::app::Runner], [crate::app::AppInstance], scenes) is
+//! platform-independent. Each platform owns its window/canvas and event loop, accumulates input
+//! into an [InputState], and calls [crate::app::Runner::frame] once per display frame.
+//! - [web]: canvas + DOM listeners + `requestAnimationFrame` (wasm32).
+//! - [native]: winit window + event loop (desktop builds, for debugging with native tools).
+//!
+//! Gamepads are polled rather than evented; see [gamepad]. Touch gestures are tracked by [touch].
+
+pub mod gamepad;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
+pub mod touch;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+
+use std::collections::HashSet;
+
+use gamepad::GamepadState;
+use glam::Vec2;
+use touch::{TouchControls, TouchRole};
+
+/// What the frame loop needs from the host environment.
+pub trait Platform {
+    /// Current framebuffer size in physical pixels. May be (0, 0) while minimized/hidden.
+    fn framebuffer_size(&self) -> (u32, u32);
+
+    /// Largest framebuffer expected (e.g. screen size).
+    fn max_framebuffer_size(&self) -> (u32, u32);
+
+    /// Creates a wgpu surface for the platform's canvas or window.
+    fn create_surface(&self, instance: &wgpu::Instance) -> Option<wgpu::Surface<'static>>;
+
+    /// Input accumulated since the last call, plus the gamepad polled now. Mouse and wheel deltas
+    /// reset; held keys and buttons persist.
+    fn take_input(&mut self) -> InputState;
+
+    /// Monotonic time in milliseconds (same clock as the frame timestamps).
+    fn now_ms(&self) -> f64;
+
+    /// Schedules the next frame callback.
+    fn request_frame(&self);
+
+    /// Whether the display shows more than SDR brightness; [crate::gpu::init_gpu] then picks a
+    /// float surface format.
+    fn hdr_display(&self) -> bool {
+        false
+    }
+
+    /// Switches the surface between extended-range (HDR) and standard presentation. Configuring the
+    /// surface resets it to standard, so this is reapplied after every configure. Returns whether
+    /// extended range is now active (false where unsupported: output falls back to SDR).
+    fn set_extended_range(&self, _enabled: bool) -> bool {
+        false
+    }
+}
+
+/// Raw input accumulated between frames, turned into a [crate::scene::FrameInput] by the runner
+/// (through its [crate::input::InputMap]).
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    /// Pointer motion while captured (locked / grabbed), in pixels.
+    pub mouse_dx: f32,
+    pub mouse_dy: f32,
+    /// `KeyboardEvent.code` names of the keys held.
+    pub keys_down: HashSet<String>,
+    /// [crate::input::MouseButton] bits held; presses only count while the pointer is captured.
+    pub mouse_buttons: u8,
+    /// Wheel movement while the pointer is captured, in notches (positive = up, away
+    /// from the user).
+    pub wheel: f32,
+    /// Pointer, wheel, key and text events for the [crate::ui] overlay (dropped while it is
+    /// hidden). Positions are in framebuffer pixels.
+    pub ui_events: Vec<egui::Event>,
+    /// Modifier keys held, as of the last key or mouse event.
+    pub ui_modifiers: egui::Modifiers,
+    /// Set by [Platform::take_input] each frame.
+    pub gamepad: GamepadState,
+    /// Touches in progress: virtual joystick and pinch (look drags go to the mouse deltas).
+    pub touch: TouchControls,
+}
+
+impl InputState {
+    pub fn mouse_move(&mut self, dx: f32, dy: f32) {
+        self.mouse_dx += dx;
+        self.mouse_dy += dy;
+    }
+
+    /// Press or release the key with the given `KeyboardEvent.code` name.
+    pub fn set_key(&mut self, code: &str, down: bool) {
+        if down {
+            self.keys_down.insert(code.to_string());
+        } else {
+            self.keys_down.remove(code);
+        }
+    }
+
+    /// Press or release a [crate::input::MouseButton] (by its mask).
+    pub const fn set_mouse_button(&mut self, mask: u8, down: bool) {
+        if down {
+            self.mouse_buttons |= mask;
+        } else {
+            self.mouse_buttons &= !mask;
+        }
+    }
+
+    /// A touch landed at `position` (framebuffer pixels) on a `width`-pixel-wide screen.
+    pub fn touch_start(&mut self, id: i64, position: Vec2, width: f32) {
+        let pos = egui::pos2(position.x, position.y);
+        if self
+            .touch
+            .start(id, position, width, crate::ui::covers(pos))
+            == TouchRole::Ui
+        {
+            let modifiers = self.ui_modifiers;
+            self.ui_events.push(egui::Event::PointerMoved(pos));
+            let button = egui::PointerButton::Primary;
+            self.ui_events.push(egui::Event::PointerButton {
+                pos,
+                button,
+                pressed: true,
+                modifiers,
+            });
+        }
+    }
+
+    pub fn touch_move(&mut self, id: i64, position: Vec2) {
+        match self.touch.moved(id, position) {
+            Some((TouchRole::Ui, _)) => self.ui_events.push(egui::Event::PointerMoved(egui::pos2(
+                position.x, position.y,
+            ))),
+            Some((_, look)) => self.mouse_move(look.x, look.y),
+            None => {}
+        }
+    }
+
+    /// A touch lifted (or was cancelled) at `position`.
+    pub fn touch_end(&mut self, id: i64, position: Vec2) {
+        if self.touch.end(id) == Some(TouchRole::Ui) {
+            let (pos, modifiers) = (egui::pos2(position.x, position.y), self.ui_modifiers);
+            let button = egui::PointerButton::Primary;
+            self.ui_events.push(egui::Event::PointerButton {
+                pos,
+                button,
+                pressed: false,
+                modifiers,
+            });
+            self.ui_events.push(egui::Event::PointerGone);
+        }
+    }
+
+    /// Queues a key event for the UI. `key` is the logical key name (`KeyboardEvent.key`,
+    /// e.g. `"a"`, `"Enter"`) and `code` the physical one (`KeyboardEvent.code`); text input
+    /// is queued separately.
+    pub fn ui_key(&mut self, key: &str, code: &str, pressed: bool, repeat: bool) {
+        let physical_key = ui_key_from_code(code);
+        let Some(key) = egui::Key::from_name(key).or(physical_key) else {
+            return;
+        };
+        self.ui_events.push(egui::Event::Key {
+            key,
+            physical_key,
+            pressed,
+            repeat,
+            modifiers: self.ui_modifiers,
+        });
+    }
+
+    /// Returns the accumulated state and resets per-frame deltas and events.
+    #[must_use]
+    pub fn take(&mut self) -> Self {
+        let out = Self {
+            keys_down: self.keys_down.clone(),
+            ui_events: std::mem::take(&mut self.ui_events),
+            touch: self.touch.clone(),
+            ..*self
+        };
+        self.mouse_dx = 0.0;
+        self.mouse_dy = 0.0;
+        self.wheel = 0.0;
+        self.touch.pinch = 0.0;
+        out
+    }
+}
+
+/// egui key for a `KeyboardEvent.code` name (`"KeyA"`, `"Digit1"`, `"Enter"`, ...).
+fn ui_key_from_code(code: &str) -> Option<egui::Key> {
+    egui::Key::from_name(code.strip_prefix("Key").unwrap_or(code))
+}
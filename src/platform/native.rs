@@ -0,0 +1,388 @@
+//! Desktop platform: winit window and event loop.
+//!
+//! Mirrors the web controls: click to capture the mouse (like pointer lock), Escape to release,
+//! WASD/Space/Shift to move, F1 for the UI overlay (which gets the cursor while it isn't captured).
+//! Frames are driven by `RedrawRequested`; the surface is configured with FIFO present so the loop
+//! runs at the display rate. A [SEQUENCE_FILE] and an [AUDIO_FILE] in the working directory play at
+//! startup (the music silently: there is no audio output yet, but it drives the clock and the audio
+//! bands); F5 pauses and resumes them, F6 restarts them.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use glam::Vec2;
+use winit::application::ApplicationHandler;
+use winit::event::{
+    DeviceEvent, DeviceId, ElementState, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent,
+};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::window::{CursorGrabMode, Window, WindowId};
+
+use super::gamepad::NativeGamepad;
+use super::{InputState, Platform};
+use crate::app::{AppInstance, Runner};
+use crate::input::{self, Bindings};
+use crate::sequencer::{Sequence, Sequencer};
+
+/// Pixel wheel deltas (touchpads, some mice) per notch for [InputState::wheel].
+const WHEEL_PIXELS_PER_NOTCH: f32 = 100.0;
+/// Input bindings read at startup from the working directory, if present (the JSON that the web
+/// build's `getInputBindings()` returns).
+const BINDINGS_FILE: &str = "input_bindings.json";
+/// Timeline script played at startup, if present (see [crate::sequencer]).
+const SEQUENCE_FILE: &str = "sequence.json";
+/// Music played at startup, if present (see [crate::audio]).
+const AUDIO_FILE: &str = "audio.wav";
+
+/// A winit window plus the input accumulated since the last frame.
+pub struct NativePlatform {
+    window: Arc<Window>,
+    input: InputState,
+    start: Instant,
+    cursor_grabbed: bool,
+    /// Last cursor position in window pixels, for UI button events.
+    cursor_position: egui::Pos2,
+    gamepad: NativeGamepad,
+}
+
+impl NativePlatform {
+    fn new(window: Arc<Window>) -> Self {
+        Self {
+            window,
+            input: InputState::default(),
+            start: Instant::now(),
+            cursor_grabbed: false,
+            cursor_position: egui::Pos2::ZERO,
+            gamepad: NativeGamepad::new(),
+        }
+    }
+
+    fn set_cursor_grab(&mut self, grab: bool) {
+        let mode = if grab {
+            CursorGrabMode::Locked
+        } else {
+            CursorGrabMode::None
+        };
+        let result = self.window.set_cursor_grab(mode).or_else(|_| {
+            // Not every platform supports Locked; Confined plus relative device
+            // motion is equivalent.
+            if grab {
+                self.window.set_cursor_grab(CursorGrabMode::Confined)
+            } else {
+                Ok(())
+            }
+        });
+        if result.is_ok() {
+            self.window.set_cursor_visible(!grab);
+            self.cursor_grabbed = grab;
+        }
+    }
+}
+
+impl Platform for NativePlatform {
+    fn framebuffer_size(&self) -> (u32, u32) {
+        let size = self.window.inner_size();
+        (size.width, size.height)
+    }
+
+    fn max_framebuffer_size(&self) -> (u32, u32) {
+        self.window.current_monitor().map_or_else(
+            || self.framebuffer_size(),
+            |m| (m.size().width, m.size().height),
+        )
+    }
+
+    fn create_surface(&self, instance: &wgpu::Instance) -> Option<wgpu::Surface<'static>> {
+        instance.create_surface(self.window.clone()).ok()
+    }
+
+    fn take_input(&mut self) -> InputState {
+        let mut input = self.input.take();
+        input.gamepad = self.gamepad.poll(self.now_ms());
+        input
+    }
+
+    fn now_ms(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000.0
+    }
+
+    fn request_frame(&self) {
+        self.window.request_redraw();
+    }
+}
+
+/// winit application state; the window only exists between `resumed` and exit.
+struct NativeApp {
+    instance: Option<Box<dyn AppInstance>>,
+    state: Option<(NativePlatform, Runner)>,
+    error: Option<String>,
+}
+
+impl ApplicationHandler for NativeApp {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.state.is_some() {
+            return;
+        }
+        let Some(instance) = self.instance.take() else {
+            return;
+        };
+        let attributes = Window::default_attributes().with_title("wasm2");
+        let window = match event_loop.create_window(attributes) {
+            Ok(window) => Arc::new(window),
+            Err(e) => {
+                self.error = Some(format!("creating window: {e}"));
+                event_loop.exit();
+                return;
+            }
+        };
+
+        let platform = NativePlatform::new(window);
+        let mut runner = Runner::new(
+            instance,
+            None,
+            platform.framebuffer_size(),
+            platform.max_framebuffer_size(),
+        );
+        if let Some(bindings) = load_bindings() {
+            runner.set_bindings(bindings);
+        }
+        if std::path::Path::new(SEQUENCE_FILE).exists() {
+            match Sequence::load(SEQUENCE_FILE.as_ref()) {
+                Ok(sequence) => {
+                    log!("Playing {SEQUENCE_FILE} ({:.1} s).", sequence.length());
+                    runner.set_sequencer(Some(Sequencer::new(sequence)));
+                }
+                Err(e) => {
+                    log_error!("{e}");
+                }
+            }
+        }
+        if std::path::Path::new(AUDIO_FILE).exists() {
+            let audio = runner.audio_mut();
+            match audio.load(AUDIO_FILE) {
+                Ok(()) => audio.play(),
+                Err(e) => {
+                    log_error!("{e}");
+                }
+            }
+        }
+        let gpu_instance = crate::gpu::create_instance();
+        let gpu = platform.create_surface(&gpu_instance).and_then(|surface| {
+            pollster::block_on(crate::gpu::init_gpu(
+                gpu_instance,
+                surface,
+                platform.hdr_display(),
+            ))
+        });
+        let Some(gpu) = gpu else {
+            self.error = Some("no suitable GPU adapter/device for the window surface".into());
+            event_loop.exit();
+            return;
+        };
+        log!("GPU initialized ({:?}).", gpu.adapter.get_info().backend);
+        runner.set_gpu(gpu);
+        platform.request_frame();
+        self.state = Some((platform, runner));
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Some((platform, runner)) = self.state.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::RedrawRequested => {
+                let timestamp = platform.now_ms();
+                let ui_was_shown = runner.app.render_settings.ui;
+                runner.frame(platform, timestamp);
+                // Opening the UI (F1 by default) frees the cursor to use it.
+                if runner.app.render_settings.ui && !ui_was_shown {
+                    platform.set_cursor_grab(false);
+                }
+                platform.request_frame();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                platform.cursor_position = egui::pos2(position.x as f32, position.y as f32);
+                if !platform.cursor_grabbed {
+                    platform
+                        .input
+                        .ui_events
+                        .push(egui::Event::PointerMoved(platform.cursor_position));
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                platform.input.ui_events.push(egui::Event::PointerGone);
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let pressed = state == ElementState::Pressed;
+                // Input bindings see presses while grabbed, and every release (so none stick).
+                let binding = match button {
+                    MouseButton::Left => Some(input::MouseButton::Left),
+                    MouseButton::Middle => Some(input::MouseButton::Middle),
+                    MouseButton::Right => Some(input::MouseButton::Right),
+                    _ => None,
+                };
+                if let Some(binding) = binding.filter(|_| platform.cursor_grabbed || !pressed) {
+                    platform.input.set_mouse_button(binding.mask(), pressed);
+                }
+                if platform.cursor_grabbed {
+                    return;
+                }
+                if pressed && button == MouseButton::Left && !crate::ui::wants_pointer() {
+                    platform.set_cursor_grab(true);
+                    return;
+                }
+                let button = match button {
+                    MouseButton::Left => egui::PointerButton::Primary,
+                    MouseButton::Right => egui::PointerButton::Secondary,
+                    MouseButton::Middle => egui::PointerButton::Middle,
+                    _ => return,
+                };
+                let event = egui::Event::PointerButton {
+                    pos: platform.cursor_position,
+                    button,
+                    pressed,
+                    modifiers: platform.input.ui_modifiers,
+                };
+                platform.input.ui_events.push(event);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (unit, delta) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        (egui::MouseWheelUnit::Line, egui::vec2(x, y))
+                    }
+                    MouseScrollDelta::PixelDelta(p) => (
+                        egui::MouseWheelUnit::Point,
+                        egui::vec2(p.x as f32, p.y as f32),
+                    ),
+                };
+                // Bindings while grabbed (one line per notch), else the UI.
+                if platform.cursor_grabbed {
+                    platform.input.wheel += match unit {
+                        egui::MouseWheelUnit::Point => delta.y / WHEEL_PIXELS_PER_NOTCH,
+                        _ => delta.y,
+                    };
+                    return;
+                }
+                let modifiers = platform.input.ui_modifiers;
+                let phase = egui::TouchPhase::Move;
+                platform.input.ui_events.push(egui::Event::MouseWheel {
+                    unit,
+                    delta,
+                    phase,
+                    modifiers,
+                });
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                let state = modifiers.state();
+                platform.input.ui_modifiers = egui::Modifiers {
+                    alt: state.alt_key(),
+                    ctrl: state.control_key(),
+                    shift: state.shift_key(),
+                    mac_cmd: cfg!(target_os = "macos") && state.super_key(),
+                    command: if cfg!(target_os = "macos") {
+                        state.super_key()
+                    } else {
+                        state.control_key()
+                    },
+                };
+            }
+            WindowEvent::Touch(touch) => {
+                let id = touch.id as i64;
+                let position = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+                match touch.phase {
+                    TouchPhase::Started => {
+                        let width = platform.framebuffer_size().0 as f32;
+                        platform.input.touch_start(id, position, width);
+                    }
+                    TouchPhase::Moved => platform.input.touch_move(id, position),
+                    TouchPhase::Ended | TouchPhase::Cancelled => {
+                        platform.input.touch_end(id, position);
+                    }
+                }
+            }
+            WindowEvent::Focused(false) => {
+                platform.set_cursor_grab(false);
+                platform.input = InputState::default();
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                let PhysicalKey::Code(code) = event.physical_key else {
+                    return;
+                };
+                let pressed = event.state == ElementState::Pressed;
+                match code {
+                    KeyCode::Escape if pressed => platform.set_cursor_grab(false),
+                    _ => {
+                        let name = format!("{code:?}");
+                        platform.input.ui_key(&name, &name, pressed, event.repeat);
+                        if let Some(text) = event
+                            .text
+                            .filter(|_| pressed && !platform.input.ui_modifiers.command)
+                        {
+                            if !text.chars().any(char::is_control) {
+                                platform
+                                    .input
+                                    .ui_events
+                                    .push(egui::Event::Text(text.to_string()));
+                            }
+                        }
+                        platform.input.set_key(&name, pressed);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        let Some((platform, _)) = self.state.as_mut() else {
+            return;
+        };
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
+            if platform.cursor_grabbed {
+                platform.input.mouse_move(dx as f32, dy as f32);
+            }
+        }
+    }
+}
+
+/// Bindings from [BINDINGS_FILE]; `None` (keeping the defaults) if it is missing or invalid.
+fn load_bindings() -> Option<Bindings> {
+    let text = std::fs::read_to_string(BINDINGS_FILE).ok()?;
+    match serde_json::from_str(&text) {
+        Ok(bindings) => {
+            log!("Input bindings loaded from {BINDINGS_FILE}.");
+            Some(bindings)
+        }
+        Err(e) => {
+            log_error!("{BINDINGS_FILE}: {e}");
+            None
+        }
+    }
+}
+
+/// Opens a window and runs `app_instance` until it is closed. V toggles stereo (the web
+/// build's VR button).
+pub fn run(app_instance: Box<dyn AppInstance>) -> Result<(), String> {
+    let event_loop = EventLoop::new().map_err(|e| format!("creating event loop: {e}"))?;
+    let mut app = NativeApp {
+        instance: Some(app_instance),
+        state: None,
+        error: None,
+    };
+    event_loop
+        .run_app(&mut app)
+        .map_err(|e| format!("event loop: {e}"))?;
+    app.error.map_or(Ok(()), Err)
+}
@@ -0,0 +1,137 @@
+//! Touch controls for phones and tablets: a virtual joystick on the left half of the screen, drag
+//! to look on the right half, and a two-finger pinch on the right half to zoom.
+//!
+//! The platforms feed touches (Pointer Events on the web, winit touch events natively) into
+//! [super::InputState]; look drags become ordinary mouse deltas, while the joystick and pinch are
+//! read through the `touch_x` / `touch_y` / `pinch` axis bindings (see [crate::input]). Touches
+//! that start on the [crate::ui] panel go to the UI instead.
+
+use glam::{Vec2, Vec4};
+
+use crate::text;
+
+/// How far (pixels) the finger has to move from where it landed for full joystick deflection.
+pub const JOYSTICK_RADIUS: f32 = 60.0;
+
+/// What a touch controls, decided when it lands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchRole {
+    Joystick,
+    Look,
+    /// Over the UI panel: forwarded to the UI as pointer events.
+    Ui,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Touch {
+    id: i64,
+    role: TouchRole,
+    /// Where the touch landed (the joystick's center).
+    start: Vec2,
+    position: Vec2,
+}
+
+/// Active touches plus the pinch accumulated since the last frame.
+#[derive(Clone, Debug, Default)]
+pub struct TouchControls {
+    touches: Vec<Touch>,
+    /// Pinch since the last frame as the log of the finger distance ratio (positive = spreading).
+    pub pinch: f32,
+}
+
+impl TouchControls {
+    /// Starts tracking touch `id` at `position`; `width` is the screen width (the left half holds
+    /// the joystick). Returns its role.
+    pub fn start(&mut self, id: i64, position: Vec2, width: f32, over_ui: bool) -> TouchRole {
+        self.end(id);
+        let role = if over_ui {
+            TouchRole::Ui
+        } else if position.x < width * 0.5 && self.joystick().is_none() {
+            TouchRole::Joystick
+        } else {
+            TouchRole::Look
+        };
+        self.touches.push(Touch {
+            id,
+            role,
+            start: position,
+            position,
+        });
+        role
+    }
+
+    /// Moves touch `id`; returns its role and the look delta it produced (zero unless it is the
+    /// only look touch: two of them pinch instead).
+    pub fn moved(&mut self, id: i64, position: Vec2) -> Option<(TouchRole, Vec2)> {
+        let index = self.touches.iter().position(|t| t.id == id)?;
+        let previous = self.touches[index].position;
+        let role = self.touches[index].role;
+        let pinch_before = self.pinch_distance();
+        self.touches[index].position = position;
+        let mut look = Vec2::ZERO;
+        if role == TouchRole::Look {
+            match (pinch_before, self.pinch_distance()) {
+                (Some(before), Some(after)) if before > 0.0 && after > 0.0 => {
+                    self.pinch += (after / before).ln();
+                }
+                _ => look = position - previous,
+            }
+        }
+        Some((role, look))
+    }
+
+    /// Stops tracking touch `id` (lifted or cancelled); returns its role.
+    pub fn end(&mut self, id: i64) -> Option<TouchRole> {
+        let index = self.touches.iter().position(|t| t.id == id)?;
+        Some(self.touches.remove(index).role)
+    }
+
+    /// Joystick deflection, x right and y up (finger moved up the screen), inside the unit circle.
+    pub fn stick(&self) -> Vec2 {
+        self.joystick().map_or(Vec2::ZERO, |t| {
+            let offset = (t.position - t.start) / JOYSTICK_RADIUS;
+            Vec2::new(offset.x, -offset.y).clamp_length_max(1.0)
+        })
+    }
+
+    /// Queues the joystick's ring and knob for this frame's `text` pass while it is held.
+    pub fn draw(&self) {
+        let Some(touch) = self.joystick() else {
+            return;
+        };
+        let knob = touch.start + (touch.position - touch.start).clamp_length_max(JOYSTICK_RADIUS);
+        draw_centered(
+            touch.start,
+            JOYSTICK_RADIUS * 2.6,
+            Vec4::new(1.0, 1.0, 1.0, 0.35),
+            "○",
+        );
+        draw_centered(
+            knob,
+            JOYSTICK_RADIUS * 1.2,
+            Vec4::new(1.0, 1.0, 1.0, 0.6),
+            "●",
+        );
+    }
+
+    fn joystick(&self) -> Option<&Touch> {
+        self.touches.iter().find(|t| t.role == TouchRole::Joystick)
+    }
+
+    /// Distance between the first two look touches, if there are two.
+    fn pinch_distance(&self) -> Option<f32> {
+        let mut looks = self.touches.iter().filter(|t| t.role == TouchRole::Look);
+        let (a, b) = (looks.next()?, looks.next()?);
+        Some(a.position.distance(b.position))
+    }
+}
+
+/// One glyph centered on `center`, `size` pixels tall.
+fn draw_centered(center: Vec2, size: f32, color: Vec4, glyph: &str) {
+    text::draw_text(
+        center - text::measure_text(size, glyph) * 0.5,
+        size,
+        color,
+        glyph,
+    );
+}
@@ -0,0 +1,1029 @@
+//! Browser platform: fullscreen canvas, DOM input listeners, `requestAnimationFrame` loop.
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use glam::Vec2;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+
+use super::gamepad::GamepadState;
+use super::{InputState, Platform};
+use crate::app::{AppInstance, Runner};
+use crate::audio::AudioFrame;
+use crate::camera::CameraExposure;
+use crate::color_grading::{ColorGrading, Lut3d, LutKind, Tonemapper};
+use crate::gpu::GpuContext;
+use crate::input::{Bindings, MouseButton};
+use crate::render_settings::{QualityPreset, RenderSettings};
+use crate::sequencer::{Sequence, Sequencer};
+use crate::transition::{Transition, TransitionKind};
+
+type FrameCallback = Rc<RefCell<Option<Closure<dyn FnMut(f64)>>>>;
+
+/// Wheel deltas per notch for [InputState::wheel], by `deltaMode` (typical browser values).
+const WHEEL_PIXELS_PER_NOTCH: f32 = 100.0;
+const WHEEL_LINES_PER_NOTCH: f32 = 3.0;
+
+thread_local! {
+    /// Render settings as seen by JavaScript, plus a dirty flag. The rAF loop copies edits into the
+    /// [crate::app::App] (and saves them); otherwise it mirrors the app's settings back here (and
+    /// saves them if the app changed them, e.g. from the UI overlay).
+    static JS_SETTINGS: RefCell<(RenderSettings, bool)> =
+        RefCell::new((RenderSettings::default(), false));
+    /// Input bindings set from JavaScript and not yet applied; the rAF loop hands them to the
+    /// [Runner] and saves them.
+    static JS_BINDINGS: RefCell<Option<Bindings>> = const { RefCell::new(None) };
+    /// The runner's bindings as last applied, for `getInputBindings`.
+    static CURRENT_BINDINGS: RefCell<Bindings> = RefCell::new(Bindings::default());
+    /// Scene switch requested from JavaScript, applied by the rAF loop.
+    static JS_SCENE_SWITCH: Cell<Option<(usize, Transition)>> = const { Cell::new(None) };
+    /// The runner's scene names and current scene, for `getScenes` / `getCurrentScene`.
+    static SCENES: RefCell<(Vec<&'static str>, usize)> = const { RefCell::new((Vec::new(), 0)) };
+    /// Sequencer calls from JavaScript, applied in order by the rAF loop.
+    static JS_SEQUENCE: RefCell<Vec<SequenceCommand>> = const { RefCell::new(Vec::new()) };
+    /// The playing sequence's time (seconds), for `getSequenceTime`.
+    static SEQUENCE_TIME: Cell<Option<f64>> = const { Cell::new(None) };
+    /// Camera exposure set from JavaScript, applied by the rAF loop.
+    static JS_EXPOSURE: Cell<Option<CameraExposure>> = const { Cell::new(None) };
+    /// LUTs loaded (or cleared) from JavaScript, applied in order by the rAF loop.
+    static JS_LUTS: RefCell<Vec<(LutKind, Option<Lut3d>)>> = const { RefCell::new(Vec::new()) };
+    /// Audio calls from JavaScript, applied in order by the rAF loop.
+    static JS_AUDIO: RefCell<Vec<AudioCommand>> = const { RefCell::new(Vec::new()) };
+    /// The track's last frame, for `getAudioTime` / `getAudioBands`.
+    static AUDIO_FRAME: Cell<AudioFrame> = Cell::new(AudioFrame::default());
+    /// Whether frames present in extended range, for `isHdrOutputActive`.
+    static HDR_ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+enum AudioCommand {
+    Load(String),
+    Play,
+    Pause,
+    Seek(f64),
+}
+
+enum SequenceCommand {
+    Play(Box<Sequence>),
+    Stop,
+    Seek(f64),
+    SetPlaying(bool),
+    SetLooping(bool),
+}
+
+fn edit_settings(edit: impl FnOnce(&mut RenderSettings)) {
+    JS_SETTINGS.with_borrow_mut(|(settings, dirty)| {
+        edit(settings);
+        *settings = settings.sanitized();
+        *dirty = true;
+    });
+}
+
+/// Applies pending JS edits to `settings`, or mirrors `settings` back to JS;
+/// persists either change.
+fn sync_settings(settings: &mut RenderSettings) {
+    JS_SETTINGS.with_borrow_mut(|(js, dirty)| {
+        if *dirty {
+            *dirty = false;
+            *settings = *js;
+            settings.save();
+        } else if *js != *settings {
+            *js = *settings;
+            settings.save();
+        }
+    });
+}
+
+/// Modifier state of a DOM keyboard or mouse event, for the UI overlay.
+const fn ui_modifiers(alt: bool, ctrl: bool, shift: bool, meta: bool) -> egui::Modifiers {
+    egui::Modifiers {
+        alt,
+        ctrl,
+        shift,
+        mac_cmd: false,
+        command: ctrl || meta,
+    }
+}
+
+/// JS: `setRenderPreset("low" | "medium" | "high")`. Returns false for unknown names.
+#[wasm_bindgen(js_name = setRenderPreset)]
+pub fn set_render_preset(name: &str) -> bool {
+    let Some(preset) = QualityPreset::from_name(name) else {
+        return false;
+    };
+    edit_settings(|s| s.apply_preset(preset));
+    true
+}
+
+/// JS: `setRenderFeature("warehouse" | "scene" | "gbuffer" | "taa" | "post" | "dynamic_resolution"
+/// | "auto_exposure" | "hdr" | "hud" | "ui", enabled)`.
+#[wasm_bindgen(js_name = setRenderFeature)]
+pub fn set_render_feature(name: &str, enabled: bool) -> bool {
+    let mut known = false;
+    edit_settings(|s| known = s.set_feature(name, enabled));
+    known
+}
+
+/// JS: `setBloomMips(count)`, 2..=5.
+#[wasm_bindgen(js_name = setBloomMips)]
+pub fn set_bloom_mips(count: u32) {
+    edit_settings(|s| {
+        s.bloom_mips = count;
+        s.preset = QualityPreset::Custom;
+    });
+}
+
+/// JS: `setRaymarchSteps(march, shadow)`.
+#[wasm_bindgen(js_name = setRaymarchSteps)]
+pub fn set_raymarch_steps(march: u32, shadow: u32) {
+    edit_settings(|s| {
+        s.raymarch_steps = march;
+        s.shadow_steps = shadow;
+        s.preset = QualityPreset::Custom;
+    });
+}
+
+/// JS: `setRenderScale(scale)`, 0.25..=1.0 (1.0 = full res). Upper bound when dynamic
+/// resolution is on.
+#[wasm_bindgen(js_name = setRenderScale)]
+pub fn set_render_scale(scale: f32) {
+    edit_settings(|s| s.render_scale = scale);
+}
+
+/// JS: `setDynamicResolution(enabled, targetFps, minScale)`.
+#[wasm_bindgen(js_name = setDynamicResolution)]
+pub fn set_dynamic_resolution(enabled: bool, target_fps: f32, min_scale: f32) {
+    edit_settings(|s| {
+        s.dynamic_resolution = enabled;
+        s.target_fps = target_fps;
+        s.min_render_scale = min_scale;
+    });
+}
+
+/// JS: `setEyeAdaptation(speed, minEv, maxEv)`: how fast auto-exposure adapts (per second) and the
+/// EV100 range it stays in. Turn it on with `setRenderFeature("auto_exposure", true)`.
+#[wasm_bindgen(js_name = setEyeAdaptation)]
+pub fn set_eye_adaptation(speed: f32, min_ev: f32, max_ev: f32) {
+    edit_settings(|s| {
+        s.adaptation_speed = speed;
+        s.min_ev = min_ev;
+        s.max_ev = max_ev;
+    });
+}
+
+/// JS: `setCameraExposure(aperture, shutterSeconds, iso, compensation = 0)`, e.g.
+/// `setCameraExposure(1.2, 1 / 60, 6400)` (the default). Used while auto-exposure is off, except
+/// `compensation` (stops), which applies either way. Returns false unless all are positive.
+#[wasm_bindgen(js_name = setCameraExposure)]
+pub fn set_camera_exposure(
+    aperture: f32,
+    shutter_s: f32,
+    iso: f32,
+    compensation: Option<f32>,
+) -> bool {
+    let compensation = compensation.unwrap_or(0.0);
+    if !([aperture, shutter_s, iso]
+        .iter()
+        .all(|v| v.is_finite() && *v > 0.0)
+        && compensation.is_finite())
+    {
+        log_error!("setCameraExposure: aperture, shutter and ISO must be positive");
+        return false;
+    }
+    JS_EXPOSURE.set(Some(CameraExposure {
+        aperture,
+        shutter_s,
+        iso,
+        compensation,
+    }));
+    true
+}
+
+/// JS: `setTonemapper("aces" | "agx" | "reinhard" | "tony_mc_mapface" | "none")`. Tony McMapface
+/// needs its LUT (`loadColorLut("tony_mc_mapface", bytes)`) and uses AgX until then. Returns false
+/// for unknown names.
+#[wasm_bindgen(js_name = setTonemapper)]
+pub fn set_tonemapper(name: &str) -> bool {
+    let Some(tonemapper) = Tonemapper::from_name(name) else {
+        log_error!("setTonemapper: unknown tonemapper {name:?}");
+        return false;
+    };
+    edit_settings(|s| s.tonemapper = tonemapper);
+    true
+}
+
+/// JS: `setColorGrading(temperature, tint, contrast, saturation, lutStrength = 1)`: white balance
+/// (-1..1 each), contrast and saturation (1 = unchanged), and the grading LUT's blend (0..1).
+#[wasm_bindgen(js_name = setColorGrading)]
+pub fn set_color_grading(
+    temperature: f32,
+    tint: f32,
+    contrast: f32,
+    saturation: f32,
+    lut_strength: Option<f32>,
+) {
+    let lut_strength = lut_strength.unwrap_or(1.0);
+    edit_settings(|s| {
+        s.grading = ColorGrading {
+            temperature,
+            tint,
+            contrast,
+            saturation,
+            lut_strength,
+        }
+    });
+}
+
+/// JS: `loadColorLut("grading" | "tony_mc_mapface", bytes)` with the contents of a `.cube` file or
+/// of `tony_mc_mapface.dds` (a `Uint8Array`, e.g. from `fetch(url).then(r => r.arrayBuffer())`).
+/// Returns false (keeping the current LUT) if it doesn't parse.
+#[wasm_bindgen(js_name = loadColorLut)]
+pub fn load_color_lut(kind: &str, bytes: &[u8]) -> bool {
+    let Some(kind) = LutKind::from_name(kind) else {
+        log_error!("loadColorLut: unknown LUT {kind:?} (expected grading or tony_mc_mapface)");
+        return false;
+    };
+    match Lut3d::from_bytes(bytes) {
+        Ok(lut) => {
+            JS_LUTS.with_borrow_mut(|luts| luts.push((kind, Some(lut))));
+            true
+        }
+        Err(e) => {
+            log_error!("loadColorLut: {e}");
+            false
+        }
+    }
+}
+
+/// JS: `clearColorLut("grading" | "tony_mc_mapface")`. Returns false for unknown names.
+#[wasm_bindgen(js_name = clearColorLut)]
+pub fn clear_color_lut(kind: &str) -> bool {
+    let Some(kind) = LutKind::from_name(kind) else {
+        return false;
+    };
+    JS_LUTS.with_borrow_mut(|luts| luts.push((kind, None)));
+    true
+}
+
+/// JS: `setHdrBrightness(peakNits, paperWhiteNits = 203)`: the display's peak brightness, which HDR
+/// tonemapping reaches, and SDR white's. Turn HDR output on with `setRenderFeature("hdr", true)`.
+#[wasm_bindgen(js_name = setHdrBrightness)]
+pub fn set_hdr_brightness(peak_nits: f32, paper_white_nits: Option<f32>) {
+    edit_settings(|s| {
+        s.peak_nits = peak_nits;
+        s.paper_white_nits = paper_white_nits.unwrap_or(203.0);
+    });
+}
+
+/// JS: `isHdrOutputActive()` -> whether frames present in extended range: HDR output is on, the
+/// display is HDR and the browser supports extended tone mapping. False means SDR output.
+#[wasm_bindgen(js_name = isHdrOutputActive)]
+pub fn is_hdr_output_active() -> bool {
+    HDR_ACTIVE.get()
+}
+
+/// JS: `getRenderSettings()` -> plain object with the current settings.
+#[wasm_bindgen(js_name = getRenderSettings)]
+pub fn get_render_settings() -> JsValue {
+    JS_SETTINGS
+        .with_borrow(|(settings, _)| serde_wasm_bindgen::to_value(settings))
+        .unwrap_or(JsValue::NULL)
+}
+
+/// JS: `resetRenderSettings()` back to defaults (High).
+#[wasm_bindgen(js_name = resetRenderSettings)]
+pub fn reset_render_settings() {
+    edit_settings(|s| *s = RenderSettings::default());
+}
+
+/// JS: `getInputBindings()` -> `{ actions: { name: [binding, ...] }, axes: { ... } }` (see
+/// [crate::input::Bindings]).
+#[wasm_bindgen(js_name = getInputBindings)]
+pub fn get_input_bindings() -> JsValue {
+    JS_BINDINGS
+        .with_borrow(|pending| {
+            pending.as_ref().map_or_else(
+                || CURRENT_BINDINGS.with_borrow(serde_wasm_bindgen::to_value),
+                serde_wasm_bindgen::to_value,
+            )
+        })
+        .unwrap_or(JsValue::NULL)
+}
+
+/// JS: `setInputBindings(bindings)`, the shape `getInputBindings` returns; replaces all bindings.
+/// Returns false (keeping the current ones) if it doesn't parse.
+#[wasm_bindgen(js_name = setInputBindings)]
+pub fn set_input_bindings(bindings: JsValue) -> bool {
+    match serde_wasm_bindgen::from_value::<Bindings>(bindings) {
+        Ok(bindings) => {
+            JS_BINDINGS.set(Some(bindings));
+            true
+        }
+        Err(e) => {
+            log_error!("setInputBindings: {e}");
+            false
+        }
+    }
+}
+
+/// JS: `resetInputBindings()` back to the defaults.
+#[wasm_bindgen(js_name = resetInputBindings)]
+pub fn reset_input_bindings() {
+    JS_BINDINGS.set(Some(Bindings::default()));
+}
+
+/// Applies bindings set from JS since the last frame, and persists them.
+fn sync_bindings(runner: &mut Runner) {
+    if let Some(bindings) = JS_BINDINGS.take() {
+        bindings.save();
+        CURRENT_BINDINGS.set(bindings.clone());
+        runner.set_bindings(bindings);
+    }
+}
+
+/// JS: `getScenes()` -> the scene names, in switching order.
+#[wasm_bindgen(js_name = getScenes)]
+pub fn get_scenes() -> Vec<String> {
+    SCENES.with_borrow(|(names, _)| names.iter().map(|name| (*name).to_string()).collect())
+}
+
+/// JS: `getCurrentScene()` -> index of the current scene in `getScenes()`.
+#[wasm_bindgen(js_name = getCurrentScene)]
+pub fn get_current_scene() -> usize {
+    SCENES.with_borrow(|(_, current)| *current)
+}
+
+/// JS: `switchScene(index, transition = "fade", durationMs = 1000)`, with `transition` one of
+/// `"cut"`, `"fade"`, `"wipe"`. Takes effect next frame; returns false for an unknown scene or
+/// transition.
+#[wasm_bindgen(js_name = switchScene)]
+pub fn switch_scene(index: usize, transition: Option<String>, duration_ms: Option<f64>) -> bool {
+    let default = Transition::default();
+    let Some(kind) = transition.map_or(Some(default.kind), |name| TransitionKind::from_name(&name))
+    else {
+        log_error!("switchScene: unknown transition (expected cut, fade or wipe)");
+        return false;
+    };
+    if index >= SCENES.with_borrow(|(names, _)| names.len()) {
+        log_error!("switchScene: no scene {index}");
+        return false;
+    }
+    let duration_ms = duration_ms.unwrap_or(default.duration_ms).max(0.0);
+    JS_SCENE_SWITCH.set(Some((index, Transition { kind, duration_ms })));
+    true
+}
+
+/// Applies a scene switch requested from JS, and mirrors the current scene back.
+fn sync_scene(runner: &mut Runner) {
+    if let Some((index, transition)) = JS_SCENE_SWITCH.take() {
+        runner.switch_scene(index, transition);
+    }
+    SCENES.with_borrow_mut(|(_, current)| *current = runner.current_scene());
+}
+
+/// JS: `playSequence(script)`, a timeline script object (see [crate::sequencer]; the JSON files'
+/// shape). Plays it from 0, replacing any other. Returns false (keeping the current one) if it
+/// doesn't parse.
+#[wasm_bindgen(js_name = playSequence)]
+pub fn play_sequence(script: JsValue) -> bool {
+    match serde_wasm_bindgen::from_value::<Sequence>(script) {
+        Ok(sequence) => {
+            JS_SEQUENCE.with_borrow_mut(|commands| {
+                commands.push(SequenceCommand::Play(Box::new(sequence)))
+            });
+            true
+        }
+        Err(e) => {
+            log_error!("playSequence: {e}");
+            false
+        }
+    }
+}
+
+/// JS: `stopSequence()`; scenes and camera stay where the sequence left them.
+#[wasm_bindgen(js_name = stopSequence)]
+pub fn stop_sequence() {
+    JS_SEQUENCE.with_borrow_mut(|commands| commands.push(SequenceCommand::Stop));
+}
+
+/// JS: `seekSequence(seconds)`.
+#[wasm_bindgen(js_name = seekSequence)]
+pub fn seek_sequence(time_s: f64) {
+    JS_SEQUENCE.with_borrow_mut(|commands| commands.push(SequenceCommand::Seek(time_s)));
+}
+
+/// JS: `setSequencePlaying(playing)`: pause (false) or resume (true).
+#[wasm_bindgen(js_name = setSequencePlaying)]
+pub fn set_sequence_playing(playing: bool) {
+    JS_SEQUENCE.with_borrow_mut(|commands| commands.push(SequenceCommand::SetPlaying(playing)));
+}
+
+/// JS: `setSequenceLoop(looping)`, overriding the script's `loop`.
+#[wasm_bindgen(js_name = setSequenceLoop)]
+pub fn set_sequence_loop(looping: bool) {
+    JS_SEQUENCE.with_borrow_mut(|commands| commands.push(SequenceCommand::SetLooping(looping)));
+}
+
+/// JS: `getSequenceTime()` -> seconds into the playing sequence, or `undefined` without one.
+#[wasm_bindgen(js_name = getSequenceTime)]
+pub fn get_sequence_time() -> Option<f64> {
+    SEQUENCE_TIME.get()
+}
+
+/// Applies sequencer calls from JS, and mirrors the sequence time back.
+fn sync_sequence(runner: &mut Runner) {
+    for command in JS_SEQUENCE.take() {
+        match command {
+            SequenceCommand::Play(sequence) => {
+                runner.set_sequencer(Some(Sequencer::new(*sequence)))
+            }
+            SequenceCommand::Stop => runner.set_sequencer(None),
+            command => {
+                let Some(sequencer) = runner.sequencer_mut() else {
+                    continue;
+                };
+                match command {
+                    SequenceCommand::Seek(time_s) => sequencer.seek(time_s),
+                    SequenceCommand::SetPlaying(playing) => sequencer.set_playing(playing),
+                    SequenceCommand::SetLooping(looping) => sequencer.set_looping(looping),
+                    SequenceCommand::Play(_) | SequenceCommand::Stop => {}
+                }
+            }
+        }
+    }
+    SEQUENCE_TIME.set(runner.sequencer_mut().map(|s| s.time()));
+}
+
+/// JS: `loadAudio(url)` replaces the music with the track at `url` (any format the browser
+/// decodes), paused at 0; `playAudio()` then starts it once decoded. While it plays, the demo
+/// clock follows it.
+#[wasm_bindgen(js_name = loadAudio)]
+pub fn load_audio(url: String) {
+    JS_AUDIO.with_borrow_mut(|commands| commands.push(AudioCommand::Load(url)));
+}
+
+/// JS: `playAudio()`. Browsers hold audio back until the user has clicked or typed on the page.
+#[wasm_bindgen(js_name = playAudio)]
+pub fn play_audio() {
+    JS_AUDIO.with_borrow_mut(|commands| commands.push(AudioCommand::Play));
+}
+
+/// JS: `pauseAudio()`.
+#[wasm_bindgen(js_name = pauseAudio)]
+pub fn pause_audio() {
+    JS_AUDIO.with_borrow_mut(|commands| commands.push(AudioCommand::Pause));
+}
+
+/// JS: `seekAudio(seconds)`.
+#[wasm_bindgen(js_name = seekAudio)]
+pub fn seek_audio(time_s: f64) {
+    JS_AUDIO.with_borrow_mut(|commands| commands.push(AudioCommand::Seek(time_s)));
+}
+
+/// JS: `getAudioTime()` -> seconds into the track, or `undefined` until one is loaded.
+#[wasm_bindgen(js_name = getAudioTime)]
+pub fn get_audio_time() -> Option<f64> {
+    AUDIO_FRAME.get().position_s
+}
+
+/// JS: `getAudioBands()` -> `Float32Array` of this frame's band levels (0..1, bass first).
+#[wasm_bindgen(js_name = getAudioBands)]
+pub fn get_audio_bands() -> Vec<f32> {
+    AUDIO_FRAME.get().bands.to_vec()
+}
+
+/// Applies audio calls from JS, and mirrors the last frame's state back.
+fn sync_audio(runner: &mut Runner) {
+    let audio = runner.audio_mut();
+    for command in JS_AUDIO.take() {
+        match command {
+            AudioCommand::Load(url) => {
+                if let Err(e) = audio.load(&url) {
+                    log_error!("loadAudio: {e}");
+                }
+            }
+            AudioCommand::Play => audio.play(),
+            AudioCommand::Pause => audio.pause(),
+            AudioCommand::Seek(time_s) => audio.seek(time_s),
+        }
+    }
+    AUDIO_FRAME.set(*audio.frame());
+}
+
+/// Canvas-backed platform. Listeners write into shared state; the rAF loop reads it.
+pub struct WebPlatform {
+    canvas: Rc<web_sys::HtmlCanvasElement>,
+    input: Rc<RefCell<InputState>>,
+    max_size: (u32, u32),
+    frame_callback: FrameCallback,
+}
+
+impl WebPlatform {
+    /// Creates the fullscreen canvas and installs resize, pointer-lock, mouse, wheel, touch
+    /// and keyboard listeners.
+    fn new() -> Self {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let canvas = document
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+
+        #[allow(unused_must_use)]
+        {
+            canvas.style().set_property("position", "fixed");
+            canvas.style().set_property("left", "0");
+            canvas.style().set_property("top", "0");
+            canvas.style().set_property("width", "100%");
+            canvas.style().set_property("height", "100%");
+            // Touches drive the touch controls instead of scrolling or zooming the page.
+            canvas.style().set_property("touch-action", "none");
+            document.body().unwrap().append_child(&canvas);
+        }
+
+        log!("Canvas created; WebGPU init starting.");
+
+        let width = canvas.client_width() as u32;
+        let height = canvas.client_height() as u32;
+        if width != 0 && height != 0 {
+            canvas.set_width(width);
+            canvas.set_height(height);
+        }
+
+        let screen = web_sys::window().unwrap().screen().unwrap();
+        let platform = Self {
+            canvas: Rc::new(canvas),
+            input: Rc::new(RefCell::new(InputState::default())),
+            max_size: (
+                screen.width().ok().unwrap() as u32,
+                screen.height().ok().unwrap() as u32,
+            ),
+            frame_callback: Rc::new(RefCell::new(None)),
+        };
+        platform.add_listeners(&document);
+        platform
+    }
+
+    pub fn canvas(&self) -> Rc<web_sys::HtmlCanvasElement> {
+        self.canvas.clone()
+    }
+
+    fn add_listeners(&self, document: &web_sys::Document) {
+        // Resize: update the canvas backing size; the runner picks it up next frame.
+        {
+            let canvas = self.canvas.clone();
+            let closure = Closure::wrap(Box::new(move || {
+                let width = canvas.client_width() as u32;
+                let height = canvas.client_height() as u32;
+                if width != 0 && height != 0 && canvas.width() != width && canvas.height() != height
+                {
+                    canvas.set_width(width);
+                    canvas.set_height(height);
+                }
+            }) as Box<dyn FnMut()>);
+            web_sys::window()
+                .unwrap()
+                .set_onresize(Option::Some(closure.as_ref().unchecked_ref()));
+            closure.forget();
+        }
+
+        // Pointer lock on canvas click, unless the click was on the UI overlay or came from a touch
+        // (clicks are pointer events in current browsers).
+        {
+            let canvas_for_click = self.canvas.clone();
+            let closure = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
+                let touch = e
+                    .dyn_ref::<web_sys::PointerEvent>()
+                    .is_some_and(|e| e.pointer_type() != "mouse");
+                if !touch && !crate::ui::wants_pointer() {
+                    canvas_for_click.request_pointer_lock();
+                }
+            }) as Box<dyn FnMut(web_sys::MouseEvent)>);
+            #[allow(unused_must_use)]
+            {
+                self.canvas
+                    .add_event_listener_with_callback("click", closure.as_ref().unchecked_ref());
+            }
+            closure.forget();
+        }
+
+        // Mouse move: accumulate deltas while the pointer is locked, else move the UI pointer (the
+        // canvas fills the window at one framebuffer pixel per CSS pixel).
+        {
+            let input = self.input.clone();
+            let document_for_mouse = document.clone();
+            let closure = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
+                let mut input = input.borrow_mut();
+                if document_for_mouse.pointer_lock_element().is_some() {
+                    input.mouse_move(e.movement_x() as f32, e.movement_y() as f32);
+                } else {
+                    let pos = egui::pos2(e.client_x() as f32, e.client_y() as f32);
+                    input.ui_events.push(egui::Event::PointerMoved(pos));
+                }
+            }) as Box<dyn FnMut(web_sys::MouseEvent)>);
+            #[allow(unused_must_use)]
+            {
+                document.add_event_listener_with_callback(
+                    "mousemove",
+                    closure.as_ref().unchecked_ref(),
+                );
+            }
+            closure.forget();
+        }
+
+        // Mouse buttons: input bindings while the pointer is locked, else UI clicks and drags.
+        // Releases always clear the binding state so a button can't stick when the lock ends.
+        for (event, pressed) in [("mousedown", true), ("mouseup", false)] {
+            let input = self.input.clone();
+            let document_for_mouse = document.clone();
+            let closure = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
+                let (button, binding) = match e.button() {
+                    0 => (egui::PointerButton::Primary, MouseButton::Left),
+                    1 => (egui::PointerButton::Middle, MouseButton::Middle),
+                    2 => (egui::PointerButton::Secondary, MouseButton::Right),
+                    _ => return,
+                };
+                let locked = document_for_mouse.pointer_lock_element().is_some();
+                if locked || !pressed {
+                    input.borrow_mut().set_mouse_button(binding.mask(), pressed);
+                }
+                if locked {
+                    return;
+                }
+                let modifiers =
+                    ui_modifiers(e.alt_key(), e.ctrl_key(), e.shift_key(), e.meta_key());
+                let pos = egui::pos2(e.client_x() as f32, e.client_y() as f32);
+                let mut input = input.borrow_mut();
+                input.ui_modifiers = modifiers;
+                input.ui_events.push(egui::Event::PointerButton {
+                    pos,
+                    button,
+                    pressed,
+                    modifiers,
+                });
+            }) as Box<dyn FnMut(web_sys::MouseEvent)>);
+            #[allow(unused_must_use)]
+            {
+                document.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref());
+            }
+            closure.forget();
+        }
+
+        // Wheel: input bindings while the pointer is locked, else scrolls UI panels.
+        {
+            let input = self.input.clone();
+            let document_for_wheel = document.clone();
+            let closure = Closure::wrap(Box::new(move |e: web_sys::WheelEvent| {
+                let (unit, notch) = match e.delta_mode() {
+                    web_sys::WheelEvent::DOM_DELTA_LINE => {
+                        (egui::MouseWheelUnit::Line, WHEEL_LINES_PER_NOTCH)
+                    }
+                    web_sys::WheelEvent::DOM_DELTA_PAGE => (egui::MouseWheelUnit::Page, 1.0),
+                    _ => (egui::MouseWheelUnit::Point, WHEEL_PIXELS_PER_NOTCH),
+                };
+                let mut input = input.borrow_mut();
+                if document_for_wheel.pointer_lock_element().is_some() {
+                    input.wheel -= e.delta_y() as f32 / notch;
+                    return;
+                }
+                let modifiers = input.ui_modifiers;
+                input.ui_events.push(egui::Event::MouseWheel {
+                    unit,
+                    // DOM deltas scroll the view; egui's move the content.
+                    delta: egui::vec2(-e.delta_x() as f32, -e.delta_y() as f32),
+                    phase: egui::TouchPhase::Move,
+                    modifiers,
+                });
+            }) as Box<dyn FnMut(web_sys::WheelEvent)>);
+            #[allow(unused_must_use)]
+            {
+                self.canvas
+                    .add_event_listener_with_callback("wheel", closure.as_ref().unchecked_ref());
+            }
+            closure.forget();
+        }
+
+        // Touch and pen (Pointer Events): the touch controls, or the UI where it covers the canvas.
+        // Handled events are cancelled so no emulated mouse events follow.
+        for event in ["pointerdown", "pointermove", "pointerup", "pointercancel"] {
+            let input = self.input.clone();
+            let canvas = self.canvas.clone();
+            let closure = Closure::wrap(Box::new(move |e: web_sys::PointerEvent| {
+                if e.pointer_type() == "mouse" {
+                    return;
+                }
+                e.prevent_default();
+                let id = i64::from(e.pointer_id());
+                let position = Vec2::new(e.client_x() as f32, e.client_y() as f32);
+                let mut input = input.borrow_mut();
+                match e.type_().as_str() {
+                    "pointerdown" => {
+                        // Keep receiving this pointer's events if it slides off the canvas.
+                        let _ = canvas.set_pointer_capture(e.pointer_id());
+                        input.touch_start(id, position, canvas.width() as f32);
+                    }
+                    "pointermove" => input.touch_move(id, position),
+                    _ => input.touch_end(id, position),
+                }
+            }) as Box<dyn FnMut(web_sys::PointerEvent)>);
+            #[allow(unused_must_use)]
+            {
+                self.canvas
+                    .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref());
+            }
+            closure.forget();
+        }
+
+        // Keyboard: track held keys (by code) and forward keys and text to the UI. F1 and F3 (the
+        // default `toggle_ui` and `toggle_hud` keys) would open the browser's help and search.
+        for (event, down) in [("keydown", true), ("keyup", false)] {
+            let input = self.input.clone();
+            let closure = Closure::wrap(Box::new(move |e: web_sys::KeyboardEvent| {
+                if matches!(e.code().as_str(), "F1" | "F3") {
+                    e.prevent_default();
+                }
+                let mut input = input.borrow_mut();
+                input.ui_modifiers =
+                    ui_modifiers(e.alt_key(), e.ctrl_key(), e.shift_key(), e.meta_key());
+                let key = e.key();
+                input.ui_key(&key, &e.code(), down, e.repeat());
+                // Printable keys are one character long; named keys ("Enter", "Shift") aren't.
+                if down && key.chars().count() == 1 && !input.ui_modifiers.command {
+                    input.ui_events.push(egui::Event::Text(key));
+                }
+                input.set_key(&e.code(), down);
+            }) as Box<dyn FnMut(web_sys::KeyboardEvent)>);
+            #[allow(unused_must_use)]
+            {
+                document.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref());
+            }
+            closure.forget();
+        }
+    }
+
+    fn add_vr_button(pending_stereo_toggle: Rc<RefCell<bool>>) {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let button = document
+            .create_element("div")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlDivElement>()
+            .unwrap();
+
+        #[allow(unused_must_use)]
+        {
+            button.set_inner_text("🤓");
+            button.style().set_property("position", "fixed");
+            button.style().set_property("right", "0");
+            button.style().set_property("bottom", "0");
+            button.style().set_property("font-size", "10em");
+            button.style().set_property("cursor", "pointer");
+            button.style().set_property("user-select", "none");
+            button.style().set_property("transform", "rotate(45deg)");
+            button.style().set_property(
+                "text-shadow",
+                "#f00 -0.05em 0.05em 0.1em, #0ff 0.05em -0.05em 0.1em",
+            );
+            document.body().unwrap().append_child(&button);
+        }
+
+        let closure = Closure::wrap(Box::new(move || {
+            *pending_stereo_toggle.borrow_mut() = true;
+        }) as Box<dyn FnMut()>);
+        #[allow(unused_must_use)]
+        {
+            button.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref());
+        }
+        closure.forget();
+    }
+}
+
+impl Platform for WebPlatform {
+    fn framebuffer_size(&self) -> (u32, u32) {
+        (self.canvas.width(), self.canvas.height())
+    }
+
+    fn max_framebuffer_size(&self) -> (u32, u32) {
+        self.max_size
+    }
+
+    fn create_surface(&self, instance: &wgpu::Instance) -> Option<wgpu::Surface<'static>> {
+        instance
+            .create_surface(wgpu::SurfaceTarget::Canvas(self.canvas.as_ref().clone()))
+            .ok()
+    }
+
+    fn take_input(&mut self) -> InputState {
+        let mut input = self.input.borrow_mut().take();
+        input.gamepad = poll_gamepad();
+        input
+    }
+
+    fn now_ms(&self) -> f64 {
+        web_sys::window()
+            .and_then(|w| w.performance())
+            .map_or(0.0, |p| p.now())
+    }
+
+    fn hdr_display(&self) -> bool {
+        web_sys::window()
+            .and_then(|w| w.match_media("(dynamic-range: high)").ok().flatten())
+            .is_some_and(|query| query.matches())
+    }
+
+    /// Sets the canvas configuration's `toneMapping` mode, which wgpu doesn't expose: the
+    /// current configuration is read back with `getConfiguration()`, amended and reapplied.
+    /// Browsers without extended tone mapping (or `getConfiguration`) leave it out, so the mode
+    /// is read back too.
+    fn set_extended_range(&self, enabled: bool) -> bool {
+        let get = |target: &JsValue, key: &str| {
+            js_sys::Reflect::get(target, &JsValue::from_str(key)).ok()
+        };
+        let method =
+            |target: &JsValue, name: &str| get(target, name)?.dyn_into::<js_sys::Function>().ok();
+        let Some(context) = self
+            .canvas
+            .get_context("webgpu")
+            .ok()
+            .flatten()
+            .map(JsValue::from)
+        else {
+            return false;
+        };
+        let (Some(get_configuration), Some(configure)) = (
+            method(&context, "getConfiguration"),
+            method(&context, "configure"),
+        ) else {
+            return false;
+        };
+        let configuration = || {
+            get_configuration
+                .call0(&context)
+                .ok()
+                .filter(JsValue::is_object)
+        };
+        let Some(config) = configuration() else {
+            return false;
+        };
+        let tone_mapping = js_sys::Object::new();
+        let mode = JsValue::from_str(if enabled { "extended" } else { "standard" });
+        if js_sys::Reflect::set(&tone_mapping, &JsValue::from_str("mode"), &mode).is_err()
+            || js_sys::Reflect::set(&config, &JsValue::from_str("toneMapping"), &tone_mapping)
+                .is_err()
+            || configure.call1(&context, &config).is_err()
+        {
+            return false;
+        }
+        let applied = configuration()
+            .and_then(|config| get(&get(&config, "toneMapping")?, "mode"))
+            .and_then(|mode| mode.as_string());
+        enabled && applied.as_deref() == Some("extended")
+    }
+
+    fn request_frame(&self) {
+        if let Some(callback) = self.frame_callback.borrow().as_ref() {
+            #[allow(unused_must_use)]
+            {
+                web_sys::window()
+                    .unwrap()
+                    .request_animation_frame(callback.as_ref().unchecked_ref());
+            }
+        }
+    }
+}
+
+/// The first connected gamepad from `navigator.getGamepads()`. Pads without the standard mapping
+/// are read as if they had it (axes and buttons in the same order), which is usually close.
+fn poll_gamepad() -> GamepadState {
+    let Some(pads) = web_sys::window().and_then(|w| w.navigator().get_gamepads().ok()) else {
+        return GamepadState::default();
+    };
+    let to_f32 = |value: JsValue| value.as_f64().unwrap_or(0.0) as f32;
+    for pad in pads.iter() {
+        let Ok(pad) = pad.dyn_into::<web_sys::Gamepad>() else {
+            continue;
+        };
+        if !pad.connected() {
+            continue;
+        }
+        let axes: Vec<f32> = pad.axes().iter().map(to_f32).collect();
+        let buttons: Vec<f32> = pad
+            .buttons()
+            .iter()
+            .map(|button| {
+                button
+                    .dyn_into::<web_sys::GamepadButton>()
+                    .map_or(0.0, |b| b.value() as f32)
+            })
+            .collect();
+        return GamepadState::from_standard(&axes, &buttons);
+    }
+    GamepadState::default()
+}
+
+/// Starts the browser app: canvas, async WebGPU init, VR button, and the rAF loop.
+pub fn run(app_instance: Box<dyn AppInstance>) {
+    let mut platform = WebPlatform::new();
+    let mut runner = Runner::new(
+        app_instance,
+        Some(platform.canvas()),
+        platform.framebuffer_size(),
+        platform.max_framebuffer_size(),
+    );
+    if let Some(settings) = RenderSettings::load() {
+        log!("Render settings restored ({:?}).", settings.preset);
+        runner.app.render_settings = settings;
+    }
+    // Seed the JS copy before any listener or export can edit it, so edits start from these.
+    JS_SETTINGS.set((runner.app.render_settings, false));
+    if let Some(bindings) = Bindings::load() {
+        log!("Input bindings restored.");
+        CURRENT_BINDINGS.set(bindings.clone());
+        runner.set_bindings(bindings);
+    }
+    SCENES.set((runner.scene_names(), runner.current_scene()));
+
+    let pending_stereo_toggle = Rc::new(RefCell::new(false));
+    let pending_gpu = Rc::new(RefCell::new(None::<GpuContext>));
+
+    // Async WebGPU init. Stores the context in pending_gpu for the rAF loop to apply.
+    {
+        let pending_gpu_async = pending_gpu.clone();
+        let instance = crate::gpu::create_instance();
+        let surface = platform.create_surface(&instance);
+        let hdr_display = platform.hdr_display();
+        spawn_local(async move {
+            let Some(surface) = surface else {
+                return;
+            };
+            if let Some(gpu) = crate::gpu::init_gpu(instance, surface, hdr_display).await {
+                *pending_gpu_async.borrow_mut() = Some(gpu);
+                log!("WebGPU initialized.");
+            }
+        });
+    }
+
+    let frame_callback = platform.frame_callback.clone();
+    let pending_stereo_toggle_for_loop = pending_stereo_toggle.clone();
+    let closure = Closure::wrap(Box::new(move |timestamp| {
+        platform.request_frame();
+
+        if let Some(gpu) = pending_gpu.borrow_mut().take() {
+            runner.set_gpu(gpu);
+        }
+        if pending_stereo_toggle_for_loop.replace(false) {
+            runner.toggle_stereo();
+        }
+        sync_settings(&mut runner.app.render_settings);
+        sync_bindings(&mut runner);
+        sync_scene(&mut runner);
+        sync_sequence(&mut runner);
+        sync_audio(&mut runner);
+        if let Some(exposure) = JS_EXPOSURE.take() {
+            runner.app.camera.exposure = exposure;
+        }
+        for (kind, lut) in JS_LUTS.take() {
+            runner.app.color_luts.set(kind, lut);
+        }
+        let ui_was_shown = runner.app.render_settings.ui;
+        runner.frame(&mut platform, timestamp);
+        // Opening the UI (F1 by default) releases the pointer to use it.
+        if runner.app.render_settings.ui && !ui_was_shown {
+            web_sys::window()
+                .unwrap()
+                .document()
+                .unwrap()
+                .exit_pointer_lock();
+        }
+        HDR_ACTIVE.set(runner.hdr_active());
+    }) as Box<dyn FnMut(f64)>);
+
+    *frame_callback.borrow_mut() = Some(closure);
+
+    let vr_supported = js_sys::eval("navigator.xr !== undefined")
+        .unwrap()
+        .as_bool()
+        .unwrap();
+    log!("WebXR support: {}", vr_supported);
+
+    if vr_supported {
+        WebPlatform::add_vr_button(pending_stereo_toggle);
+    } else {
+        log!("WebXR not available (navigator.xr is undefined).");
+    }
+
+    log!("Starting render loop...");
+    #[allow(unused_must_use)]
+    {
+        web_sys::window().unwrap().request_animation_frame(
+            frame_callback
+                .borrow()
+                .as_ref()
+                .unwrap()
+                .as_ref()
+                .unchecked_ref(),
+        );
+    }
+}
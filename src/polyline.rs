@@ -0,0 +1,651 @@
+//! Thick anti-aliased polylines: any number of independent strips, drawn in one instanced call.
+//!
+//! Each segment becomes a screen-space quad (`wgsl/polyline.wgsl`), so widths are in pixels
+//! regardless of distance, with miter / round / bevel joins, butt / square / round caps, dashes and
+//! per-vertex color. Points are 3D; for 2D overlays use `z = 0` with an orthographic matrix.
+//!
+//! Strips live on the CPU until [Polylines::prepare] uploads them (the buffer grows as needed).
+//! [Polylines::draw] records into any pass whose color format and [DepthTest] it was prepared for;
+//! [PolylinePass] draws them into the G-buffer [COLOR], depth-tested against [DEPTH].
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use glam::{Mat4, Vec3, Vec4};
+use wgpu::RenderPass;
+
+use crate::gpu::stats;
+use crate::render_graph::{GraphPass, PassContext, Slot, COLOR, DEPTH};
+use crate::stereo_camera::Eye;
+use crate::view::ViewState;
+
+const POLYLINE_WGSL: &str = include_str!("wgsl/polyline.wgsl");
+
+/// Segments the instance buffer holds before its first resize.
+const INITIAL_CAPACITY: usize = 256;
+/// Uniform slots, one per [Eye], so both stereo views can be drawn in one submission.
+const UNIFORM_SLOTS: u64 = 3;
+/// Dynamic uniform offsets must be 256-byte aligned.
+const UNIFORM_STRIDE: u64 = 256;
+
+/// Shape of the outer corner where two segments meet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Sharp corner, cut flat (as [LineJoin::Bevel]) past [LineStyle::miter_limit].
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// Shape of a strip's open ends.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineCap {
+    /// Ends exactly at the end point.
+    #[default]
+    Butt,
+    /// Extends half the width past the end point.
+    Square,
+    Round,
+}
+
+/// How polylines are depth-tested (reversed-Z; they never write depth).
+#[derive(Clone, Copy, Debug)]
+pub enum DepthTest<'a> {
+    /// Not tested; the pass has no depth attachment.
+    None,
+    /// Against the pass's depth attachment, of this format.
+    Attachment(wgpu::TextureFormat),
+    /// In the shader, against a depth texture that isn't attached (read as unfilterable float, so
+    /// `Depth32Float` works on every backend). `flipped`: the color target is stored upside down
+    /// relative to the depth (as the TAA resolve is); drawing and the lookup both account for it.
+    Texture {
+        view: &'a wgpu::TextureView,
+        flipped: bool,
+    },
+}
+
+/// Pipeline variant for a [DepthTest] (also used by [crate::text]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PipelineDepth {
+    None,
+    Attachment(wgpu::TextureFormat),
+    Texture,
+}
+
+impl From<DepthTest<'_>> for PipelineDepth {
+    fn from(depth: DepthTest<'_>) -> Self {
+        match depth {
+            DepthTest::None => Self::None,
+            DepthTest::Attachment(format) => Self::Attachment(format),
+            DepthTest::Texture { .. } => Self::Texture,
+        }
+    }
+}
+
+/// How a strip is stroked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineStyle {
+    /// Width in pixels; thinner lines are drawn 1 px wide with reduced alpha.
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// Longest miter, in multiples of half the width (SVG's `stroke-miterlimit`).
+    pub miter_limit: f32,
+    /// Dash and gap lengths, measured along the strip in the points' units. `None` is solid.
+    pub dash: Option<(f32, f32)>,
+    /// Joins the last point back to the first (no caps).
+    pub closed: bool,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        Self {
+            width: 2.0,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+            dash: None,
+            closed: false,
+        }
+    }
+}
+
+/// `Segment` in `polyline.wgsl`: one instance per segment.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SegmentInstance {
+    prev: [f32; 3],
+    a: [f32; 3],
+    b: [f32; 3],
+    next: [f32; 3],
+    color_a: [f32; 4],
+    color_b: [f32; 4],
+    distance: [f32; 2],
+    width_limit: [f32; 2],
+    dash: [f32; 2],
+    flags: u32,
+}
+
+const SEGMENT_STRIDE: u64 = std::mem::size_of::<SegmentInstance>() as u64;
+
+const HAS_PREV: u32 = 1 << 4;
+const HAS_NEXT: u32 = 1 << 5;
+
+/// `Uniforms` in `polyline.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PolylineUniforms {
+    view_projection: [f32; 16],
+    viewport: [f32; 4],
+    depth_y: [f32; 2],
+    _pad: [f32; 2],
+}
+
+/// A set of polyline strips and the GPU state to draw them.
+#[derive(Default)]
+pub struct Polylines {
+    segments: Vec<SegmentInstance>,
+    dirty: bool,
+    /// Passes the depth test everywhere (the pass may still have a depth attachment).
+    on_top: bool,
+    gpu: Option<PolylinesGpu>,
+}
+
+/// Instance and uniform buffers, plus one pipeline per (color format, depth test) drawn with.
+struct PolylinesGpu {
+    queue: wgpu::Queue,
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    /// Adds group 1 (depth texture) for [DepthTest::Texture].
+    depth_texture_pipeline_layout: wgpu::PipelineLayout,
+    depth_layout: wgpu::BindGroupLayout,
+    pipelines: Vec<(wgpu::TextureFormat, PipelineDepth, wgpu::RenderPipeline)>,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// This frame's [DepthTest::Texture] view (set by [Polylines::prepare]).
+    depth_bind_group: Option<wgpu::BindGroup>,
+    instance_buffer: wgpu::Buffer,
+    capacity: usize,
+    segment_count: u32,
+}
+
+impl Polylines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes all strips (takes effect at the next [Polylines::prepare]).
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.dirty = true;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Draws over all geometry instead of depth-testing.
+    pub fn set_on_top(&mut self, on_top: bool) {
+        if self.on_top != on_top {
+            self.on_top = on_top;
+            if let Some(gpu) = &mut self.gpu {
+                gpu.pipelines.clear();
+            }
+        }
+    }
+
+    /// Adds a strip in a single color (straight alpha).
+    pub fn add_strip(&mut self, points: &[Vec3], color: Vec4, style: &LineStyle) {
+        self.add_strip_with_colors(points, &vec![color; points.len()], style);
+    }
+
+    /// Adds a strip with a color per point, blended along each segment. Extra points or colors
+    /// are ignored; repeated points are skipped. A single point draws just its caps (a dot with
+    /// round or square caps).
+    pub fn add_strip_with_colors(&mut self, points: &[Vec3], colors: &[Vec4], style: &LineStyle) {
+        let mut vertices: Vec<(Vec3, Vec4)> = Vec::with_capacity(points.len());
+        for (&point, &color) in points.iter().zip(colors) {
+            if vertices.last().is_none_or(|&(last, _)| last != point) {
+                vertices.push((point, color));
+            }
+        }
+        if style.closed && vertices.len() > 2 && vertices[0].0 == vertices[vertices.len() - 1].0 {
+            vertices.pop();
+        }
+        let n = vertices.len();
+        if n == 0 {
+            return;
+        }
+        let closed = style.closed && n > 2;
+        let segment_count = match n {
+            1 => 1,
+            _ if closed => n,
+            _ => n - 1,
+        };
+
+        let join = match style.join {
+            LineJoin::Miter => 0,
+            LineJoin::Round => 1,
+            LineJoin::Bevel => 2,
+        };
+        let cap = match style.cap {
+            LineCap::Butt => 0,
+            LineCap::Square => 1,
+            LineCap::Round => 2,
+        };
+        let dash = style
+            .dash
+            .map_or([0.0; 2], |(on, off)| [on.max(0.0), off.max(0.0)]);
+        let point = |i: usize| vertices[i % n].0;
+
+        self.segments.reserve(segment_count);
+        let mut distance = 0.0;
+        for i in 0..segment_count {
+            let (a, color_a) = vertices[i];
+            let (b, color_b) = vertices[(i + 1) % n];
+            let has_prev = closed || i > 0;
+            let has_next = closed || i + 2 < n;
+            let length = a.distance(b);
+            self.segments.push(SegmentInstance {
+                prev: if has_prev { point(i + n - 1) } else { a }.to_array(),
+                a: a.to_array(),
+                b: b.to_array(),
+                next: if has_next { point(i + 2) } else { b }.to_array(),
+                color_a: color_a.to_array(),
+                color_b: color_b.to_array(),
+                distance: [distance, distance + length],
+                width_limit: [style.width, style.miter_limit],
+                dash,
+                flags: join
+                    | (cap << 2)
+                    | if has_prev { HAS_PREV } else { 0 }
+                    | if has_next { HAS_NEXT } else { 0 },
+            });
+            distance += length;
+        }
+        self.dirty = true;
+    }
+
+    /// Creates the GPU state (and a pipeline for `color_format` / `depth`) if
+    /// needed and uploads the strips if they changed. Call every frame before the
+    /// pass when using [DepthTest::Texture].
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color_format: wgpu::TextureFormat,
+        depth: DepthTest<'_>,
+    ) {
+        let pipeline_depth = self.pipeline_depth(depth);
+        let gpu = self
+            .gpu
+            .get_or_insert_with(|| PolylinesGpu::new(device, queue));
+        gpu.ensure_pipeline(device, color_format, pipeline_depth, self.on_top);
+        gpu.depth_bind_group = match depth {
+            DepthTest::Texture { view, .. } if !self.on_top => {
+                Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("polyline_depth"),
+                    layout: &gpu.depth_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    }],
+                }))
+            }
+            _ => None,
+        };
+        if self.dirty {
+            gpu.upload(device, &self.segments);
+            self.dirty = false;
+        }
+    }
+
+    /// Draws all strips for `view` (its view-projection and viewport). `color_format` and `depth`
+    /// must match a [Polylines::prepare] call; otherwise nothing is drawn.
+    pub fn draw(
+        &self,
+        pass: &mut RenderPass<'_>,
+        view: &ViewState,
+        color_format: wgpu::TextureFormat,
+        depth: DepthTest<'_>,
+    ) {
+        let (x, y, w, h) = view.viewport;
+        let viewport = [x as f32, y as f32, w as f32, h as f32];
+        self.draw_with(
+            pass,
+            view.eye,
+            view.view_projection,
+            viewport,
+            color_format,
+            depth,
+        );
+    }
+
+    /// [Polylines::draw] with an explicit matrix (e.g. jitter-free, or orthographic for
+    /// screen-space overlays) and viewport (x, y, width, height in pixels). `eye` picks
+    /// the uniform slot.
+    pub fn draw_with(
+        &self,
+        pass: &mut RenderPass<'_>,
+        eye: Eye,
+        view_projection: Mat4,
+        viewport: [f32; 4],
+        color_format: wgpu::TextureFormat,
+        depth: DepthTest<'_>,
+    ) {
+        let Some(gpu) = &self.gpu else {
+            return;
+        };
+        if gpu.segment_count == 0 {
+            return;
+        }
+        let pipeline_depth = self.pipeline_depth(depth);
+        let Some(pipeline) = gpu.pipeline(color_format, pipeline_depth) else {
+            return;
+        };
+        let slot = match eye {
+            Eye::Mono => 0,
+            Eye::Left => 1,
+            Eye::Right => 2,
+        };
+        // A flipped target: draw upside down, and map its rows back to the depth texture's.
+        let (view_projection, depth_y) = match depth {
+            DepthTest::Texture { flipped: true, .. } => (
+                Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0)) * view_projection,
+                [-1.0, 2.0 * viewport[1] + viewport[3]],
+            ),
+            _ => (view_projection, [1.0, 0.0]),
+        };
+        let offset = slot * UNIFORM_STRIDE;
+        let uniforms = PolylineUniforms {
+            view_projection: view_projection.to_cols_array(),
+            viewport,
+            depth_y,
+            _pad: [0.0; 2],
+        };
+        gpu.queue
+            .write_buffer(&gpu.uniform_buffer, offset, bytemuck::bytes_of(&uniforms));
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &gpu.bind_group, &[offset as u32]);
+        if pipeline_depth == PipelineDepth::Texture {
+            let Some(depth_bind_group) = &gpu.depth_bind_group else {
+                return;
+            };
+            pass.set_bind_group(1, depth_bind_group, &[]);
+        }
+        pass.set_vertex_buffer(0, gpu.instance_buffer.slice(..));
+        pass.draw(0..6, 0..gpu.segment_count);
+        stats::record_draw(gpu.segment_count);
+    }
+
+    /// On-top strips skip a shader depth test entirely; with an attachment they compare `Always`.
+    fn pipeline_depth(&self, depth: DepthTest<'_>) -> PipelineDepth {
+        match depth {
+            DepthTest::Texture { .. } if self.on_top => PipelineDepth::None,
+            _ => PipelineDepth::from(depth),
+        }
+    }
+}
+
+/// Draws shared [Polylines] over the G-buffer [COLOR], depth-tested against [DEPTH] (so TAA and
+/// post-processing apply to them). G-buffer path only.
+pub struct PolylinePass {
+    lines: Rc<RefCell<Polylines>>,
+}
+
+impl PolylinePass {
+    pub const fn new(lines: Rc<RefCell<Polylines>>) -> Self {
+        Self { lines }
+    }
+}
+
+impl GraphPass for PolylinePass {
+    fn inputs(&self) -> Vec<Slot> {
+        vec![COLOR, DEPTH]
+    }
+
+    fn outputs(&self) -> Vec<Slot> {
+        vec![COLOR]
+    }
+
+    fn record(&mut self, ctx: &mut PassContext<'_>) {
+        const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+        const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+        let (Some(color_view), Some(depth_view)) = (ctx.view(COLOR), ctx.view(DEPTH)) else {
+            return;
+        };
+        let mut lines = self.lines.borrow_mut();
+        lines.prepare(
+            ctx.device,
+            ctx.queue,
+            COLOR_FORMAT,
+            DepthTest::Attachment(DEPTH_FORMAT),
+        );
+        if lines.is_empty() {
+            return;
+        }
+        let mut pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("polylines"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            multiview_mask: None,
+            occlusion_query_set: None,
+        });
+        for view in ctx.views {
+            let (vx, vy, vw, vh) = view.viewport;
+            pass.set_viewport(vx as f32, vy as f32, vw as f32, vh as f32, 0.0, 1.0);
+            lines.draw(
+                &mut pass,
+                view,
+                COLOR_FORMAT,
+                DepthTest::Attachment(DEPTH_FORMAT),
+            );
+        }
+    }
+}
+
+impl PolylinesGpu {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("polyline"),
+            source: wgpu::ShaderSource::Wgsl(POLYLINE_WGSL.into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("polyline_bind_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<PolylineUniforms>() as u64,
+                    ),
+                },
+                count: None,
+            }],
+        });
+        let depth_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("polyline_depth_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("polyline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            immediate_size: 0,
+        });
+        let depth_texture_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("polyline_depth_texture_layout"),
+                bind_group_layouts: &[&bind_group_layout, &depth_layout],
+                immediate_size: 0,
+            });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("polyline_uniforms"),
+            size: UNIFORM_SLOTS * UNIFORM_STRIDE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("polyline_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &uniform_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<PolylineUniforms>() as u64),
+                }),
+            }],
+        });
+        Self {
+            queue: queue.clone(),
+            shader,
+            pipeline_layout,
+            depth_texture_pipeline_layout,
+            depth_layout,
+            pipelines: Vec::new(),
+            uniform_buffer,
+            bind_group,
+            depth_bind_group: None,
+            instance_buffer: create_instance_buffer(device, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            segment_count: 0,
+        }
+    }
+
+    fn pipeline(
+        &self,
+        color_format: wgpu::TextureFormat,
+        depth: PipelineDepth,
+    ) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines
+            .iter()
+            .find(|(color, d, _)| *color == color_format && *d == depth)
+            .map(|(_, _, pipeline)| pipeline)
+    }
+
+    /// `Attachment` pipelines of an on-top [Polylines] compare `Always`.
+    fn ensure_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        depth: PipelineDepth,
+        on_top: bool,
+    ) {
+        if self.pipeline(color_format, depth).is_some() {
+            return;
+        }
+        let attributes = wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32x3,
+            2 => Float32x3,
+            3 => Float32x3,
+            4 => Float32x4,
+            5 => Float32x4,
+            6 => Float32x2,
+            7 => Float32x2,
+            8 => Float32x2,
+            9 => Uint32,
+        ];
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("polyline"),
+            layout: Some(if depth == PipelineDepth::Texture {
+                &self.depth_texture_pipeline_layout
+            } else {
+                &self.pipeline_layout
+            }),
+            vertex: wgpu::VertexState {
+                module: &self.shader,
+                entry_point: Some("vs"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: SEGMENT_STRIDE,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &attributes,
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.shader,
+                entry_point: Some(if depth == PipelineDepth::Texture {
+                    "fs_depth_texture"
+                } else {
+                    "fs"
+                }),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: match depth {
+                PipelineDepth::Attachment(format) => Some(wgpu::DepthStencilState {
+                    format,
+                    depth_write_enabled: false,
+                    depth_compare: if on_top {
+                        wgpu::CompareFunction::Always
+                    } else {
+                        wgpu::CompareFunction::GreaterEqual
+                    },
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                _ => None,
+            },
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview_mask: None,
+        });
+        self.pipelines.push((color_format, depth, pipeline));
+    }
+
+    /// Copies `segments` into the instance buffer, doubling it until they fit.
+    fn upload(&mut self, device: &wgpu::Device, segments: &[SegmentInstance]) {
+        if segments.len() > self.capacity {
+            self.capacity = segments.len().next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, self.capacity);
+        }
+        if !segments.is_empty() {
+            self.queue
+                .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(segments));
+        }
+        self.segment_count = segments.len() as u32;
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("polyline_segments"),
+        size: capacity as u64 * SEGMENT_STRIDE,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,1383 @@
+//! Render graph: passes declare named texture slots they read and write; the graph orders them by
+//! those dependencies, culls passes nothing consumes, and allocates the transient targets.
+//!
+//! Built-in passes (registered by [RenderGraph::new], in this order):
+//! - `gbuffer`: warehouse + scene into [COLOR] / [VELOCITY] / [DEPTH] (mono only).
+//! - `taa`: [COLOR] + [VELOCITY] + [HISTORY_PREV] -> [RESOLVE] + [HISTORY].
+//! - `bloom`: [RESOLVE] -> [BLOOM_MIPS] (Kawase chain + lens).
+//! - `exposure`: luminance histogram of [RESOLVE], adapting the `screen` pass's exposure (side
+//!   effect only; with auto-exposure on).
+//! - `screen`: [RESOLVE] + `BLOOM_MIPS[0]` -> [TARGET] (tonemap, sRGB). During a scene transition
+//!   it also blends in the outgoing scene, rendered alongside into its own targets (see
+//!   [RenderGraph::start_transition]).
+//! - `present`: [RESOLVE] -> [TARGET] (plain copy).
+//! - `forward`: warehouse + scene straight into [TARGET], one viewport per view (stereo).
+//! - `debug`: [crate::debug_draw] primitives over [RESOLVE], depth-tested against [DEPTH]
+//!   (`debug-draw` feature only). Scene passes are inserted before it, so it draws last.
+//! - `text`: [crate::text] over [TARGET], world labels depth-tested against [DEPTH] when produced
+//!   (else the forward depth buffer).
+//! - `ui`: the [crate::ui] overlay over [TARGET], last.
+//!
+//! Rules:
+//! - A pass that writes a slot without reading it *produces* it. If several enabled passes produce
+//!   the same slot, the first one registered wins and the others are culled; that is how `present`
+//!   and `forward` act as fallbacks for `screen`.
+//! - A pass that reads *and* writes a slot *modifies* it (e.g. an overlay drawn on [TARGET]): it
+//!   runs after the producer, modifiers run in registration order, and readers see the result.
+//! - A pass whose inputs have no producer is culled. [RESOLVE] falls back to [COLOR] when
+//!   TAA is off.
+//! - Passes that don't feed [TARGET] are culled, except passes without outputs (side effects only).
+//! - Optional inputs ([GraphPass::optional_inputs]) order a pass after their writers when something
+//!   produces them, and are otherwise ignored; they never cull the pass.
+//! - [PassContext::view] only returns slots produced this frame (plus [HISTORY_PREV] and [TARGET]).
+//!
+//! Scenes add passes through [crate::scene::Scene::render_passes], usually inside a pass group
+//! ([RenderGraph::set_group]) so they only run while their scene is current; the toggles in
+//! [RenderSettings] (on [App::render_settings]) enable or disable the built-ins at runtime.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::app::{App, AppInstance};
+#[cfg(feature = "debug-draw")]
+use crate::debug_draw;
+use crate::gpu::{GbufferSet, GpuContext, ScreenTransition, BLOOM_MIP_COUNT};
+use crate::polyline::DepthTest;
+use crate::render_settings::RenderSettings;
+use crate::text;
+use crate::transition::Transition;
+use crate::ui;
+use crate::view::ViewState;
+
+/// Name of a texture slot in the graph.
+pub type Slot = &'static str;
+
+/// HDR scene color (`Rgba16Float`), written by the G-buffer pass.
+pub const COLOR: Slot = "color";
+/// Screen-space motion vectors (`Rg16Float`), written by the G-buffer pass.
+pub const VELOCITY: Slot = "velocity";
+/// Reversed-Z depth (`Depth32Float`), written by the G-buffer pass.
+pub const DEPTH: Slot = "depth";
+/// TAA history from the previous frame. Persistent: always available, never produced.
+pub const HISTORY_PREV: Slot = "history_prev";
+/// TAA history written this frame (read as [HISTORY_PREV] next frame).
+pub const HISTORY: Slot = "history";
+/// TAA resolve (HDR).
+pub const RESOLVE: Slot = "resolve";
+/// Bloom chain: half-res .. 1/32-res levels, then the lens flare output.
+pub const BLOOM_MIPS: [Slot; BLOOM_MIP_COUNT] = [
+    "bloom_mip_0",
+    "bloom_mip_1",
+    "bloom_mip_2",
+    "bloom_mip_3",
+    "bloom_mip_4",
+    "bloom_lens",
+];
+/// Final output (swap chain or offscreen texture in [GpuContext::surface_format]).
+pub const TARGET: Slot = "target";
+
+/// A pass added by a scene. Pipelines can be created lazily from [PassContext::device]
+/// on first use.
+pub trait GraphPass {
+    /// Slots sampled (or loaded) by this pass.
+    fn inputs(&self) -> Vec<Slot>;
+    /// Slots read when available (e.g. [DEPTH], which only the G-buffer path produces).
+    fn optional_inputs(&self) -> Vec<Slot> {
+        Vec::new()
+    }
+    /// Slots written by this pass. Empty = side effects only (never culled).
+    fn outputs(&self) -> Vec<Slot>;
+    fn record(&mut self, ctx: &mut PassContext<'_>);
+}
+
+/// Everything a [GraphPass] needs to record its commands.
+pub struct PassContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    pub views: &'a [ViewState],
+    pub time_s: f32,
+    /// Render size ([App::render_size]): the transient targets' size, which the target may exceed.
+    pub width: u32,
+    pub height: u32,
+    resources: &'a Resources<'a>,
+}
+
+impl PassContext<'_> {
+    /// View of `slot` (after aliasing), or `None` if the slot is unknown or not
+    /// produced this frame.
+    pub fn view(&self, slot: Slot) -> Option<wgpu::TextureView> {
+        self.resources.view(slot)
+    }
+
+    /// Format of the final target.
+    pub const fn target_format(&self) -> wgpu::TextureFormat {
+        self.resources.target_format
+    }
+}
+
+/// Size and format of a scene-declared transient texture.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureDesc {
+    pub format: wgpu::TextureFormat,
+    /// Size is the render size `>> shift` (0 = full res, 1 = half res, ...).
+    pub shift: u32,
+}
+
+/// Transient targets: the built-in [GbufferSet] plus scene-declared textures, reallocated when the
+/// render size changes (window resize or dynamic resolution step).
+struct TexturePool {
+    gbuffer: Option<GbufferSet>,
+    declared: HashMap<Slot, (TextureDesc, Option<wgpu::Texture>)>,
+    size: (u32, u32),
+    /// Which history texture is [HISTORY_PREV]; flips after every TAA pass.
+    history_index: usize,
+    /// Cleared by a scene switch: the next TAA pass reads the current color instead of another
+    /// scene's history.
+    history_valid: bool,
+}
+
+impl TexturePool {
+    fn ensure(
+        &mut self,
+        gpu: &GpuContext,
+        encoder: &mut wgpu::CommandEncoder,
+        width: u32,
+        height: u32,
+        need_gbuffer: bool,
+    ) {
+        let device = &gpu.device;
+        let mut previous = None;
+        if self.size != (width, height) {
+            self.size = (width, height);
+            previous = self.gbuffer.take();
+            for (_, texture) in self.declared.values_mut() {
+                *texture = None;
+            }
+        }
+        if need_gbuffer && self.gbuffer.is_none() {
+            self.gbuffer = Some(resize_gbuffer(
+                gpu,
+                encoder,
+                previous.as_ref(),
+                self.history_index,
+                width,
+                height,
+            ));
+        }
+        for (slot, (desc, texture)) in &mut self.declared {
+            if texture.is_none() {
+                *texture = Some(device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some(slot),
+                    size: wgpu::Extent3d {
+                        width: (width >> desc.shift).max(1),
+                        height: (height >> desc.shift).max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: desc.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                }));
+            }
+        }
+    }
+
+    fn view(&self, slot: Slot) -> Option<wgpu::TextureView> {
+        if let Some((_, texture)) = self.declared.get(slot) {
+            return texture
+                .as_ref()
+                .map(|t| t.create_view(&wgpu::TextureViewDescriptor::default()));
+        }
+        gbuffer_view(self.gbuffer.as_ref()?, self.history_index, slot)
+    }
+}
+
+/// New targets for `width` x `height`, keeping `previous`'s TAA history (velocity is in NDC, so the
+/// resampled history reprojects the same way at the new size).
+fn resize_gbuffer(
+    gpu: &GpuContext,
+    encoder: &mut wgpu::CommandEncoder,
+    previous: Option<&GbufferSet>,
+    history_index: usize,
+    width: u32,
+    height: u32,
+) -> GbufferSet {
+    let gbuffer = GbufferSet::new(&gpu.device, width, height);
+    if let Some(previous) = previous {
+        gpu.run_resample_pass(
+            encoder,
+            &previous.history_view(history_index),
+            &gbuffer.history_view(history_index),
+            width,
+            height,
+        );
+    }
+    gbuffer
+}
+
+/// View of a built-in G-buffer slot in `gbuffer`, whose [HISTORY_PREV] is `history_index`.
+fn gbuffer_view(
+    gbuffer: &GbufferSet,
+    history_index: usize,
+    slot: Slot,
+) -> Option<wgpu::TextureView> {
+    match slot {
+        COLOR => Some(gbuffer.color_view()),
+        VELOCITY => Some(gbuffer.velocity_view()),
+        DEPTH => Some(gbuffer.depth_view()),
+        RESOLVE => Some(gbuffer.resolve_view()),
+        HISTORY_PREV => Some(gbuffer.history_view(history_index)),
+        HISTORY => Some(gbuffer.history_view(1 - history_index)),
+        _ => BLOOM_MIPS
+            .iter()
+            .position(|&mip| mip == slot)
+            .map(|i| gbuffer.bloom_mip_view(i)),
+    }
+}
+
+/// Slot lookup for one frame: aliases resolved at compile time, plus the frame's target view.
+struct Resources<'a> {
+    pool: &'a TexturePool,
+    aliases: &'a HashMap<Slot, Slot>,
+    produced: &'a HashSet<Slot>,
+    target: &'a wgpu::TextureView,
+    target_format: wgpu::TextureFormat,
+}
+
+impl Resources<'_> {
+    fn view(&self, slot: Slot) -> Option<wgpu::TextureView> {
+        let slot = self.aliases.get(slot).copied().unwrap_or(slot);
+        if slot == TARGET {
+            Some(self.target.clone())
+        } else if slot == HISTORY_PREV || self.produced.contains(slot) {
+            self.pool.view(slot)
+        } else {
+            None
+        }
+    }
+}
+
+enum PassKind {
+    Gbuffer,
+    Taa,
+    Bloom,
+    Exposure,
+    Screen,
+    Present,
+    Forward,
+    #[cfg(feature = "debug-draw")]
+    Debug,
+    Text,
+    Ui,
+    Custom(Box<dyn GraphPass>),
+}
+
+struct Node {
+    name: &'static str,
+    inputs: Vec<Slot>,
+    optional_inputs: Vec<Slot>,
+    outputs: Vec<Slot>,
+    enabled: bool,
+    /// Pass group ([RenderGraph::set_group]); the node only runs while its group is active.
+    group: Option<&'static str>,
+    kind: PassKind,
+}
+
+impl Node {
+    fn builtin(name: &'static str, inputs: &[Slot], outputs: &[Slot], kind: PassKind) -> Self {
+        Self {
+            name,
+            inputs: inputs.to_vec(),
+            optional_inputs: Vec::new(),
+            outputs: outputs.to_vec(),
+            enabled: true,
+            group: None,
+            kind,
+        }
+    }
+
+    fn with_optional_inputs(mut self, slots: &[Slot]) -> Self {
+        self.optional_inputs = slots.to_vec();
+        self
+    }
+
+    fn modifies(&self, slot: Slot) -> bool {
+        self.inputs.contains(&slot) && self.outputs.contains(&slot)
+    }
+}
+
+/// The outgoing scene's own targets during a [transition](RenderGraph::start_transition).
+struct OutgoingTargets {
+    gbuffer: GbufferSet,
+    /// As [TexturePool::history_index] and [TexturePool::history_valid], for `gbuffer`.
+    history_index: usize,
+    history_valid: bool,
+}
+
+struct ActiveTransition {
+    transition: Transition,
+    /// Set by the first frame recorded after [RenderGraph::start_transition].
+    start_ms: Option<f64>,
+    /// The pool's targets when the transition started (so the outgoing scene keeps its TAA
+    /// history), or new ones on the first frame if there were none.
+    outgoing: Option<OutgoingTargets>,
+}
+
+impl Default for RenderGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The frame's pass list, compiled order, and transient targets. See the module docs for the rules.
+pub struct RenderGraph {
+    nodes: Vec<Node>,
+    /// Fallbacks for slots without a producer (e.g. [RESOLVE] -> [COLOR] without TAA).
+    fallbacks: Vec<(Slot, Slot)>,
+    pool: TexturePool,
+    /// Compiled: live node indices in execution order.
+    order: Vec<usize>,
+    /// Compiled: slot -> slot actually read (fallbacks applied).
+    aliases: HashMap<Slot, Slot>,
+    /// Compiled: slots written by live passes.
+    produced: HashSet<Slot>,
+    need_gbuffer: bool,
+    dirty: bool,
+    applied: Option<(RenderSettings, bool)>,
+    /// Group given to passes added from now on.
+    group: Option<&'static str>,
+    inactive_groups: HashSet<&'static str>,
+    transition: Option<ActiveTransition>,
+}
+
+impl RenderGraph {
+    /// Graph with the built-in passes registered (see module docs).
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![
+                Node::builtin("gbuffer", &[], &[COLOR, VELOCITY, DEPTH], PassKind::Gbuffer),
+                Node::builtin(
+                    "taa",
+                    &[COLOR, VELOCITY, HISTORY_PREV],
+                    &[RESOLVE, HISTORY],
+                    PassKind::Taa,
+                ),
+                Node::builtin("bloom", &[RESOLVE], &BLOOM_MIPS, PassKind::Bloom),
+                Node::builtin("exposure", &[RESOLVE], &[], PassKind::Exposure),
+                Node::builtin(
+                    "screen",
+                    &[RESOLVE, BLOOM_MIPS[0]],
+                    &[TARGET],
+                    PassKind::Screen,
+                ),
+                Node::builtin("present", &[RESOLVE], &[TARGET], PassKind::Present),
+                Node::builtin("forward", &[], &[TARGET], PassKind::Forward),
+                #[cfg(feature = "debug-draw")]
+                Node::builtin("debug", &[RESOLVE, DEPTH], &[RESOLVE], PassKind::Debug),
+                Node::builtin("text", &[TARGET], &[TARGET], PassKind::Text)
+                    .with_optional_inputs(&[DEPTH]),
+                Node::builtin("ui", &[TARGET], &[TARGET], PassKind::Ui),
+            ],
+            fallbacks: vec![(RESOLVE, COLOR)],
+            pool: TexturePool {
+                gbuffer: None,
+                declared: HashMap::new(),
+                size: (0, 0),
+                history_index: 0,
+                history_valid: false,
+            },
+            order: Vec::new(),
+            aliases: HashMap::new(),
+            produced: HashSet::new(),
+            need_gbuffer: false,
+            dirty: true,
+            applied: None,
+            group: None,
+            inactive_groups: HashSet::new(),
+            transition: None,
+        }
+    }
+
+    /// Registers a scene pass. Its position among the built-ins follows from its slots.
+    pub fn add_pass(&mut self, name: &'static str, pass: Box<dyn GraphPass>) {
+        // Before the built-in overlays (`debug`, `text`, `ui`), so they draw over
+        // scene overlays too.
+        let index = self
+            .nodes
+            .iter()
+            .position(|n| matches!(n.name, "debug" | "text" | "ui"))
+            .unwrap_or(self.nodes.len());
+        self.nodes.insert(
+            index,
+            Node {
+                name,
+                inputs: pass.inputs(),
+                optional_inputs: pass.optional_inputs(),
+                outputs: pass.outputs(),
+                enabled: true,
+                group: self.group,
+                kind: PassKind::Custom(pass),
+            },
+        );
+        self.dirty = true;
+    }
+
+    /// Declares a transient texture a scene pass can write and other passes can read.
+    pub fn declare_texture(&mut self, slot: Slot, desc: TextureDesc) {
+        self.pool.declared.insert(slot, (desc, None));
+        self.dirty = true;
+    }
+
+    /// Makes pass `name` read `to` instead of `from` (e.g. point `screen` at a custom post effect).
+    pub fn rewire(&mut self, name: &str, from: Slot, to: Slot) {
+        for node in self.nodes.iter_mut().filter(|n| n.name == name) {
+            for input in &mut node.inputs {
+                if *input == from {
+                    *input = to;
+                }
+            }
+        }
+        self.dirty = true;
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        for node in self
+            .nodes
+            .iter_mut()
+            .filter(|n| n.name == name && n.enabled != enabled)
+        {
+            node.enabled = enabled;
+            self.dirty = true;
+        }
+    }
+
+    /// Puts the passes added from now on in `group` (`None`: no group, always allowed to run).
+    /// [crate::demo::Demo] groups each scene's passes under the scene's name.
+    pub const fn set_group(&mut self, group: Option<&'static str>) {
+        self.group = group;
+    }
+
+    /// Lets the passes in `group` run (still subject to [RenderGraph::set_enabled]) or stops them.
+    pub fn set_group_active(&mut self, group: &'static str, active: bool) {
+        let changed = if active {
+            self.inactive_groups.remove(group)
+        } else {
+            self.inactive_groups.insert(group)
+        };
+        self.dirty |= changed;
+    }
+
+    /// Call when switching scenes: restarts TAA history, and blends to the incoming scene over
+    /// `transition.duration_ms`, timed from the next [RenderGraph::execute]. Until then the
+    /// outgoing scene ([AppInstance::outgoing_descriptor]) keeps rendering into its own
+    /// G-buffer, TAA history and bloom targets, which `screen` composites with the incoming
+    /// ones. Needs `screen` and a mono view: without them (post off, stereo, forward) the
+    /// switch cuts, with a log.
+    pub fn start_transition(&mut self, transition: Transition) {
+        let pool = &mut self.pool;
+        self.transition = transition.is_blended().then(|| ActiveTransition {
+            transition,
+            start_ms: None,
+            outgoing: pool.gbuffer.take().map(|gbuffer| OutgoingTargets {
+                gbuffer,
+                history_index: pool.history_index,
+                history_valid: pool.history_valid,
+            }),
+        });
+        pool.history_valid = false;
+    }
+
+    /// Enables built-ins from `settings`. `stereo` (more than one view) rules out
+    /// the G-buffer path.
+    pub fn apply_settings(&mut self, settings: &RenderSettings, stereo: bool) {
+        if self.applied == Some((*settings, stereo)) {
+            return;
+        }
+        self.applied = Some((*settings, stereo));
+        let gbuffer = settings.gbuffer && !stereo;
+        self.set_enabled("gbuffer", gbuffer);
+        self.set_enabled("taa", settings.taa);
+        self.set_enabled("bloom", settings.post);
+        self.set_enabled("exposure", settings.eye_adaptation().is_some());
+        self.set_enabled("screen", settings.post);
+        self.set_enabled("present", true);
+        self.set_enabled("forward", !gbuffer);
+        self.set_enabled("ui", settings.ui);
+    }
+
+    /// Names of the passes that will run, in order (compiles if needed).
+    pub fn pass_order(&mut self) -> Vec<&'static str> {
+        self.compile();
+        self.order.iter().map(|&i| self.nodes[i].name).collect()
+    }
+
+    fn compile(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+
+        let n = self.nodes.len();
+        let inactive = &self.inactive_groups;
+        let mut active: Vec<bool> = self
+            .nodes
+            .iter()
+            .map(|node| node.enabled && node.group.is_none_or(|group| !inactive.contains(group)))
+            .collect();
+        let mut producers: HashMap<Slot, usize>;
+        let mut aliases: HashMap<Slot, Slot>;
+
+        // Drop passes with unsatisfied inputs until stable; dropping a producer can
+        // promote a fallback.
+        loop {
+            producers = HashMap::new();
+            for (i, node) in self.nodes.iter().enumerate().filter(|(i, _)| active[*i]) {
+                for &slot in node.outputs.iter().filter(|&&s| !node.modifies(s)) {
+                    producers.entry(slot).or_insert(i);
+                }
+            }
+            aliases = HashMap::new();
+            for &(slot, fallback) in &self.fallbacks {
+                if !producers.contains_key(slot) && producers.contains_key(fallback) {
+                    aliases.insert(slot, fallback);
+                }
+            }
+            let available = |slot: Slot| {
+                let slot = aliases.get(slot).copied().unwrap_or(slot);
+                slot == HISTORY_PREV || producers.contains_key(slot)
+            };
+            let mut changed = false;
+            for (i, node) in self.nodes.iter().enumerate() {
+                if active[i] && !node.inputs.iter().all(|&s| available(s)) {
+                    active[i] = false;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        // Producers that lost to an earlier one for the same slot don't run.
+        for (i, node) in self.nodes.iter().enumerate() {
+            if node
+                .outputs
+                .iter()
+                .any(|&s| !node.modifies(s) && producers.get(s).is_some_and(|&p| p != i))
+            {
+                active[i] = false;
+            }
+        }
+
+        // Dependencies: readers after the last modifier of a slot, modifiers chained after the
+        // producer. Optional inputs only order (`after`); they don't keep their writers
+        // alive. A modifier of an aliased slot modifies its fallback (e.g. `debug` on
+        // [RESOLVE] = [COLOR] without TAA).
+        let resolve = |slot: Slot| aliases.get(slot).copied().unwrap_or(slot);
+        let modifies = |node: &Node, slot: Slot| {
+            node.inputs.iter().any(|&s| resolve(s) == slot)
+                && node.outputs.iter().any(|&s| resolve(s) == slot)
+        };
+        let mut deps: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut after: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for (i, node) in self.nodes.iter().enumerate().filter(|(i, _)| active[*i]) {
+            let required = node.inputs.iter().map(|s| (s, true));
+            for (&input, is_required) in
+                required.chain(node.optional_inputs.iter().map(|s| (s, false)))
+            {
+                let slot = resolve(input);
+                let Some(&producer) = producers.get(slot) else {
+                    continue;
+                };
+                let mut writer = producer;
+                for (j, other) in self.nodes.iter().enumerate().filter(|(j, _)| active[*j]) {
+                    if modifies(other, slot) && j != i && (!modifies(node, slot) || j < i) {
+                        writer = j;
+                    }
+                }
+                if is_required {
+                    deps[i].push(writer);
+                }
+                after[i].push(writer);
+            }
+        }
+
+        // Cull: keep what feeds the target, plus side-effect-only passes.
+        let mut live = vec![false; n];
+        let mut stack: Vec<usize> = (0..n)
+            .filter(|&i| {
+                active[i]
+                    && (self.nodes[i].outputs.is_empty() || self.nodes[i].outputs.contains(&TARGET))
+            })
+            .collect();
+        while let Some(i) = stack.pop() {
+            if !live[i] {
+                live[i] = true;
+                stack.extend(deps[i].iter().copied());
+            }
+        }
+
+        // Topological order, ties broken by registration order.
+        let mut order = Vec::new();
+        let mut done = vec![false; n];
+        while let Some(i) =
+            (0..n).find(|&i| live[i] && !done[i] && after[i].iter().all(|&d| done[d] || !live[d]))
+        {
+            done[i] = true;
+            order.push(i);
+        }
+        for i in (0..n).filter(|&i| live[i] && !done[i]) {
+            log_error!(
+                "[RenderGraph] Dependency cycle; dropping pass '{}'.",
+                self.nodes[i].name
+            );
+        }
+
+        self.need_gbuffer = order.iter().any(|&i| {
+            let node = &self.nodes[i];
+            node.inputs
+                .iter()
+                .chain(&node.outputs)
+                .any(|&s| s != TARGET && !self.pool.declared.contains_key(resolve(s)))
+        });
+        self.produced = order
+            .iter()
+            .flat_map(|&i| self.nodes[i].outputs.iter().copied())
+            .collect();
+        self.order = order;
+        self.aliases = aliases;
+        log!(
+            "[RenderGraph] {}",
+            self.order
+                .iter()
+                .map(|&i| self.nodes[i].name)
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        );
+    }
+
+    /// Records the frame for `views` into `encoder`, ending in `target_view`. Each pass is a GPU
+    /// timing span named after it ([GpuContext::mark_pass]).
+    pub fn execute(
+        &mut self,
+        gpu: &GpuContext,
+        app: &mut App,
+        app_instance: &mut dyn AppInstance,
+        views: &[ViewState],
+        encoder: &mut wgpu::CommandEncoder,
+        target_view: &wgpu::TextureView,
+    ) {
+        self.apply_settings(&app.render_settings, views.len() != 1);
+        self.compile();
+        let (width, height) = app.render_size();
+        self.pool
+            .ensure(gpu, encoder, width, height, self.need_gbuffer);
+        let time_s = (app.current_timestamp / 1000.0) as f32;
+        let transition = self.update_transition(gpu, app, app_instance, time_s);
+
+        let mut flip_history = false;
+        let Self {
+            nodes,
+            pool,
+            order,
+            aliases,
+            produced,
+            ..
+        } = self;
+        let resources = Resources {
+            pool: &*pool,
+            aliases,
+            produced,
+            target: target_view,
+            target_format: gpu.surface_format,
+        };
+        let view = |slot: Slot| {
+            resources
+                .view(slot)
+                .expect("render graph slot not allocated")
+        };
+
+        for &i in order.iter() {
+            let node = &mut nodes[i];
+            gpu.mark_pass(encoder, node.name);
+            match &mut node.kind {
+                PassKind::Gbuffer => {
+                    let targets = [
+                        view(node.outputs[0]),
+                        view(node.outputs[1]),
+                        view(node.outputs[2]),
+                    ];
+                    let view_state = &views[0];
+                    record_gbuffer(
+                        encoder,
+                        &targets,
+                        gpu,
+                        app,
+                        view_state,
+                        time_s,
+                        |app, pass| {
+                            app_instance.frame(app, view_state, Some(pass), true);
+                        },
+                    );
+                }
+                PassKind::Taa => {
+                    let history = if resources.pool.history_valid {
+                        node.inputs[2]
+                    } else {
+                        node.inputs[0]
+                    };
+                    gpu.run_taa_pass(
+                        encoder,
+                        &view(node.inputs[0]),
+                        &view(node.inputs[1]),
+                        &view(history),
+                        &view(node.outputs[0]),
+                        &view(node.outputs[1]),
+                    );
+                    flip_history = true;
+                }
+                PassKind::Bloom => {
+                    if let Some(gbuffer) = resources.pool.gbuffer.as_ref() {
+                        let levels = app.render_settings.bloom_mips as usize;
+                        gpu.run_bloom_passes(encoder, &view(node.inputs[0]), gbuffer, levels);
+                    }
+                }
+                PassKind::Exposure => {
+                    gpu.run_exposure_pass(encoder, &view(node.inputs[0]), width, height);
+                }
+                PassKind::Screen => {
+                    let cam_dir = views[0].direction();
+                    gpu.run_screen_pass(
+                        encoder,
+                        &view(node.inputs[0]),
+                        &view(node.inputs[1]),
+                        target_view,
+                        [cam_dir.x, cam_dir.y, cam_dir.z],
+                        transition.as_ref(),
+                    );
+                }
+                PassKind::Present => {
+                    gpu.run_present_pass(encoder, &view(node.inputs[0]), target_view);
+                }
+                PassKind::Forward => {
+                    record_forward(encoder, target_view, gpu, app, app_instance, views, time_s);
+                }
+                #[cfg(feature = "debug-draw")]
+                PassKind::Debug => {
+                    // The TAA resolve is stored upside down (fullscreen passes don't flip UVs).
+                    let flipped = !resources.aliases.contains_key(node.inputs[0]);
+                    record_debug(
+                        encoder,
+                        gpu,
+                        &view(node.inputs[0]),
+                        &view(node.inputs[1]),
+                        flipped,
+                        &views[0],
+                    );
+                }
+                PassKind::Text => {
+                    record_text(
+                        encoder,
+                        gpu,
+                        app,
+                        &view(node.inputs[0]),
+                        resources.view(DEPTH).as_ref(),
+                        views,
+                    );
+                }
+                PassKind::Ui => record_ui(encoder, gpu, app, &view(node.inputs[0])),
+                PassKind::Custom(pass) => {
+                    let mut ctx = PassContext {
+                        device: &gpu.device,
+                        queue: &gpu.queue,
+                        encoder,
+                        views,
+                        time_s,
+                        width,
+                        height,
+                        resources: &resources,
+                    };
+                    pass.record(&mut ctx);
+                }
+            }
+        }
+
+        if flip_history {
+            pool.history_index = 1 - pool.history_index;
+            pool.history_valid = true;
+        }
+    }
+
+    /// Renders the outgoing scene of a running transition into its targets and returns
+    /// what `screen` blends this frame; ends the transition once it is over, or (logged)
+    /// when it can't blend.
+    ///
+    /// The outgoing passes go in their own submission, ahead of the frame's: scenes and the
+    /// warehouse write their per-frame buffers with `queue.write_buffer`, which would
+    /// otherwise leave both scenes drawing with the last values written. They are not part of
+    /// the GPU timings.
+    fn update_transition(
+        &mut self,
+        gpu: &GpuContext,
+        app: &mut App,
+        app_instance: &mut dyn AppInstance,
+        time_s: f32,
+    ) -> Option<ScreenTransition> {
+        let Self {
+            nodes,
+            pool,
+            order,
+            aliases,
+            transition,
+            ..
+        } = self;
+        let active = transition.as_mut()?;
+        let now_ms = app.current_timestamp;
+        let elapsed_ms = now_ms - *active.start_ms.get_or_insert(now_ms);
+        let live = |kind: fn(&PassKind) -> bool| {
+            order
+                .iter()
+                .map(|&i| &nodes[i])
+                .find(|node| kind(&node.kind))
+        };
+        let screen = live(|kind| matches!(kind, PassKind::Screen));
+        let (Some(screen), Some(view_state)) = (screen, app.outgoing_view.clone()) else {
+            log!("[RenderGraph] Scene transition needs the screen pass and a mono view; cutting.");
+            *transition = None;
+            app_instance.end_transition(app);
+            return None;
+        };
+        if elapsed_ms >= active.transition.duration_ms {
+            *transition = None;
+            app_instance.end_transition(app);
+            return None;
+        }
+
+        let (width, height) = pool.size;
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("transition"),
+            });
+        let outgoing = active.outgoing.get_or_insert_with(|| OutgoingTargets {
+            gbuffer: GbufferSet::new(&gpu.device, width, height),
+            history_index: 0,
+            history_valid: false,
+        });
+        if (outgoing.gbuffer.width, outgoing.gbuffer.height) != (width, height) {
+            let previous = Some(&outgoing.gbuffer);
+            outgoing.gbuffer = resize_gbuffer(
+                gpu,
+                &mut encoder,
+                previous,
+                outgoing.history_index,
+                width,
+                height,
+            );
+        }
+        let gbuffer = &outgoing.gbuffer;
+        let targets = [
+            gbuffer.color_view(),
+            gbuffer.velocity_view(),
+            gbuffer.depth_view(),
+        ];
+        record_gbuffer(
+            &mut encoder,
+            &targets,
+            gpu,
+            app,
+            &view_state,
+            time_s,
+            |app, pass| {
+                app_instance.frame_outgoing(app, &view_state, pass);
+            },
+        );
+        if live(|kind| matches!(kind, PassKind::Taa)).is_some() {
+            let index = outgoing.history_index;
+            let history = if outgoing.history_valid {
+                gbuffer.history_view(index)
+            } else {
+                gbuffer.color_view()
+            };
+            gpu.run_taa_pass(
+                &mut encoder,
+                &targets[0],
+                &targets[1],
+                &history,
+                &gbuffer.resolve_view(),
+                &gbuffer.history_view(1 - index),
+            );
+            outgoing.history_index = 1 - index;
+            outgoing.history_valid = true;
+        }
+        // Slots as `screen` reads them this frame; scene-declared ones fall back to
+        // the built-in inputs.
+        let view = |slot: Slot, builtin: Slot| {
+            let read = |slot: Slot| {
+                let slot = aliases.get(slot).copied().unwrap_or(slot);
+                gbuffer_view(gbuffer, outgoing.history_index, slot)
+            };
+            read(slot).or_else(|| read(builtin))
+        };
+        if let Some(bloom) = live(|kind| matches!(kind, PassKind::Bloom)) {
+            let levels = app.render_settings.bloom_mips as usize;
+            gpu.run_bloom_passes(
+                &mut encoder,
+                &view(bloom.inputs[0], RESOLVE)?,
+                gbuffer,
+                levels,
+            );
+        }
+        let resolve_view = view(screen.inputs[0], RESOLVE)?;
+        let bloom_view = view(screen.inputs[1], BLOOM_MIPS[0])?;
+        gpu.queue.submit(Some(encoder.finish()));
+        Some(ScreenTransition {
+            resolve_view,
+            bloom_view,
+            kind: active.transition.kind,
+            progress: (elapsed_ms / active.transition.duration_ms).clamp(0.0, 1.0) as f32,
+        })
+    }
+}
+
+/// Mono G-buffer pass: `targets` are color, velocity, depth. Warehouse raymarch, then scene draws.
+fn record_gbuffer(
+    encoder: &mut wgpu::CommandEncoder,
+    targets: &[wgpu::TextureView; 3],
+    gpu: &GpuContext,
+    app: &mut App,
+    view_state: &ViewState,
+    time_s: f32,
+    draw_scene: impl FnOnce(&mut App, &mut wgpu::RenderPass<'_>),
+) {
+    let [color_view, velocity_view, depth_view] = targets;
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("gbuffer"),
+        color_attachments: &[
+            Some(wgpu::RenderPassColorAttachment {
+                view: color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            }),
+            Some(wgpu::RenderPassColorAttachment {
+                view: velocity_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            }),
+        ],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(0.0),
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        timestamp_writes: None,
+        multiview_mask: None,
+        occlusion_query_set: None,
+    });
+    let (vx, vy, vw, vh) = view_state.viewport;
+    pass.set_viewport(vx as f32, vy as f32, vw as f32, vh as f32, 0.0, 1.0);
+    if app.render_settings.warehouse {
+        let (width, height) = app.render_size();
+        gpu.draw_warehouse_gbuffer(&mut pass, view_state, time_s, width, height);
+    }
+    if app.render_settings.scene {
+        draw_scene(app, &mut pass);
+    }
+}
+
+/// Debug primitives over the TAA resolve, depth-tested in the shader against the G-buffer depth
+/// (`flipped`: the color is stored upside down relative to it). The resolve is jitter-free, so the
+/// primitives use the jitter-free matrix.
+#[cfg(feature = "debug-draw")]
+fn record_debug(
+    encoder: &mut wgpu::CommandEncoder,
+    gpu: &GpuContext,
+    color_view: &wgpu::TextureView,
+    depth_view: &wgpu::TextureView,
+    flipped: bool,
+    view_state: &ViewState,
+) {
+    const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    let depth = DepthTest::Texture {
+        view: depth_view,
+        flipped,
+    };
+    debug_draw::prepare(&gpu.device, &gpu.queue, COLOR_FORMAT, depth);
+    if debug_draw::is_empty() {
+        return;
+    }
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("debug"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: color_view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        multiview_mask: None,
+        occlusion_query_set: None,
+    });
+    let (vx, vy, vw, vh) = view_state.viewport;
+    pass.set_viewport(vx as f32, vy as f32, vw as f32, vh as f32, 0.0, 1.0);
+    let view_projection = view_state.view_projection_no_jitter;
+    debug_draw::draw(&mut pass, view_state, view_projection, COLOR_FORMAT, depth);
+}
+
+/// Text over the final image. World labels are depth-tested against [DEPTH] on the
+/// G-buffer path (at render scale; the target is upright again after the screen pass) or
+/// the forward depth buffer.
+fn record_text(
+    encoder: &mut wgpu::CommandEncoder,
+    gpu: &GpuContext,
+    app: &App,
+    target_view: &wgpu::TextureView,
+    depth_view: Option<&wgpu::TextureView>,
+    views: &[ViewState],
+) {
+    let forward_depth = gpu.main_depth_view().filter(|_| depth_view.is_none());
+    let depth = match (depth_view, forward_depth) {
+        (Some(view), _) => DepthTest::Texture {
+            view,
+            flipped: false,
+        },
+        (None, Some(_)) => DepthTest::Attachment(wgpu::TextureFormat::Depth32Float),
+        (None, None) => DepthTest::None,
+    };
+    text::prepare(&gpu.device, &gpu.queue, gpu.surface_format, depth);
+    if text::is_empty() {
+        return;
+    }
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("text"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target_view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment: forward_depth.map(|view| {
+            wgpu::RenderPassDepthStencilAttachment {
+                view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }
+        }),
+        timestamp_writes: None,
+        multiview_mask: None,
+        occlusion_query_set: None,
+    });
+    for view_state in views {
+        // Mono views are at render scale; the target is always full size.
+        let (vx, vy, vw, vh) = if views.len() == 1 {
+            (0, 0, app.width as i32, app.height as i32)
+        } else {
+            view_state.viewport
+        };
+        let viewport = [vx as f32, vy as f32, vw as f32, vh as f32];
+        pass.set_viewport(viewport[0], viewport[1], viewport[2], viewport[3], 0.0, 1.0);
+        text::draw(&mut pass, view_state, viewport, gpu.surface_format, depth);
+    }
+}
+
+/// The UI overlay over the whole target (full size in every mode, like the canvas it
+/// takes input from).
+fn record_ui(
+    encoder: &mut wgpu::CommandEncoder,
+    gpu: &GpuContext,
+    app: &App,
+    target_view: &wgpu::TextureView,
+) {
+    ui::prepare(
+        &gpu.device,
+        &gpu.queue,
+        gpu.surface_format,
+        (app.width, app.height),
+    );
+    if ui::is_empty() {
+        return;
+    }
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("ui"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target_view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        multiview_mask: None,
+        occlusion_query_set: None,
+    });
+    ui::draw(&mut pass, gpu.surface_format);
+}
+
+/// Direct pass into the target, one viewport per view (stereo, or G-buffer path disabled).
+fn record_forward(
+    encoder: &mut wgpu::CommandEncoder,
+    target_view: &wgpu::TextureView,
+    gpu: &GpuContext,
+    app: &mut App,
+    app_instance: &mut dyn AppInstance,
+    views: &[ViewState],
+    time_s: f32,
+) {
+    #[cfg(feature = "debug-draw")]
+    let debug_depth = match gpu.main_depth_view() {
+        Some(_) => DepthTest::Attachment(wgpu::TextureFormat::Depth32Float),
+        None => DepthTest::None,
+    };
+    #[cfg(feature = "debug-draw")]
+    debug_draw::prepare(&gpu.device, &gpu.queue, gpu.surface_format, debug_depth);
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("main"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target_view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 0.02,
+                    g: 0.02,
+                    b: 0.08,
+                    a: 1.0,
+                }),
+                store: wgpu::StoreOp::Store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment: gpu.main_depth_view().map(|v| {
+            wgpu::RenderPassDepthStencilAttachment {
+                view: v,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }
+        }),
+        timestamp_writes: None,
+        multiview_mask: None,
+        occlusion_query_set: None,
+    });
+    let settings = app.render_settings;
+    for view_state in views {
+        let (vx, vy, vw, vh) = view_state.viewport;
+        pass.set_viewport(vx as f32, vy as f32, vw as f32, vh as f32, 0.0, 1.0);
+        // Always run the fullscreen pass: raymarch, or solid gray so cubes show on top.
+        gpu.draw_warehouse(
+            &mut pass,
+            view_state,
+            time_s,
+            app.width,
+            app.height,
+            !settings.warehouse,
+        );
+        if settings.scene {
+            app_instance.frame(app, view_state, Some(&mut pass), false);
+        }
+    }
+    // After every view's scene, so primitives queued while drawing one view still show in both.
+    #[cfg(feature = "debug-draw")]
+    for view_state in views {
+        let (vx, vy, vw, vh) = view_state.viewport;
+        pass.set_viewport(vx as f32, vy as f32, vw as f32, vh as f32, 0.0, 1.0);
+        let view_projection = view_state.view_projection;
+        debug_draw::draw(
+            &mut pass,
+            view_state,
+            view_projection,
+            gpu.surface_format,
+            debug_depth,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scene pass that only declares its slots.
+    struct Stub {
+        inputs: Vec<Slot>,
+        outputs: Vec<Slot>,
+    }
+
+    impl GraphPass for Stub {
+        fn inputs(&self) -> Vec<Slot> {
+            self.inputs.clone()
+        }
+
+        fn outputs(&self) -> Vec<Slot> {
+            self.outputs.clone()
+        }
+
+        fn record(&mut self, _ctx: &mut PassContext<'_>) {}
+    }
+
+    fn stub(inputs: &[Slot], outputs: &[Slot]) -> Box<dyn GraphPass> {
+        Box::new(Stub {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+        })
+    }
+
+    /// The built-ins without `debug`, so orders don't depend on the `debug-draw` feature.
+    fn graph() -> RenderGraph {
+        let mut graph = RenderGraph::new();
+        graph.set_enabled("debug", false);
+        graph
+    }
+
+    #[test]
+    fn builtins_run_in_dependency_order() {
+        let order = graph().pass_order();
+        assert_eq!(
+            order,
+            ["gbuffer", "taa", "bloom", "exposure", "screen", "text", "ui"]
+        );
+    }
+
+    #[test]
+    fn first_producer_wins_and_later_ones_take_over_when_it_is_off() {
+        let mut graph = graph();
+        graph.set_enabled("screen", false);
+        // `present` now produces the target; nothing reads the bloom chain any more.
+        assert_eq!(
+            graph.pass_order(),
+            ["gbuffer", "taa", "exposure", "present", "text", "ui"]
+        );
+        graph.set_enabled("gbuffer", false);
+        // Nothing produces color, so TAA, exposure and `present` go too, leaving `forward`.
+        assert_eq!(graph.pass_order(), ["forward", "text", "ui"]);
+        graph.set_enabled("gbuffer", true);
+        graph.set_enabled("screen", true);
+        assert_eq!(
+            graph.pass_order(),
+            ["gbuffer", "taa", "bloom", "exposure", "screen", "text", "ui"]
+        );
+    }
+
+    #[test]
+    fn resolve_falls_back_to_color_without_taa() {
+        let mut graph = graph();
+        graph.set_enabled("taa", false);
+        assert_eq!(
+            graph.pass_order(),
+            ["gbuffer", "bloom", "exposure", "screen", "text", "ui"]
+        );
+    }
+
+    #[cfg(feature = "debug-draw")]
+    #[test]
+    fn modifiers_run_between_the_producer_and_the_readers() {
+        let mut graph = RenderGraph::new();
+        let order = graph.pass_order();
+        assert_eq!(
+            order,
+            ["gbuffer", "taa", "debug", "bloom", "exposure", "screen", "text", "ui"]
+        );
+        // Without TAA, `debug` draws over the color it falls back to.
+        graph.set_enabled("taa", false);
+        assert_eq!(
+            graph.pass_order(),
+            ["gbuffer", "debug", "bloom", "exposure", "screen", "text", "ui"]
+        );
+    }
+
+    #[test]
+    fn scene_overlays_run_before_the_builtin_ones() {
+        let mut graph = graph();
+        graph.add_pass("overlay", stub(&[TARGET], &[TARGET]));
+        assert_eq!(
+            graph.pass_order(),
+            ["gbuffer", "taa", "bloom", "exposure", "screen", "overlay", "text", "ui"]
+        );
+    }
+
+    #[test]
+    fn culls_passes_nothing_consumes() {
+        let mut graph = graph();
+        graph.add_pass("unread", stub(&[RESOLVE], &["unread_output"]));
+        graph.add_pass("unfed", stub(&["never_produced"], &[TARGET]));
+        graph.add_pass("side_effect", stub(&[RESOLVE], &[]));
+        let order = graph.pass_order();
+        assert!(
+            !order.contains(&"unread") && !order.contains(&"unfed"),
+            "{order:?}"
+        );
+        assert!(order.contains(&"side_effect"), "{order:?}");
+    }
+
+    #[test]
+    fn rewire_routes_a_pass_through_a_scene_pass() {
+        let mut graph = graph();
+        graph.add_pass("grade", stub(&[RESOLVE], &["graded"]));
+        assert!(!graph.pass_order().contains(&"grade"));
+        graph.rewire("screen", RESOLVE, "graded");
+        assert_eq!(
+            graph.pass_order(),
+            ["gbuffer", "taa", "bloom", "exposure", "grade", "screen", "text", "ui"]
+        );
+    }
+
+    #[test]
+    fn passes_only_run_while_their_group_is_active() {
+        let mut graph = graph();
+        graph.set_group(Some("scene"));
+        graph.add_pass("scene_overlay", stub(&[TARGET], &[TARGET]));
+        graph.set_group(None);
+        graph.add_pass("shared_overlay", stub(&[TARGET], &[TARGET]));
+        let order = graph.pass_order();
+        assert!(
+            order.contains(&"scene_overlay") && order.contains(&"shared_overlay"),
+            "{order:?}"
+        );
+        graph.set_group_active("scene", false);
+        let order = graph.pass_order();
+        assert!(
+            !order.contains(&"scene_overlay") && order.contains(&"shared_overlay"),
+            "{order:?}"
+        );
+        graph.set_group_active("scene", true);
+        assert!(graph.pass_order().contains(&"scene_overlay"));
+    }
+
+    #[test]
+    fn disabled_passes_stay_disabled_in_an_active_group() {
+        let mut graph = graph();
+        graph.set_group(Some("scene"));
+        graph.add_pass("scene_overlay", stub(&[TARGET], &[TARGET]));
+        graph.set_enabled("scene_overlay", false);
+        graph.set_group_active("scene", true);
+        assert!(!graph.pass_order().contains(&"scene_overlay"));
+        graph.set_enabled("scene_overlay", true);
+        assert!(graph.pass_order().contains(&"scene_overlay"));
+    }
+}
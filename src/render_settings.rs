@@ -0,0 +1,235 @@
+//! Runtime render settings: feature toggles for the render graph plus quality knobs.
+//!
+//! Held by [crate::app::App] as `render_settings`. On the web they are exposed to JavaScript
+//! (see [crate::platform::web]) and persisted to localStorage under [STORAGE_KEY].
+
+use serde::{Deserialize, Serialize};
+
+use crate::color_grading::{ColorGrading, Tonemapper};
+use crate::gpu::{EyeAdaptation, BLOOM_MIP_LEVELS};
+
+/// localStorage key for the JSON-serialized settings.
+pub const STORAGE_KEY: &str = "wasm2.render_settings";
+
+/// Upper bound for [RenderSettings::raymarch_steps]; matches `maxIterations` in
+/// the warehouse shaders.
+pub const MAX_RAYMARCH_STEPS: u32 = 256;
+/// Upper bound for [RenderSettings::shadow_steps].
+pub const MAX_SHADOW_STEPS: u32 = 64;
+/// Lower bound for [RenderSettings::render_scale] and [RenderSettings::min_render_scale].
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+/// Bounds for [RenderSettings::min_ev] and [RenderSettings::max_ev] (EV100).
+pub const MIN_EV: f32 = -8.0;
+pub const MAX_EV: f32 = 16.0;
+/// Bounds for [RenderSettings::paper_white_nits] and [RenderSettings::peak_nits].
+pub const MIN_PAPER_WHITE_NITS: f32 = 80.0;
+pub const MAX_PEAK_NITS: f32 = 10_000.0;
+
+/// Named quality levels. `Custom` means a knob was changed after picking a preset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    #[default]
+    High,
+    Custom,
+}
+
+impl QualityPreset {
+    /// Parses `"low"`, `"medium"` or `"high"` (case-insensitive).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+}
+
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    pub preset: QualityPreset,
+    /// Raymarch the warehouse (off: solid background in `forward`, nothing in `gbuffer`).
+    pub warehouse: bool,
+    /// Let the scene draw into `gbuffer` / `forward`.
+    pub scene: bool,
+    /// Mono uses the G-buffer path; off draws straight to the target like stereo.
+    pub gbuffer: bool,
+    pub taa: bool,
+    /// Bloom + screen pass; off presents the resolve as-is.
+    pub post: bool,
+    /// Internal render scale of the G-buffer path (1.0 = full res; the screen pass upscales).
+    /// With [RenderSettings::dynamic_resolution] this is the upper bound.
+    pub render_scale: f32,
+    /// Lower the render scale (down to [RenderSettings::min_render_scale])
+    /// to hold [RenderSettings::target_fps].
+    pub dynamic_resolution: bool,
+    pub target_fps: f32,
+    pub min_render_scale: f32,
+    /// Bloom downsample levels (2..=[BLOOM_MIP_LEVELS]); fewer = tighter, cheaper glow.
+    pub bloom_mips: u32,
+    /// Primary ray march iterations for the warehouse.
+    pub raymarch_steps: u32,
+    /// Soft shadow march iterations per light.
+    pub shadow_steps: u32,
+    /// Adapt the exposure to the image's brightness (`post` only); off uses the camera's
+    /// ([crate::camera::CameraExposure]).
+    pub auto_exposure: bool,
+    /// EV100 range auto-exposure stays in: it won't brighten a scene darker than `min_ev` further,
+    /// nor darken one brighter than `max_ev`.
+    pub min_ev: f32,
+    pub max_ev: f32,
+    /// How quickly auto-exposure adapts (per second; higher is faster).
+    pub adaptation_speed: f32,
+    /// Tonemap operator of the screen pass.
+    pub tonemapper: Tonemapper,
+    /// White balance, contrast, saturation and grading LUT strength (see [crate::color_grading]).
+    pub grading: ColorGrading,
+    /// Present in extended range on HDR displays, where the browser supports it (falls back to SDR
+    /// otherwise; see [crate::platform::Platform::set_extended_range]).
+    pub hdr: bool,
+    /// Brightness of SDR white in HDR output, in nits (cd/m²).
+    pub paper_white_nits: f32,
+    /// The display's peak brightness in nits; HDR tonemapping reaches it.
+    pub peak_nits: f32,
+    /// Performance overlay ([crate::hud]).
+    pub hud: bool,
+    /// Parameter panels ([crate::ui]).
+    pub ui: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            preset: QualityPreset::High,
+            warehouse: true,
+            scene: true,
+            gbuffer: true,
+            taa: true,
+            post: true,
+            render_scale: 1.0,
+            dynamic_resolution: false,
+            target_fps: 60.0,
+            min_render_scale: 0.5,
+            bloom_mips: BLOOM_MIP_LEVELS as u32,
+            raymarch_steps: MAX_RAYMARCH_STEPS,
+            shadow_steps: 32,
+            auto_exposure: false,
+            min_ev: -4.0,
+            max_ev: 10.0,
+            adaptation_speed: 1.5,
+            tonemapper: Tonemapper::default(),
+            grading: ColorGrading::default(),
+            hdr: false,
+            paper_white_nits: 203.0,
+            peak_nits: 1000.0,
+            hud: false,
+            ui: false,
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Sets the quality knobs for `preset`; feature toggles are left alone.
+    pub const fn apply_preset(&mut self, preset: QualityPreset) {
+        let (bloom_mips, raymarch_steps, shadow_steps) = match preset {
+            QualityPreset::Low => (3, 96, 12),
+            QualityPreset::Medium => (4, 160, 24),
+            QualityPreset::High => (BLOOM_MIP_LEVELS as u32, MAX_RAYMARCH_STEPS, 32),
+            QualityPreset::Custom => return,
+        };
+        self.preset = preset;
+        self.bloom_mips = bloom_mips;
+        self.raymarch_steps = raymarch_steps;
+        self.shadow_steps = shadow_steps;
+    }
+
+    /// Clamps every knob into its supported range (e.g. after loading from storage or JS).
+    #[must_use]
+    pub fn sanitized(mut self) -> Self {
+        let finite_or = |v: f32, default: f32| if v.is_finite() { v } else { default };
+        self.render_scale = finite_or(self.render_scale, 1.0).clamp(MIN_RENDER_SCALE, 1.0);
+        self.min_render_scale =
+            finite_or(self.min_render_scale, 0.5).clamp(MIN_RENDER_SCALE, self.render_scale);
+        self.target_fps = finite_or(self.target_fps, 60.0).clamp(15.0, 240.0);
+        self.bloom_mips = self.bloom_mips.clamp(2, BLOOM_MIP_LEVELS as u32);
+        self.raymarch_steps = self.raymarch_steps.clamp(16, MAX_RAYMARCH_STEPS);
+        self.shadow_steps = self.shadow_steps.clamp(4, MAX_SHADOW_STEPS);
+        self.min_ev = finite_or(self.min_ev, -4.0).clamp(MIN_EV, MAX_EV);
+        self.max_ev = finite_or(self.max_ev, 10.0).clamp(self.min_ev, MAX_EV);
+        self.adaptation_speed = finite_or(self.adaptation_speed, 1.5).clamp(0.1, 20.0);
+        self.grading = self.grading.sanitized();
+        self.paper_white_nits =
+            finite_or(self.paper_white_nits, 203.0).clamp(MIN_PAPER_WHITE_NITS, 500.0);
+        self.peak_nits =
+            finite_or(self.peak_nits, 1000.0).clamp(self.paper_white_nits, MAX_PEAK_NITS);
+        self
+    }
+
+    /// Display peak over SDR white, for [crate::gpu::GpuContext::set_output_peak] while HDR output
+    /// is active.
+    pub fn hdr_peak(&self) -> f32 {
+        self.peak_nits / self.paper_white_nits
+    }
+
+    /// Eye adaptation for [crate::gpu::GpuContext::set_exposure], if auto-exposure is on.
+    pub fn eye_adaptation(&self) -> Option<EyeAdaptation> {
+        (self.post && self.auto_exposure).then_some(EyeAdaptation {
+            speed: self.adaptation_speed,
+            min_ev: self.min_ev,
+            max_ev: self.max_ev,
+        })
+    }
+
+    /// Turns a feature on or off by name (`warehouse`, `scene`, `gbuffer`, `taa`, `post`,
+    /// `dynamic_resolution`, `auto_exposure`, `hdr`, `hud`, `ui`).
+    /// Returns false for unknown names.
+    pub fn set_feature(&mut self, name: &str, enabled: bool) -> bool {
+        let flag = match name {
+            "warehouse" => &mut self.warehouse,
+            "scene" => &mut self.scene,
+            "gbuffer" => &mut self.gbuffer,
+            "taa" => &mut self.taa,
+            "post" => &mut self.post,
+            "dynamic_resolution" => &mut self.dynamic_resolution,
+            "auto_exposure" => &mut self.auto_exposure,
+            "hdr" => &mut self.hdr,
+            "hud" => &mut self.hud,
+            "ui" => &mut self.ui,
+            _ => return false,
+        };
+        *flag = enabled;
+        true
+    }
+
+    /// Settings saved in localStorage, if any parse.
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> Option<Self> {
+        let storage = web_sys::window()?.local_storage().ok()??;
+        let text = storage.get_item(STORAGE_KEY).ok()??;
+        let value = js_sys::JSON::parse(&text).ok()?;
+        serde_wasm_bindgen::from_value::<Self>(value)
+            .ok()
+            .map(Self::sanitized)
+    }
+
+    /// Writes the settings to localStorage (ignored if storage is unavailable, e.g. private mode).
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) {
+        let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) else {
+            return;
+        };
+        let text = serde_wasm_bindgen::to_value(self)
+            .ok()
+            .and_then(|v| js_sys::JSON::stringify(&v).ok())
+            .and_then(|s| s.as_string());
+        if let Some(text) = text {
+            if storage.set_item(STORAGE_KEY, &text).is_err() {
+                log_error!("Could not save render settings.");
+            }
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use crate::app::App;
+use crate::audio::AudioFrame;
+use crate::gpu::GpuContext;
+use crate::input::Actions;
+use crate::platform::gamepad::GamepadState;
+use crate::render_graph::RenderGraph;
 use crate::view::ViewState;
 use glam::Vec3;
+use std::collections::HashMap;
 use wgpu::RenderPass;
 
 pub struct CameraDescriptor {
@@ -29,31 +35,59 @@ pub struct SceneDescriptor {
 pub struct FrameInput {
     pub timestamp: f64,
     pub delta_time: f64,
+    /// Named actions and axes from the runner's bindings (see [crate::input]); prefer these
+    /// to raw input.
+    pub actions: Actions,
     pub mouse_dx: f32,
     pub mouse_dy: f32,
-    pub keys_held: u32,
-}
-
-impl FrameInput {
-    pub const KEY_W: u32 = 1 << 0;
-    pub const KEY_A: u32 = 1 << 1;
-    pub const KEY_S: u32 = 1 << 2;
-    pub const KEY_D: u32 = 1 << 3;
-    pub const KEY_SPACE: u32 = 1 << 4;
-    pub const KEY_SHIFT: u32 = 1 << 5;
-
-    pub fn key(&self, mask: u32) -> bool {
-        self.keys_held & mask != 0
-    }
+    /// First connected gamepad (deadzones applied); default when there is none.
+    pub gamepad: GamepadState,
+    /// Named float tracks of the playing sequence ([crate::sequencer]); empty without one.
+    pub parameters: HashMap<String, f32>,
+    /// The music's position and spectrum bands ([crate::audio]); silent without a track.
+    pub audio: AudioFrame,
 }
 
+/// One scene of the [crate::demo::Demo]. Only the current scene is updated and drawn (plus, during
+/// a [transition](crate::transition), the one switched away from, without its graph passes); the
+/// others keep their state (and their graph passes are paused) until switched back to.
 pub trait Scene {
+    /// Short name: the scene's [RenderGraph] pass group, and what `sceneNames()` lists on the web.
+    fn name(&self) -> &'static str;
+
     fn descriptor(&self) -> &SceneDescriptor;
+    /// For editing from the [crate::ui] overlay (camera FOV); the scene keeps updating it as usual.
+    fn descriptor_mut(&mut self) -> &mut SceneDescriptor;
 
     /// Called once per frame before the engine reads the descriptor.
     fn update(&mut self, input: &FrameInput);
 
     /// When `pass` is `Some`, the scene may record draws (e.g. cubes) into it.
     /// When `is_gbuffer` is true, use [crate::half_cube::HalfCube::draw_instanced_gbuffer].
-    fn on_frame(&mut self, app: &mut App, view: &ViewState, pass: Option<&mut RenderPass<'_>>, is_gbuffer: bool);
+    fn on_frame(
+        &mut self,
+        app: &mut App,
+        view: &ViewState,
+        pass: Option<&mut RenderPass<'_>>,
+        is_gbuffer: bool,
+    );
+
+    /// Called once at startup; the scene may add its own passes (see [crate::render_graph]). They
+    /// are grouped under [Scene::name] and only run while the scene is current.
+    fn render_passes(&mut self, _graph: &mut RenderGraph) {}
+
+    /// Called when the scene becomes current (at startup for the first scene), before
+    /// its next update.
+    fn enter(&mut self, _app: &App) {}
+
+    /// Called when another scene replaces this one.
+    fn exit(&mut self, _app: &App) {}
+
+    /// Called once the GPU is initialized (after [Scene::render_passes]; async on the web), to
+    /// create GPU resources up front rather than on first draw.
+    fn gpu_ready(&mut self, _gpu: &GpuContext) {}
+
+    /// Adds the scene's parameters to the [crate::ui] overlay (its "Scene" panel), while
+    /// it is open.
+    fn ui(&mut self, _ui: &mut egui::Ui) {}
 }
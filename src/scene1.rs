@@ -1,20 +1,37 @@
 use crate::app::App;
+use crate::camera_controller::{
+    CameraCollision, CameraMode, CameraRig, FlyController, OrbitController, PathController,
+    WarehouseSolid,
+};
 use crate::chunk::{Chunk, ChunkMesh};
 use crate::ecs::components::{BasePosition, HalfCube, OscillateMotion};
 use crate::ecs::systems::half_cube_render_system;
 use crate::ecs::{FrameResources, World};
 use crate::fast_rand::FastRand;
-use crate::line_2d_strip::Line2DStrip;
-use crate::particles::Particles;
+use crate::particles::{
+    sprites, AtlasBuilder, Billboard, Collider, Collision, CollisionMode, Curve, EmitterDesc,
+    EmitterShape, FlipbookMode, ParticleBlend, ParticleDrawPass, ParticleSimPass, ParticleSystem,
+    Sprite,
+};
+use crate::polyline::{LineCap, LineJoin, LineStyle, PolylinePass, Polylines};
+use crate::render_graph::RenderGraph;
 use crate::scene::{CameraDescriptor, FrameInput, Scene, SceneDescriptor};
+use crate::text;
 use crate::view::ViewState;
-use glam::Vec3;
+use glam::{Vec3, Vec4};
+use std::cell::RefCell;
 use std::f32::consts::FRAC_PI_2;
-use std::f32::consts::FRAC_PI_4;
+use std::rc::Rc;
 
-const MOUSE_SENSITIVITY: f32 = 0.002;
-const MOVE_SPEED: f32 = 5.0;
-const MAX_PITCH: f32 = 1.5;
+/// Path camera loop: past the fountain, around the cubes and back along the warehouse.
+const TOUR: [Vec3; 6] = [
+    Vec3::new(-10.0, 1.7, -10.0),
+    Vec3::new(-3.0, 2.5, -9.0),
+    Vec3::new(7.0, 4.0, -6.0),
+    Vec3::new(9.0, 2.0, 6.0),
+    Vec3::new(-2.0, 5.0, 10.0),
+    Vec3::new(-12.0, 2.5, 2.0),
+];
 
 const CHUNK_N: usize = 16;
 
@@ -26,40 +43,147 @@ const CUBE_SCALE: f32 = 0.4;
 const MOTION_AMPLITUDE: f32 = 0.5;
 /// Angular frequency for motion (radians per second).
 const MOTION_SPEED: f32 = 2.0;
+/// Extra motion amplitude at full bass while music plays (fraction of the set amplitude).
+const AUDIO_MOTION: f32 = 1.5;
+
+/// GPU particle pool sizes.
+const SPARK_CAPACITY: u32 = 1 << 18;
+const SMOKE_CAPACITY: u32 = 1 << 12;
+const MAGIC_CAPACITY: u32 = 1 << 13;
+/// Graph pass names (draw, simulate) per entry of `Scene1::effects`; smoke (alpha) draws before
+/// the additive ones.
+const EFFECT_PASSES: [(&str, &str); 3] = [
+    ("smoke", "smoke_sim"),
+    ("sparks", "sparks_sim"),
+    ("magic", "magic_sim"),
+];
+/// World-space labels beside the effects' emitters.
+const EFFECT_LABELS: [(Vec3, &str); 2] = [
+    (Vec3::new(-6.2, -0.3, -6.0), "sparks + smoke"),
+    (Vec3::new(-5.0, 2.6, -6.5), "magic"),
+];
 
 pub struct Scene1 {
-    #[allow(dead_code)]
-    line_strip: Line2DStrip,
-    #[allow(dead_code)]
-    particles: Particles,
-    #[allow(dead_code)]
-    particle_positions: Vec<f32>,
-    #[allow(dead_code)]
-    rng: FastRand,
+    /// Guide lines on and above the floor, shared with their [PolylinePass].
+    lines: Rc<RefCell<Polylines>>,
+    /// Smoke, sparks and magic, each shared with its [ParticleSimPass] and [ParticleDrawPass].
+    effects: [Rc<RefCell<ParticleSystem>>; 3],
     #[allow(dead_code)]
     chunk_mesh: ChunkMesh,
 
     /// ECS world: entities and components (moving half-cubes).
     world: World,
-    /// Reused every frame for instanced draw (filled by half_cube_render_system). Packed [x,y,z,scale] per instance.
+    /// Reused every frame for instanced draw (filled by half_cube_render_system). Packed
+    /// [x,y,z,scale] per instance.
     instance_data: Vec<f32>,
 
     descriptor: SceneDescriptor,
 
-    yaw: f32,
-    pitch: f32,
+    /// Cube parameters as last set from the UI or a sequence (every cube gets the same values).
+    cube_scale: f32,
+    motion_amplitude: f32,
+    motion_speed: f32,
+    show_labels: bool,
+
+    /// Fly (starting out), orbit whatever the camera looks at, or tour the warehouse.
+    rig: CameraRig,
 }
 
 impl Scene1 {
     pub fn new(app: &App) -> Self {
-        let mut particle_positions = Vec::new();
-        let mut rng = FastRand::new(3464357);
+        let mut atlas = AtlasBuilder::new();
+        atlas
+            .add("spark", sprites::spark(16, 64))
+            .add_flipbook("smoke", sprites::smoke(64, 4, 4), 4, 4, 16)
+            .add_flipbook("magic", sprites::magic(64, 4, 2), 4, 2, 8);
+        let atlas = Rc::new(atlas.build().expect("particle atlas"));
+        let sprite = |name: &str| atlas.region(name).map(Sprite::new);
 
-        for _ in 0..32 {
-            particle_positions.push(rng.rand());
-            particle_positions.push(rng.rand());
-            particle_positions.push(rng.rand());
-        }
+        // Fountain between the start position and the cubes.
+        let mut sparks = ParticleSystem::new(SPARK_CAPACITY);
+        sparks.set_atlas(atlas.clone());
+        sparks.forces.curl_strength = 2.0;
+        // Bounce off whatever is on screen (warehouse, cubes); the room's walls
+        // and floor elsewhere.
+        sparks.collision = Collision {
+            mode: CollisionMode::Bounce,
+            restitution: 0.4,
+            friction: 0.2,
+            colliders: warehouse_colliders(),
+            ..Default::default()
+        };
+        sparks.add_emitter(EmitterDesc {
+            position: Vec3::new(-6.0, 0.0, -6.0),
+            shape: EmitterShape::Disc { radius: 0.15 },
+            rate: 30_000.0,
+            direction: Vec3::Y,
+            cone_angle: 0.25,
+            speed: (4.0, 6.0),
+            lifetime: (1.5, 2.5),
+            color: Vec4::new(0.6, 0.3, 0.1, 1.0),
+            size: 0.03,
+            billboard: Billboard::Velocity { stretch: 0.01 },
+            sprite: sprite("spark"),
+            ..Default::default()
+        });
+
+        // Smoke drifting up from the fountain's base.
+        let mut smoke = ParticleSystem::new(SMOKE_CAPACITY);
+        smoke.set_atlas(atlas.clone());
+        smoke.blend = ParticleBlend::Alpha;
+        smoke.softness = 0.5;
+        smoke.forces.gravity = Vec3::new(0.0, 0.3, 0.0);
+        smoke.forces.drag = 0.5;
+        smoke.forces.curl_strength = 0.6;
+        smoke.forces.curl_scale = 0.5;
+        smoke.add_emitter(EmitterDesc {
+            position: Vec3::new(-6.0, 0.2, -6.0),
+            shape: EmitterShape::Disc { radius: 0.4 },
+            rate: 25.0,
+            direction: Vec3::Y,
+            cone_angle: 0.4,
+            speed: (0.3, 0.8),
+            lifetime: (4.0, 6.0),
+            color: Vec4::new(0.5, 0.48, 0.46, 0.7),
+            size: 0.35,
+            size_over_life: Curve::new(&[(0.0, 0.6), (1.0, 2.5)]),
+            color_over_life: Curve::new(&[
+                (0.0, Vec4::new(1.0, 1.0, 1.0, 0.0)),
+                (0.15, Vec4::ONE),
+                (0.7, Vec4::ONE),
+                (1.0, Vec4::new(1.0, 1.0, 1.0, 0.0)),
+            ]),
+            sprite: sprite("smoke").map(|s| Sprite {
+                mode: FlipbookMode::OverLife,
+                ..s
+            }),
+            ..Default::default()
+        });
+
+        // Twinkling magic swirl beside the fountain.
+        let mut magic = ParticleSystem::new(MAGIC_CAPACITY);
+        magic.set_atlas(atlas.clone());
+        magic.forces.gravity = Vec3::ZERO;
+        magic.forces.drag = 0.3;
+        magic.forces.curl_strength = 1.5;
+        magic.forces.curl_scale = 0.8;
+        magic.add_emitter(EmitterDesc {
+            position: Vec3::new(-4.5, 1.5, -6.5),
+            shape: EmitterShape::Sphere { radius: 0.6 },
+            rate: 300.0,
+            cone_angle: std::f32::consts::PI,
+            speed: (0.1, 0.4),
+            lifetime: (1.5, 2.5),
+            color: Vec4::new(0.6, 0.3, 1.5, 1.0),
+            size: 0.08,
+            size_over_life: Curve::new(&[(0.0, 0.3), (0.2, 1.0), (1.0, 0.2)]),
+            sprite: sprite("magic").map(|s| Sprite {
+                fps: 12.0,
+                random_frame: true,
+                ..s
+            }),
+            ..Default::default()
+        });
 
         let mut chunk = Chunk::new(CHUNK_N, CHUNK_N, CHUNK_N);
         chunk.fill_hollow_box();
@@ -98,11 +222,14 @@ impl Scene1 {
 
         let instance_data = Vec::with_capacity(N_CUBES * 4);
 
+        let tour = PathController::new(TOUR.to_vec(), true);
+        // The test chunk isn't drawn, so it isn't solid either.
+        let mut fly = FlyController::default();
+        fly.collision = Some(CameraCollision::new(vec![Box::new(WarehouseSolid)]));
+
         Self {
-            line_strip: Line2DStrip::new(),
-            particles: Particles::new(),
-            particle_positions,
-            rng,
+            lines: Rc::new(RefCell::new(guide_lines())),
+            effects: [smoke, sparks, magic].map(|system| Rc::new(RefCell::new(system))),
             chunk_mesh,
             world,
             instance_data,
@@ -114,41 +241,55 @@ impl Scene1 {
                     fov: FRAC_PI_2,
                 },
             },
-            yaw: FRAC_PI_4,
-            pitch: 0.0,
+            cube_scale: CUBE_SCALE,
+            motion_amplitude: MOTION_AMPLITUDE,
+            motion_speed: MOTION_SPEED,
+            show_labels: true,
+            rig: CameraRig::new(CameraMode::Fly, fly, OrbitController::default(), tour),
         }
     }
 }
 
 impl Scene for Scene1 {
+    fn name(&self) -> &'static str {
+        "fountain"
+    }
+
     fn descriptor(&self) -> &SceneDescriptor {
         &self.descriptor
     }
 
+    fn descriptor_mut(&mut self) -> &mut SceneDescriptor {
+        &mut self.descriptor
+    }
+
     fn update(&mut self, input: &FrameInput) {
-        self.yaw -= input.mouse_dx * MOUSE_SENSITIVITY;
-        self.pitch = (self.pitch - input.mouse_dy * MOUSE_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
-
-        let forward = Vec3::new(self.yaw.sin(), 0.0, self.yaw.cos());
-        let right = Vec3::new(-self.yaw.cos(), 0.0, self.yaw.sin());
-
-        let dt = (input.delta_time / 1000.0) as f32;
-        let speed = MOVE_SPEED * dt;
-        let mut movement = Vec3::ZERO;
-        if input.key(FrameInput::KEY_W) { movement += forward * speed; }
-        if input.key(FrameInput::KEY_S) { movement -= forward * speed; }
-        if input.key(FrameInput::KEY_D) { movement += right * speed; }
-        if input.key(FrameInput::KEY_A) { movement -= right * speed; }
-        if input.key(FrameInput::KEY_SPACE) { movement.y += speed; }
-        if input.key(FrameInput::KEY_SHIFT) { movement.y -= speed; }
-        self.descriptor.camera.position += movement;
-
-        let dir = Vec3::new(
-            self.pitch.cos() * self.yaw.sin(),
-            self.pitch.sin(),
-            self.pitch.cos() * self.yaw.cos(),
-        );
-        self.descriptor.camera.target = self.descriptor.camera.position + dir;
+        // Sequence tracks (crate::sequencer) override the UI's values while they play.
+        if let Some(&scale) = input.parameters.get("cube_scale") {
+            self.cube_scale = scale;
+            self.world.moving_cubes.scales.fill(scale);
+        }
+        if let Some(&amplitude) = input.parameters.get("motion_amplitude") {
+            self.motion_amplitude = amplitude;
+        }
+        // The cubes bounce with the music's bass (crate::audio); bands are zero in silence.
+        let bass = input.audio.bands[0].max(input.audio.bands[1]);
+        self.world
+            .moving_cubes
+            .motion_amplitudes
+            .fill(self.motion_amplitude * (1.0 + AUDIO_MOTION * bass));
+
+        self.rig.update(&mut self.descriptor.camera, input);
+
+        if input.actions.pressed("toggle_labels") {
+            self.show_labels = !self.show_labels;
+        }
+
+        if self.show_labels {
+            for (position, label) in EFFECT_LABELS {
+                text::draw_text_world(position, 0.3, Vec4::ONE, label);
+            }
+        }
     }
 
     fn on_frame(
@@ -171,15 +312,138 @@ impl Scene for Scene1 {
             camera_position,
         };
 
+        let mut pass = pass;
         half_cube_render_system(
             &self.world,
             &resources,
             app,
             &mut self.instance_data,
-            pass,
+            pass.as_deref_mut(),
             is_gbuffer,
         );
+        // The G-buffer path draws particles in their own pass (soft, after opaque geometry).
+        if let Some(pass) = pass.filter(|_| !is_gbuffer) {
+            for effect in &self.effects {
+                effect.borrow().draw(pass, view);
+            }
+        }
     }
+
+    fn render_passes(&mut self, graph: &mut RenderGraph) {
+        graph.add_pass("polylines", Box::new(PolylinePass::new(self.lines.clone())));
+        for ((draw, sim), effect) in EFFECT_PASSES.into_iter().zip(&self.effects) {
+            graph.add_pass(draw, Box::new(ParticleDrawPass::new(effect.clone())));
+            graph.add_pass(sim, Box::new(ParticleSimPass::new(effect.clone())));
+        }
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let cubes = &mut self.world.moving_cubes;
+        ui.label("Cubes");
+        if ui
+            .add(egui::Slider::new(&mut self.cube_scale, 0.05..=1.5).text("Scale"))
+            .changed()
+        {
+            cubes.scales.fill(self.cube_scale);
+        }
+        ui.add(egui::Slider::new(&mut self.motion_amplitude, 0.0..=3.0).text("Motion amplitude"));
+        if ui
+            .add(egui::Slider::new(&mut self.motion_speed, 0.0..=10.0).text("Motion speed"))
+            .changed()
+        {
+            cubes.motion_speeds.fill(self.motion_speed);
+        }
+        for ((name, _), effect) in EFFECT_PASSES.iter().zip(&self.effects) {
+            let mut effect = effect.borrow_mut();
+            ui.separator();
+            ui.label(*name);
+            ui.push_id(name, |ui| {
+                ui.add(egui::Slider::new(&mut effect.size_scale, 0.1..=4.0).text("Size"));
+                ui.add(egui::Slider::new(&mut effect.forces.curl_strength, 0.0..=5.0).text("Curl"));
+            });
+        }
+        ui.separator();
+        ui.checkbox(&mut self.show_labels, "Effect labels");
+        ui.separator();
+        self.rig.ui(ui);
+    }
+}
+
+/// A dashed ring on the floor around the cubes and a rainbow helix rising through them.
+fn guide_lines() -> Polylines {
+    let mut lines = Polylines::new();
+    let ring: Vec<Vec3> = (0..64)
+        .map(|i| {
+            let angle = i as f32 / 64.0 * std::f32::consts::TAU;
+            Vec3::new(6.0 * angle.cos(), -2.45, 6.0 * angle.sin())
+        })
+        .collect();
+    lines.add_strip(
+        &ring,
+        Vec4::new(1.0, 0.8, 0.2, 1.0),
+        &LineStyle {
+            width: 4.0,
+            dash: Some((1.0, 0.6)),
+            closed: true,
+            ..Default::default()
+        },
+    );
+
+    let helix: Vec<Vec3> = (0..=96)
+        .map(|i| {
+            let t = i as f32 / 96.0;
+            let angle = t * 3.0 * std::f32::consts::TAU;
+            Vec3::new(5.0 * angle.cos(), -2.0 + 8.0 * t, 5.0 * angle.sin())
+        })
+        .collect();
+    let colors: Vec<Vec4> = (0..helix.len())
+        .map(|i| {
+            let hue = i as f32 / helix.len() as f32 * std::f32::consts::TAU;
+            let channel = |offset: f32| 0.5 + 0.5 * (hue + offset).cos();
+            Vec4::new(channel(0.0), channel(-2.1), channel(2.1), 0.8)
+        })
+        .collect();
+    lines.add_strip_with_colors(
+        &helix,
+        &colors,
+        &LineStyle {
+            width: 6.0,
+            join: LineJoin::Round,
+            cap: LineCap::Round,
+            ..Default::default()
+        },
+    );
+    lines
+}
+
+/// Inside faces of the warehouse room (see `warehouse_sdf`: a 40 x 10 x 80 box centred at y = 2.5).
+fn warehouse_colliders() -> Vec<Collider> {
+    vec![
+        Collider::Plane {
+            normal: Vec3::Y,
+            offset: -2.5,
+        },
+        Collider::Plane {
+            normal: Vec3::NEG_Y,
+            offset: -7.5,
+        },
+        Collider::Plane {
+            normal: Vec3::X,
+            offset: -20.0,
+        },
+        Collider::Plane {
+            normal: Vec3::NEG_X,
+            offset: -20.0,
+        },
+        Collider::Plane {
+            normal: Vec3::Z,
+            offset: -40.0,
+        },
+        Collider::Plane {
+            normal: Vec3::NEG_Z,
+            offset: -40.0,
+        },
+    ]
 }
 
 impl Drop for Scene1 {
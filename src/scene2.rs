@@ -0,0 +1,264 @@
+use crate::app::App;
+use crate::camera_controller::{
+    CameraCollision, CameraMode, CameraRig, FlyController, OrbitController, PathController,
+    WarehouseSolid,
+};
+use crate::ecs::components::{BasePosition, HalfCube, OscillateMotion};
+use crate::ecs::systems::half_cube_render_system;
+use crate::ecs::{FrameResources, World};
+use crate::gpu::GpuContext;
+use crate::polyline::{DepthTest, LineCap, LineJoin, LineStyle, PolylinePass, Polylines};
+use crate::render_graph::RenderGraph;
+use crate::scene::{CameraDescriptor, FrameInput, Scene, SceneDescriptor};
+use crate::view::ViewState;
+use glam::{Vec3, Vec4};
+use std::cell::RefCell;
+use std::f32::consts::{FRAC_PI_3, TAU};
+use std::rc::Rc;
+
+/// Middle of the wave, on the warehouse floor away from [crate::scene1::Scene1]'s cubes.
+const CENTER: Vec3 = Vec3::new(0.0, -2.0, 18.0);
+/// Cubes per side of the grid, and the distance between their centers.
+const GRID_N: usize = 17;
+const GRID_SPACING: f32 = 0.6;
+const CUBE_SCALE: f32 = 0.25;
+const WAVE_AMPLITUDE: f32 = 0.4;
+/// Angular frequency of each cube's bob (radians per second).
+const WAVE_SPEED: f32 = 3.0;
+/// Phase lag per unit of distance from the center (radians), so the bobbing ripples outward.
+const WAVE_NUMBER: f32 = 1.5;
+
+/// Orbit around [CENTER] (yaw in radians, pitch clamped to stay above the floor).
+const START_YAW: f32 = 0.6;
+const START_PITCH: f32 = 0.35;
+const MIN_PITCH: f32 = 0.05;
+const MAX_PITCH: f32 = 1.3;
+const START_DISTANCE: f32 = 10.0;
+const MIN_DISTANCE: f32 = 3.0;
+const MAX_DISTANCE: f32 = 30.0;
+/// Yaw the camera drifts by per second on its own.
+const ORBIT_SPEED: f32 = 0.15;
+/// Path camera: a loop around the knot, dipping low on one side and rising on the other.
+const PATH_RADIUS: f32 = 9.0;
+const PATH_POINTS: usize = 8;
+
+/// A rippling grid of half-cubes under a trefoil knot, seen from a slowly drifting orbit camera.
+pub struct Scene2 {
+    /// Trefoil knot above the grid, shared with its [PolylinePass].
+    knot: Rc<RefCell<Polylines>>,
+    world: World,
+    /// Reused every frame for instanced draw (filled by half_cube_render_system).
+    instance_data: Vec<f32>,
+    descriptor: SceneDescriptor,
+
+    /// Wave parameters as last set from the UI or a sequence.
+    wave_amplitude: f32,
+    wave_speed: f32,
+
+    /// Orbits the knot (starting out), flies, or circles it on a path.
+    rig: CameraRig,
+}
+
+impl Scene2 {
+    pub fn new() -> Self {
+        let mut world = World::new();
+        let half_extent = (GRID_N - 1) as f32 * GRID_SPACING * 0.5;
+        for i in 0..GRID_N {
+            for j in 0..GRID_N {
+                let offset = Vec3::new(
+                    i as f32 * GRID_SPACING - half_extent,
+                    0.0,
+                    j as f32 * GRID_SPACING - half_extent,
+                );
+                world.spawn_moving_half_cube(
+                    BasePosition(CENTER + offset),
+                    OscillateMotion {
+                        axis: Vec3::Y,
+                        phase: -offset.length() * WAVE_NUMBER,
+                        amplitude: WAVE_AMPLITUDE,
+                        speed: WAVE_SPEED,
+                    },
+                    HalfCube { scale: CUBE_SCALE },
+                );
+            }
+        }
+
+        let orbit = OrbitController {
+            min_pitch: MIN_PITCH,
+            max_pitch: MAX_PITCH,
+            min_distance: MIN_DISTANCE,
+            max_distance: MAX_DISTANCE,
+            auto_rotate: ORBIT_SPEED,
+            ..Default::default()
+        };
+        let mut circle = PathController::new(circle_path(), true);
+        circle.look_at = Some(CENTER + Vec3::Y);
+        let mut fly = FlyController::default();
+        fly.collision = Some(CameraCollision::new(vec![Box::new(WarehouseSolid)]));
+
+        let mut scene = Self {
+            knot: Rc::new(RefCell::new(trefoil_knot())),
+            world,
+            instance_data: Vec::with_capacity(GRID_N * GRID_N * 4),
+            descriptor: SceneDescriptor {
+                camera: CameraDescriptor {
+                    fov: FRAC_PI_3 * 2.0,
+                    ..Default::default()
+                },
+            },
+            wave_amplitude: WAVE_AMPLITUDE,
+            wave_speed: WAVE_SPEED,
+            rig: CameraRig::new(CameraMode::Orbit, fly, orbit, circle),
+        };
+        scene.opening_shot();
+        scene
+    }
+
+    /// Orbits [CENTER] from the starting angle and distance (keeping the orbit's other settings).
+    fn opening_shot(&mut self) {
+        let orbit = &mut self.rig.orbit;
+        orbit.target = CENTER + Vec3::Y;
+        orbit.yaw = START_YAW;
+        orbit.pitch = START_PITCH;
+        orbit.distance = START_DISTANCE;
+        self.rig
+            .reset(CameraMode::Orbit, &mut self.descriptor.camera);
+    }
+}
+
+impl Scene for Scene2 {
+    fn name(&self) -> &'static str {
+        "wave"
+    }
+
+    fn descriptor(&self) -> &SceneDescriptor {
+        &self.descriptor
+    }
+
+    fn descriptor_mut(&mut self) -> &mut SceneDescriptor {
+        &mut self.descriptor
+    }
+
+    fn update(&mut self, input: &FrameInput) {
+        // Sequence tracks (crate::sequencer) override the UI's values while they play.
+        if let Some(&amplitude) = input.parameters.get("wave_amplitude") {
+            self.wave_amplitude = amplitude;
+            self.world.moving_cubes.motion_amplitudes.fill(amplitude);
+        }
+        if let Some(&speed) = input.parameters.get("orbit_speed") {
+            self.rig.orbit.auto_rotate = speed;
+        }
+        self.rig.update(&mut self.descriptor.camera, input);
+    }
+
+    fn on_frame(
+        &mut self,
+        app: &mut App,
+        view: &ViewState,
+        pass: Option<&mut wgpu::RenderPass<'_>>,
+        is_gbuffer: bool,
+    ) {
+        let resources = FrameResources {
+            time_s: (app.current_timestamp / 1000.0) as f32,
+            view,
+            camera_position: view.inverse_view.col(3).truncate(),
+        };
+        half_cube_render_system(
+            &self.world,
+            &resources,
+            app,
+            &mut self.instance_data,
+            pass,
+            is_gbuffer,
+        );
+    }
+
+    fn render_passes(&mut self, graph: &mut RenderGraph) {
+        graph.add_pass("knot", Box::new(PolylinePass::new(self.knot.clone())));
+    }
+
+    /// Each visit opens on the same shot.
+    fn enter(&mut self, _app: &App) {
+        self.opening_shot();
+    }
+
+    /// Builds the knot's pipeline and uploads it now, so switching here doesn't stall a frame.
+    fn gpu_ready(&mut self, gpu: &GpuContext) {
+        self.knot.borrow_mut().prepare(
+            &gpu.device,
+            &gpu.queue,
+            wgpu::TextureFormat::Rgba16Float,
+            DepthTest::Attachment(wgpu::TextureFormat::Depth32Float),
+        );
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let cubes = &mut self.world.moving_cubes;
+        ui.label("Wave");
+        if ui
+            .add(egui::Slider::new(&mut self.wave_amplitude, 0.0..=1.5).text("Amplitude"))
+            .changed()
+        {
+            cubes.motion_amplitudes.fill(self.wave_amplitude);
+        }
+        if ui
+            .add(egui::Slider::new(&mut self.wave_speed, 0.0..=10.0).text("Speed"))
+            .changed()
+        {
+            cubes.motion_speeds.fill(self.wave_speed);
+        }
+        ui.separator();
+        self.rig.ui(ui);
+    }
+}
+
+/// Points of the path camera's loop around [CENTER].
+fn circle_path() -> Vec<Vec3> {
+    (0..PATH_POINTS)
+        .map(|i| {
+            let angle = i as f32 / PATH_POINTS as f32 * TAU;
+            CENTER
+                + Vec3::new(
+                    PATH_RADIUS * angle.sin(),
+                    3.0 + 2.0 * angle.cos(),
+                    PATH_RADIUS * angle.cos(),
+                )
+        })
+        .collect()
+}
+
+/// A (2, 3) torus knot floating above the grid, shading from cyan to magenta and back.
+fn trefoil_knot() -> Polylines {
+    const SEGMENTS: usize = 240;
+    let center = CENTER + Vec3::new(0.0, 3.5, 0.0);
+    let (points, colors): (Vec<Vec3>, Vec<Vec4>) = (0..SEGMENTS)
+        .map(|i| {
+            let t = i as f32 / SEGMENTS as f32 * TAU;
+            let radius = 2.0 + 0.8 * (3.0 * t).cos();
+            let point = center
+                + Vec3::new(
+                    radius * (2.0 * t).cos(),
+                    0.8 * (3.0 * t).sin(),
+                    radius * (2.0 * t).sin(),
+                );
+            let mix = 0.5 + 0.5 * t.cos();
+            (
+                point,
+                Vec4::new(0.2, 0.9, 1.0, 0.9).lerp(Vec4::new(1.0, 0.2, 0.8, 0.9), mix),
+            )
+        })
+        .unzip();
+    let mut lines = Polylines::new();
+    lines.add_strip_with_colors(
+        &points,
+        &colors,
+        &LineStyle {
+            width: 5.0,
+            join: LineJoin::Round,
+            cap: LineCap::Round,
+            closed: true,
+            ..Default::default()
+        },
+    );
+    lines
+}
@@ -0,0 +1,457 @@
+//! Demo timeline: a [Sequence] script of scene cues and keyframed parameter tracks, played back by
+//! a [Sequencer] on the frame clock ([crate::scene::FrameInput::timestamp]).
+//!
+//! Scripts are serde data (JSON on disk), times in seconds:
+//!
+//! ```json
+//! {
+//!   "duration": 30, "loop": true,
+//!   "scenes": [
+//!     { "time": 0, "scene": "fountain" },
+//!     { "time": 15, "scene": "wave", "transition": "wipe", "transition_time": 1.5 }
+//!   ],
+//!   "tracks": {
+//!     "camera.position": [
+//!       { "time": 0, "value": [-10, 1.7, -10], "interpolation": "bezier" },
+//!       { "time": 15, "value": [-4, 3, -2] }
+//!     ],
+//!     "camera.fov": [{ "time": 0, "value": 90 }, { "time": 15, "value": 60 }],
+//!     "wave_amplitude": [{ "time": 15, "value": 0.2 }, { "time": 30, "value": 1.0 }]
+//!   }
+//! }
+//! ```
+//!
+//! - A scene cue makes its scene current from `time` on (by name, see [crate::scene::Scene::name]);
+//!   playback reaching it switches with its transition, seeking or looping past it cuts.
+//! - A track holds its first value before its first key and its last value after the last one.
+//!   `interpolation` shapes the segment from that key to the next: `linear` (default), `bezier` (a
+//!   cubic through the keys, tangents from the neighbouring keys, flat at the first and last key)
+//!   or `step` (hold until the next key).
+//! - Tracks named in [CAMERA_POSITION], [CAMERA_TARGET] (`[x, y, z]`), [CAMERA_FOV] (horizontal,
+//!   degrees) and [BLOOM_INTENSITY] drive the engine; every other track is a named float scenes
+//!   read from [crate::scene::FrameInput::parameters].
+
+use std::collections::{BTreeMap, HashMap};
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::transition::{Transition, TransitionKind};
+
+/// Largest difference (seconds) between a followed position and the sequence's own clock that
+/// still counts as playing on rather than a jump (see [Sequencer::follow]).
+const FOLLOW_TOLERANCE_S: f64 = 0.1;
+
+/// Overrides the scene camera's position.
+pub const CAMERA_POSITION: &str = "camera.position";
+/// Overrides the scene camera's target.
+pub const CAMERA_TARGET: &str = "camera.target";
+/// Overrides the scene camera's horizontal FOV, in degrees.
+pub const CAMERA_FOV: &str = "camera.fov";
+/// Bloom multiplier for the screen pass ([crate::app::App::bloom_intensity]).
+pub const BLOOM_INTENSITY: &str = "bloom_intensity";
+
+/// A timeline script. See the module docs for the format.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sequence {
+    /// Length in seconds; 0 = up to the last key or cue.
+    pub duration: f32,
+    /// Start over at the end instead of holding the last frame.
+    #[serde(rename = "loop")]
+    pub looping: bool,
+    pub scenes: Vec<SceneCue>,
+    pub tracks: BTreeMap<String, Vec<Keyframe>>,
+}
+
+/// Makes `scene` current from `time` on.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneCue {
+    pub time: f32,
+    pub scene: String,
+    /// How playback reaching the cue switches (default `cut`).
+    #[serde(default)]
+    pub transition: TransitionKind,
+    /// Transition length in seconds.
+    #[serde(default = "default_transition_time")]
+    pub transition_time: f32,
+}
+
+const fn default_transition_time() -> f32 {
+    1.0
+}
+
+/// A track value: a number, or `[x, y, z]` for the camera tracks.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Scalar(f32),
+    Vector([f32; 3]),
+}
+
+impl Value {
+    /// As a vector; a number `x` is `(x, 0, 0)`.
+    const fn to_vec3(self) -> Vec3 {
+        match self {
+            Self::Scalar(x) => Vec3::new(x, 0.0, 0.0),
+            Self::Vector(v) => Vec3::from_array(v),
+        }
+    }
+}
+
+/// Shape of the segment from a key to the next one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    Bezier,
+    Step,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: f32,
+    pub value: Value,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+impl Sequence {
+    /// Playback length: [Sequence::duration], or the last key or cue when that is 0.
+    pub fn length(&self) -> f32 {
+        if self.duration > 0.0 {
+            return self.duration;
+        }
+        let cues = self.scenes.iter().map(|cue| cue.time);
+        let keys = self.tracks.values().flatten().map(|key| key.time);
+        cues.chain(keys).fold(0.0, f32::max)
+    }
+
+    /// Sorts cues and keys by time (scripts may list them in any order).
+    fn sorted(mut self) -> Self {
+        self.scenes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        for keys in self.tracks.values_mut() {
+            keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+        self
+    }
+}
+
+/// Samples a track (sorted by time) at `time`. `None` for an empty track.
+fn sample(keys: &[Keyframe], time: f32) -> Option<Vec3> {
+    let next = keys.partition_point(|key| key.time <= time);
+    if next == 0 || next == keys.len() {
+        return keys
+            .get(next.saturating_sub(1))
+            .map(|key| key.value.to_vec3());
+    }
+    let (k0, k1) = (&keys[next - 1], &keys[next]);
+    let (v0, v1) = (k0.value.to_vec3(), k1.value.to_vec3());
+    let span = k1.time - k0.time;
+    let s = if span > 0.0 {
+        (time - k0.time) / span
+    } else {
+        1.0
+    };
+    Some(match k0.interpolation {
+        Interpolation::Step => v0,
+        Interpolation::Linear => v0.lerp(v1, s),
+        Interpolation::Bezier => {
+            // Control points a third of the way along each key's tangent (cubic Hermite as Bezier).
+            let c0 = v0 + tangent(keys, next - 1) * (span / 3.0);
+            let c1 = v1 - tangent(keys, next) * (span / 3.0);
+            let t = 1.0 - s;
+            v0 * (t * t * t) + c0 * (3.0 * t * t * s) + c1 * (3.0 * t * s * s) + v1 * (s * s * s)
+        }
+    })
+}
+
+/// Slope at key `i` (per second) from its neighbours; flat at the ends.
+fn tangent(keys: &[Keyframe], i: usize) -> Vec3 {
+    if i == 0 || i + 1 >= keys.len() {
+        return Vec3::ZERO;
+    }
+    let (before, after) = (&keys[i - 1], &keys[i + 1]);
+    let span = after.time - before.time;
+    if span > 0.0 {
+        (after.value.to_vec3() - before.value.to_vec3()) / span
+    } else {
+        Vec3::ZERO
+    }
+}
+
+/// What a [Sequence] sets on one frame. Fields are `None` when the sequence has no such track.
+#[derive(Clone, Debug, Default)]
+pub struct SequenceFrame {
+    /// Sequence time, seconds.
+    pub time_s: f32,
+    /// Scene (by name) to switch to this frame, and how.
+    pub switch_to: Option<(String, Transition)>,
+    pub camera_position: Option<Vec3>,
+    pub camera_target: Option<Vec3>,
+    /// Horizontal FOV in radians.
+    pub camera_fov: Option<f32>,
+    pub bloom_intensity: Option<f32>,
+    /// The other tracks' values.
+    pub parameters: HashMap<String, f32>,
+}
+
+/// Plays a [Sequence]: advances with the frame clock while playing, and can be paused, sought and
+/// looped.
+pub struct Sequencer {
+    sequence: Sequence,
+    /// Sequence time, seconds.
+    time_s: f64,
+    playing: bool,
+    looping: bool,
+    /// Timestamp of the last update; time advances by the difference.
+    last_timestamp: Option<f64>,
+    /// Cue applied last (index into `sequence.scenes`).
+    cue: Option<usize>,
+    /// Set by a seek or loop: the next cue change cuts instead of transitioning.
+    jumped: bool,
+    /// Position to take at the next update instead of advancing (see [Sequencer::follow]).
+    follow: Option<f64>,
+}
+
+impl Sequencer {
+    /// Starts playing `sequence` from 0 at the next update.
+    pub fn new(sequence: Sequence) -> Self {
+        let looping = sequence.looping;
+        Self {
+            sequence: sequence.sorted(),
+            time_s: 0.0,
+            playing: true,
+            looping,
+            last_timestamp: None,
+            cue: None,
+            jumped: true,
+            follow: None,
+        }
+    }
+
+    pub const fn sequence(&self) -> &Sequence {
+        &self.sequence
+    }
+
+    /// Current sequence time, seconds.
+    pub const fn time(&self) -> f64 {
+        self.time_s
+    }
+
+    pub const fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub const fn set_playing(&mut self, playing: bool) {
+        self.playing = playing;
+    }
+
+    pub const fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    pub const fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Jumps to `time_s` (clamped to the sequence); the scene cue there applies with a cut.
+    pub fn seek(&mut self, time_s: f64) {
+        self.time_s = time_s.clamp(0.0, f64::from(self.sequence.length()));
+        self.jumped = true;
+    }
+
+    /// Makes the next update take the sequence to `time_s` rather than advance it by the clock,
+    /// e.g. to the music's position ([crate::audio]). Ignored while paused; a position away
+    /// from where playback would have got to applies like a seek.
+    pub const fn follow(&mut self, time_s: f64) {
+        self.follow = Some(time_s);
+    }
+
+    /// Advances to `timestamp` (milliseconds, the frame clock) and evaluates the sequence there.
+    pub fn update(&mut self, timestamp: f64) -> SequenceFrame {
+        let elapsed_ms = self
+            .last_timestamp
+            .map_or(0.0, |last| (timestamp - last).max(0.0));
+        self.last_timestamp = Some(timestamp);
+        let length = f64::from(self.sequence.length());
+        let follow = self.follow.take();
+        if self.playing {
+            let advanced = self.time_s + elapsed_ms / 1000.0;
+            self.time_s = match follow {
+                Some(time_s) => {
+                    let time_s = if self.looping && length > 0.0 {
+                        time_s % length
+                    } else {
+                        time_s
+                    };
+                    self.jumped |= (time_s - advanced).abs() > FOLLOW_TOLERANCE_S;
+                    time_s
+                }
+                None => advanced,
+            };
+        }
+        if self.time_s >= length {
+            if self.looping && length > 0.0 {
+                self.time_s %= length;
+                self.jumped = true;
+            } else {
+                self.time_s = length;
+            }
+        }
+        self.evaluate()
+    }
+
+    fn evaluate(&mut self) -> SequenceFrame {
+        let time = self.time_s as f32;
+        let mut frame = SequenceFrame {
+            time_s: time,
+            ..Default::default()
+        };
+
+        let cues = &self.sequence.scenes;
+        let cue = cues.partition_point(|cue| cue.time <= time).checked_sub(1);
+        if cue != self.cue {
+            if let Some(cue) = cue.map(|i| &cues[i]) {
+                let transition = if self.jumped {
+                    Transition::CUT
+                } else {
+                    Transition {
+                        kind: cue.transition,
+                        duration_ms: f64::from(cue.transition_time) * 1000.0,
+                    }
+                };
+                frame.switch_to = Some((cue.scene.clone(), transition));
+            }
+            self.cue = cue;
+        }
+        self.jumped = false;
+
+        for (name, keys) in &self.sequence.tracks {
+            let Some(value) = sample(keys, time) else {
+                continue;
+            };
+            match name.as_str() {
+                CAMERA_POSITION => frame.camera_position = Some(value),
+                CAMERA_TARGET => frame.camera_target = Some(value),
+                CAMERA_FOV => frame.camera_fov = Some(value.x.to_radians()),
+                BLOOM_INTENSITY => frame.bloom_intensity = Some(value.x),
+                _ => {
+                    frame.parameters.insert(name.clone(), value.x);
+                }
+            }
+        }
+        frame
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Sequence {
+    /// Reads a JSON script.
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("reading {}: {e}", path.display()))?;
+        serde_json::from_str(&text).map_err(|e| format!("parsing {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+// Held and keyed values come back exactly.
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::*;
+
+    fn key(time: f32, value: f32, interpolation: Interpolation) -> Keyframe {
+        Keyframe {
+            time,
+            value: Value::Scalar(value),
+            interpolation,
+        }
+    }
+
+    fn at(keys: &[Keyframe], time: f32) -> f32 {
+        sample(keys, time).unwrap().x
+    }
+
+    fn sequencer(json: &str) -> Sequencer {
+        Sequencer::new(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn tracks_hold_outside_their_keys() {
+        let keys = [
+            key(1.0, 2.0, Interpolation::Linear),
+            key(3.0, 4.0, Interpolation::Linear),
+        ];
+        assert_eq!(at(&keys, 0.0), 2.0);
+        assert_eq!(at(&keys, 2.0), 3.0);
+        assert_eq!(at(&keys, 5.0), 4.0);
+        assert_eq!(sample(&[], 1.0), None);
+
+        let steps = [
+            key(0.0, 1.0, Interpolation::Step),
+            key(1.0, 5.0, Interpolation::Step),
+        ];
+        assert_eq!(at(&steps, 0.99), 1.0);
+        assert_eq!(at(&steps, 1.0), 5.0);
+    }
+
+    #[test]
+    fn bezier_is_flat_at_the_ends_and_smooth_through_keys() {
+        let keys = [
+            key(0.0, 0.0, Interpolation::Bezier),
+            key(1.0, 1.0, Interpolation::Bezier),
+            key(2.0, 2.0, Interpolation::Bezier),
+        ];
+        let slope = |time: f32| (at(&keys, time + 1e-3) - at(&keys, time - 1e-3)) / 2e-3;
+        assert!(slope(1e-3).abs() < 0.01);
+        assert!(slope(2.0 - 1e-3).abs() < 0.01);
+        // The middle key's tangent comes from its neighbours: (2 - 0) / (2 - 0).
+        assert!((slope(1.0) - 1.0).abs() < 0.01);
+        assert_eq!(at(&keys, 1.0), 1.0);
+    }
+
+    #[test]
+    fn playback_reaching_a_cue_transitions() {
+        let mut sequencer = sequencer(
+            r#"{ "scenes": [
+                { "time": 0, "scene": "a" },
+                { "time": 1, "scene": "b", "transition": "fade", "transition_time": 0.5 }
+            ] }"#,
+        );
+        // The first cue applies with a cut, like a seek.
+        let frame = sequencer.update(1000.0);
+        assert_eq!(frame.switch_to, Some(("a".to_string(), Transition::CUT)));
+        assert_eq!(sequencer.update(1500.0).switch_to, None);
+        let fade = Transition {
+            kind: TransitionKind::Fade,
+            duration_ms: 500.0,
+        };
+        assert_eq!(
+            sequencer.update(2100.0).switch_to,
+            Some(("b".to_string(), fade))
+        );
+        // Without `loop`, the end holds.
+        assert_eq!(sequencer.update(5000.0).time_s, 1.0);
+    }
+
+    #[test]
+    fn seeking_and_looping_cut() {
+        let mut sequencer = sequencer(
+            r#"{ "duration": 2, "loop": true, "scenes": [
+                { "time": 0, "scene": "a" },
+                { "time": 1, "scene": "b", "transition": "wipe" }
+            ] }"#,
+        );
+        sequencer.update(0.0);
+        sequencer.seek(1.5);
+        assert_eq!(
+            sequencer.update(0.0).switch_to,
+            Some(("b".to_string(), Transition::CUT))
+        );
+        let frame = sequencer.update(1000.0);
+        assert!((frame.time_s - 0.5).abs() < 1e-6);
+        assert_eq!(frame.switch_to, Some(("a".to_string(), Transition::CUT)));
+    }
+}
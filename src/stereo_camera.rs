@@ -107,7 +107,6 @@ impl StereoCamera {
     }
 
     /// Inter-pupillary distance in world units.
-    #[allow(dead_code)]
     pub fn eye_distance(&self) -> f32 {
         self.ipd
     }
@@ -129,7 +128,6 @@ impl StereoCamera {
     }
 
     /// Convergence distance in world units.
-    #[allow(dead_code)]
     pub fn convergence_distance(&self) -> f32 {
         self.convergence_distance
     }
@@ -139,13 +137,11 @@ impl StereoCamera {
         self.update_projection();
     }
 
-    #[allow(dead_code)]
     pub fn set_fovy(&mut self, fovy: f32) {
         self.fovy = fovy;
         self.update_projection();
     }
 
-    #[allow(dead_code)]
     pub fn fovy(&self) -> f32 {
         self.fovy
     }
@@ -271,4 +267,3 @@ impl StereoCamera {
         ]
     }
 }
-
@@ -0,0 +1,286 @@
+//! Font atlas: glyphs of a TrueType font rasterized into a single-channel signed distance field,
+//! plus the metrics to lay out text with kerning.
+//!
+//! Printable ASCII is rasterized up front; other characters are added on first use. Each glyph is
+//! rendered at [SUPERSAMPLE]x, thresholded, and run through an exact Euclidean distance transform
+//! (Felzenszwalb & Huttenlocher) for the inside and outside; the box-filtered difference is stored
+//! as `0.5 - distance / (2 * SDF_SPREAD)`, so 0.5 is the outline and higher values are inside.
+
+use std::collections::HashMap;
+
+use ab_glyph::{point, Font as _, FontRef, GlyphId};
+use glam::Vec2;
+
+/// DejaVu Sans Mono (Bitstream Vera license, see `LICENSE-DejaVu.txt`).
+const BUILTIN_FONT: &[u8] = include_bytes!("DejaVuSansMono.ttf");
+
+/// Atlas side in texels (`R8Unorm`).
+pub(super) const ATLAS_SIZE: u32 = 512;
+/// Line height (ascender to descender) glyphs are rasterized at, in atlas texels.
+const SDF_SIZE: f32 = 32.0;
+/// Distance encoded on either side of the outline, in atlas texels. `SPREAD` in `text.wgsl`.
+const SDF_SPREAD: f32 = 4.0;
+/// Rasterization supersampling per axis for the distance transform.
+const SUPERSAMPLE: u32 = 4;
+/// Empty texels between glyphs, so filtering never reads a neighbour.
+const GAP: u32 = 1;
+/// Stands in for "no seed" in the distance transform.
+const FAR: f32 = 1e20;
+
+/// A glyph quad from [FontAtlas::layout], in units of the line height relative to the text's
+/// top-left corner (y down).
+#[derive(Clone, Copy, Debug)]
+pub(super) struct PlacedGlyph {
+    pub min: Vec2,
+    pub size: Vec2,
+    /// Atlas rect (top-left, size) in UV.
+    pub uv_min: Vec2,
+    pub uv_size: Vec2,
+}
+
+/// Where a rasterized glyph is in the atlas and how it sits on the pen position.
+#[derive(Clone, Copy, Debug)]
+struct GlyphEntry {
+    /// Top-left of the quad relative to the pen on the baseline, in atlas texels (y down).
+    offset: Vec2,
+    /// Quad size in atlas texels.
+    size: Vec2,
+    /// Top-left in the atlas, in texels.
+    texel: Vec2,
+}
+
+/// The built-in font's SDF atlas. [FontAtlas::take_dirty] hands out the texels when
+/// glyphs were added.
+pub(super) struct FontAtlas {
+    font: FontRef<'static>,
+    /// `None`: nothing to draw (whitespace) or the atlas is full.
+    glyphs: HashMap<GlyphId, Option<GlyphEntry>>,
+    pixels: Vec<u8>,
+    /// Shelf packer: next free texel and the current row's height.
+    cursor: (u32, u32),
+    row_height: u32,
+    dirty: bool,
+}
+
+impl FontAtlas {
+    pub fn new() -> Self {
+        let font = FontRef::try_from_slice(BUILTIN_FONT).expect("built-in font");
+        let mut atlas = Self {
+            font,
+            glyphs: HashMap::new(),
+            pixels: vec![0; (ATLAS_SIZE * ATLAS_SIZE) as usize],
+            cursor: (0, 0),
+            row_height: 0,
+            dirty: true,
+        };
+        for c in ' '..='~' {
+            atlas.glyph(atlas.font.glyph_id(c));
+        }
+        atlas
+    }
+
+    /// Calls `place` for every visible glyph of `text` (`\n` starts a new line) and returns the
+    /// extent (widest line, line count), both in units of the line height.
+    pub fn layout(&mut self, text: &str, mut place: impl FnMut(PlacedGlyph)) -> Vec2 {
+        let font = &self.font;
+        let scale = SDF_SIZE / font.height_unscaled();
+        let (ascent, line_advance) = (
+            font.ascent_unscaled() * scale,
+            SDF_SIZE + font.line_gap_unscaled() * scale,
+        );
+        let mut pen = Vec2::new(0.0, ascent);
+        let mut width: f32 = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            if c == '\n' {
+                width = width.max(pen.x);
+                pen = Vec2::new(0.0, pen.y + line_advance);
+                previous = None;
+                continue;
+            }
+            if c.is_control() {
+                continue;
+            }
+            let id = self.font.glyph_id(c);
+            if let Some(previous) = previous {
+                pen.x += self.font.kern_unscaled(previous, id) * scale;
+            }
+            if let Some(entry) = self.glyph(id) {
+                place(PlacedGlyph {
+                    min: (pen + entry.offset) / SDF_SIZE,
+                    size: entry.size / SDF_SIZE,
+                    uv_min: entry.texel / ATLAS_SIZE as f32,
+                    uv_size: entry.size / ATLAS_SIZE as f32,
+                });
+            }
+            pen.x += self.font.h_advance_unscaled(id) * scale;
+            previous = Some(id);
+        }
+        let lines = (pen.y - ascent) / line_advance + 1.0;
+        Vec2::new(width.max(pen.x) / SDF_SIZE, lines * line_advance / SDF_SIZE)
+    }
+
+    /// The atlas texels if glyphs were added since the last call.
+    pub fn take_dirty(&mut self) -> Option<&[u8]> {
+        std::mem::take(&mut self.dirty).then_some(self.pixels.as_slice())
+    }
+
+    /// Atlas entry for `id`, rasterizing it on first use.
+    fn glyph(&mut self, id: GlyphId) -> Option<GlyphEntry> {
+        if let Some(&entry) = self.glyphs.get(&id) {
+            return entry;
+        }
+        let entry = self.rasterize(id);
+        self.glyphs.insert(id, entry);
+        entry
+    }
+
+    fn rasterize(&mut self, id: GlyphId) -> Option<GlyphEntry> {
+        let ss = SUPERSAMPLE as i32;
+        let glyph = id.with_scale_and_position(SDF_SIZE * SUPERSAMPLE as f32, point(0.0, 0.0));
+        let outline = self.font.outline_glyph(glyph)?;
+        let bounds = outline.px_bounds();
+        // Atlas texels covering the outline plus the spread; the supersampled grid is
+        // aligned to them.
+        let spread = SDF_SPREAD as i32;
+        let min_x = (bounds.min.x as i32).div_euclid(ss) - spread;
+        let min_y = (bounds.min.y as i32).div_euclid(ss) - spread;
+        let max_x = (bounds.max.x as i32 + ss - 1).div_euclid(ss) + spread;
+        let max_y = (bounds.max.y as i32 + ss - 1).div_euclid(ss) + spread;
+        let (width, height) = ((max_x - min_x) as u32, (max_y - min_y) as u32);
+        let Some((atlas_x, atlas_y)) = self.allocate(width, height) else {
+            log!("[Text] Font atlas full; glyph {} not drawn.", id.0);
+            return None;
+        };
+
+        let (hi_width, hi_height) = (
+            (width * SUPERSAMPLE) as usize,
+            (height * SUPERSAMPLE) as usize,
+        );
+        let (shift_x, shift_y) = (
+            bounds.min.x as i32 - min_x * ss,
+            bounds.min.y as i32 - min_y * ss,
+        );
+        let mut inside = vec![false; hi_width * hi_height];
+        outline.draw(|x, y, coverage| {
+            let (x, y) = ((x as i32 + shift_x) as usize, (y as i32 + shift_y) as usize);
+            if x < hi_width && y < hi_height && coverage >= 0.5 {
+                inside[y * hi_width + x] = true;
+            }
+        });
+        let field = signed_distance(&inside, hi_width, hi_height);
+
+        let samples = (SUPERSAMPLE * SUPERSAMPLE) as f32;
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let mut sum = 0.0;
+                for sy in 0..SUPERSAMPLE as usize {
+                    let row = (y * SUPERSAMPLE as usize + sy) * hi_width + x * SUPERSAMPLE as usize;
+                    sum += field[row..row + SUPERSAMPLE as usize].iter().sum::<f32>();
+                }
+                let distance = sum / samples / SUPERSAMPLE as f32;
+                let value = (0.5 - distance / (2.0 * SDF_SPREAD)).clamp(0.0, 1.0);
+                let texel = (atlas_y as usize + y) * ATLAS_SIZE as usize + atlas_x as usize + x;
+                self.pixels[texel] = (value * 255.0).round() as u8;
+            }
+        }
+        self.dirty = true;
+        Some(GlyphEntry {
+            offset: Vec2::new(min_x as f32, min_y as f32),
+            size: Vec2::new(width as f32, height as f32),
+            texel: Vec2::new(atlas_x as f32, atlas_y as f32),
+        })
+    }
+
+    /// Shelf-packs a `width` x `height` rect; `None` when the atlas is full.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if self.cursor.0 + width > ATLAS_SIZE {
+            self.cursor = (0, self.cursor.1 + self.row_height + GAP);
+            self.row_height = 0;
+        }
+        if width > ATLAS_SIZE || self.cursor.1 + height > ATLAS_SIZE {
+            return None;
+        }
+        let position = self.cursor;
+        self.cursor.0 += width + GAP;
+        self.row_height = self.row_height.max(height);
+        Some(position)
+    }
+}
+
+/// Distance in pixels from each pixel center to the outline between `inside` and outside pixels:
+/// positive outside, negative inside.
+fn signed_distance(inside: &[bool], width: usize, height: usize) -> Vec<f32> {
+    let to_inside = distance_transform(width, height, |i| inside[i]);
+    let to_outside = distance_transform(width, height, |i| !inside[i]);
+    inside
+        .iter()
+        .zip(to_inside.iter().zip(&to_outside))
+        .map(|(&inside, (&to_inside, &to_outside))| {
+            if inside {
+                0.5 - to_outside
+            } else {
+                to_inside - 0.5
+            }
+        })
+        .collect()
+}
+
+/// Euclidean distance from each pixel to the nearest pixel where `seed` holds.
+fn distance_transform(width: usize, height: usize, seed: impl Fn(usize) -> bool) -> Vec<f32> {
+    let mut grid: Vec<f32> = (0..width * height)
+        .map(|i| if seed(i) { 0.0 } else { FAR })
+        .collect();
+    let n = width.max(height);
+    let (mut line, mut out) = (vec![0.0; n], vec![0.0; n]);
+    let (mut hull, mut bounds) = (vec![0; n], vec![0.0; n + 1]);
+    for x in 0..width {
+        for y in 0..height {
+            line[y] = grid[y * width + x];
+        }
+        distance_transform_1d(&line[..height], &mut out[..height], &mut hull, &mut bounds);
+        for y in 0..height {
+            grid[y * width + x] = out[y];
+        }
+    }
+    for y in 0..height {
+        let row = &mut grid[y * width..(y + 1) * width];
+        line[..width].copy_from_slice(row);
+        distance_transform_1d(&line[..width], row, &mut hull, &mut bounds);
+    }
+    for d in &mut grid {
+        *d = d.sqrt();
+    }
+    grid
+}
+
+/// Squared distance transform of a sampled function `f` (lower envelope of parabolas rooted at
+/// each sample). `hull` and `bounds` are scratch space of `f.len()` and `f.len() + 1`.
+fn distance_transform_1d(f: &[f32], out: &mut [f32], hull: &mut [usize], bounds: &mut [f32]) {
+    let intersection = |q: usize, p: usize| {
+        ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2.0 * (q - p) as f32)
+    };
+    let mut k = 0;
+    hull[0] = 0;
+    bounds[0] = f32::NEG_INFINITY;
+    bounds[1] = f32::INFINITY;
+    for q in 1..f.len() {
+        let mut s = intersection(q, hull[k]);
+        while s <= bounds[k] {
+            k -= 1;
+            s = intersection(q, hull[k]);
+        }
+        k += 1;
+        hull[k] = q;
+        bounds[k] = s;
+        bounds[k + 1] = f32::INFINITY;
+    }
+    k = 0;
+    for (q, out) in out.iter_mut().enumerate() {
+        while bounds[k + 1] < q as f32 {
+            k += 1;
+        }
+        let offset = q as f32 - hull[k] as f32;
+        *out = offset * offset + f[hull[k]];
+    }
+}
@@ -0,0 +1,555 @@
+//! Text on the canvas: [draw_text] in screen pixels and [draw_text_world] as camera-facing labels.
+//!
+//! Like [crate::debug_draw], calls queue text for the current frame only (call them every frame,
+//! from [crate::scene::Scene::update]). The render graph's built-in `text` pass draws it over the
+//! final image, after tonemapping, so colors come out as given: world labels first, depth-tested
+//! against the scene, then screen text on top. In stereo, every view gets both; screen positions
+//! are relative to each eye's viewport.
+//!
+//! Glyphs come from a signed distance field atlas of the built-in monospace font (DejaVu Sans Mono,
+//! see [font]), so text stays sharp at any size, and get a thin dark outline to read over anything.
+//! [draw_rect] queues solid screen rectangles in the same pass, for panels and bars behind text.
+
+mod font;
+
+use std::cell::RefCell;
+
+use glam::{Vec2, Vec3, Vec4};
+use wgpu::RenderPass;
+
+use crate::gpu::stats;
+use crate::polyline::{DepthTest, PipelineDepth};
+use crate::stereo_camera::Eye;
+use crate::view::ViewState;
+use font::{FontAtlas, PlacedGlyph, ATLAS_SIZE};
+
+const TEXT_WGSL: &str = include_str!("../wgsl/text.wgsl");
+
+/// Glyphs the instance buffer holds before its first resize.
+const INITIAL_CAPACITY: usize = 1024;
+/// Uniform slots, one per [Eye].
+const UNIFORM_SLOTS: u64 = 3;
+/// Dynamic uniform offsets must be 256-byte aligned.
+const UNIFORM_STRIDE: u64 = 256;
+/// [GlyphInstance::flags]: anchored in the world rather than on screen.
+const WORLD: u32 = 1;
+/// [GlyphInstance::flags]: filled with the color, no glyph.
+const SOLID: u32 = 2;
+
+/// Queues `text` with its top-left corner at `position`, in pixels from the viewport's top-left.
+/// `size` is the line height in pixels.
+pub fn draw_text(position: Vec2, size: f32, color: Vec4, text: &str) {
+    TEXT.with_borrow_mut(|queue| queue.add(position.extend(0.0), size, color, text, false));
+}
+
+/// Queues `text` as a label facing the camera, top-left corner at `position`. `size` is the line
+/// height in world units.
+pub fn draw_text_world(position: Vec3, size: f32, color: Vec4, text: &str) {
+    TEXT.with_borrow_mut(|queue| queue.add(position, size, color, text, true));
+}
+
+/// Queues a filled rectangle with its top-left corner at `position` and `size`, in pixels from the
+/// viewport's top-left. Drawn in call order with [draw_text], so queue backgrounds first.
+pub fn draw_rect(position: Vec2, size: Vec2, color: Vec4) {
+    TEXT.with_borrow_mut(|queue| {
+        queue.screen.push(GlyphInstance {
+            anchor: [0.0; 3],
+            flags: SOLID,
+            rect: [position.x, position.y, size.x, size.y],
+            uv: [0.0; 4],
+            color: color.to_array(),
+        });
+    });
+}
+
+/// Width of the widest line and total height of `text` drawn at `size`.
+pub fn measure_text(size: f32, text: &str) -> Vec2 {
+    TEXT.with_borrow_mut(|queue| queue.atlas.layout(text, |_| ()) * size)
+}
+
+/// One glyph quad. Matches `Glyph` in `text.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GlyphInstance {
+    /// World position of the text's top-left corner ([WORLD]); unused for screen text.
+    anchor: [f32; 3],
+    flags: u32,
+    /// Quad (x, y, width, height) from the anchor: pixels on screen, world units along the
+    /// camera's right / down axes in the world.
+    rect: [f32; 4],
+    /// Atlas rect (u, v, width, height).
+    uv: [f32; 4],
+    color: [f32; 4],
+}
+
+const GLYPH_STRIDE: u64 = std::mem::size_of::<GlyphInstance>() as u64;
+
+/// `Uniforms` in `text.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct TextUniforms {
+    view_projection: [f32; 16],
+    right: [f32; 4],
+    up: [f32; 4],
+    viewport: [f32; 4],
+    depth_map: [f32; 4],
+    flip_y: f32,
+    _pad: [f32; 3],
+}
+
+thread_local! {
+    static TEXT: RefCell<TextQueue> = RefCell::new(TextQueue::new());
+}
+
+/// This frame's glyphs (world labels, then screen text) and the font atlas.
+struct TextQueue {
+    atlas: FontAtlas,
+    world: Vec<GlyphInstance>,
+    screen: Vec<GlyphInstance>,
+    gpu: Option<TextGpu>,
+}
+
+impl TextQueue {
+    fn new() -> Self {
+        Self {
+            atlas: FontAtlas::new(),
+            world: Vec::new(),
+            screen: Vec::new(),
+            gpu: None,
+        }
+    }
+
+    fn add(&mut self, position: Vec3, size: f32, color: Vec4, text: &str, world: bool) {
+        let glyphs = if world {
+            &mut self.world
+        } else {
+            &mut self.screen
+        };
+        let offset = if world {
+            Vec2::ZERO
+        } else {
+            position.truncate()
+        };
+        self.atlas.layout(text, |glyph: PlacedGlyph| {
+            let min = offset + glyph.min * size;
+            let size = glyph.size * size;
+            glyphs.push(GlyphInstance {
+                anchor: if world { position.to_array() } else { [0.0; 3] },
+                flags: if world { WORLD } else { 0 },
+                rect: [min.x, min.y, size.x, size.y],
+                uv: [
+                    glyph.uv_min.x,
+                    glyph.uv_min.y,
+                    glyph.uv_size.x,
+                    glyph.uv_size.y,
+                ],
+                color: color.to_array(),
+            });
+        });
+    }
+}
+
+/// Creates the GPU state for drawing into `color_format` with `depth`, and uploads the atlas (when
+/// glyphs were added) and this frame's glyphs. Call before the pass that draws them.
+pub(crate) fn prepare(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    color_format: wgpu::TextureFormat,
+    depth: DepthTest<'_>,
+) {
+    TEXT.with_borrow_mut(|text| {
+        let gpu = text.gpu.get_or_insert_with(|| TextGpu::new(device, queue));
+        gpu.ensure_pipeline(device, color_format, PipelineDepth::from(depth));
+        if let Some(pixels) = text.atlas.take_dirty() {
+            gpu.upload_atlas(pixels);
+        }
+        gpu.depth = match depth {
+            DepthTest::Texture { view, flipped } => {
+                let size = view.texture().size();
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("text_depth"),
+                    layout: &gpu.depth_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    }],
+                });
+                Some((
+                    bind_group,
+                    Vec2::new(size.width as f32, size.height as f32),
+                    flipped,
+                ))
+            }
+            _ => None,
+        };
+        gpu.upload(device, &text.world, &text.screen);
+    });
+}
+
+/// Draws this frame's text for `view` into `viewport` (x, y, width, height in target pixels; the
+/// view's own viewport may be at a lower render scale). Same format and depth test as [prepare].
+pub(crate) fn draw(
+    pass: &mut RenderPass<'_>,
+    view: &ViewState,
+    viewport: [f32; 4],
+    color_format: wgpu::TextureFormat,
+    depth: DepthTest<'_>,
+) {
+    TEXT.with_borrow(|text| {
+        if let Some(gpu) = &text.gpu {
+            gpu.draw(
+                pass,
+                view,
+                viewport,
+                color_format,
+                PipelineDepth::from(depth),
+            );
+        }
+    });
+}
+
+/// Whether anything was queued this frame.
+pub(crate) fn is_empty() -> bool {
+    TEXT.with_borrow(|text| text.world.is_empty() && text.screen.is_empty())
+}
+
+/// Drops this frame's text.
+pub(crate) fn end_frame() {
+    TEXT.with_borrow_mut(|text| {
+        text.world.clear();
+        text.screen.clear();
+    });
+}
+
+/// Atlas, instance and uniform buffers, plus one pipeline per (color format, depth test).
+struct TextGpu {
+    queue: wgpu::Queue,
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    /// Adds group 1 (scene depth) for [DepthTest::Texture].
+    depth_texture_pipeline_layout: wgpu::PipelineLayout,
+    depth_layout: wgpu::BindGroupLayout,
+    pipelines: Vec<(wgpu::TextureFormat, PipelineDepth, wgpu::RenderPipeline)>,
+    atlas: wgpu::Texture,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// This frame's [DepthTest::Texture]: bind group, texture size and whether the
+    /// target is flipped.
+    depth: Option<(wgpu::BindGroup, Vec2, bool)>,
+    instance_buffer: wgpu::Buffer,
+    capacity: usize,
+    glyph_count: u32,
+}
+
+impl TextGpu {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("text"),
+            source: wgpu::ShaderSource::Wgsl(TEXT_WGSL.into()),
+        });
+        let atlas = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("text_atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("text_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("text_bind_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<TextUniforms>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let depth_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("text_depth_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("text_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            immediate_size: 0,
+        });
+        let depth_texture_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("text_depth_texture_layout"),
+                bind_group_layouts: &[&bind_group_layout, &depth_layout],
+                immediate_size: 0,
+            });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("text_uniforms"),
+            size: UNIFORM_SLOTS * UNIFORM_STRIDE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let atlas_view = atlas.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("text_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &uniform_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<TextUniforms>() as u64),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        Self {
+            queue: queue.clone(),
+            shader,
+            pipeline_layout,
+            depth_texture_pipeline_layout,
+            depth_layout,
+            pipelines: Vec::new(),
+            atlas,
+            uniform_buffer,
+            bind_group,
+            depth: None,
+            instance_buffer: create_instance_buffer(device, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            glyph_count: 0,
+        }
+    }
+
+    fn pipeline(
+        &self,
+        color_format: wgpu::TextureFormat,
+        depth: PipelineDepth,
+    ) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines
+            .iter()
+            .find(|(color, d, _)| *color == color_format && *d == depth)
+            .map(|(_, _, pipeline)| pipeline)
+    }
+
+    fn ensure_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        depth: PipelineDepth,
+    ) {
+        if self.pipeline(color_format, depth).is_some() {
+            return;
+        }
+        let attributes = wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Uint32,
+            2 => Float32x4,
+            3 => Float32x4,
+            4 => Float32x4,
+        ];
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("text"),
+            layout: Some(if depth == PipelineDepth::Texture {
+                &self.depth_texture_pipeline_layout
+            } else {
+                &self.pipeline_layout
+            }),
+            vertex: wgpu::VertexState {
+                module: &self.shader,
+                entry_point: Some("vs"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: GLYPH_STRIDE,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &attributes,
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.shader,
+                entry_point: Some(if depth == PipelineDepth::Texture {
+                    "fs_depth_texture"
+                } else {
+                    "fs"
+                }),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // Screen glyphs sit at the near plane (depth 1), so they always pass.
+            depth_stencil: match depth {
+                PipelineDepth::Attachment(format) => Some(wgpu::DepthStencilState {
+                    format,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::GreaterEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                _ => None,
+            },
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview_mask: None,
+        });
+        self.pipelines.push((color_format, depth, pipeline));
+    }
+
+    fn upload_atlas(&self, pixels: &[u8]) {
+        self.queue.write_texture(
+            self.atlas.as_image_copy(),
+            pixels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(ATLAS_SIZE),
+                rows_per_image: Some(ATLAS_SIZE),
+            },
+            wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Copies `world` then `screen` into the instance buffer, doubling it until they fit.
+    fn upload(&mut self, device: &wgpu::Device, world: &[GlyphInstance], screen: &[GlyphInstance]) {
+        let count = world.len() + screen.len();
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, self.capacity);
+        }
+        if !world.is_empty() {
+            self.queue
+                .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(world));
+        }
+        if !screen.is_empty() {
+            let offset = world.len() as u64 * GLYPH_STRIDE;
+            self.queue
+                .write_buffer(&self.instance_buffer, offset, bytemuck::cast_slice(screen));
+        }
+        self.glyph_count = count as u32;
+    }
+
+    fn draw(
+        &self,
+        pass: &mut RenderPass<'_>,
+        view: &ViewState,
+        viewport: [f32; 4],
+        color_format: wgpu::TextureFormat,
+        depth: PipelineDepth,
+    ) {
+        if self.glyph_count == 0 {
+            return;
+        }
+        let Some(pipeline) = self.pipeline(color_format, depth) else {
+            return;
+        };
+        let depth_texture = self
+            .depth
+            .as_ref()
+            .filter(|_| depth == PipelineDepth::Texture);
+        if depth == PipelineDepth::Texture && depth_texture.is_none() {
+            return;
+        }
+        // Depth texel from a target pixel: scaled to the depth's size, mirrored if the
+        // target is flipped.
+        let (depth_map, flipped) =
+            depth_texture.map_or(([1.0, 1.0, 0.0, 0.0], false), |&(_, size, flipped)| {
+                let scale = size / Vec2::new(viewport[2], viewport[3]);
+                if flipped {
+                    ([scale.x, -scale.y, 0.0, size.y], true)
+                } else {
+                    ([scale.x, scale.y, 0.0, 0.0], false)
+                }
+            });
+        let slot = match view.eye {
+            Eye::Mono => 0,
+            Eye::Left => 1,
+            Eye::Right => 2,
+        };
+        // World labels face the camera and use the jitter-free matrix, like everything after TAA.
+        let camera = view.inverse_view;
+        let uniforms = TextUniforms {
+            view_projection: view.view_projection_no_jitter.to_cols_array(),
+            right: camera.x_axis.to_array(),
+            up: camera.y_axis.to_array(),
+            viewport,
+            depth_map,
+            flip_y: if flipped { -1.0 } else { 1.0 },
+            _pad: [0.0; 3],
+        };
+        let offset = slot * UNIFORM_STRIDE;
+        self.queue
+            .write_buffer(&self.uniform_buffer, offset, bytemuck::bytes_of(&uniforms));
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[offset as u32]);
+        if let Some((bind_group, _, _)) = depth_texture {
+            pass.set_bind_group(1, bind_group, &[]);
+        }
+        pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        pass.draw(0..6, 0..self.glyph_count);
+        stats::record_draw(self.glyph_count);
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("text_glyphs"),
+        size: capacity as u64 * GLYPH_STRIDE,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
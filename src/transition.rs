@@ -0,0 +1,71 @@
+//! Scene transitions: how the screen pass blends from the outgoing scene to the incoming one.
+//!
+//! When [crate::app::Runner::switch_scene] starts a transition, the outgoing scene keeps updating
+//! and the render graph keeps drawing it into its own G-buffer, TAA history and bloom targets (see
+//! [crate::render_graph::RenderGraph::start_transition]); the `screen` pass composites its resolve
+//! and bloom with the incoming scene's for `duration_ms`. Transitions need the post-processing path
+//! (`screen`); in stereo, forward or with post off, switches cut (and say so in the log).
+
+use serde::{Deserialize, Serialize};
+
+/// How the outgoing image gives way to the incoming one. Serialized as `"cut"`, `"fade"`, `"wipe"`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+    /// Switch on the next frame.
+    #[default]
+    Cut,
+    /// Cross-fade.
+    Fade,
+    /// The incoming scene slides in from the left behind a soft edge.
+    Wipe,
+}
+
+impl TransitionKind {
+    /// Parses `"cut"`, `"fade"` or `"wipe"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cut" => Some(Self::Cut),
+            "fade" => Some(Self::Fade),
+            "wipe" => Some(Self::Wipe),
+            _ => None,
+        }
+    }
+
+    /// Value of `transition_mode` in `screen_parity.wgsl` (0 = no transition).
+    pub(crate) const fn shader_mode(self) -> u32 {
+        match self {
+            Self::Cut => 0,
+            Self::Fade => 1,
+            Self::Wipe => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub duration_ms: f64,
+}
+
+impl Default for Transition {
+    /// One-second cross-fade (scene switch keys).
+    fn default() -> Self {
+        Self {
+            kind: TransitionKind::Fade,
+            duration_ms: 1000.0,
+        }
+    }
+}
+
+impl Transition {
+    pub const CUT: Self = Self {
+        kind: TransitionKind::Cut,
+        duration_ms: 0.0,
+    };
+
+    /// Whether there is anything to blend (a timed fade or wipe).
+    pub fn is_blended(&self) -> bool {
+        self.kind != TransitionKind::Cut && self.duration_ms > 0.0
+    }
+}
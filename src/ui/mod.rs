@@ -0,0 +1,382 @@
+//! Parameter UI: an [egui] overlay with panels for the cameras, render settings and the scene.
+//!
+//! [UiOverlay::run] builds the frame's UI from [crate::app::Runner::update] (while
+//! [crate::render_settings::RenderSettings::ui] is on, F1) and queues the tessellated meshes; the
+//! render graph's built-in `ui` pass draws them last, over [crate::render_graph::TARGET] at full
+//! size. Input comes from the platform listeners as [egui::Event]s in
+//! [crate::platform::InputState::ui_events]; while the pointer is locked (mouse look) the UI gets
+//! no pointer events, and the scene gets no keys while a UI text field has focus.
+//!
+//! Scenes add their own controls through [crate::scene::Scene::ui].
+
+mod painter;
+
+use std::cell::RefCell;
+
+use crate::app::{App, AppInstance};
+use crate::color_grading::Tonemapper;
+use crate::render_settings::{
+    QualityPreset, RenderSettings, MAX_EV, MAX_PEAK_NITS, MAX_RAYMARCH_STEPS, MAX_SHADOW_STEPS,
+    MIN_EV, MIN_PAPER_WHITE_NITS, MIN_RENDER_SCALE,
+};
+use painter::UiGpu;
+
+thread_local! {
+    static UI: RefCell<UiQueue> = RefCell::new(UiQueue::default());
+}
+
+/// This frame's meshes, texture changes not yet uploaded, and the GPU state that draws them.
+#[derive(Default)]
+struct UiQueue {
+    primitives: Vec<egui::ClippedPrimitive>,
+    /// Accumulated until the next [prepare]: egui sends each change only once.
+    textures: Vec<egui::TexturesDelta>,
+    pixels_per_point: f32,
+    /// Whether the pointer was over (or dragging) a UI element in the last UI frame.
+    wants_pointer: bool,
+    /// The parameters window's screen rectangle in the last UI frame; `None` while hidden.
+    panel: Option<egui::Rect>,
+    gpu: Option<UiGpu>,
+}
+
+/// The egui context. Owned by [crate::app::Runner].
+pub struct UiOverlay {
+    ctx: egui::Context,
+}
+
+impl Default for UiOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UiOverlay {
+    pub fn new() -> Self {
+        Self {
+            ctx: egui::Context::default(),
+        }
+    }
+
+    /// Runs one UI frame over `app` and `instance` with this frame's `events` (see
+    /// [crate::platform::InputState]) and queues it for the `ui` pass.
+    pub fn run(
+        &self,
+        app: &mut App,
+        instance: &mut dyn AppInstance,
+        events: Vec<egui::Event>,
+        modifiers: egui::Modifiers,
+    ) {
+        let size = egui::vec2(app.width as f32, app.height as f32);
+        let raw_input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, size)),
+            time: Some(app.current_timestamp / 1000.0),
+            predicted_dt: (app.delta_time / 1000.0) as f32,
+            modifiers,
+            events,
+            focused: true,
+            ..Default::default()
+        };
+        let mut panel = None;
+        let output = self.ctx.run_ui(raw_input, |ui| {
+            panel = egui::Window::new("Parameters")
+                .default_pos([app.width as f32 - 300.0, 8.0])
+                .default_width(280.0)
+                .vscroll(true)
+                .show(ui.ctx(), |ui| parameters(ui, app, instance))
+                .map(|response| response.response.rect);
+        });
+        let primitives = self.ctx.tessellate(output.shapes, output.pixels_per_point);
+        let wants_pointer = self.ctx.egui_wants_pointer_input() || self.ctx.is_pointer_over_egui();
+        UI.with_borrow_mut(|queue| {
+            queue.primitives = primitives;
+            queue.textures.push(output.textures_delta);
+            queue.pixels_per_point = output.pixels_per_point;
+            queue.wants_pointer = wants_pointer;
+            queue.panel = panel;
+        });
+    }
+
+    /// Whether a UI widget has keyboard focus (keys shouldn't move the camera).
+    pub fn wants_keyboard(&self) -> bool {
+        self.ctx.egui_wants_keyboard_input()
+    }
+}
+
+/// The panels: stereo camera, mono camera, render settings, scene.
+fn parameters(ui: &mut egui::Ui, app: &mut App, instance: &mut dyn AppInstance) {
+    egui::CollapsingHeader::new("Stereo camera").show(ui, |ui| {
+        ui.checkbox(&mut app.use_stereo, "Stereo");
+        let camera = &mut app.stereo_camera;
+        let mut eye_distance = camera.eye_distance();
+        if ui
+            .add(egui::Slider::new(&mut eye_distance, 0.0..=0.3).text("Eye distance"))
+            .changed()
+        {
+            camera.set_eye_distance(eye_distance);
+        }
+        let mut convergence = camera.convergence_distance();
+        let slider = egui::Slider::new(&mut convergence, 0.1..=50.0)
+            .logarithmic(true)
+            .text("Convergence");
+        if ui.add(slider).changed() {
+            camera.set_convergence_distance(convergence);
+        }
+        let mut fovy = camera.fovy().to_degrees();
+        if ui
+            .add(
+                egui::Slider::new(&mut fovy, 20.0..=120.0)
+                    .suffix("°")
+                    .text("Vertical FOV"),
+            )
+            .changed()
+        {
+            camera.set_fovy(fovy.to_radians());
+        }
+    });
+
+    egui::CollapsingHeader::new("Camera")
+        .default_open(true)
+        .show(ui, |ui| {
+            let camera = &mut instance.descriptor_mut().camera;
+            let mut fov = camera.fov.to_degrees();
+            if ui
+                .add(
+                    egui::Slider::new(&mut fov, 30.0..=150.0)
+                        .suffix("°")
+                        .text("FOV"),
+                )
+                .changed()
+            {
+                camera.fov = fov.to_radians();
+            }
+            let position = camera.position;
+            ui.label(format!(
+                "Position  {:.2}  {:.2}  {:.2}",
+                position.x, position.y, position.z
+            ));
+
+            // Auto-exposure replaces aperture, shutter and ISO; compensation applies either way.
+            let exposure = &mut app.camera.exposure;
+            ui.add_enabled_ui(app.render_settings.eye_adaptation().is_none(), |ui| {
+                ui.add(
+                    egui::Slider::new(&mut exposure.aperture, 1.0..=22.0)
+                        .logarithmic(true)
+                        .prefix("f/")
+                        .text("Aperture"),
+                );
+                let mut shutter = 1.0 / exposure.shutter_s;
+                let slider = egui::Slider::new(&mut shutter, 1.0..=8000.0)
+                    .logarithmic(true)
+                    .prefix("1/")
+                    .suffix(" s");
+                if ui.add(slider.text("Shutter")).changed() {
+                    exposure.shutter_s = 1.0 / shutter;
+                }
+                ui.add(
+                    egui::Slider::new(&mut exposure.iso, 50.0..=25600.0)
+                        .logarithmic(true)
+                        .text("ISO"),
+                );
+                ui.label(format!(
+                    "EV100 {:.1}  (exposure {:.3})",
+                    exposure.ev100(),
+                    exposure.exposure()
+                ));
+            });
+            ui.add(
+                egui::Slider::new(&mut exposure.compensation, -5.0..=5.0)
+                    .suffix(" EV")
+                    .text("Compensation"),
+            );
+        });
+
+    egui::CollapsingHeader::new("Render")
+        .default_open(true)
+        .show(ui, |ui| {
+            let mut settings = app.render_settings;
+            render_settings(ui, &mut settings);
+            if settings != app.render_settings {
+                app.render_settings = settings.sanitized();
+            }
+            ui.add(egui::Slider::new(&mut app.bloom_intensity, 0.0..=4.0).text("Bloom intensity"));
+        });
+
+    egui::CollapsingHeader::new("Scene")
+        .default_open(true)
+        .show(ui, |ui| instance.ui(ui));
+}
+
+/// Feature toggles and quality knobs; changing a knob switches the preset to Custom, like
+/// the JS setters.
+fn render_settings(ui: &mut egui::Ui, settings: &mut RenderSettings) {
+    ui.horizontal_wrapped(|ui| {
+        ui.checkbox(&mut settings.warehouse, "Warehouse");
+        ui.checkbox(&mut settings.scene, "Scene");
+        ui.checkbox(&mut settings.gbuffer, "G-buffer");
+        ui.checkbox(&mut settings.taa, "TAA");
+        ui.checkbox(&mut settings.post, "Post");
+        ui.checkbox(&mut settings.hud, "HUD");
+    });
+
+    let mut preset = settings.preset;
+    egui::ComboBox::from_label("Preset")
+        .selected_text(format!("{preset:?}"))
+        .show_ui(ui, |ui| {
+            for option in [
+                QualityPreset::Low,
+                QualityPreset::Medium,
+                QualityPreset::High,
+            ] {
+                ui.selectable_value(&mut preset, option, format!("{option:?}"));
+            }
+        });
+    if preset != settings.preset {
+        settings.apply_preset(preset);
+    }
+    let knobs = [
+        ui.add(
+            egui::Slider::new(
+                &mut settings.bloom_mips,
+                2..=crate::gpu::BLOOM_MIP_LEVELS as u32,
+            )
+            .text("Bloom mips"),
+        ),
+        ui.add(
+            egui::Slider::new(&mut settings.raymarch_steps, 16..=MAX_RAYMARCH_STEPS)
+                .text("Raymarch steps"),
+        ),
+        ui.add(
+            egui::Slider::new(&mut settings.shadow_steps, 4..=MAX_SHADOW_STEPS)
+                .text("Shadow steps"),
+        ),
+    ];
+    if knobs.iter().any(egui::Response::changed) {
+        settings.preset = QualityPreset::Custom;
+    }
+
+    ui.add(
+        egui::Slider::new(&mut settings.render_scale, MIN_RENDER_SCALE..=1.0).text("Render scale"),
+    );
+    ui.checkbox(&mut settings.dynamic_resolution, "Dynamic resolution");
+    ui.add_enabled_ui(settings.dynamic_resolution, |ui| {
+        ui.add(egui::Slider::new(&mut settings.target_fps, 15.0..=240.0).text("Target FPS"));
+        ui.add(
+            egui::Slider::new(&mut settings.min_render_scale, MIN_RENDER_SCALE..=1.0)
+                .text("Min scale"),
+        );
+    });
+
+    ui.add_enabled(
+        settings.post,
+        egui::Checkbox::new(&mut settings.auto_exposure, "Auto exposure"),
+    );
+    ui.add_enabled_ui(settings.post && settings.auto_exposure, |ui| {
+        ui.add(egui::Slider::new(&mut settings.min_ev, MIN_EV..=MAX_EV).text("Min EV"));
+        ui.add(egui::Slider::new(&mut settings.max_ev, MIN_EV..=MAX_EV).text("Max EV"));
+        ui.add(
+            egui::Slider::new(&mut settings.adaptation_speed, 0.1..=20.0)
+                .logarithmic(true)
+                .text("Adaptation speed"),
+        );
+    });
+
+    // Tonemapping and grading belong to the screen pass.
+    ui.add_enabled_ui(settings.post, |ui| {
+        egui::ComboBox::from_label("Tonemapper")
+            .selected_text(format!("{:?}", settings.tonemapper))
+            .show_ui(ui, |ui| {
+                for option in Tonemapper::ALL {
+                    ui.selectable_value(&mut settings.tonemapper, option, format!("{option:?}"));
+                }
+            });
+        let grading = &mut settings.grading;
+        ui.add(egui::Slider::new(&mut grading.temperature, -1.0..=1.0).text("Temperature"));
+        ui.add(egui::Slider::new(&mut grading.tint, -1.0..=1.0).text("Tint"));
+        ui.add(
+            egui::Slider::new(&mut grading.contrast, 0.25..=4.0)
+                .logarithmic(true)
+                .text("Contrast"),
+        );
+        ui.add(egui::Slider::new(&mut grading.saturation, 0.0..=2.0).text("Saturation"));
+        ui.add(egui::Slider::new(&mut grading.lut_strength, 0.0..=1.0).text("LUT strength"));
+
+        ui.checkbox(&mut settings.hdr, "HDR output");
+        ui.add_enabled_ui(settings.hdr, |ui| {
+            let nits = MIN_PAPER_WHITE_NITS..=MAX_PEAK_NITS;
+            ui.add(
+                egui::Slider::new(&mut settings.peak_nits, nits)
+                    .logarithmic(true)
+                    .suffix(" nits")
+                    .text("Peak"),
+            );
+            let slider =
+                egui::Slider::new(&mut settings.paper_white_nits, MIN_PAPER_WHITE_NITS..=500.0);
+            ui.add(slider.suffix(" nits").text("Paper white"));
+        });
+    });
+}
+
+/// A frame with the UI hidden: nothing is drawn and the pointer is free for mouse look.
+pub fn skip_frame() {
+    UI.with_borrow_mut(|queue| {
+        queue.wants_pointer = false;
+        queue.panel = None;
+    });
+}
+
+/// Whether `pos` (framebuffer pixels) is on the UI panel, or the pointer is busy with the UI (e.g.
+/// an open combo box), so a touch landing there belongs to the UI. False while hidden.
+pub fn covers(pos: egui::Pos2) -> bool {
+    UI.with_borrow(|queue| {
+        queue.wants_pointer || queue.panel.is_some_and(|rect| rect.contains(pos))
+    })
+}
+
+/// Whether the pointer is over the UI, so a click there shouldn't start mouse look.
+/// False while hidden.
+pub fn wants_pointer() -> bool {
+    UI.with_borrow(|queue| queue.wants_pointer)
+}
+
+/// Creates the GPU state for `color_format`, applies pending texture changes and uploads this
+/// frame's meshes for a `target_size` target. Call before the pass that draws them.
+pub(crate) fn prepare(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    color_format: wgpu::TextureFormat,
+    target_size: (u32, u32),
+) {
+    UI.with_borrow_mut(|ui| {
+        let gpu = ui.gpu.get_or_insert_with(|| UiGpu::new(device, queue));
+        gpu.ensure_pipeline(device, color_format);
+        for delta in ui.textures.drain(..) {
+            gpu.update_textures(device, &delta);
+        }
+        gpu.upload(
+            device,
+            &ui.primitives,
+            ui.pixels_per_point,
+            target_size,
+            color_format,
+        );
+    });
+}
+
+/// Draws this frame's UI; the pass covers the whole target. Same format as [prepare].
+pub(crate) fn draw(pass: &mut wgpu::RenderPass<'_>, color_format: wgpu::TextureFormat) {
+    UI.with_borrow(|ui| {
+        if let Some(gpu) = &ui.gpu {
+            gpu.draw(pass, color_format);
+        }
+    });
+}
+
+/// Whether anything was queued this frame.
+pub(crate) fn is_empty() -> bool {
+    UI.with_borrow(|ui| ui.primitives.is_empty())
+}
+
+/// Drops this frame's meshes (pending texture changes stay until the next [prepare]).
+pub(crate) fn end_frame() {
+    UI.with_borrow_mut(|ui| ui.primitives.clear());
+}
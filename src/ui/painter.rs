@@ -0,0 +1,429 @@
+//! wgpu renderer for egui's tessellated meshes (textures, clip rects),
+//! drawn into [crate::render_graph::TARGET].
+//!
+//! egui blends in gamma space with premultiplied sRGB colors, so meshes are drawn as-is into
+//! non-sRGB targets and converted to linear in the shader for `*Srgb` ones (the hardware
+//! re-encodes them). Textures are `Rgba8Unorm` for the same reason.
+
+use std::collections::HashMap;
+
+use crate::gpu::stats;
+
+const UI_WGSL: &str = include_str!("../wgsl/ui.wgsl");
+
+/// Vertices / indices the buffers hold before their first resize.
+const INITIAL_VERTICES: usize = 4096;
+const INITIAL_INDICES: usize = 8192;
+const VERTEX_STRIDE: u64 = std::mem::size_of::<egui::epaint::Vertex>() as u64;
+const INDEX_SIZE: u64 = std::mem::size_of::<u32>() as u64;
+
+/// `Uniforms` in `ui.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct UiUniforms {
+    screen_size: [f32; 2],
+    srgb_target: u32,
+    _pad: u32,
+}
+
+/// One mesh from [UiGpu::upload]: its texture, scissor rect (target pixels) and index range.
+struct MeshDraw {
+    texture: egui::TextureId,
+    scissor: [u32; 4],
+    indices: std::ops::Range<u32>,
+    base_vertex: i32,
+}
+
+struct UiTexture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+/// egui textures, vertex / index buffers and one pipeline per color format.
+pub(super) struct UiGpu {
+    queue: wgpu::Queue,
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    texture_layout: wgpu::BindGroupLayout,
+    pipelines: Vec<(wgpu::TextureFormat, wgpu::RenderPipeline)>,
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    textures: HashMap<egui::TextureId, UiTexture>,
+    vertex_buffer: wgpu::Buffer,
+    vertex_capacity: usize,
+    index_buffer: wgpu::Buffer,
+    index_capacity: usize,
+    draws: Vec<MeshDraw>,
+}
+
+impl UiGpu {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ui"),
+            source: wgpu::ShaderSource::Wgsl(UI_WGSL.into()),
+        });
+        let uniform_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ui_uniform_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<UiUniforms>() as u64
+                    ),
+                },
+                count: None,
+            }],
+        });
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ui_texture_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ui_layout"),
+            bind_group_layouts: &[&uniform_layout, &texture_layout],
+            immediate_size: 0,
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ui_uniforms"),
+            size: std::mem::size_of::<UiUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ui_uniform_bind_group"),
+            layout: &uniform_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+        Self {
+            queue: queue.clone(),
+            shader,
+            pipeline_layout,
+            texture_layout,
+            pipelines: Vec::new(),
+            uniform_buffer,
+            uniform_bind_group,
+            textures: HashMap::new(),
+            vertex_buffer: create_buffer(
+                device,
+                "ui_vertices",
+                wgpu::BufferUsages::VERTEX,
+                INITIAL_VERTICES as u64 * VERTEX_STRIDE,
+            ),
+            vertex_capacity: INITIAL_VERTICES,
+            index_buffer: create_buffer(
+                device,
+                "ui_indices",
+                wgpu::BufferUsages::INDEX,
+                INITIAL_INDICES as u64 * INDEX_SIZE,
+            ),
+            index_capacity: INITIAL_INDICES,
+            draws: Vec::new(),
+        }
+    }
+
+    fn pipeline(&self, color_format: wgpu::TextureFormat) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines
+            .iter()
+            .find(|(format, _)| *format == color_format)
+            .map(|(_, pipeline)| pipeline)
+    }
+
+    pub fn ensure_pipeline(&mut self, device: &wgpu::Device, color_format: wgpu::TextureFormat) {
+        if self.pipeline(color_format).is_some() {
+            return;
+        }
+        let attributes = wgpu::vertex_attr_array![
+            0 => Float32x2,
+            1 => Float32x2,
+            2 => Unorm8x4,
+        ];
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ui"),
+            layout: Some(&self.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &self.shader,
+                entry_point: Some("vs"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: VERTEX_STRIDE,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &attributes,
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.shader,
+                entry_point: Some("fs"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    // Premultiplied color; alpha accumulates coverage so the target stays opaque.
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::OneMinusDstAlpha,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            cache: None,
+            multiview_mask: None,
+        });
+        self.pipelines.push((color_format, pipeline));
+    }
+
+    /// Creates, updates (`pos` set: a patch at that texel) and frees egui's textures.
+    pub fn update_textures(&mut self, device: &wgpu::Device, delta: &egui::TexturesDelta) {
+        for (id, image_delta) in &delta.set {
+            let egui::ImageData::Color(image) = &image_delta.image;
+            let [width, height] = image.size;
+            let size = wgpu::Extent3d {
+                width: width as u32,
+                height: height as u32,
+                depth_or_array_layers: 1,
+            };
+            let origin = match image_delta.pos {
+                Some([x, y]) => wgpu::Origin3d {
+                    x: x as u32,
+                    y: y as u32,
+                    z: 0,
+                },
+                None => {
+                    self.textures
+                        .insert(*id, self.create_texture(device, size, image_delta.options));
+                    wgpu::Origin3d::ZERO
+                }
+            };
+            let Some(texture) = self.textures.get(id) else {
+                log_error!("[UI] Update for unknown texture {id:?}.");
+                continue;
+            };
+            self.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &texture.texture,
+                    mip_level: 0,
+                    origin,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytemuck::cast_slice(&image.pixels),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * size.width),
+                    rows_per_image: Some(size.height),
+                },
+                size,
+            );
+        }
+        for id in &delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        size: wgpu::Extent3d,
+        options: egui::TextureOptions,
+    ) -> UiTexture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("ui_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let filter = |filter: egui::TextureFilter| match filter {
+            egui::TextureFilter::Nearest => wgpu::FilterMode::Nearest,
+            egui::TextureFilter::Linear => wgpu::FilterMode::Linear,
+        };
+        let address_mode = match options.wrap_mode {
+            egui::TextureWrapMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            egui::TextureWrapMode::Repeat => wgpu::AddressMode::Repeat,
+            egui::TextureWrapMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("ui_sampler"),
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            mag_filter: filter(options.magnification),
+            min_filter: filter(options.minification),
+            ..Default::default()
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ui_texture_bind_group"),
+            layout: &self.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        UiTexture {
+            texture,
+            bind_group,
+        }
+    }
+
+    /// Copies every mesh of `primitives` into the vertex / index buffers (growing them as needed)
+    /// and records its draw, clipped to `target_size` pixels at `pixels_per_point`.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        primitives: &[egui::ClippedPrimitive],
+        pixels_per_point: f32,
+        target_size: (u32, u32),
+        color_format: wgpu::TextureFormat,
+    ) {
+        self.draws.clear();
+        let meshes = primitives.iter().filter_map(|p| match &p.primitive {
+            egui::epaint::Primitive::Mesh(mesh) => Some((p.clip_rect, mesh)),
+            egui::epaint::Primitive::Callback(_) => None,
+        });
+        let mut vertices: Vec<egui::epaint::Vertex> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        for (clip_rect, mesh) in meshes {
+            let Some(scissor) = scissor_rect(clip_rect, pixels_per_point, target_size) else {
+                continue;
+            };
+            if mesh.indices.is_empty() {
+                continue;
+            }
+            let first = indices.len() as u32;
+            self.draws.push(MeshDraw {
+                texture: mesh.texture_id,
+                scissor,
+                indices: first..first + mesh.indices.len() as u32,
+                base_vertex: vertices.len() as i32,
+            });
+            vertices.extend_from_slice(&mesh.vertices);
+            indices.extend_from_slice(&mesh.indices);
+        }
+        if vertices.len() > self.vertex_capacity {
+            self.vertex_capacity = vertices.len().next_power_of_two();
+            let size = self.vertex_capacity as u64 * VERTEX_STRIDE;
+            self.vertex_buffer =
+                create_buffer(device, "ui_vertices", wgpu::BufferUsages::VERTEX, size);
+        }
+        if indices.len() > self.index_capacity {
+            self.index_capacity = indices.len().next_power_of_two();
+            let size = self.index_capacity as u64 * INDEX_SIZE;
+            self.index_buffer =
+                create_buffer(device, "ui_indices", wgpu::BufferUsages::INDEX, size);
+        }
+        if !vertices.is_empty() {
+            self.queue
+                .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+            self.queue
+                .write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&indices));
+        }
+        let uniforms = UiUniforms {
+            screen_size: [
+                target_size.0 as f32 / pixels_per_point,
+                target_size.1 as f32 / pixels_per_point,
+            ],
+            srgb_target: u32::from(color_format.is_srgb()),
+            _pad: 0,
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+    }
+
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>, color_format: wgpu::TextureFormat) {
+        let Some(pipeline) = self.pipeline(color_format) else {
+            return;
+        };
+        if self.draws.is_empty() {
+            return;
+        }
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for draw in &self.draws {
+            let Some(texture) = self.textures.get(&draw.texture) else {
+                continue;
+            };
+            let [x, y, width, height] = draw.scissor;
+            pass.set_scissor_rect(x, y, width, height);
+            pass.set_bind_group(1, &texture.bind_group, &[]);
+            pass.draw_indexed(draw.indices.clone(), draw.base_vertex, 0..1);
+            stats::record_draw(1);
+        }
+    }
+}
+
+/// `clip_rect` (points) in target pixels as (x, y, width, height); `None` if nothing is visible.
+fn scissor_rect(
+    clip_rect: egui::Rect,
+    pixels_per_point: f32,
+    (width, height): (u32, u32),
+) -> Option<[u32; 4]> {
+    let min_x = (clip_rect.min.x * pixels_per_point)
+        .round()
+        .clamp(0.0, width as f32) as u32;
+    let min_y = (clip_rect.min.y * pixels_per_point)
+        .round()
+        .clamp(0.0, height as f32) as u32;
+    let max_x = (clip_rect.max.x * pixels_per_point)
+        .round()
+        .clamp(min_x as f32, width as f32) as u32;
+    let max_y = (clip_rect.max.y * pixels_per_point)
+        .round()
+        .clamp(min_y as f32, height as f32) as u32;
+    (max_x > min_x && max_y > min_y).then_some([min_x, min_y, max_x - min_x, max_y - min_y])
+}
+
+fn create_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    size: u64,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -3,12 +3,22 @@
 #![allow(dead_code)]
 
 // A macro to provide `println!(..)`-style syntax for `console.log` logging.
+// Native builds (offline renderer) print to stdout instead.
+#[cfg(target_arch = "wasm32")]
 macro_rules! log {
   ( $( $t:tt )* ) => {
     web_sys::console::log_1(&format!( $( $t )* ).into());
   }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+macro_rules! log {
+  ( $( $t:tt )* ) => {
+    println!( $( $t )* );
+  }
+}
+
+#[cfg(target_arch = "wasm32")]
 #[allow(unused_macros)]
 macro_rules! log_error {
   ( $( $t:tt )* ) => {
@@ -16,6 +26,14 @@ macro_rules! log_error {
   }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(unused_macros)]
+macro_rules! log_error {
+  ( $( $t:tt )* ) => {
+    eprintln!( $( $t )* );
+  }
+}
+
 /// Reinterprets a slice of `T` as bytes. Use only when `T` is `repr(C)` and the
 /// buffer is used as raw bytes (e.g. for WebGL buffer upload).
 #[inline]
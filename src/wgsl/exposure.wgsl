@@ -0,0 +1,92 @@
+// Auto-exposure: a log-luminance histogram of the resolve, then its average adapted over time
+// into the exposure the screen pass applies (crate::gpu::exposure).
+
+const BINS: u32 = 256u;
+// Rec. 709 luminance weights.
+const LUMA: vec3<f32> = vec3<f32>(0.2126, 0.7152, 0.0722);
+// EV100 of a scene whose average luminance is 1 (log2(L * S / K), S = 100, K = 12.5).
+const EV_OFFSET: f32 = 3.0;
+
+struct ExposureUniforms {
+    // Histogram range: log2 luminance at bin 1, and across bins 1..255.
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    // Fraction of the way to the target EV covered this frame (1 - exp(-dt * speed)).
+    adaptation: f32,
+    // Added to the adapted EV, in stops (positive brightens).
+    compensation: f32,
+    min_ev: f32,
+    max_ev: f32,
+}
+
+// Read by the screen pass as ScreenUniforms.exposure (copied on the GPU).
+struct ExposureState {
+    ev100: f32,
+    exposure: f32,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: ExposureUniforms;
+@group(0) @binding(1) var<storage, read_write> histogram: array<atomic<u32>, BINS>;
+@group(0) @binding(2) var<storage, read_write> state: ExposureState;
+@group(0) @binding(3) var resolve_tex: texture_2d<f32>;
+
+var<workgroup> local_bins: array<atomic<u32>, BINS>;
+var<workgroup> weighted: array<f32, BINS>;
+
+// Bin 0 holds black and anything below the range, so it can be left out of the average.
+fn bin(color: vec3<f32>) -> u32 {
+    let luminance = dot(color, LUMA);
+    if luminance < 1e-5 {
+        return 0u;
+    }
+    let t = clamp((log2(luminance) - uniforms.min_log_luminance) / uniforms.log_luminance_range, 0.0, 1.0);
+    return u32(t * 254.0 + 1.0);
+}
+
+@compute @workgroup_size(16, 16)
+fn build_histogram(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(local_invocation_index) index: u32,
+) {
+    atomicStore(&local_bins[index], 0u);
+    workgroupBarrier();
+
+    let size = textureDimensions(resolve_tex);
+    if id.x < size.x && id.y < size.y {
+        atomicAdd(&local_bins[bin(textureLoad(resolve_tex, id.xy, 0).rgb)], 1u);
+    }
+    workgroupBarrier();
+
+    atomicAdd(&histogram[index], atomicLoad(&local_bins[index]));
+}
+
+// One workgroup: averages the histogram (geometric mean, bin 0 left out), clears it for the next
+// frame and eases the state towards the result.
+@compute @workgroup_size(256)
+fn average(@builtin(local_invocation_index) index: u32) {
+    let count = atomicExchange(&histogram[index], 0u);
+    weighted[index] = f32(count) * f32(index);
+    workgroupBarrier();
+
+    for (var stride = BINS / 2u; stride > 0u; stride >>= 1u) {
+        if index < stride {
+            weighted[index] += weighted[index + stride];
+        }
+        workgroupBarrier();
+    }
+
+    if index == 0u {
+        let size = textureDimensions(resolve_tex);
+        let lit = f32(size.x * size.y) - f32(count);
+        var target_ev = uniforms.min_ev;
+        if lit > 0.0 {
+            let mean_bin = weighted[0] / lit - 1.0;
+            let log_luminance = uniforms.min_log_luminance + mean_bin / 254.0 * uniforms.log_luminance_range;
+            target_ev = log_luminance + EV_OFFSET;
+        }
+        target_ev = clamp(target_ev, uniforms.min_ev, uniforms.max_ev);
+        state.ev100 = mix(state.ev100, target_ev, uniforms.adaptation);
+        // Saturation-based exposure: the luminance that just saturates the sensor maps to 1.
+        state.exposure = exp2(uniforms.compensation) / (1.2 * exp2(state.ev100));
+    }
+}
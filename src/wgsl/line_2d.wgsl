@@ -1,30 +0,0 @@
-// 2D line strip. Vertex: position xy, color rgb. Uniform: view_projection (e.g. ortho).
-
-struct LineUniforms {
-    view_projection: mat4x4<f32>,
-}
-
-@group(0) @binding(0) var<uniform> u: LineUniforms;
-
-struct VertexInput {
-    @location(0) position: vec2<f32>,
-    @location(1) color: vec3<f32>,
-}
-
-struct VertexOutput {
-    @builtin(position) clip: vec4<f32>,
-    @location(0) color: vec3<f32>,
-}
-
-@vertex
-fn vs(in: VertexInput) -> VertexOutput {
-    var out: VertexOutput;
-    out.clip = u.view_projection * vec4<f32>(in.position, 0.0, 1.0);
-    out.color = in.color;
-    return out;
-}
-
-@fragment
-fn fs(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color, 1.0);
-}
@@ -1,30 +1,228 @@
-// Particles: instanced quads (one quad per particle). Vertex: quad corner offset; instance: position xyz.
+// Particles: one billboard per live particle, drawn indirectly (instance count = alive count from the
+// simulation). Each instance looks its particle up through the alive list; size and color follow the
+// emitter's over-life curves. Textured emitters sample the atlas (two flipbook frames, cross-faded);
+// others get a soft round spot. Output is premultiplied (additive or alpha blending is set by the pipeline).
+// `fs_soft` depth-tests against the G-buffer's reversed-Z depth itself and fades particles where they
+// meet opaque geometry.
+
+struct Particle {
+    position: vec3<f32>,
+    age: f32,
+    velocity: vec3<f32>,
+    lifetime: f32,
+    color: vec4<f32>,
+    size: f32,
+    emitter: u32,
+    // Per-particle random bits (flipbook start frame).
+    seed: u32,
+    _pad: u32,
+}
+
+struct AliveList {
+    count: u32,
+    indices: array<u32>,
+}
+
+struct Emitter {
+    position: vec3<f32>,
+    shape: u32,
+    shape_params: vec4<f32>,
+    direction: vec3<f32>,
+    cone_cos: f32,
+    speed_min: f32,
+    speed_max: f32,
+    lifetime_min: f32,
+    lifetime_max: f32,
+    color: vec4<f32>,
+    size: f32,
+    spawn_start: u32,
+    spawn_count: u32,
+    // 0 view-facing, 1 velocity-stretched
+    billboard: u32,
+    stretch: f32,
+    size_times: vec4<f32>,
+    size_values: vec4<f32>,
+    color_times: vec4<f32>,
+    color_values: array<vec4<f32>, 4>,
+    // Atlas UV min + size.
+    sprite_rect: vec4<f32>,
+    // Columns, rows, frames, flags (bits 0-1 mode: 0 untextured, 1 loop, 2 clamp, 3 over life;
+    // bit 2 blend frames; bit 3 random start frame).
+    sprite_grid: vec4<u32>,
+    sprite_fps: f32,
+}
 
 struct ParticleUniforms {
     view_projection: mat4x4<f32>,
-    point_scale: f32,
+    camera_position: vec3<f32>,
+    // Projection near plane: reversed-Z infinite depth d maps to view distance near / d.
+    near: f32,
+    camera_right: vec3<f32>,
+    // Soft particle fade distance in world units (0 = hard).
+    softness: f32,
+    camera_up: vec3<f32>,
+    size_scale: f32,
 }
 
 @group(0) @binding(0) var<uniform> u: ParticleUniforms;
+@group(0) @binding(1) var<storage, read> particles: array<Particle>;
+@group(0) @binding(2) var<storage, read> alive_list: AliveList;
+@group(0) @binding(3) var<storage, read> emitters: array<Emitter>;
+@group(0) @binding(4) var atlas: texture_2d<f32>;
+@group(0) @binding(5) var atlas_sampler: sampler;
+// Depth32Float bound as unfilterable float (`texture_depth_2d` loads don't translate to GLSL).
+@group(1) @binding(0) var scene_depth: texture_2d<f32>;
+
+const QUAD_OFFSETS = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0),
+);
+
+// Index of the curve segment containing `t` and the blend factor within it.
+fn curve_segment(times: vec4<f32>, t: f32) -> vec2<f32> {
+    for (var i = 1; i < 4; i++) {
+        if (t <= times[i]) {
+            let span = times[i] - times[i - 1];
+            let f = select(1.0, clamp((t - times[i - 1]) / span, 0.0, 1.0), span > 0.0);
+            return vec2<f32>(f32(i), f);
+        }
+    }
+    return vec2<f32>(3.0, 1.0);
+}
+
+fn eval_size(emitter: u32, t: f32) -> f32 {
+    let seg = curve_segment(emitters[emitter].size_times, t);
+    let i = i32(seg.x);
+    return mix(emitters[emitter].size_values[i - 1], emitters[emitter].size_values[i], seg.y);
+}
 
-struct VertexInput {
-    @location(0) offset: vec2<f32>,
-    @location(1) instance_pos: vec3<f32>,
+fn eval_color(emitter: u32, t: f32) -> vec4<f32> {
+    let seg = curve_segment(emitters[emitter].color_times, t);
+    let i = i32(seg.x);
+    return mix(emitters[emitter].color_values[i - 1], emitters[emitter].color_values[i], seg.y);
+}
+
+const SPRITE_MODE_MASK: u32 = 3u;
+const SPRITE_LOOP: u32 = 1u;
+const SPRITE_OVER_LIFE: u32 = 3u;
+const SPRITE_BLEND: u32 = 4u;
+const SPRITE_RANDOM: u32 = 8u;
+
+// Atlas UV of `local` (0..1 within the quad) in flipbook `frame`, inset by half a texel so linear
+// filtering stays inside the frame.
+fn frame_uv(e: u32, frame: u32, local: vec2<f32>) -> vec2<f32> {
+    let rect = emitters[e].sprite_rect;
+    let grid = emitters[e].sprite_grid;
+    let frame_size = rect.zw / vec2<f32>(f32(grid.x), f32(grid.y));
+    let cell = vec2<f32>(f32(frame % grid.x), f32(frame / grid.x));
+    let half_texel = 0.5 / vec2<f32>(textureDimensions(atlas));
+    let inner = max(frame_size - 2.0 * half_texel, vec2<f32>(0.0));
+    return rect.xy + cell * frame_size + half_texel + local * inner;
 }
 
 struct VertexOutput {
     @builtin(position) clip: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) offset: vec2<f32>,
+    // Distance from the camera along the view axis (for soft particles).
+    @location(2) view_depth: f32,
+    // Atlas UVs of the current and next flipbook frame.
+    @location(3) uv: vec4<f32>,
+    // x: cross-fade to the next frame, y: 1 if textured.
+    @location(4) sprite: vec2<f32>,
 }
 
 @vertex
-fn vs(in: VertexInput) -> VertexOutput {
-    let world_pos = in.instance_pos + vec3<f32>(in.offset.x * u.point_scale, in.offset.y * u.point_scale, 0.0);
+fn vs(@builtin(vertex_index) vertex_index: u32, @builtin(instance_index) instance_index: u32) -> VertexOutput {
+    let p = particles[alive_list.indices[instance_index]];
+    let t = clamp(p.age / p.lifetime, 0.0, 1.0);
+    let offset = QUAD_OFFSETS[vertex_index];
+    let size = p.size * eval_size(p.emitter, t) * u.size_scale;
+
+    var axis_x = u.camera_right * size;
+    var axis_y = u.camera_up * size;
+    if (emitters[p.emitter].billboard == 1u) {
+        // Stretch along the velocity as seen from the camera; falls back to view-facing when it points at us.
+        let to_camera = normalize(u.camera_position - p.position);
+        let v = p.velocity - to_camera * dot(p.velocity, to_camera);
+        let speed = length(v);
+        if (speed > 1e-4) {
+            let dir = v / speed;
+            axis_y = dir * (size + speed * emitters[p.emitter].stretch);
+            axis_x = normalize(cross(dir, to_camera)) * size;
+        }
+    }
+    let world_pos = p.position + axis_x * offset.x + axis_y * offset.y;
+
     var out: VertexOutput;
     out.clip = u.view_projection * vec4<f32>(world_pos, 1.0);
+    out.color = p.color * eval_color(p.emitter, t);
+    out.offset = offset;
+    out.view_depth = out.clip.w;
+
+    let grid = emitters[p.emitter].sprite_grid;
+    let mode = grid.w & SPRITE_MODE_MASK;
+    if (mode != 0u) {
+        let frames = grid.z;
+        var frame = p.age * emitters[p.emitter].sprite_fps;
+        if (mode == SPRITE_OVER_LIFE) {
+            frame = t * f32(frames);
+        }
+        if ((grid.w & SPRITE_RANDOM) != 0u) {
+            frame += f32(p.seed % frames);
+        }
+        var frame0 = u32(frame);
+        var frame1 = frame0 + 1u;
+        if (mode == SPRITE_LOOP) {
+            frame0 %= frames;
+            frame1 %= frames;
+        } else {
+            frame0 = min(frame0, frames - 1u);
+            frame1 = min(frame1, frames - 1u);
+        }
+        // Texture v runs down, quad offset y runs up.
+        let local = vec2<f32>(offset.x, -offset.y) * 0.5 + 0.5;
+        out.uv = vec4<f32>(frame_uv(p.emitter, frame0, local), frame_uv(p.emitter, frame1, local));
+        out.sprite = vec2<f32>(select(0.0, fract(frame), (grid.w & SPRITE_BLEND) != 0u), 1.0);
+    }
     return out;
 }
 
+// Atlas texel (straight alpha) or round falloff across the quad, times the particle color; premultiplied.
+fn shade(in: VertexOutput) -> vec4<f32> {
+    // Explicit LOD: the atlas has no mips, and this keeps sampling valid in non-uniform control flow.
+    let texel = mix(
+        textureSampleLevel(atlas, atlas_sampler, in.uv.xy, 0.0),
+        textureSampleLevel(atlas, atlas_sampler, in.uv.zw, 0.0),
+        in.sprite.x,
+    );
+    let falloff = 1.0 - smoothstep(0.5, 1.0, length(in.offset));
+    let sprite = select(vec4<f32>(1.0, 1.0, 1.0, falloff), texel, in.sprite.y > 0.5);
+    let color = in.color * sprite;
+    let alpha = clamp(color.a, 0.0, 1.0);
+    return vec4<f32>(color.rgb * alpha, alpha);
+}
+
 @fragment
 fn fs(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(1.0, 0.9, 0.6, 1.0);
+    return shade(in);
+}
+
+@fragment
+fn fs_soft(in: VertexOutput) -> @location(0) vec4<f32> {
+    let depth = textureLoad(scene_depth, vec2<i32>(in.clip.xy), 0).r;
+    // Reversed-Z: 0 is infinitely far, so nothing behind the particle to fade against.
+    let scene_view_depth = select(1e9, u.near / depth, depth > 0.0);
+    // The depth test happens here: the pass has no depth attachment (read-only depth isn't available
+    // everywhere, e.g. the GL backend), so the same texture can be sampled.
+    let gap = scene_view_depth - in.view_depth;
+    if (gap <= 0.0) {
+        discard;
+    }
+    let fade = select(1.0, clamp(gap / u.softness, 0.0, 1.0), u.softness > 0.0);
+    return shade(in) * fade;
 }
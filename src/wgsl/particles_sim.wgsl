@@ -0,0 +1,392 @@
+// GPU particle simulation. Per frame: `emit` pops dead slots off the free list and spawns into them,
+// `simulate` integrates live particles, pushes expired ones back on the free list and appends the
+// survivors to the alive list that the draw reads (instance count is copied into the indirect args).
+// Optional collisions: against the G-buffer depth for particles on screen (normal reconstructed from
+// neighbouring depth, surface motion from the velocity target), analytic planes/spheres otherwise.
+
+struct Particle {
+    position: vec3<f32>,
+    age: f32,
+    velocity: vec3<f32>,
+    // <= 0: dead slot.
+    lifetime: f32,
+    color: vec4<f32>,
+    size: f32,
+    emitter: u32,
+    // Per-particle random bits (flipbook start frame).
+    seed: u32,
+    _pad: u32,
+}
+
+struct Emitter {
+    position: vec3<f32>,
+    // 0 point, 1 sphere, 2 box, 3 disc
+    shape: u32,
+    shape_params: vec4<f32>,
+    direction: vec3<f32>,
+    cone_cos: f32,
+    speed_min: f32,
+    speed_max: f32,
+    lifetime_min: f32,
+    lifetime_max: f32,
+    color: vec4<f32>,
+    size: f32,
+    spawn_start: u32,
+    spawn_count: u32,
+    // Render-only from here (see particles.wgsl).
+    billboard: u32,
+    stretch: f32,
+    size_times: vec4<f32>,
+    size_values: vec4<f32>,
+    color_times: vec4<f32>,
+    color_values: array<vec4<f32>, 4>,
+    // Atlas UV min + size.
+    sprite_rect: vec4<f32>,
+    // Columns, rows, frames, flags (bits 0-1 mode: 0 untextured, 1 loop, 2 clamp, 3 over life;
+    // bit 2 blend frames; bit 3 random start frame).
+    sprite_grid: vec4<u32>,
+    sprite_fps: f32,
+}
+
+struct SimParams {
+    gravity: vec3<f32>,
+    dt: f32,
+    drag: f32,
+    curl_strength: f32,
+    curl_scale: f32,
+    time: f32,
+    capacity: u32,
+    spawn_total: u32,
+    emitter_count: u32,
+    seed: u32,
+}
+
+struct CollisionParams {
+    // Jitter-free matrices of the view that rendered `scene_depth`.
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+    previous_view_projection: mat4x4<f32>,
+    previous_inverse_view_projection: mat4x4<f32>,
+    camera_position: vec3<f32>,
+    near: f32,
+    // 0 off, 1 bounce, 2 kill
+    mode: u32,
+    // 1 if scene_depth / scene_velocity hold this frame's G-buffer.
+    screen: u32,
+    restitution: f32,
+    friction: f32,
+    thickness: f32,
+    plane_count: u32,
+    sphere_count: u32,
+    _pad: f32,
+    // normal.xyz, offset: free space is dot(normal, p) >= offset
+    planes: array<vec4<f32>, 8>,
+    // center.xyz, radius
+    spheres: array<vec4<f32>, 8>,
+}
+
+struct FreeList {
+    count: atomic<i32>,
+    indices: array<u32>,
+}
+
+struct AliveList {
+    count: atomic<u32>,
+    indices: array<u32>,
+}
+
+@group(0) @binding(0) var<uniform> params: SimParams;
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<storage, read> emitters: array<Emitter>;
+@group(0) @binding(3) var<storage, read_write> free_list: FreeList;
+@group(0) @binding(4) var<storage, read_write> alive_list: AliveList;
+@group(1) @binding(0) var<uniform> collision: CollisionParams;
+@group(1) @binding(1) var scene_depth: texture_2d<f32>;
+@group(1) @binding(2) var scene_velocity: texture_2d<f32>;
+
+const COLLISION_OFF: u32 = 0u;
+const COLLISION_KILL: u32 = 2u;
+// Pushed this far off a surface after a bounce so the next step starts outside it.
+const SURFACE_OFFSET: f32 = 0.01;
+// Surface speeds from the velocity target above this are treated as depth-edge noise.
+const MAX_SURFACE_SPEED: f32 = 50.0;
+
+// PCG hash: https://www.jcgt.org/published/0009/03/02/
+fn pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn rand(state: ptr<function, u32>) -> f32 {
+    *state = pcg(*state);
+    return f32(*state) / 4294967295.0;
+}
+
+fn rand_unit_vector(state: ptr<function, u32>) -> vec3<f32> {
+    let z = rand(state) * 2.0 - 1.0;
+    let phi = rand(state) * 6.2831853;
+    let r = sqrt(max(1.0 - z * z, 0.0));
+    return vec3<f32>(r * cos(phi), z, r * sin(phi));
+}
+
+// Random direction within `cone_cos` of `axis` (uniform over the spherical cap).
+fn rand_cone(state: ptr<function, u32>, axis: vec3<f32>, cone_cos: f32) -> vec3<f32> {
+    let z = mix(1.0, cone_cos, rand(state));
+    let phi = rand(state) * 6.2831853;
+    let r = sqrt(max(1.0 - z * z, 0.0));
+    let local = vec3<f32>(r * cos(phi), r * sin(phi), z);
+    let helper = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(axis.x) > 0.9);
+    let tangent = normalize(cross(helper, axis));
+    let bitangent = cross(axis, tangent);
+    return tangent * local.x + bitangent * local.y + axis * local.z;
+}
+
+fn spawn_offset(state: ptr<function, u32>, e: Emitter) -> vec3<f32> {
+    switch e.shape {
+        case 1u: {
+            return rand_unit_vector(state) * e.shape_params.x * pow(rand(state), 1.0 / 3.0);
+        }
+        case 2u: {
+            let r = vec3<f32>(rand(state), rand(state), rand(state)) * 2.0 - 1.0;
+            return r * e.shape_params.xyz;
+        }
+        case 3u: {
+            let radius = e.shape_params.x * sqrt(rand(state));
+            let phi = rand(state) * 6.2831853;
+            return vec3<f32>(radius * cos(phi), 0.0, radius * sin(phi));
+        }
+        default: {
+            return vec3<f32>(0.0);
+        }
+    }
+}
+
+@compute @workgroup_size(64)
+fn emit(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i = gid.x;
+    if (i >= params.spawn_total) {
+        return;
+    }
+    var emitter_index = 0u;
+    for (var e = 0u; e < params.emitter_count; e++) {
+        if (i >= emitters[e].spawn_start && i < emitters[e].spawn_start + emitters[e].spawn_count) {
+            emitter_index = e;
+            break;
+        }
+    }
+    let e = emitters[emitter_index];
+
+    let free = atomicSub(&free_list.count, 1);
+    if (free <= 0) {
+        // Pool exhausted: undo and drop this spawn.
+        atomicAdd(&free_list.count, 1);
+        return;
+    }
+    let slot = free_list.indices[free - 1];
+
+    var state = pcg(i ^ pcg(params.seed));
+    var p: Particle;
+    p.position = e.position + spawn_offset(&state, e);
+    p.velocity = rand_cone(&state, e.direction, e.cone_cos) * mix(e.speed_min, e.speed_max, rand(&state));
+    p.age = 0.0;
+    p.lifetime = mix(e.lifetime_min, e.lifetime_max, rand(&state));
+    p.color = e.color;
+    p.size = e.size;
+    p.emitter = emitter_index;
+    p.seed = pcg(state);
+    particles[slot] = p;
+}
+
+// Smooth vector potential; its curl is a divergence-free flow field.
+fn potential(p: vec3<f32>, t: f32) -> vec3<f32> {
+    return vec3<f32>(
+        sin(p.y * 1.3 + t * 0.7) + sin(p.z * 2.1 - t * 0.4) * 0.5,
+        sin(p.z * 1.7 - t * 0.5) + sin(p.x * 2.3 + t * 0.6) * 0.5,
+        sin(p.x * 1.1 + t * 0.3) + sin(p.y * 1.9 - t * 0.8) * 0.5,
+    );
+}
+
+fn curl_noise(p: vec3<f32>, t: f32) -> vec3<f32> {
+    let e = 0.01;
+    let dx = vec3<f32>(e, 0.0, 0.0);
+    let dy = vec3<f32>(0.0, e, 0.0);
+    let dz = vec3<f32>(0.0, 0.0, e);
+    let d_dx = potential(p + dx, t) - potential(p - dx, t);
+    let d_dy = potential(p + dy, t) - potential(p - dy, t);
+    let d_dz = potential(p + dz, t) - potential(p - dz, t);
+    return vec3<f32>(d_dy.z - d_dz.y, d_dz.x - d_dx.z, d_dx.y - d_dy.x) / (2.0 * e);
+}
+
+fn unproject(inverse_view_projection: mat4x4<f32>, ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    let h = inverse_view_projection * vec4<f32>(ndc, depth, 1.0);
+    return h.xyz / h.w;
+}
+
+// World position of depth texel `texel`, or `fallback` where the depth is empty (sky).
+fn texel_position(texel: vec2<i32>, size: vec2<i32>, fallback: vec3<f32>) -> vec3<f32> {
+    let t = clamp(texel, vec2<i32>(0), size - 1);
+    let depth = textureLoad(scene_depth, t, 0).r;
+    let uv = (vec2<f32>(t) + 0.5) / vec2<f32>(size);
+    let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    return select(fallback, unproject(collision.inverse_view_projection, ndc, depth), depth > 0.0);
+}
+
+// Surface normal at `texel` from its neighbours' positions, taking the nearer neighbour on each axis
+// so the difference doesn't straddle a depth edge. Faces the camera.
+fn depth_normal(texel: vec2<i32>, size: vec2<i32>) -> vec3<f32> {
+    let center = texel_position(texel, size, vec3<f32>(0.0));
+    let left = texel_position(texel - vec2<i32>(1, 0), size, center);
+    let right = texel_position(texel + vec2<i32>(1, 0), size, center);
+    let up = texel_position(texel - vec2<i32>(0, 1), size, center);
+    let down = texel_position(texel + vec2<i32>(0, 1), size, center);
+    let dx = select(right - center, center - left, length(center - left) < length(right - center));
+    let dy = select(down - center, center - up, length(center - up) < length(down - center));
+    let to_camera = collision.camera_position - center;
+    var n = cross(dx, dy);
+    if (dot(n, n) < 1e-12) {
+        return normalize(to_camera);
+    }
+    n = normalize(n);
+    return select(-n, n, dot(n, to_camera) >= 0.0);
+}
+
+struct Hit {
+    found: bool,
+    point: vec3<f32>,
+    normal: vec3<f32>,
+    surface_velocity: vec3<f32>,
+}
+
+// Screen-space hit: the particle projects onto the depth target and sits less than `thickness`
+// behind the stored surface. `on_screen` reports whether the depth target could be used at all.
+fn screen_hit(position: vec3<f32>, on_screen: ptr<function, bool>) -> Hit {
+    var hit: Hit;
+    let clip = collision.view_projection * vec4<f32>(position, 1.0);
+    if (clip.w <= collision.near) {
+        return hit;
+    }
+    let ndc = clip.xyz / clip.w;
+    if (any(abs(ndc.xy) >= vec2<f32>(1.0))) {
+        return hit;
+    }
+    *on_screen = true;
+    let size = vec2<i32>(textureDimensions(scene_depth));
+    let texel = clamp(vec2<i32>(vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5) * vec2<f32>(size)), vec2<i32>(0), size - 1);
+    let depth = textureLoad(scene_depth, texel, 0).r;
+    if (depth <= 0.0) {
+        return hit;
+    }
+    // Reversed-Z infinite projection: view distance = near / depth.
+    let penetration = clip.w - collision.near / depth;
+    if (penetration <= 0.0 || penetration >= collision.thickness) {
+        return hit;
+    }
+    hit.found = true;
+    hit.point = unproject(collision.inverse_view_projection, ndc.xy, depth);
+    hit.normal = depth_normal(texel, size);
+
+    // Surface motion: where the surface was last frame according to the velocity target, versus
+    // where a static point would have been. Equal for static geometry, so camera motion cancels.
+    let velocity = textureLoad(scene_velocity, texel, 0).xy;
+    let static_clip = collision.previous_view_projection * vec4<f32>(hit.point, 1.0);
+    let previous = unproject(collision.previous_inverse_view_projection, ndc.xy + velocity, static_clip.z / static_clip.w);
+    let surface_velocity = (hit.point - previous) / max(params.dt, 1e-4);
+    let speed = length(surface_velocity);
+    hit.surface_velocity = surface_velocity * min(1.0, MAX_SURFACE_SPEED / max(speed, 1e-6));
+    return hit;
+}
+
+fn analytic_hit(position: vec3<f32>) -> Hit {
+    var hit: Hit;
+    for (var i = 0u; i < collision.plane_count; i++) {
+        let plane = collision.planes[i];
+        let d = dot(plane.xyz, position) - plane.w;
+        if (d < 0.0 && d > -collision.thickness) {
+            hit.found = true;
+            hit.point = position - plane.xyz * d;
+            hit.normal = plane.xyz;
+            return hit;
+        }
+    }
+    for (var i = 0u; i < collision.sphere_count; i++) {
+        let sphere = collision.spheres[i];
+        let offset = position - sphere.xyz;
+        let dist = length(offset);
+        if (dist < sphere.w && dist > sphere.w - collision.thickness) {
+            hit.found = true;
+            hit.normal = select(vec3<f32>(0.0, 1.0, 0.0), offset / dist, dist > 1e-6);
+            hit.point = sphere.xyz + hit.normal * sphere.w;
+            return hit;
+        }
+    }
+    return hit;
+}
+
+// Resolves collisions for `p` (already integrated). Returns false if the particle should die.
+fn collide(p: ptr<function, Particle>) -> bool {
+    if (collision.mode == COLLISION_OFF) {
+        return true;
+    }
+    var on_screen = false;
+    var hit: Hit;
+    if (collision.screen != 0u) {
+        hit = screen_hit((*p).position, &on_screen);
+    }
+    if (!on_screen) {
+        hit = analytic_hit((*p).position);
+    }
+    if (!hit.found) {
+        return true;
+    }
+    if (collision.mode == COLLISION_KILL) {
+        return false;
+    }
+    let relative = (*p).velocity - hit.surface_velocity;
+    let normal_speed = dot(relative, hit.normal);
+    if (normal_speed < 0.0) {
+        let normal_part = hit.normal * normal_speed;
+        let tangent_part = relative - normal_part;
+        (*p).velocity = tangent_part * (1.0 - collision.friction) - normal_part * collision.restitution + hit.surface_velocity;
+    }
+    (*p).position = hit.point + hit.normal * SURFACE_OFFSET;
+    return true;
+}
+
+fn release(i: u32) {
+    particles[i].lifetime = 0.0;
+    let free = atomicAdd(&free_list.count, 1);
+    free_list.indices[free] = i;
+}
+
+@compute @workgroup_size(64)
+fn simulate(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i = gid.x;
+    if (i >= params.capacity) {
+        return;
+    }
+    var p = particles[i];
+    if (p.lifetime <= 0.0) {
+        return;
+    }
+    p.age += params.dt;
+    if (p.age >= p.lifetime) {
+        release(i);
+        return;
+    }
+
+    var accel = params.gravity;
+    if (params.curl_strength > 0.0) {
+        accel += curl_noise(p.position * params.curl_scale, params.time) * params.curl_strength;
+    }
+    p.velocity = (p.velocity + accel * params.dt) * max(1.0 - params.drag * params.dt, 0.0);
+    p.position += p.velocity * params.dt;
+    if (!collide(&p)) {
+        release(i);
+        return;
+    }
+    particles[i] = p;
+
+    let alive = atomicAdd(&alive_list.count, 1u);
+    alive_list.indices[alive] = i;
+}
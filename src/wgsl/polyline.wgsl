@@ -0,0 +1,310 @@
+// Thick anti-aliased polylines. One instance per segment: the vertex shader projects the segment
+// (and its neighbours, for joins) to pixels and covers it with a screen-space quad; the fragment
+// shader evaluates the stroke's signed distance in pixels for joins, caps, dashes and coverage.
+//
+// Joins are split along the bisector of the two segments: each segment draws only its own side, so
+// translucent strokes don't double-blend at joints.
+
+struct Uniforms {
+    view_projection: mat4x4<f32>,
+    /// Pass viewport (x, y, width, height) in framebuffer pixels.
+    viewport: vec4<f32>,
+    /// Framebuffer row to `scene_depth` row: y * scale + offset (flips for upside-down targets).
+    depth_y: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> u: Uniforms;
+/// Scene depth for `fs_depth_texture` (reversed-Z, read as unfilterable float).
+@group(1) @binding(0) var scene_depth: texture_2d<f32>;
+
+const JOIN_MITER: u32 = 0u;
+const JOIN_ROUND: u32 = 1u;
+const CAP_BUTT: u32 = 0u;
+const CAP_SQUARE: u32 = 1u;
+const CAP_ROUND: u32 = 2u;
+const HAS_PREV: u32 = 16u;
+const HAS_NEXT: u32 = 32u;
+/// End cut by the near plane: butt cap there regardless of the cap style.
+const CUT_A: u32 = 64u;
+const CUT_B: u32 = 128u;
+
+/// Closest clip-space w kept when a segment crosses the near plane.
+const MIN_W: f32 = 1e-4;
+/// Anti-aliasing fringe around the stroke (pixels).
+const FRINGE: f32 = 1.0;
+
+struct Segment {
+    @location(0) prev: vec3<f32>,
+    @location(1) a: vec3<f32>,
+    @location(2) b: vec3<f32>,
+    @location(3) next: vec3<f32>,
+    @location(4) color_a: vec4<f32>,
+    @location(5) color_b: vec4<f32>,
+    /// Distance along the strip at a and b (strip units).
+    @location(6) distance: vec2<f32>,
+    /// Width (pixels), miter limit (multiples of the half width).
+    @location(7) width_limit: vec2<f32>,
+    /// Dash on and off lengths (strip units); on = 0 is a solid line.
+    @location(8) dash: vec2<f32>,
+    /// Join (bits 0-1), cap (bits 2-3), HAS_PREV, HAS_NEXT (CUT_A / CUT_B are set by `vs`).
+    @location(9) flags: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip: vec4<f32>,
+    /// Screen positions of a (xy) and b (zw).
+    @location(0) @interpolate(flat) ends: vec4<f32>,
+    /// Screen positions of prev (xy) and next (zw).
+    @location(1) @interpolate(flat) neighbours: vec4<f32>,
+    @location(2) @interpolate(flat) color_a: vec4<f32>,
+    @location(3) @interpolate(flat) color_b: vec4<f32>,
+    /// Distance at a and b, clip w at a and b (for perspective-correct dashes and colors).
+    @location(4) @interpolate(flat) distance_w: vec4<f32>,
+    /// Half width, miter limit, dash on, dash off.
+    @location(5) @interpolate(flat) params: vec4<f32>,
+    @location(6) @interpolate(flat) flags: u32,
+}
+
+fn to_screen(clip: vec4<f32>) -> vec2<f32> {
+    let ndc = clip.xy / clip.w;
+    return u.viewport.xy + (ndc * vec2<f32>(0.5, -0.5) + 0.5) * u.viewport.zw;
+}
+
+fn to_clip(screen: vec2<f32>, z: f32, w: f32) -> vec4<f32> {
+    let ndc = ((screen - u.viewport.xy) / u.viewport.zw - 0.5) * vec2<f32>(2.0, -2.0);
+    return vec4<f32>(ndc * w, z, w);
+}
+
+/// Moves `point` along the segment towards `toward` until it is in front of the near plane.
+fn clip_to_near(point: vec4<f32>, toward: vec4<f32>) -> vec4<f32> {
+    if point.w >= MIN_W {
+        return point;
+    }
+    return mix(point, toward, (MIN_W - point.w) / (toward.w - point.w));
+}
+
+fn direction(start: vec2<f32>, end: vec2<f32>) -> vec2<f32> {
+    let d = end - start;
+    if dot(d, d) < 1e-8 {
+        return vec2<f32>(1.0, 0.0);
+    }
+    return normalize(d);
+}
+
+fn perpendicular(d: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(-d.y, d.x);
+}
+
+/// Tangent of the bisector between two directions; the first segment owns the side where
+/// `dot(p - joint, tangent) < 0`. At a hairpin both segments keep their own side (`own`).
+fn bisector(first: vec2<f32>, second: vec2<f32>, own: vec2<f32>) -> vec2<f32> {
+    let sum = first + second;
+    if dot(sum, sum) < 1e-6 {
+        return own;
+    }
+    return normalize(sum);
+}
+
+@vertex
+fn vs(segment: Segment, @builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    var ca = u.view_projection * vec4<f32>(segment.a, 1.0);
+    var cb = u.view_projection * vec4<f32>(segment.b, 1.0);
+    if ca.w < MIN_W && cb.w < MIN_W {
+        out.clip = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        out.params = vec4<f32>(0.0);
+        return out;
+    }
+
+    var flags = segment.flags;
+    var dist = segment.distance;
+    var color_a = segment.color_a;
+    var color_b = segment.color_b;
+    // A segment cut by the near plane ends there with a butt cap.
+    if ca.w < MIN_W {
+        let t = (MIN_W - ca.w) / (cb.w - ca.w);
+        ca = mix(ca, cb, t);
+        dist.x = mix(dist.x, dist.y, t);
+        color_a = mix(color_a, color_b, t);
+        flags = (flags & ~HAS_PREV) | CUT_A;
+    }
+    if cb.w < MIN_W {
+        let t = (MIN_W - cb.w) / (ca.w - cb.w);
+        cb = mix(cb, ca, t);
+        dist.y = mix(dist.y, dist.x, t);
+        color_b = mix(color_b, color_a, t);
+        flags = (flags & ~HAS_NEXT) | CUT_B;
+    }
+    // Neighbours only set join directions; clipping keeps their on-screen direction.
+    let cp = clip_to_near(u.view_projection * vec4<f32>(segment.prev, 1.0), ca);
+    let cn = clip_to_near(u.view_projection * vec4<f32>(segment.next, 1.0), cb);
+
+    let sa = to_screen(ca);
+    let sb = to_screen(cb);
+    let d = direction(sa, sb);
+    let n = perpendicular(d);
+
+    // Strokes thinner than a pixel are drawn one pixel wide and fainter.
+    var half_width = segment.width_limit.x * 0.5;
+    if half_width < 0.5 {
+        let fade = max(half_width * 2.0, 0.0);
+        color_a.a *= fade;
+        color_b.a *= fade;
+        half_width = 0.5;
+    }
+    let limit = max(segment.width_limit.y, 1.0);
+
+    // How far past each end the stroke can reach: the miter tip at a joint, the cap otherwise.
+    let join = flags & 3u;
+    let cap = (flags >> 2u) & 3u;
+    var joint_reach = half_width;
+    if join == JOIN_MITER {
+        joint_reach = half_width * limit;
+    }
+    let cap_reach = select(half_width, 0.0, cap == CAP_BUTT);
+    let reach_a = select(select(cap_reach, 0.0, (flags & CUT_A) != 0u), joint_reach, (flags & HAS_PREV) != 0u) + FRINGE;
+    let reach_b = select(select(cap_reach, 0.0, (flags & CUT_B) != 0u), joint_reach, (flags & HAS_NEXT) != 0u) + FRINGE;
+
+    // Two triangles: (0, 1, 2), (2, 1, 3) over corners (end, side).
+    var corners = array<u32, 6>(0u, 1u, 2u, 2u, 1u, 3u);
+    let corner = corners[vertex_index];
+    let side = select(-1.0, 1.0, (corner & 1u) != 0u) * (half_width + FRINGE);
+    if corner < 2u {
+        out.clip = to_clip(sa - d * reach_a + n * side, ca.z, ca.w);
+    } else {
+        out.clip = to_clip(sb + d * reach_b + n * side, cb.z, cb.w);
+    }
+
+    out.ends = vec4<f32>(sa, sb);
+    out.neighbours = vec4<f32>(to_screen(cp), to_screen(cn));
+    out.color_a = color_a;
+    out.color_b = color_b;
+    out.distance_w = vec4<f32>(dist, ca.w, cb.w);
+    out.params = vec4<f32>(half_width, limit, segment.dash);
+    out.flags = flags;
+    return out;
+}
+
+/// Signed distance (pixels) of the stroke around a joint at `joint`, on this segment's side:
+/// `away` points from the segment into the joint, `other` along the neighbouring segment.
+fn join_distance(band: f32, p: vec2<f32>, joint: vec2<f32>, away: vec2<f32>, other: vec2<f32>, join: u32, half_width: f32, limit: f32) -> f32 {
+    let past = dot(p - joint, away);
+    if join == JOIN_ROUND {
+        if past > 0.0 {
+            return length(p - joint) - half_width;
+        }
+        return band;
+    }
+    // Outer miter direction; beyond the miter limit (and for bevels) the tip is cut flat.
+    let normal = perpendicular(away);
+    let sum = normal + perpendicular(other);
+    var miter = away;
+    var cos_half = 0.0;
+    if dot(sum, sum) > 1e-6 {
+        miter = normalize(sum);
+        cos_half = dot(normal, miter);
+        // Turning towards `normal` puts the outer corner on the other side.
+        if dot(other, normal) > 0.0 {
+            miter = -miter;
+        }
+        cos_half = abs(cos_half);
+    }
+    if join == JOIN_MITER && cos_half * limit >= 1.0 {
+        return band;
+    }
+    return max(band, dot(p - joint, miter) - half_width * cos_half);
+}
+
+/// Signed distance (pixels) of a cap at `end`, with `away` pointing out of the stroke.
+fn cap_distance(band: f32, p: vec2<f32>, end: vec2<f32>, away: vec2<f32>, cap: u32, half_width: f32) -> f32 {
+    let past = dot(p - end, away);
+    if cap == CAP_ROUND {
+        if past > 0.0 {
+            return length(p - end) - half_width;
+        }
+        return band;
+    }
+    if cap == CAP_SQUARE {
+        return max(band, past - half_width);
+    }
+    return max(band, past);
+}
+
+@fragment
+fn fs(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(in);
+}
+
+/// `fs` with a depth test against `scene_depth`, for passes that can't attach it.
+@fragment
+fn fs_depth_texture(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = shade(in);
+    let texel = vec2<i32>(i32(in.clip.x), i32(in.clip.y * u.depth_y.x + u.depth_y.y));
+    if in.clip.z < textureLoad(scene_depth, texel, 0).r {
+        discard;
+    }
+    return color;
+}
+
+/// Premultiplied coverage of the stroke (SDF from the segment, joins, caps and dashes).
+fn shade(in: VertexOutput) -> vec4<f32> {
+    let p = in.clip.xy;
+    let sa = in.ends.xy;
+    let sb = in.ends.zw;
+    let d = direction(sa, sb);
+    let n = perpendicular(d);
+    let half_width = in.params.x;
+    let limit = in.params.y;
+    let join = in.flags & 3u;
+    let cap = (in.flags >> 2u) & 3u;
+
+    // Perspective-correct position along the segment, for colors and dashes.
+    let length_px = max(distance(sa, sb), 1e-4);
+    let t = clamp(dot(p - sa, d) / length_px, 0.0, 1.0);
+    let wa = in.distance_w.z;
+    let wb = in.distance_w.w;
+    let s = (t / wb) / ((1.0 - t) / wa + t / wb);
+    let along = mix(in.distance_w.x, in.distance_w.y, s);
+    let along_px = max(fwidth(along), 1e-6);
+
+    let band = abs(dot(p - sa, n)) - half_width;
+    var sdf = band;
+    if (in.flags & HAS_PREV) != 0u {
+        let d_prev = direction(in.neighbours.xy, sa);
+        if dot(p - sa, bisector(d_prev, d, d)) < 0.0 {
+            discard;
+        }
+        sdf = join_distance(sdf, p, sa, -d, -d_prev, join, half_width, limit);
+    } else {
+        sdf = cap_distance(sdf, p, sa, -d, select(cap, CAP_BUTT, (in.flags & CUT_A) != 0u), half_width);
+    }
+    if (in.flags & HAS_NEXT) != 0u {
+        let d_next = direction(sb, in.neighbours.zw);
+        if dot(p - sb, bisector(d, d_next, d)) >= 0.0 {
+            discard;
+        }
+        sdf = join_distance(sdf, p, sb, d, d_next, join, half_width, limit);
+    } else {
+        sdf = cap_distance(sdf, p, sb, d, select(cap, CAP_BUTT, (in.flags & CUT_B) != 0u), half_width);
+    }
+
+    let dash_on = in.params.z;
+    let dash_off = in.params.w;
+    if dash_on > 0.0 && dash_off > 0.0 {
+        let period = dash_on + dash_off;
+        let phase = along - floor(along / period) * period;
+        var gap = -min(phase, dash_on - phase);
+        if phase > dash_on {
+            gap = min(phase - dash_on, period - phase);
+        }
+        sdf = max(sdf, gap / along_px);
+    }
+
+    let coverage = clamp(0.5 - sdf, 0.0, 1.0);
+    if coverage <= 0.0 {
+        discard;
+    }
+    let color = mix(in.color_a, in.color_b, s);
+    let alpha = color.a * coverage;
+    return vec4<f32>(color.rgb * alpha, alpha);
+}
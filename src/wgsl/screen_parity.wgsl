@@ -1,10 +1,45 @@
-// Final composite: ACES tonemap, sRGB, weighted scene/bloom blend, starburst, vignette.
+// Final composite: weighted scene/bloom blend (pulsing with the music's bass), starburst, vignette,
+// exposure, grading (white balance, contrast, saturation), tonemap, sRGB, grading LUT. During a scene
+// transition, also composites the outgoing scene (rendered into its own targets) and fades or wipes between the two.
+// In HDR output, values reach the display's peak (`output_peak`, with 1 = SDR white) instead of 1.
 
 const TAU: f32 = 6.283185307179586476925286766559;
 
+// Soft edge of the wipe, in screen widths.
+const WIPE_EDGE: f32 = 0.1;
+// Extra bloom at full bass while music plays (fraction of the set intensity).
+const AUDIO_BLOOM: f32 = 0.75;
+// Rec. 709 luminance weights.
+const LUMA: vec3<f32> = vec3<f32>(0.2126, 0.7152, 0.0722);
+// Contrast pivots around it.
+const MIDDLE_GREY: f32 = 0.18;
+// Scene luminance the extended Reinhard operator maps to white (to the peak, times the peak, in HDR).
+const REINHARD_WHITE: f32 = 4.0;
+
 struct ScreenUniforms {
     camera_dir: vec3<f32>,
-    _pad: f32,
+    // 0 = none, 1 = fade, 2 = wipe (TransitionKind::shader_mode).
+    transition_mode: u32,
+    transition_progress: f32,
+    bloom_intensity: f32,
+    // Scale into the tonemapper's range: the camera's manual exposure, or the adapted one
+    // (copied in from crate::gpu::exposure).
+    exposure: f32,
+    // 0 = ACES fitted, 1 = AgX, 2 = Reinhard extended, 3 = Tony McMapface, 4 = none
+    // (crate::color_grading::Tonemapper::shader_mode).
+    tonemapper: u32,
+    // Music spectrum levels 0..1, bass first (crate::audio::BAND_EDGES_HZ); zero in silence.
+    audio_bands: array<vec4<f32>, 2>,
+    // Linear Rec. 709 white balance (crate::color_grading::ColorGrading::white_balance).
+    white_balance: mat3x3<f32>,
+    // Input range of the grading LUT; a strength of 0 skips it.
+    lut_domain_min: vec3<f32>,
+    lut_strength: f32,
+    lut_domain_max: vec3<f32>,
+    contrast: f32,
+    saturation: f32,
+    // 1 in SDR; the display peak over SDR white in HDR output (crate::gpu::GpuContext::set_output_peak).
+    output_peak: f32,
 }
 
 struct VertexOutput {
@@ -24,13 +59,94 @@ fn vs(@location(0) pos: vec2<f32>) -> VertexOutput {
 @group(0) @binding(1) var bloom_tex: texture_2d<f32>;
 @group(0) @binding(2) var samp: sampler;
 @group(0) @binding(3) var<uniform> uniforms: ScreenUniforms;
+// Outgoing scene resolve and bloom during a transition (this frame's views otherwise).
+@group(0) @binding(4) var outgoing_resolve_tex: texture_2d<f32>;
+@group(0) @binding(5) var outgoing_bloom_tex: texture_2d<f32>;
+// Creative grade, indexed by sRGB-encoded color.
+@group(0) @binding(6) var grading_lut: texture_3d<f32>;
+// Tony McMapface's operator, indexed by x / (x + 1) of linear color.
+@group(0) @binding(7) var tony_mc_mapface_lut: texture_3d<f32>;
+
+// White balance, then contrast around middle grey in log2, then saturation (linear scene light).
+fn grade(v_in: vec3<f32>) -> vec3<f32> {
+    var v = uniforms.white_balance * v_in;
+    if uniforms.contrast != 1.0 {
+        let pivot = log2(MIDDLE_GREY);
+        v = exp2((log2(max(v, vec3<f32>(1e-10))) - pivot) * uniforms.contrast + pivot);
+    }
+    return max(mix(vec3<f32>(dot(v, LUMA)), v, uniforms.saturation), vec3<f32>(0.0));
+}
+
+// Samples a `size`³ LUT at `t` in 0..1, through texel centers.
+fn sample_lut(lut: texture_3d<f32>, t: vec3<f32>) -> vec3<f32> {
+    let size = f32(textureDimensions(lut).x);
+    return textureSampleLevel(lut, samp, t * ((size - 1.0) / size) + 0.5 / size, 0.0).rgb;
+}
 
 // ACES filmic approximation (Krzysztof Narkowicz)
-fn tonemap(v_in: vec3<f32>) -> vec3<f32> {
-    let v = v_in * 0.6;
+fn aces_fitted(v: vec3<f32>) -> vec3<f32> {
     return clamp((v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
 }
 
+// AgX (Troy Sobotka), with Benjamin Wrensch's polynomial fit of the default sigmoid: log2 encode in
+// the inset space, sigmoid, outset, then back to linear.
+fn agx(v_in: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        vec3<f32>(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+        vec3<f32>(0.0784335999999992, 0.878468636469772, 0.0784336),
+        vec3<f32>(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+    );
+    let outset = mat3x3<f32>(
+        vec3<f32>(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+        vec3<f32>(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+        vec3<f32>(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+    var v = clamp(log2(max(inset * v_in, vec3<f32>(1e-10))), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    v = (v - min_ev) / (max_ev - min_ev);
+    let v2 = v * v;
+    let v4 = v2 * v2;
+    v = 15.5 * v4 * v2 - 40.14 * v4 * v + 31.96 * v4 - 6.868 * v2 * v + 0.4298 * v2 + 0.1191 * v - 0.00232;
+    return pow(max(outset * v, vec3<f32>(0.0)), vec3<f32>(2.2));
+}
+
+// Reinhard on luminance, reaching `peak` at REINHARD_WHITE * peak; hue and saturation are kept.
+// Scaled from the SDR curve (peak 1) so that it stays close to linear up to SDR white in HDR output.
+fn reinhard_extended(v: vec3<f32>, peak: f32) -> vec3<f32> {
+    let l = dot(v, LUMA);
+    let mapped = l * (1.0 + l / (peak * REINHARD_WHITE * REINHARD_WHITE)) / (1.0 + l / peak);
+    return clamp(v * (mapped / max(l, 1e-6)), vec3<f32>(0.0), vec3<f32>(peak));
+}
+
+// Tony McMapface (Tomasz Stachowiak): the whole operator is its LUT.
+fn tony_mc_mapface(v: vec3<f32>) -> vec3<f32> {
+    return sample_lut(tony_mc_mapface_lut, v / (v + 1.0));
+}
+
+// Exposure, grading, then the selected operator; linear display light in 0..output_peak (HDR output
+// only selects extended Reinhard or none).
+fn tonemap(v_in: vec3<f32>) -> vec3<f32> {
+    let v = grade(v_in * uniforms.exposure);
+    switch uniforms.tonemapper {
+        case 0u: { return aces_fitted(v); }
+        case 1u: { return agx(v); }
+        case 2u: { return reinhard_extended(v, uniforms.output_peak); }
+        case 3u: { return tony_mc_mapface(v); }
+        default: { return clamp(v, vec3<f32>(0.0), vec3<f32>(uniforms.output_peak)); }
+    }
+}
+
+// The grading LUT over sRGB-encoded color, blended by its strength.
+fn apply_lut(encoded: vec3<f32>) -> vec3<f32> {
+    if uniforms.lut_strength <= 0.0 {
+        return encoded;
+    }
+    let t = saturate((encoded - uniforms.lut_domain_min) / (uniforms.lut_domain_max - uniforms.lut_domain_min));
+    return mix(encoded, sample_lut(grading_lut, t), uniforms.lut_strength);
+}
+
+// Also encodes values above 1: a float canvas reads them as extended sRGB.
 fn srgb(linear: vec3<f32>) -> vec3<f32> {
     let a = 12.92 * linear;
     let b = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
@@ -59,20 +175,44 @@ fn starburst(phi: f32, dir: vec3<f32>) -> f32 {
     return valueNoise(vec2<f32>(phi / TAU * 1000.0, t * 76.54321), vec2<f32>(10000.0, 10000.0)) * 0.25 + 0.75;
 }
 
-@fragment
-fn fs(vin: VertexOutput) -> @location(0) vec4<f32> {
-    let np = vin.uv * 2.0 - 1.0;
+// Scene plus starburst bloom, vignetted and tonemapped (linear, before the sRGB encode).
+fn composite(scene: vec3<f32>, bloom: vec3<f32>, uv: vec2<f32>) -> vec3<f32> {
+    let np = uv * 2.0 - 1.0;
     let polar_r = length(np);
     let polar_phi = atan2(np.x, np.y);
 
-    let scene = textureSample(resolve_tex, samp, vin.uv).rgb;
-    let bloom = textureSample(bloom_tex, samp, vin.uv).rgb;
-
-    var image = (2.0 / 3.0) * scene + (1.0 / 3.0) * bloom * starburst(polar_phi, uniforms.camera_dir);
+    let bass = max(uniforms.audio_bands[0].x, uniforms.audio_bands[0].y);
+    let bloom_weight = (1.0 / 3.0) * uniforms.bloom_intensity * (1.0 + AUDIO_BLOOM * bass);
+    var image = (2.0 / 3.0) * scene + bloom_weight * bloom * starburst(polar_phi, uniforms.camera_dir);
 
     let r = polar_r * 0.5;
     let vignette = pow(1.0 - r * r, 4.0);
     image *= vignette;
 
-    return vec4<f32>(srgb(tonemap(image)), 1.0);
+    return tonemap(image);
+}
+
+// How much of the incoming scene shows at `uv`.
+fn transition_weight(uv: vec2<f32>) -> f32 {
+    let p = uniforms.transition_progress;
+    if uniforms.transition_mode == 2u {
+        let edge = p * (1.0 + WIPE_EDGE);
+        return 1.0 - smoothstep(edge - WIPE_EDGE, edge, uv.x);
+    }
+    return smoothstep(0.0, 1.0, p);
+}
+
+@fragment
+fn fs(vin: VertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(resolve_tex, samp, vin.uv).rgb;
+    let bloom = textureSample(bloom_tex, samp, vin.uv).rgb;
+    var color = composite(scene, bloom, vin.uv);
+
+    if uniforms.transition_mode != 0u {
+        let scene_out = textureSample(outgoing_resolve_tex, samp, vin.uv).rgb;
+        let bloom_out = textureSample(outgoing_bloom_tex, samp, vin.uv).rgb;
+        color = mix(composite(scene_out, bloom_out, vin.uv), color, transition_weight(vin.uv));
+    }
+
+    return vec4<f32>(apply_lut(srgb(color)), 1.0);
 }
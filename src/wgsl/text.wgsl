@@ -0,0 +1,109 @@
+// SDF text: one instanced quad per glyph, in screen pixels or camera-facing in the world. Solid
+// quads (HUD boxes and bars) share the pipeline and skip the atlas.
+// The atlas stores 0.5 - distance / (2 * SPREAD), so 0.5 is the outline.
+
+struct Uniforms {
+    view_projection: mat4x4<f32>,
+    /// Camera right / up in world space (billboard axes for world glyphs).
+    right: vec4<f32>,
+    up: vec4<f32>,
+    /// Pass viewport (x, y, width, height) in target pixels.
+    viewport: vec4<f32>,
+    /// Target pixel (relative to the viewport) to `scene_depth` texel: xy * scale (xy) + offset (zw).
+    depth_map: vec4<f32>,
+    /// -1 when the target is stored upside down.
+    flip_y: f32,
+}
+
+struct Glyph {
+    /// Top-left of world text; unused on screen.
+    @location(0) anchor: vec3<f32>,
+    @location(1) flags: u32,
+    /// Quad (x, y, width, height): pixels from the viewport's top-left, or world units along right / -up.
+    @location(2) rect: vec4<f32>,
+    @location(3) uv: vec4<f32>,
+    @location(4) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) flags: u32,
+}
+
+@group(0) @binding(0) var<uniform> u: Uniforms;
+@group(0) @binding(1) var atlas: texture_2d<f32>;
+@group(0) @binding(2) var atlas_sampler: sampler;
+/// Scene depth for `fs_depth_texture` (reversed-Z, read as unfilterable float).
+@group(1) @binding(0) var scene_depth: texture_2d<f32>;
+
+const WORLD: u32 = 1u;
+const SOLID: u32 = 2u;
+/// SDF_SPREAD in font.rs: atlas texels encoded on either side of the outline.
+const SPREAD: f32 = 4.0;
+/// Dark outline around every glyph, in screen pixels (at most the spread allows).
+const OUTLINE_PX: f32 = 1.0;
+const OUTLINE_ALPHA: f32 = 0.6;
+
+@vertex
+fn vs(@builtin(vertex_index) vertex_index: u32, glyph: Glyph) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0), vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+    let local = glyph.rect.xy + corner * glyph.rect.zw;
+
+    var out: VertexOutput;
+    if (glyph.flags & WORLD) != 0u {
+        let p = glyph.anchor + u.right.xyz * local.x - u.up.xyz * local.y;
+        out.clip = u.view_projection * vec4<f32>(p, 1.0);
+    } else {
+        // Near plane (reversed-Z depth 1): in front of everything.
+        let ndc = vec2<f32>(local.x / u.viewport.z * 2.0 - 1.0, 1.0 - local.y / u.viewport.w * 2.0);
+        out.clip = vec4<f32>(ndc, 1.0, 1.0);
+    }
+    out.clip.y *= u.flip_y;
+    out.uv = glyph.uv.xy + corner * glyph.uv.zw;
+    out.color = glyph.color;
+    out.flags = glyph.flags;
+    return out;
+}
+
+@fragment
+fn fs(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(in);
+}
+
+/// `fs` with world glyphs depth-tested against `scene_depth`, for passes that can't attach it.
+@fragment
+fn fs_depth_texture(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = shade(in);
+    if (in.flags & WORLD) != 0u {
+        let texel = vec2<i32>((in.clip.xy - u.viewport.xy) * u.depth_map.xy + u.depth_map.zw);
+        if in.clip.z < textureLoad(scene_depth, texel, 0).r {
+            discard;
+        }
+    }
+    return color;
+}
+
+/// Premultiplied glyph fill over its outline, or the plain color for solid quads.
+fn shade(in: VertexOutput) -> vec4<f32> {
+    let texel = in.uv * vec2<f32>(textureDimensions(atlas));
+    let texels_per_px = max(length(vec2<f32>(length(dpdx(texel)), length(dpdy(texel)))) * 0.70710678, 1e-4);
+    let value = textureSample(atlas, atlas_sampler, in.uv).r;
+    if (in.flags & SOLID) != 0u {
+        return vec4<f32>(in.color.rgb * in.color.a, in.color.a);
+    }
+    // Screen pixels from the outline, positive outside.
+    let dist = (0.5 - value) * 2.0 * SPREAD / texels_per_px;
+    let fill = clamp(0.5 - dist, 0.0, 1.0) * in.color.a;
+    let outline = clamp(0.5 - (dist - OUTLINE_PX), 0.0, 1.0) * in.color.a * OUTLINE_ALPHA;
+    let alpha = fill + outline * (1.0 - fill);
+    if alpha <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb * fill, alpha);
+}
@@ -0,0 +1,45 @@
+// egui meshes: positions in points, premultiplied sRGB vertex colors and textures (blended in gamma
+// space, as egui expects).
+
+struct Uniforms {
+    /// Target size in points.
+    screen_size: vec2<f32>,
+    /// 1 when the target is `*Srgb` (the hardware re-encodes what we write), else 0.
+    srgb_target: u32,
+    _pad: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    /// Premultiplied, gamma-encoded.
+    @location(1) color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> u: Uniforms;
+@group(1) @binding(0) var ui_texture: texture_2d<f32>;
+@group(1) @binding(1) var ui_sampler: sampler;
+
+@vertex
+fn vs(@location(0) position: vec2<f32>, @location(1) uv: vec2<f32>, @location(2) color: vec4<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip = vec4<f32>(position.x / u.screen_size.x * 2.0 - 1.0, 1.0 - position.y / u.screen_size.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = in.color * textureSample(ui_texture, ui_sampler, in.uv);
+    if u.srgb_target != 0u {
+        return vec4<f32>(linear_from_gamma(color.rgb), color.a);
+    }
+    return color;
+}
+
+fn linear_from_gamma(srgb: vec3<f32>) -> vec3<f32> {
+    let lower = srgb / 12.92;
+    let higher = pow((srgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(higher, lower, srgb < vec3<f32>(0.04045));
+}
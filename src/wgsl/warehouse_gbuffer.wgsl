@@ -13,8 +13,10 @@ struct WarehouseUniforms {
     resolution: vec3<f32>,
     viewport_origin: vec2<f32>,
     viewport_size: vec2<f32>,
-    _pad1: vec2<f32>,
+    background_only: f32,
+    shadow_steps: u32,
     fov: f32,
+    march_steps: u32,
 }
 
 @group(0) @binding(0) var<uniform> u: WarehouseUniforms;
@@ -299,7 +301,7 @@ fn calcSoftshadow(ro: vec3<f32>, rd: vec3<f32>, error: f32, mint: f32, tmax: f32
     var res = 1.0;
     var t = mint;
     var ph = 1e10;
-    for (var i = 0u; i < 32u; i++) {
+    for (var i = 0u; i < u.shadow_steps; i++) {
         let h = sceneNoLights(ro + rd * t);
         let y = h * h / (2.0 * ph);
         let d = sqrt(h * h - y * y);
@@ -320,7 +322,7 @@ fn render(uv: vec2<f32>, rayOrigin: vec3<f32>, cx: vec3<f32>, cy: vec3<f32>, cz:
     let noiz = halfPixelScale * (2.0 * hash1_v3(vec3<f32>(uv, u.time)) - 1.0);
     var totalDistance = marchNear + noiz;
 
-    for (var it = 0u; it < maxIterations; it++) {
+    for (var it = 0u; it < min(u.march_steps, maxIterations); it++) {
         let stepSize = scene_dist(rayPos);
         totalDistance += stepSize;
         rayPos = rayOrigin + totalDistance * rayDir;